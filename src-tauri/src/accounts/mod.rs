@@ -0,0 +1,580 @@
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::config::{Credentials, Region, SharedCredentialsProvider};
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+use crate::storage::encryption::customer_key_name;
+use crate::utils::{app_data_dir, read_json, write_json};
+use crate::vault::Vault;
+
+pub mod profiles;
+pub mod session;
+
+use profiles::PROFILE_ID_PREFIX;
+use session::{RoleConfig, RoleSessions};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+// Used when an account leaves the region empty, as most S3-compatible services
+// accept any region
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+/// A named S3 connection, as persisted on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    /// Stable identifier used in paths, as in `s3://<id>@bucket/key`
+    pub id: String,
+    pub name: String,
+    /// Custom endpoint for S3-compatible services, `None` for AWS
+    pub endpoint_url: Option<String>,
+    pub region: Option<String>,
+    /// Address buckets as `endpoint/bucket` instead of `bucket.endpoint`
+    pub path_style: bool,
+    pub access_key_id: String,
+    /// Secrets live in the vault and are only filled in when the account is
+    /// used. Files written before the vault existed may still contain them.
+    #[serde(default, skip_serializing)]
+    pub secret_access_key: String,
+    #[serde(default, skip_serializing)]
+    pub session_token: Option<String>,
+    /// Bucket shown directly in the sidebar
+    pub default_bucket: Option<String>,
+    /// AWS profile the account was imported from. Such accounts are read from
+    /// the AWS config files on every start, are never saved and connect through
+    /// the profile's own credential chain.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Role assumed with the keys above; S3 is then accessed with the session
+    #[serde(default)]
+    pub role: Option<RoleConfig>,
+}
+
+/// What the frontend gets to see of an account; secrets never leave the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub id: String,
+    pub name: String,
+    pub endpoint_url: Option<String>,
+    pub region: Option<String>,
+    pub path_style: bool,
+    pub access_key_id: String,
+    pub default_bucket: Option<String>,
+    pub profile: Option<String>,
+    /// How an imported profile obtains its credentials, or why it cannot
+    pub credential_source: Option<String>,
+    pub role: Option<RoleConfig>,
+}
+
+impl From<&Account> for AccountInfo {
+    fn from(account: &Account) -> Self {
+        Self {
+            id: account.id.clone(),
+            name: account.name.clone(),
+            endpoint_url: account.endpoint_url.clone(),
+            region: account.region.clone(),
+            path_style: account.path_style,
+            access_key_id: account.access_key_id.clone(),
+            default_bucket: account.default_bucket.clone(),
+            profile: account.profile.clone(),
+            credential_source: None,
+            role: account.role.clone(),
+        }
+    }
+}
+
+/// An account as entered in the settings dialog. Leaving the secret empty when
+/// editing keeps the stored one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountInput {
+    pub id: Option<String>,
+    pub name: String,
+    pub endpoint_url: Option<String>,
+    pub region: Option<String>,
+    pub path_style: bool,
+    pub access_key_id: String,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub default_bucket: Option<String>,
+    #[serde(default)]
+    pub role: Option<RoleConfig>,
+}
+
+// The secret parts of an account, stored as one vault entry
+#[derive(Serialize, Deserialize)]
+struct AccountSecret {
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+fn secret_name(id: &str) -> String {
+    format!("account/{}", id)
+}
+
+const PROFILE_READ_ONLY: &str = "AWS profiles are edited in ~/.aws/config and ~/.aws/credentials";
+
+fn is_profile_id(id: &str) -> bool {
+    id.starts_with(PROFILE_ID_PREFIX)
+}
+
+// Treat blank form fields as not set
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// Derive an ID from the account name that is safe to use inside an s3:// path
+fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "account".to_string()
+    } else {
+        slug
+    }
+}
+
+// Check the role settings of the form, a blank role ARN means no role
+fn resolve_role(role: RoleConfig) -> Result<Option<RoleConfig>, String> {
+    let role_arn = role.role_arn.trim().to_string();
+    if role_arn.is_empty() {
+        return Ok(None);
+    }
+
+    let sts_endpoint_url = non_empty(role.sts_endpoint_url);
+    if let Some(endpoint) = &sts_endpoint_url {
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err("STS endpoint URL must start with http:// or https://".to_string());
+        }
+    }
+
+    // The limits STS enforces for AssumeRole
+    if let Some(duration) = role.duration_secs {
+        if !(900..=43200).contains(&duration) {
+            return Err("Session duration must be between 900 and 43200 seconds".to_string());
+        }
+    }
+
+    Ok(Some(RoleConfig {
+        role_arn,
+        session_name: non_empty(role.session_name),
+        external_id: non_empty(role.external_id),
+        mfa_serial: non_empty(role.mfa_serial),
+        duration_secs: role.duration_secs,
+        sts_endpoint_url,
+    }))
+}
+
+/// Build an S3 client for an account, along with the credentials it signs
+/// with. Accounts with a role get their credentials from `sessions`.
+pub async fn build_client(
+    account: &Account,
+    sessions: &Arc<RoleSessions>,
+) -> (Client, Option<SharedCredentialsProvider>) {
+    if let Some(profile) = &account.profile {
+        return profile_client(account, profile).await;
+    }
+
+    let credentials = match &account.role {
+        Some(_) => sessions.provider(account),
+        None => SharedCredentialsProvider::new(Credentials::new(
+            &account.access_key_id,
+            &account.secret_access_key,
+            account.session_token.clone(),
+            None,
+            "s3-finder",
+        )),
+    };
+
+    let mut builder = aws_sdk_s3::config::Builder::new()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new(
+            account
+                .region
+                .clone()
+                .unwrap_or_else(|| DEFAULT_REGION.to_string()),
+        ))
+        .credentials_provider(credentials.clone())
+        .force_path_style(account.path_style);
+
+    if let Some(endpoint) = &account.endpoint_url {
+        builder = builder.endpoint_url(endpoint);
+    }
+
+    let config = builder.build();
+
+    (Client::from_conf(config), Some(credentials))
+}
+
+// Profiles may assume roles, run a credential_process or use SSO, so their
+// credentials come from the SDK's profile provider rather than from our copy
+async fn profile_client(
+    account: &Account,
+    profile: &str,
+) -> (Client, Option<SharedCredentialsProvider>) {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest()).profile_name(profile);
+    if let Some(endpoint) = &account.endpoint_url {
+        loader = loader.endpoint_url(endpoint);
+    }
+    let sdk_config = loader.load().await;
+
+    let mut builder =
+        aws_sdk_s3::config::Builder::from(&sdk_config).force_path_style(account.path_style);
+    if sdk_config.region().is_none() {
+        builder = builder.region(Region::new(DEFAULT_REGION));
+    }
+
+    (
+        Client::from_conf(builder.build()),
+        sdk_config.credentials_provider(),
+    )
+}
+
+/// Turn a failed request into a message that tells the user what to fix.
+pub fn describe_connection_error<E, R>(error: &SdkError<E, R>) -> String
+where
+    E: ProvideErrorMetadata + Error + 'static,
+    R: std::fmt::Debug + 'static,
+{
+    // Transport errors only reveal their cause in the chain of sources
+    let mut details = Vec::new();
+    let mut source: Option<&(dyn Error + 'static)> = Some(error);
+    while let Some(cause) = source {
+        details.push(cause.to_string());
+        source = cause.source();
+    }
+    let details = details.join(": ");
+    let lower = details.to_lowercase();
+
+    match error {
+        SdkError::ServiceError(service) => match service.err().code() {
+            Some("InvalidAccessKeyId") => {
+                "Authentication failed: the access key ID is not known to this endpoint".to_string()
+            }
+            Some("SignatureDoesNotMatch") => {
+                "Authentication failed: the secret access key is wrong".to_string()
+            }
+            Some("ExpiredToken") | Some("TokenRefreshRequired") => {
+                "Authentication failed: the session token has expired".to_string()
+            }
+            Some("InvalidToken") => {
+                "Authentication failed: the session token is invalid".to_string()
+            }
+            Some("AccessDenied") => {
+                "Connected, but these credentials are not allowed to list buckets".to_string()
+            }
+            Some("AuthorizationHeaderMalformed") => {
+                "The region does not match the endpoint or bucket".to_string()
+            }
+            Some(code) => format!(
+                "The server rejected the request ({}): {}",
+                code,
+                service.err().message().unwrap_or(&details)
+            ),
+            None => format!("The server rejected the request: {}", details),
+        },
+        SdkError::TimeoutError(_) => "Connection timed out".to_string(),
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            if lower.contains("dns")
+                || lower.contains("failed to lookup")
+                || lower.contains("name or service not known")
+            {
+                format!(
+                    "DNS lookup failed, check the endpoint host name: {}",
+                    details
+                )
+            } else if lower.contains("certificate")
+                || lower.contains("tls")
+                || lower.contains("ssl")
+            {
+                format!("TLS handshake failed, check the certificate or use http:// for local services: {}", details)
+            } else if lower.contains("connection refused") {
+                format!("Connection refused, check the endpoint port: {}", details)
+            } else {
+                format!("Could not reach the endpoint: {}", details)
+            }
+        }
+        _ => details,
+    }
+}
+
+/// Connect with an account and report how many buckets it can see.
+pub async fn test_connection(
+    account: &Account,
+    sessions: &Arc<RoleSessions>,
+) -> Result<String, String> {
+    let (client, _) = build_client(account, sessions).await;
+
+    // Keys restricted to a single bucket cannot list buckets, so test that one
+    if let Some(bucket) = &account.default_bucket {
+        client
+            .head_bucket()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(|e| describe_connection_error(&e))?;
+        return Ok(format!("Connected to bucket {}", bucket));
+    }
+
+    let output = client
+        .list_buckets()
+        .send()
+        .await
+        .map_err(|e| describe_connection_error(&e))?;
+
+    Ok(format!(
+        "Connected, {} buckets visible",
+        output.buckets().len()
+    ))
+}
+
+/// All configured accounts, kept in memory and saved to the app data directory
+/// on every change. Their secrets are kept in the vault.
+pub struct AccountStore {
+    accounts: Mutex<Vec<Account>>,
+    vault: Arc<Vault>,
+    sessions: Arc<RoleSessions>,
+}
+
+impl AccountStore {
+    pub fn load(vault: Arc<Vault>) -> Self {
+        let accounts = app_data_dir()
+            .and_then(|dir| read_json::<Vec<Account>>(&dir.join(ACCOUNTS_FILE)))
+            .unwrap_or_default();
+
+        Self {
+            accounts: Mutex::new(accounts),
+            vault,
+            sessions: Arc::new(RoleSessions::default()),
+        }
+    }
+
+    pub fn vault(&self) -> Arc<Vault> {
+        self.vault.clone()
+    }
+
+    pub fn sessions(&self) -> Arc<RoleSessions> {
+        self.sessions.clone()
+    }
+
+    fn store_secret(&self, account: &Account) -> Result<(), String> {
+        let secret = Zeroizing::new(
+            serde_json::to_string(&AccountSecret {
+                secret_access_key: account.secret_access_key.clone(),
+                session_token: account.session_token.clone(),
+            })
+            .map_err(|e| format!("Failed to serialize secret: {}", e))?,
+        );
+
+        self.vault.set(&secret_name(&account.id), &secret)
+    }
+
+    /// Move secrets still held in plain text by an older accounts file into
+    /// the vault, which must be unlocked.
+    pub fn migrate_secrets(&self) -> Result<(), String> {
+        let mut accounts = self.accounts.lock().map_err(|e| e.to_string())?;
+
+        if accounts.iter().all(|a| a.secret_access_key.is_empty()) {
+            return Ok(());
+        }
+
+        for account in accounts.iter_mut() {
+            if !account.secret_access_key.is_empty() {
+                self.store_secret(account)?;
+                account.secret_access_key.clear();
+                account.session_token = None;
+            }
+        }
+
+        Self::save(&accounts)
+    }
+
+    // Secrets are never written, so an account still holding one from an
+    // older file would lose it
+    fn save(accounts: &[Account]) -> Result<(), String> {
+        if accounts.iter().any(|a| !a.secret_access_key.is_empty()) {
+            return Err(
+                "Unlock the credential vault first, so the saved secrets can be moved into it"
+                    .to_string(),
+            );
+        }
+
+        write_json(&app_data_dir()?.join(ACCOUNTS_FILE), &accounts)
+    }
+
+    /// Saved accounts followed by every profile found in the AWS config files,
+    /// which are read again on each call to pick up edits.
+    pub fn list(&self) -> Result<Vec<AccountInfo>, String> {
+        let accounts = self.accounts.lock().map_err(|e| e.to_string())?;
+        let mut list: Vec<AccountInfo> = accounts.iter().map(AccountInfo::from).collect();
+
+        let profiles = profiles::load()?;
+        list.extend(profiles.values().map(|profile| {
+            let mut info = AccountInfo::from(&profile.to_account());
+            info.credential_source =
+                Some(profile.credential_source(&profiles).unwrap_or_else(|e| e));
+            info
+        }));
+
+        Ok(list)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Account, String> {
+        if let Some(name) = id.strip_prefix(PROFILE_ID_PREFIX) {
+            return profiles::load()?
+                .get(name)
+                .map(|profile| profile.to_account())
+                .ok_or_else(|| format!("AWS profile not found: {}", name));
+        }
+
+        let mut account = self
+            .accounts
+            .lock()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .find(|account| account.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown account: {}", id))?;
+
+        // Not yet migrated secrets are used as they are
+        if account.secret_access_key.is_empty() {
+            let secret = self
+                .vault
+                .get(&secret_name(id))?
+                .ok_or_else(|| format!("No secret key stored for account {}", account.name))?;
+            let secret: AccountSecret = serde_json::from_str(&secret)
+                .map_err(|e| format!("Failed to read secret: {}", e))?;
+
+            account.secret_access_key = secret.secret_access_key;
+            account.session_token = secret.session_token;
+        }
+
+        Ok(account)
+    }
+
+    /// Turn form input into a complete account, filling in the stored secret
+    /// when the input leaves it empty. Nothing is saved.
+    pub fn resolve(&self, input: AccountInput) -> Result<Account, String> {
+        // Imported profiles are not editable, they are always used as configured
+        if let Some(id) = input.id.as_deref().filter(|id| is_profile_id(id)) {
+            return self.get(id);
+        }
+
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Account name cannot be empty".to_string());
+        }
+
+        let access_key_id = input.access_key_id.trim().to_string();
+        if access_key_id.is_empty() {
+            return Err("Access key ID cannot be empty".to_string());
+        }
+
+        let existing = match &input.id {
+            Some(id) => Some(self.get(id)?),
+            None => None,
+        };
+
+        // A new secret replaces the token too, a blank one keeps both
+        let (secret_access_key, session_token) = match non_empty(input.secret_access_key) {
+            Some(secret) => (secret, non_empty(input.session_token)),
+            None => {
+                let existing = existing.ok_or("Secret access key cannot be empty")?;
+                (
+                    existing.secret_access_key,
+                    non_empty(input.session_token).or(existing.session_token),
+                )
+            }
+        };
+
+        let endpoint_url = non_empty(input.endpoint_url);
+        if let Some(endpoint) = &endpoint_url {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err("Endpoint URL must start with http:// or https://".to_string());
+            }
+        }
+
+        Ok(Account {
+            id: input.id.unwrap_or_default(),
+            name,
+            endpoint_url,
+            region: non_empty(input.region),
+            path_style: input.path_style,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            default_bucket: non_empty(input.default_bucket),
+            profile: None,
+            role: input.role.map(resolve_role).transpose()?.flatten(),
+        })
+    }
+
+    /// Add a new account or replace an existing one.
+    pub fn upsert(&self, input: AccountInput) -> Result<AccountInfo, String> {
+        if input.id.as_deref().is_some_and(is_profile_id) {
+            return Err(PROFILE_READ_ONLY.to_string());
+        }
+
+        self.migrate_secrets()?;
+        let mut account = self.resolve(input)?;
+        let mut accounts = self.accounts.lock().map_err(|e| e.to_string())?;
+
+        if !accounts.iter().any(|a| a.id == account.id) {
+            let base = slugify(&account.name);
+            let mut id = base.clone();
+            let mut suffix = 2;
+            while accounts.iter().any(|a| a.id == id) {
+                id = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+            account.id = id;
+        }
+
+        // The secret goes to the vault first, so a locked vault saves nothing
+        self.store_secret(&account)?;
+        account.secret_access_key.clear();
+        account.session_token = None;
+
+        match accounts.iter_mut().find(|a| a.id == account.id) {
+            Some(existing) => *existing = account.clone(),
+            None => accounts.push(account.clone()),
+        }
+
+        Self::save(&accounts)?;
+        self.sessions.end(&account.id);
+
+        Ok(AccountInfo::from(&account))
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        if is_profile_id(id) {
+            return Err(PROFILE_READ_ONLY.to_string());
+        }
+
+        self.migrate_secrets()?;
+        let mut accounts = self.accounts.lock().map_err(|e| e.to_string())?;
+        if !accounts.iter().any(|account| account.id == id) {
+            return Err(format!("Unknown account: {}", id));
+        }
+
+        // Forget the secrets first, so that none are left without an account
+        self.vault.remove(&customer_key_name(Some(id)))?;
+        self.vault.remove(&secret_name(id))?;
+        self.sessions.end(id);
+
+        accounts.retain(|account| account.id != id);
+        Self::save(&accounts)
+    }
+}
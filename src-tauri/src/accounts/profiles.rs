@@ -0,0 +1,490 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::Account;
+
+/// Prefix of the IDs of accounts imported from AWS profiles, which keeps them
+/// apart from the slugs of accounts created in the app.
+pub const PROFILE_ID_PREFIX: &str = "aws:";
+
+// Properties of one profile; nested properties such as `s3 = ...` followed by an
+// indented `addressing_style = path` are stored as `s3.addressing_style`
+type Properties = BTreeMap<String, String>;
+
+/// A profile from the AWS shared config and credentials files.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub name: String,
+    properties: Properties,
+}
+
+impl Profile {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.properties
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.get("region")
+    }
+
+    /// The endpoint for S3, preferring the S3-specific setting.
+    pub fn endpoint_url(&self) -> Option<&str> {
+        self.get("s3.endpoint_url").or(self.get("endpoint_url"))
+    }
+
+    pub fn path_style(&self) -> bool {
+        match self.get("s3.addressing_style") {
+            Some(style) => style == "path",
+            // Same default as the environment connection: custom endpoints are
+            // mostly S3-compatible services that need path-style addressing
+            None => self.endpoint_url().is_some(),
+        }
+    }
+
+    fn has_static_keys(&self) -> bool {
+        self.get("aws_access_key_id").is_some() && self.get("aws_secret_access_key").is_some()
+    }
+
+    /// Describe where the profile gets its credentials, following
+    /// `source_profile` chains. Broken chains are reported as errors.
+    pub fn credential_source(
+        &self,
+        profiles: &BTreeMap<String, Profile>,
+    ) -> Result<String, String> {
+        let mut visited = HashSet::new();
+        let mut profile = self;
+        let mut roles = Vec::new();
+
+        loop {
+            if let Some(role) = profile.get("role_arn") {
+                roles.push(role.to_string());
+
+                if let Some(source) = profile.get("credential_source") {
+                    return Ok(describe_roles(&roles, &format!("the {}", source)));
+                }
+
+                let Some(source) = profile.get("source_profile") else {
+                    return Err(format!(
+                        "Profile {} sets role_arn without source_profile or credential_source",
+                        profile.name
+                    ));
+                };
+
+                // A profile may name itself as source when it also has keys
+                if source == profile.name && profile.has_static_keys() {
+                    return Ok(describe_roles(&roles, "static keys"));
+                }
+
+                if !visited.insert(profile.name.clone()) {
+                    return Err(format!("Profile {} has a source_profile loop", self.name));
+                }
+
+                profile = profiles
+                    .get(source)
+                    .ok_or_else(|| format!("Source profile {} not found", source))?;
+                continue;
+            }
+
+            let base = if profile.get("credential_process").is_some() {
+                "credential_process"
+            } else if profile.get("sso_session").is_some() || profile.get("sso_start_url").is_some()
+            {
+                "AWS SSO"
+            } else if profile.has_static_keys() {
+                "static keys"
+            } else {
+                return Err(format!("Profile {} has no credentials", profile.name));
+            };
+
+            return Ok(describe_roles(&roles, base));
+        }
+    }
+
+    /// The account under which this profile appears in S3 Finder. Credentials
+    /// are not copied, they are resolved through the profile when connecting.
+    pub fn to_account(&self) -> Account {
+        Account {
+            id: format!("{}{}", PROFILE_ID_PREFIX, self.name),
+            name: self.name.clone(),
+            endpoint_url: self.endpoint_url().map(str::to_string),
+            region: self.region().map(str::to_string),
+            path_style: self.path_style(),
+            access_key_id: self
+                .get("aws_access_key_id")
+                .unwrap_or_default()
+                .to_string(),
+            secret_access_key: String::new(),
+            session_token: None,
+            default_bucket: None,
+            profile: Some(self.name.clone()),
+            role: None,
+        }
+    }
+}
+
+fn describe_roles(roles: &[String], base: &str) -> String {
+    match roles.first() {
+        Some(role) if roles.len() == 1 => format!("Role {} using {}", role, base),
+        Some(role) => format!(
+            "Role {} through {} roles using {}",
+            role,
+            roles.len() - 1,
+            base
+        ),
+        None => base.to_string(),
+    }
+}
+
+fn aws_file(env_var: &str, name: &str) -> Option<PathBuf> {
+    match std::env::var_os(env_var) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::home_dir().map(|home| home.join(".aws").join(name)),
+    }
+}
+
+fn read_aws_file(path: Option<PathBuf>) -> Result<String, String> {
+    let Some(path) = path else {
+        return Ok(String::new());
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+// Parse an AWS ini file into properties per profile. In the config file profiles
+// are written as `[profile name]` (except `[default]`), in the credentials file
+// as `[name]`; other sections such as `[sso-session x]` are skipped.
+fn parse(content: &str, config_file: bool, profiles: &mut BTreeMap<String, Properties>) {
+    let mut section: Option<String> = None;
+    let mut parent: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+
+        if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            let header = header.trim();
+            section = if !config_file || header == "default" {
+                Some(header.to_string())
+            } else {
+                header
+                    .strip_prefix("profile ")
+                    .map(|name| name.trim().to_string())
+            };
+            parent = None;
+            continue;
+        }
+
+        let Some(name) = &section else {
+            continue;
+        };
+        let Some((key, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+        let properties = profiles.entry(name.clone()).or_default();
+
+        if line.starts_with(char::is_whitespace) {
+            if let Some(parent) = &parent {
+                properties.insert(format!("{}.{}", parent, key), value);
+            }
+            continue;
+        }
+
+        // An empty value opens a nested block of indented properties
+        parent = value.is_empty().then(|| key.clone());
+        properties.insert(key, value);
+    }
+}
+
+/// Read every profile from the shared config and credentials files, honouring
+/// `AWS_CONFIG_FILE` and `AWS_SHARED_CREDENTIALS_FILE`.
+pub fn load() -> Result<BTreeMap<String, Profile>, String> {
+    Ok(profiles(
+        &read_aws_file(aws_file("AWS_CONFIG_FILE", "config"))?,
+        &read_aws_file(aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials"))?,
+    ))
+}
+
+fn profiles(config: &str, credentials: &str) -> BTreeMap<String, Profile> {
+    let mut properties = BTreeMap::new();
+
+    parse(config, true, &mut properties);
+    // Values from the credentials file win, as they do for the SDK
+    parse(credentials, false, &mut properties);
+
+    properties
+        .into_iter()
+        // Such names cannot be written in an s3:// path
+        .filter(|(name, _)| !name.is_empty() && !name.contains(['/', '@']))
+        .map(|(name, properties)| (name.clone(), Profile { name, properties }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(profiles: &BTreeMap<String, Profile>, name: &str) -> Result<String, String> {
+        profiles[name].credential_source(profiles)
+    }
+
+    #[test]
+    fn config_sections_need_the_profile_prefix() {
+        let config = "\
+[default]
+region = eu-west-1
+
+[profile work]
+region = us-east-2
+output = json
+
+[work-without-prefix]
+region = ap-south-1
+
+[sso-session corp]
+sso_region = us-east-1
+";
+        let credentials = "\
+[default]
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = secret
+
+[profile odd]
+aws_access_key_id = AKIDODD
+aws_secret_access_key = secret
+
+[ci]
+aws_access_key_id = AKIDCI
+aws_secret_access_key = secret
+";
+        let profiles = profiles(config, credentials);
+
+        assert_eq!(
+            profiles.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["ci", "default", "profile odd", "work"]
+        );
+        assert_eq!(profiles["default"].region(), Some("eu-west-1"));
+        assert_eq!(profiles["work"].region(), Some("us-east-2"));
+        assert_eq!(profiles["ci"].region(), None);
+
+        let default = profiles["default"].to_account();
+        assert_eq!(default.id, "aws:default");
+        assert_eq!(default.access_key_id, "AKIDDEFAULT");
+        // Secrets stay with the AWS files
+        assert!(default.secret_access_key.is_empty());
+        assert_eq!(default.profile.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn credentials_file_wins() {
+        let profiles = profiles(
+            "[profile work]\nregion = us-east-2\naws_access_key_id = FROMCONFIG\n",
+            "[work]\naws_access_key_id = FROMCREDENTIALS\naws_secret_access_key = s\n",
+        );
+
+        assert_eq!(profiles["work"].region(), Some("us-east-2"));
+        assert_eq!(
+            profiles["work"].to_account().access_key_id,
+            "FROMCREDENTIALS"
+        );
+    }
+
+    #[test]
+    fn nested_s3_settings() {
+        let config = "\
+[profile minio]
+endpoint_url = http://localhost:1
+s3 =
+    endpoint_url = http://localhost:9000
+    addressing_style = virtual
+region = us-east-1
+
+[profile custom]
+endpoint_url = http://localhost:9000
+
+[profile aws]
+region = us-east-1
+";
+        let profiles = profiles(config, "");
+
+        assert_eq!(
+            profiles["minio"].endpoint_url(),
+            Some("http://localhost:9000")
+        );
+        assert!(!profiles["minio"].path_style());
+        // The indented block ends at the next top-level property
+        assert_eq!(profiles["minio"].region(), Some("us-east-1"));
+        assert!(profiles["custom"].path_style());
+        assert!(!profiles["aws"].path_style());
+    }
+
+    #[test]
+    fn skips_comments_and_unusable_names() {
+        let config = "\
+# comment
+; another
+region = ignored outside a section
+[profile a/b]
+region = x
+[profile me@corp]
+region = x
+[profile  spaced ]
+region = eu-central-1
+";
+        let profiles = profiles(config, "");
+
+        assert_eq!(profiles.keys().collect::<Vec<_>>(), ["spaced"]);
+        assert_eq!(profiles["spaced"].region(), Some("eu-central-1"));
+    }
+
+    #[test]
+    fn describes_credential_sources() {
+        let config = "\
+[profile process]
+credential_process = /usr/bin/creds
+
+[profile sso]
+sso_session = corp
+
+[profile legacy-sso]
+sso_start_url = https://corp.awsapps.com/start
+
+[profile ec2]
+role_arn = arn:aws:iam::1:role/ec2
+credential_source = Ec2InstanceMetadata
+
+[profile empty]
+region = us-east-1
+
+[profile orphan-role]
+role_arn = arn:aws:iam::1:role/orphan
+";
+        let credentials = "\
+[keys]
+aws_access_key_id = AKID
+aws_secret_access_key = secret
+
+[half]
+aws_access_key_id = AKID
+";
+        let profiles = profiles(config, credentials);
+
+        assert_eq!(source(&profiles, "process").unwrap(), "credential_process");
+        assert_eq!(source(&profiles, "sso").unwrap(), "AWS SSO");
+        assert_eq!(source(&profiles, "legacy-sso").unwrap(), "AWS SSO");
+        assert_eq!(source(&profiles, "keys").unwrap(), "static keys");
+        assert_eq!(
+            source(&profiles, "ec2").unwrap(),
+            "Role arn:aws:iam::1:role/ec2 using the Ec2InstanceMetadata"
+        );
+        assert_eq!(
+            source(&profiles, "empty").unwrap_err(),
+            "Profile empty has no credentials"
+        );
+        assert_eq!(
+            source(&profiles, "half").unwrap_err(),
+            "Profile half has no credentials"
+        );
+        assert_eq!(
+            source(&profiles, "orphan-role").unwrap_err(),
+            "Profile orphan-role sets role_arn without source_profile or credential_source"
+        );
+    }
+
+    #[test]
+    fn follows_source_profile_chains() {
+        let config = "\
+[profile admin]
+role_arn = arn:aws:iam::3:role/admin
+source_profile = ops
+
+[profile ops]
+role_arn = arn:aws:iam::2:role/ops
+source_profile = base
+
+[profile base]
+sso_session = corp
+
+[profile self]
+role_arn = arn:aws:iam::1:role/self
+source_profile = self
+aws_access_key_id = AKID
+aws_secret_access_key = secret
+
+[profile via-self]
+role_arn = arn:aws:iam::4:role/via
+source_profile = self
+
+[profile dangling]
+role_arn = arn:aws:iam::1:role/dangling
+source_profile = nowhere
+";
+        let profiles = profiles(config, "");
+
+        assert_eq!(
+            source(&profiles, "ops").unwrap(),
+            "Role arn:aws:iam::2:role/ops using AWS SSO"
+        );
+        assert_eq!(
+            source(&profiles, "admin").unwrap(),
+            "Role arn:aws:iam::3:role/admin through 1 roles using AWS SSO"
+        );
+        assert_eq!(
+            source(&profiles, "self").unwrap(),
+            "Role arn:aws:iam::1:role/self using static keys"
+        );
+        assert_eq!(
+            source(&profiles, "via-self").unwrap(),
+            "Role arn:aws:iam::4:role/via through 1 roles using static keys"
+        );
+        assert_eq!(
+            source(&profiles, "dangling").unwrap_err(),
+            "Source profile nowhere not found"
+        );
+    }
+
+    #[test]
+    fn detects_source_profile_loops() {
+        let config = "\
+[profile a]
+role_arn = arn:aws:iam::1:role/a
+source_profile = b
+
+[profile b]
+role_arn = arn:aws:iam::1:role/b
+source_profile = c
+
+[profile c]
+role_arn = arn:aws:iam::1:role/c
+source_profile = a
+
+[profile lonely]
+role_arn = arn:aws:iam::1:role/lonely
+source_profile = lonely
+
+[profile into-loop]
+role_arn = arn:aws:iam::1:role/into
+source_profile = a
+";
+        let profiles = profiles(config, "");
+
+        for name in ["a", "b", "c", "lonely", "into-loop"] {
+            assert_eq!(
+                source(&profiles, name).unwrap_err(),
+                format!("Profile {} has a source_profile loop", name)
+            );
+        }
+    }
+}
@@ -0,0 +1,512 @@
+// Temporary credentials from STS AssumeRole. Sessions are cached per account
+// until shortly before they expire; roles without MFA are then assumed again
+// on the fly, roles with MFA need a new token code from the UI.
+
+use aws_config::BehaviorVersion;
+use aws_credential_types::credential_fn::provide_credentials_fn;
+use aws_credential_types::provider::error::CredentialsError;
+use aws_sdk_s3::config::{Credentials, Region, SharedCredentialsProvider};
+use aws_sdk_s3::error::DisplayErrorContext;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::{Account, DEFAULT_REGION};
+
+/// Start of the error returned when a role session needs an MFA token code,
+/// which the frontend looks for to prompt for one.
+pub const MFA_REQUIRED: &str = "MFA code required";
+
+// Sessions are renewed this long before STS says they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+// MFA sessions cannot be renewed silently, so they are used until the end
+const EXPIRY_SKEW: Duration = Duration::from_secs(30);
+
+const DEFAULT_DURATION_SECS: i32 = 3600;
+
+/// An IAM role an account assumes with its own keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleConfig {
+    pub role_arn: String,
+    pub session_name: Option<String>,
+    pub external_id: Option<String>,
+    /// ARN or serial number of the MFA device. Sessions of such roles are
+    /// started with a token code entered by the user.
+    pub mfa_serial: Option<String>,
+    pub duration_secs: Option<i32>,
+    /// STS endpoint; defaults to the account's endpoint, as local stand-ins
+    /// such as MinIO and LocalStack serve STS next to S3
+    pub sts_endpoint_url: Option<String>,
+}
+
+impl RoleConfig {
+    fn session_name(&self, account: &Account) -> String {
+        let name = self
+            .session_name
+            .clone()
+            .unwrap_or_else(|| format!("s3-finder-{}", account.id));
+        name.chars().take(64).collect()
+    }
+
+    // The credentials may be used up to this long before they expire
+    fn margin(&self) -> Duration {
+        if self.mfa_serial.is_some() {
+            EXPIRY_SKEW
+        } else {
+            REFRESH_MARGIN
+        }
+    }
+}
+
+/// Role sessions of all accounts, in memory only.
+#[derive(Default)]
+pub struct RoleSessions {
+    sessions: Mutex<HashMap<String, Credentials>>,
+}
+
+impl RoleSessions {
+    /// Assume the role of an account and cache the session, returning when it
+    /// expires. `mfa_code` is required for roles with an MFA device.
+    pub async fn start(
+        &self,
+        account: &Account,
+        mfa_code: Option<&str>,
+    ) -> Result<SystemTime, String> {
+        let credentials = assume_role(account, mfa_code).await?;
+        let expiry = credentials
+            .expiry()
+            .ok_or("STS returned credentials without an expiry")?;
+
+        self.sessions
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(account.id.clone(), credentials);
+
+        Ok(expiry)
+    }
+
+    /// Drop the session of one account, after its settings changed.
+    pub fn end(&self, id: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(id);
+        }
+    }
+
+    /// Drop all sessions, as they were obtained with keys from the vault.
+    pub fn clear(&self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.clear();
+        }
+    }
+
+    fn cached(&self, id: &str, margin: Duration) -> Option<Credentials> {
+        let sessions = self.sessions.lock().ok()?;
+        let credentials = sessions.get(id)?;
+        let usable_until = credentials.expiry()?.checked_sub(margin)?;
+
+        (usable_until > SystemTime::now()).then(|| {
+            // Hand out an earlier expiry so the client asks again in time
+            Credentials::new(
+                credentials.access_key_id(),
+                credentials.secret_access_key(),
+                credentials.session_token().map(str::to_string),
+                Some(usable_until),
+                "s3-finder-role",
+            )
+        })
+    }
+
+    async fn credentials(&self, account: &Account) -> Result<Credentials, CredentialsError> {
+        let Some(role) = &account.role else {
+            return Err(CredentialsError::invalid_configuration(
+                "The account does not assume a role",
+            ));
+        };
+
+        if let Some(credentials) = self.cached(&account.id, role.margin()) {
+            return Ok(credentials);
+        }
+
+        if role.mfa_serial.is_some() {
+            return Err(CredentialsError::not_loaded(format!(
+                "{} to start a session for {}",
+                MFA_REQUIRED, account.name
+            )));
+        }
+
+        self.start(account, None)
+            .await
+            .map_err(CredentialsError::provider_error)?;
+
+        self.cached(&account.id, role.margin())
+            .ok_or_else(|| CredentialsError::provider_error("The role session expired at once"))
+    }
+
+    /// Credentials provider for the S3 client of an account with a role.
+    pub fn provider(self: &Arc<Self>, account: &Account) -> SharedCredentialsProvider {
+        let sessions = self.clone();
+        let account = Arc::new(account.clone());
+
+        SharedCredentialsProvider::new(provide_credentials_fn(move || {
+            let sessions = sessions.clone();
+            let account = account.clone();
+            async move { sessions.credentials(&account).await }
+        }))
+    }
+}
+
+async fn assume_role(account: &Account, mfa_code: Option<&str>) -> Result<Credentials, String> {
+    let role = account
+        .role
+        .as_ref()
+        .ok_or("The account does not assume a role")?;
+
+    let base = Credentials::new(
+        &account.access_key_id,
+        &account.secret_access_key,
+        account.session_token.clone(),
+        None,
+        "s3-finder",
+    );
+
+    let mut builder = aws_sdk_sts::config::Builder::new()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new(
+            account
+                .region
+                .clone()
+                .unwrap_or_else(|| DEFAULT_REGION.to_string()),
+        ))
+        .credentials_provider(base);
+
+    if let Some(endpoint) = role
+        .sts_endpoint_url
+        .as_ref()
+        .or(account.endpoint_url.as_ref())
+    {
+        builder = builder.endpoint_url(endpoint);
+    }
+
+    let client = aws_sdk_sts::Client::from_conf(builder.build());
+    let mut request = client
+        .assume_role()
+        .role_arn(&role.role_arn)
+        .role_session_name(role.session_name(account))
+        .duration_seconds(role.duration_secs.unwrap_or(DEFAULT_DURATION_SECS));
+
+    if let Some(external_id) = &role.external_id {
+        request = request.external_id(external_id);
+    }
+
+    if let Some(serial) = &role.mfa_serial {
+        let code = mfa_code
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .ok_or_else(|| format!("{} to start a session for {}", MFA_REQUIRED, account.name))?;
+        request = request.serial_number(serial).token_code(code);
+    }
+
+    let output = request.send().await.map_err(|e| {
+        format!(
+            "Failed to assume role {}: {}",
+            role.role_arn,
+            DisplayErrorContext(&e)
+        )
+    })?;
+
+    let credentials = output.credentials().ok_or("STS returned no credentials")?;
+    let expiry = SystemTime::try_from(*credentials.expiration())
+        .map_err(|e| format!("Failed to read session expiry: {}", e))?;
+
+    Ok(Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        Some(expiry),
+        "s3-finder-role",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_credential_types::provider::ProvideCredentials;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // A stand-in for STS that answers every AssumeRole request with a session
+    // expiring `lifetime` from now and remembers the request bodies
+    struct Sts {
+        endpoint: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Sts {
+        async fn start(lifetime: Duration) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let seen = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let body = read_request(&mut stream).await;
+                    let number = {
+                        let mut seen = seen.lock().unwrap();
+                        seen.push(body);
+                        seen.len()
+                    };
+                    let expiry = chrono::Utc::now() + lifetime;
+                    let xml = format!(
+                        "<AssumeRoleResponse xmlns=\"https://sts.amazonaws.com/doc/2011-06-15/\">\
+                         <AssumeRoleResult><Credentials>\
+                         <AccessKeyId>ASIA{}</AccessKeyId>\
+                         <SecretAccessKey>secret</SecretAccessKey>\
+                         <SessionToken>token</SessionToken>\
+                         <Expiration>{}</Expiration>\
+                         </Credentials></AssumeRoleResult>\
+                         <ResponseMetadata><RequestId>{}</RequestId></ResponseMetadata>\
+                         </AssumeRoleResponse>",
+                        number,
+                        expiry.format("%Y-%m-%dT%H:%M:%SZ"),
+                        number
+                    );
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        xml.len(),
+                        xml
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+
+            Self { endpoint, requests }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&data);
+            let Some(end) = text.find("\r\n\r\n") else {
+                continue;
+            };
+            let length = text[..end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if data.len() >= end + 4 + length {
+                return text[end + 4..].to_string();
+            }
+        }
+
+        String::new()
+    }
+
+    fn account(sts: &Sts, mfa_serial: Option<&str>) -> Account {
+        Account {
+            id: "work".to_string(),
+            name: "Work".to_string(),
+            endpoint_url: None,
+            region: Some("us-east-1".to_string()),
+            path_style: false,
+            access_key_id: "AKIDBASE".to_string(),
+            secret_access_key: "base-secret".to_string(),
+            session_token: None,
+            default_bucket: None,
+            profile: None,
+            role: Some(RoleConfig {
+                role_arn: "arn:aws:iam::123456789012:role/reader".to_string(),
+                session_name: None,
+                external_id: Some("outside".to_string()),
+                mfa_serial: mfa_serial.map(str::to_string),
+                duration_secs: None,
+                sts_endpoint_url: Some(sts.endpoint.clone()),
+            }),
+        }
+    }
+
+    fn cache(sessions: &RoleSessions, id: &str, key: &str, lifetime: Duration) {
+        sessions.sessions.lock().unwrap().insert(
+            id.to_string(),
+            Credentials::new(
+                key,
+                "secret",
+                Some("token".to_string()),
+                Some(SystemTime::now() + lifetime),
+                "test",
+            ),
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_sessions_are_reused() {
+        let sts = Sts::start(Duration::from_secs(3600)).await;
+        let sessions = Arc::new(RoleSessions::default());
+        let account = account(&sts, None);
+        cache(&sessions, "work", "ASIACACHED", Duration::from_secs(3600));
+
+        let credentials = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIACACHED");
+        assert_eq!(credentials.session_token(), Some("token"));
+        // Handed out with the margin taken off
+        let left = credentials
+            .expiry()
+            .unwrap()
+            .duration_since(SystemTime::now())
+            .unwrap();
+        assert!(left <= Duration::from_secs(3600) - REFRESH_MARGIN);
+        assert!(sts.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sessions_are_renewed_inside_the_margin() {
+        let sts = Sts::start(Duration::from_secs(3600)).await;
+        let sessions = Arc::new(RoleSessions::default());
+        let account = account(&sts, None);
+        cache(
+            &sessions,
+            "work",
+            "ASIAOLD",
+            REFRESH_MARGIN - Duration::from_secs(10),
+        );
+
+        let credentials = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIA1");
+
+        let requests = sts.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("Action=AssumeRole"));
+        assert!(requests[0].contains("RoleSessionName=s3-finder-work"));
+        assert!(requests[0].contains("ExternalId=outside"));
+        assert!(!requests[0].contains("TokenCode"));
+
+        // The new session is cached
+        let again = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(again.access_key_id(), "ASIA1");
+        assert_eq!(sts.requests().len(), 1);
+
+        sessions.end("work");
+        let renewed = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(renewed.access_key_id(), "ASIA2");
+    }
+
+    #[tokio::test]
+    async fn sessions_too_short_for_the_margin_fail() {
+        let sts = Sts::start(Duration::from_secs(60)).await;
+        let sessions = Arc::new(RoleSessions::default());
+        let account = account(&sts, None);
+
+        assert!(sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .is_err());
+        assert_eq!(sts.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn mfa_roles_need_a_code() {
+        let sts = Sts::start(Duration::from_secs(3600)).await;
+        let sessions = Arc::new(RoleSessions::default());
+        let account = account(&sts, Some("arn:aws:iam::123456789012:mfa/me"));
+
+        let error = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap_err();
+        assert!(DisplayErrorContext(&error)
+            .to_string()
+            .contains(&format!("{} to start a session for Work", MFA_REQUIRED)));
+
+        for code in [None, Some(""), Some("  ")] {
+            assert!(sessions
+                .start(&account, code)
+                .await
+                .unwrap_err()
+                .starts_with(MFA_REQUIRED));
+        }
+        assert!(sts.requests().is_empty());
+
+        sessions.start(&account, Some(" 123456 ")).await.unwrap();
+        let requests = sts.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("SerialNumber=arn%3Aaws%3Aiam%3A%3A123456789012%3Amfa%2Fme"));
+        assert!(requests[0].contains("TokenCode=123456"));
+
+        let credentials = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIA1");
+    }
+
+    #[tokio::test]
+    async fn mfa_sessions_are_used_until_they_expire() {
+        let sts = Sts::start(Duration::from_secs(3600)).await;
+        let sessions = Arc::new(RoleSessions::default());
+        let account = account(&sts, Some("arn:aws:iam::123456789012:mfa/me"));
+
+        // Well inside the refresh margin of roles without MFA
+        cache(&sessions, "work", "ASIAMFA", Duration::from_secs(120));
+        let credentials = sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIAMFA");
+
+        cache(
+            &sessions,
+            "work",
+            "ASIAMFA",
+            EXPIRY_SKEW - Duration::from_secs(5),
+        );
+        assert!(sessions
+            .provider(&account)
+            .provide_credentials()
+            .await
+            .is_err());
+
+        sessions.clear();
+        assert!(sessions.sessions.lock().unwrap().is_empty());
+        assert!(sts.requests().is_empty());
+    }
+}
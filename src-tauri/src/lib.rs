@@ -1,81 +1,46 @@
 use base64::{engine::general_purpose, Engine as _};
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::{Path, PathBuf};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FileItem {
-    pub name: String,
-    pub path: String,
-    pub is_dir: bool,
-    pub size: Option<u64>,
-    pub modified: Option<String>,
-    pub icon: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DirectoryContents {
-    pub current_path: String,
-    pub parent_path: Option<String>,
-    pub items: Vec<FileItem>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FilePreview {
-    pub file_type: String,
-    pub content: String,
-    pub size: u64,
-    pub encoding: String, // "text" or "base64"
-}
-
-// Security: Validate and sanitize file paths to prevent directory traversal attacks
-fn validate_path(path: &str) -> Result<PathBuf, String> {
-    let path = Path::new(path);
-
-    // Resolve the canonical path to prevent directory traversal
-    let canonical = path
-        .canonicalize()
-        .map_err(|_| "Invalid or inaccessible path".to_string())?;
-
-    // Get home directory for validation
-    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory".to_string())?;
-
-    // Allow access to home directory and its subdirectories
-    if canonical.starts_with(&home_dir) {
-        return Ok(canonical);
-    }
-
-    // Allow access to common system directories (read-only)
-    let allowed_system_paths = [
-        "/Applications",
-        "/System/Applications",
-        "/usr/local",
-        "/opt",
-    ];
-
-    for allowed_path in &allowed_system_paths {
-        if canonical.starts_with(allowed_path) {
-            return Ok(canonical);
-        }
-    }
-
-    Err("Access denied: Path is outside allowed directories".to_string())
-}
-
-// Validate path for write operations (more restrictive)
-fn validate_write_path(path: &str) -> Result<PathBuf, String> {
-    let canonical = validate_path(path)?;
-
-    // Only allow write operations in home directory
-    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory".to_string())?;
-
-    if !canonical.starts_with(&home_dir) {
-        return Err("Write access denied: Only home directory is writable".to_string());
-    }
-
-    Ok(canonical)
-}
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use zeroize::Zeroizing;
+
+mod accounts;
+mod storage;
+mod sync;
+mod transfer;
+mod types;
+mod utils;
+mod vault;
+
+use accounts::session::RoleSessions;
+use accounts::{AccountInfo, AccountInput};
+use storage::archive::{
+    is_archive_class, validate_restore, validate_storage_class, PendingRestores, RESTORE_EVENT,
+};
+use storage::bucket::{BucketProperties, NewBucket, PropertyDiff};
+use storage::encryption::{CustomerKey, CustomerKeys, Encryption, EncryptionSettings};
+use storage::lifecycle::LifecycleConfiguration;
+use storage::presign::{PresignHistory, PresignedUrl};
+use storage::s3::S3Storage;
+use storage::trash::{self, Restored, TrashItem, Trashed};
+use storage::watch::DirectoryWatcher;
+use storage::{
+    s3_uri, validate_name, StorageManager, StorageUri, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+use sync::state::SyncState;
+use sync::watch::{WatchManager, WatchStatus};
+use sync::{SyncOptions, SyncPlan, SyncRun};
+use transfer::manager::{destination_for, transfer_item};
+use transfer::{
+    no_progress, DeleteJob, DeleteManager, DownloadState, MultipartUploader, RangedDownloader,
+    TransferJob, TransferKind, TransferManager, TransferSettings, UploadState,
+};
+use types::{DirectoryContents, FileItem, FilePreview, ObjectMetadata, ObjectPatch};
+use utils::get_file_type;
+use vault::{VaultStatus, VAULT_EVENT};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -84,81 +49,78 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn read_directory(path: String) -> Result<DirectoryContents, String> {
-    // Validate path for security
-    let dir_path = validate_path(&path)?;
-
-    if !dir_path.is_dir() {
-        return Err("Path is not a directory".to_string());
-    }
+async fn read_directory(
+    path: String,
+    continuation_token: Option<String>,
+    page_size: Option<usize>,
+    storage: State<'_, StorageManager>,
+) -> Result<DirectoryContents, String> {
+    let page_size = page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    storage
+        .provider(&path)
+        .await?
+        .list_page(&path, continuation_token, page_size)
+        .await
+}
 
-    let mut items = Vec::new();
-
-    match fs::read_dir(&dir_path) {
-        Ok(entries) => {
-            for entry in entries {
-                match entry {
-                    Ok(entry) => {
-                        let path = entry.path();
-                        let metadata = entry.metadata().ok();
-
-                        let name = path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("Unknown")
-                            .to_string();
-
-                        let is_dir = path.is_dir();
-                        let size =
-                            metadata
-                                .as_ref()
-                                .and_then(|m| if !is_dir { Some(m.len()) } else { None });
-
-                        let modified =
-                            metadata
-                                .as_ref()
-                                .and_then(|m| m.modified().ok())
-                                .and_then(|time| {
-                                    let datetime: DateTime<Utc> = time.into();
-                                    Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-                                });
-
-                        let icon = if is_dir {
-                            "folder".to_string()
-                        } else {
-                            get_file_icon(&name)
-                        };
-
-                        items.push(FileItem {
-                            name,
-                            path: path.to_string_lossy().to_string(),
-                            is_dir,
-                            size,
-                            modified,
-                            icon,
-                        });
-                    }
-                    Err(_) => continue,
-                }
-            }
-        }
-        Err(e) => return Err(format!("Failed to read directory: {}", e)),
-    }
+/// Watch the directories shown as columns, replacing the previous set, and
+/// push their changes as `directory-changed` events.
+#[tauri::command]
+fn watch_directories(
+    paths: Vec<String>,
+    watcher: State<'_, DirectoryWatcher>,
+) -> Result<(), String> {
+    watcher.watch(&paths)
+}
 
-    // Sort items: directories first, then files, both alphabetically
-    items.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
+/// List one page of the object versions and delete markers below an S3 prefix.
+#[tauri::command]
+async fn read_versions(
+    path: String,
+    continuation_token: Option<String>,
+    page_size: Option<usize>,
+    storage: State<'_, StorageManager>,
+) -> Result<DirectoryContents, String> {
+    let page_size = page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    storage
+        .s3_for(&path)
+        .await?
+        .list_versions_page(&path, continuation_token, page_size)
+        .await
+}
 
-    let parent_path = dir_path.parent().map(|p| p.to_string_lossy().to_string());
+#[tauri::command]
+async fn restore_version(
+    path: String,
+    version_id: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<(), String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .restore_version(&path, &version_id, transfer_settings(&settings)?)
+        .await
+}
 
-    Ok(DirectoryContents {
-        current_path: dir_path.to_string_lossy().to_string(),
-        parent_path,
-        items,
-    })
+/// Permanently delete one version of an object, or remove a delete marker.
+#[tauri::command]
+async fn delete_version(
+    path: String,
+    version_id: String,
+    storage: State<'_, StorageManager>,
+) -> Result<(), String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .delete_version(&path, &version_id)
+        .await
 }
 
 #[tauri::command]
@@ -170,245 +132,580 @@ async fn get_home_directory() -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn create_folder(path: String, name: String) -> Result<String, String> {
-    // Validate parent path for write access
-    let parent_path = validate_write_path(&path)?;
+async fn create_folder(
+    path: String,
+    name: String,
+    storage: State<'_, StorageManager>,
+) -> Result<String, String> {
+    validate_name(&name).map_err(|_| "Invalid folder name".to_string())?;
 
-    // Validate folder name to prevent injection
-    if name.is_empty() || name.contains('/') || name.contains('\\') || name == "." || name == ".." {
-        return Err("Invalid folder name".to_string());
+    let provider = storage.provider(&path).await?;
+    let new_folder_path = provider.join(&path, &name);
+
+    if provider.exists(&new_folder_path).await? {
+        return Err("Folder already exists".to_string());
     }
 
-    let new_folder_path = parent_path.join(&name);
+    provider.create_dir(&new_folder_path).await?;
 
-    if new_folder_path.exists() {
-        return Err("Folder already exists".to_string());
+    Ok(new_folder_path)
+}
+
+/// Delete an item. Local items are moved to the Trash; deleting them for good
+/// takes `delete_items`.
+#[tauri::command]
+async fn delete_item(path: String, storage: State<'_, StorageManager>) -> Result<(), String> {
+    match StorageUri::parse(&path)? {
+        StorageUri::Local(_) => trash::move_to_trash(&path).map(|_| ()),
+        StorageUri::S3 { .. } => storage.provider(&path).await?.delete(&path).await,
     }
+}
 
-    match fs::create_dir(&new_folder_path) {
-        Ok(_) => Ok(new_folder_path.to_string_lossy().to_string()),
-        Err(e) => Err(format!("Failed to create folder: {}", e)),
+/// Move local items to the Trash, returning them as the Trash lists them so
+/// the move can be undone. An item that cannot be moved does not keep the
+/// others from going.
+#[tauri::command]
+async fn move_to_trash(paths: Vec<String>) -> Result<Trashed, String> {
+    let mut trashed = Trashed::default();
+    for path in &paths {
+        let result = StorageUri::parse(path).and_then(|uri| match uri {
+            StorageUri::Local(_) => trash::move_to_trash(path),
+            StorageUri::S3 { .. } => Err("S3 objects cannot be moved to the Trash".to_string()),
+        });
+        match result {
+            Ok(item) => trashed.items.push(item),
+            Err(e) => trashed.errors.push(format!("{}: {}", path, e)),
+        }
     }
+
+    Ok(trashed)
 }
 
 #[tauri::command]
-async fn delete_item(path: String) -> Result<(), String> {
-    // Validate path for write access
-    let item_path = validate_write_path(&path)?;
+async fn list_trash() -> Result<Vec<TrashItem>, String> {
+    trash::list()
+}
 
-    if item_path.is_dir() {
-        match fs::remove_dir_all(&item_path) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to delete folder: {}", e)),
-        }
-    } else {
-        match fs::remove_file(&item_path) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to delete file: {}", e)),
+/// Put items back where they were trashed from and return their paths. An
+/// item that cannot be put back stays in the Trash, the others are restored.
+#[tauri::command]
+async fn restore_from_trash(ids: Vec<String>) -> Result<Restored, String> {
+    let mut restored = Restored::default();
+    for id in &ids {
+        match trash::restore(id) {
+            Ok(path) => restored.paths.push(path),
+            Err(e) => restored.errors.push(format!("{}: {}", id, e)),
         }
     }
+
+    Ok(restored)
 }
 
+/// Delete items in the Trash for good and return the items that could not
+/// be deleted, as `id: error`.
 #[tauri::command]
-async fn rename_item(old_path: String, new_name: String) -> Result<String, String> {
-    // Validate old path for write access
-    let old_item_path = validate_write_path(&old_path)?;
+async fn delete_from_trash(ids: Vec<String>) -> Result<Vec<String>, String> {
+    Ok(ids
+        .iter()
+        .filter_map(|id| trash::delete(id).err().map(|e| format!("{}: {}", id, e)))
+        .collect())
+}
 
-    // Validate new name to prevent injection
-    if new_name.is_empty()
-        || new_name.contains('/')
-        || new_name.contains('\\')
-        || new_name == "."
-        || new_name == ".."
-    {
-        return Err("Invalid file name".to_string());
-    }
+/// Delete everything in the Trash for good and return the entries that could
+/// not be deleted.
+#[tauri::command]
+async fn empty_trash() -> Result<Vec<String>, String> {
+    trash::empty()
+}
 
-    let parent = old_item_path
-        .parent()
+/// Create a bucket for the account `path` belongs to.
+#[tauri::command]
+async fn create_bucket(
+    path: String,
+    name: String,
+    options: NewBucket,
+    storage: State<'_, StorageManager>,
+) -> Result<String, String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .create_bucket(&name, &options)
+        .await
+}
+
+/// Delete an empty bucket.
+#[tauri::command]
+async fn delete_bucket(path: String, storage: State<'_, StorageManager>) -> Result<(), String> {
+    storage.s3_for(&path).await?.delete_bucket(&path).await
+}
+
+/// Delete everything in a bucket and then the bucket, in the background like
+/// other deletes.
+#[tauri::command]
+async fn empty_and_delete_bucket(
+    path: String,
+    deletes: State<'_, DeleteManager>,
+) -> Result<DeleteJob, String> {
+    deletes.start_bucket(path)
+}
+
+#[tauri::command]
+async fn rename_item(
+    old_path: String,
+    new_name: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<String, String> {
+    validate_name(&new_name).map_err(|_| "Invalid file name".to_string())?;
+
+    let provider = storage.provider(&old_path).await?;
+    let parent = provider
+        .parent(&old_path)
         .ok_or("Cannot determine parent directory")?;
-    let new_item_path = parent.join(&new_name);
+    let new_item_path = provider.join(&parent, &new_name);
 
-    if new_item_path.exists() {
+    if provider.exists(&new_item_path).await? {
         return Err("An item with this name already exists".to_string());
     }
 
-    match fs::rename(&old_item_path, &new_item_path) {
-        Ok(_) => Ok(new_item_path.to_string_lossy().to_string()),
-        Err(e) => Err(format!("Failed to rename item: {}", e)),
-    }
+    provider
+        .rename(&old_path, &new_item_path, transfer_settings(&settings)?)
+        .await?;
+
+    Ok(new_item_path)
+}
+
+fn transfer_settings(settings: &Mutex<TransferSettings>) -> Result<TransferSettings, String> {
+    settings
+        .lock()
+        .map(|s| s.clone())
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn copy_item(source_path: String, dest_dir: String) -> Result<String, String> {
-    let source = Path::new(&source_path);
-    let dest_parent = Path::new(&dest_dir);
+async fn copy_item(
+    source_path: String,
+    dest_dir: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<String, String> {
+    let dest_path = destination_for(&storage, &source_path, &dest_dir).await?;
+
+    let settings = TransferSettings {
+        encryption: Encryption::default_at(&dest_dir)?,
+        ..transfer_settings(&settings)?
+    };
 
-    if !source.exists() {
-        return Err("Source item does not exist".to_string());
-    }
+    transfer_item(
+        &storage,
+        settings,
+        &source_path,
+        &dest_path,
+        TransferKind::Copy,
+        true,
+        no_progress(),
+    )
+    .await?;
+
+    Ok(dest_path)
+}
 
-    if !dest_parent.exists() || !dest_parent.is_dir() {
-        return Err("Destination directory does not exist".to_string());
-    }
+#[tauri::command]
+async fn move_item(
+    source_path: String,
+    dest_dir: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<String, String> {
+    let dest_path = destination_for(&storage, &source_path, &dest_dir).await?;
+
+    let settings = TransferSettings {
+        encryption: Encryption::default_at(&dest_dir)?,
+        ..transfer_settings(&settings)?
+    };
 
-    let file_name = source.file_name().ok_or("Cannot determine file name")?;
-    let dest_path = dest_parent.join(file_name);
+    transfer_item(
+        &storage,
+        settings,
+        &source_path,
+        &dest_path,
+        TransferKind::Move,
+        true,
+        no_progress(),
+    )
+    .await?;
+
+    Ok(dest_path)
+}
 
-    if dest_path.exists() {
-        return Err("An item with this name already exists in destination".to_string());
-    }
+#[tauri::command]
+async fn get_item_info(
+    path: String,
+    storage: State<'_, StorageManager>,
+) -> Result<FileItem, String> {
+    let provider = storage.provider(&path).await?;
 
-    if source.is_dir() {
-        copy_dir_recursive(source, &dest_path)?;
-    } else {
-        fs::copy(source, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+    if !provider.exists(&path).await? {
+        return Err("Item does not exist".to_string());
     }
 
-    Ok(dest_path.to_string_lossy().to_string())
+    provider.stat(&path).await
 }
 
+/// Everything the inspector shows about an S3 object.
 #[tauri::command]
-async fn move_item(source_path: String, dest_dir: String) -> Result<String, String> {
-    let source = Path::new(&source_path);
-    let dest_parent = Path::new(&dest_dir);
+async fn get_object_metadata(
+    path: String,
+    version_id: Option<String>,
+    storage: State<'_, StorageManager>,
+) -> Result<ObjectMetadata, String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .object_metadata(&path, version_id.as_deref())
+        .await
+}
+
+// Run `action` on every S3 object, `concurrency` at a time, and report every
+// object it failed for in one error
+async fn for_each_object<F, Fut>(
+    storage: &StorageManager,
+    paths: Vec<String>,
+    concurrency: usize,
+    verb: &str,
+    action: F,
+) -> Result<(), String>
+where
+    F: Fn(Arc<S3Storage>, String) -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let total = paths.len();
+    let mut tasks = JoinSet::new();
+
+    for path in paths {
+        let s3 = storage.s3_for(&path).await?;
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| e.to_string())?;
+        let task = action(s3, path.clone());
+
+        tasks.spawn(async move {
+            let _permit = permit;
+            task.await.map_err(|e| format!("{}: {}", path, e))
+        });
+    }
 
-    if !source.exists() {
-        return Err("Source item does not exist".to_string());
+    let mut failures = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Err(e) = result.map_err(|e| format!("Task failed: {}", e))? {
+            failures.push(e);
+        }
     }
 
-    if !dest_parent.exists() || !dest_parent.is_dir() {
-        return Err("Destination directory does not exist".to_string());
+    if failures.is_empty() {
+        Ok(())
+    } else if total == 1 {
+        Err(failures.remove(0))
+    } else {
+        Err(format!(
+            "Failed to {} {} of {} objects:\n{}",
+            verb,
+            failures.len(),
+            total,
+            failures.join("\n")
+        ))
     }
+}
 
-    let file_name = source.file_name().ok_or("Cannot determine file name")?;
-    let dest_path = dest_parent.join(file_name);
+/// Apply the same metadata and tag changes to every given S3 object.
+#[tauri::command]
+async fn update_objects(
+    paths: Vec<String>,
+    patch: ObjectPatch,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<(), String> {
+    let patch = Arc::new(patch);
+    let settings = transfer_settings(&settings)?;
+
+    for_each_object(
+        &storage,
+        paths,
+        settings.concurrency,
+        "update",
+        move |s3, path| {
+            let patch = patch.clone();
+            let settings = settings.clone();
+            async move { s3.update_object(&path, &patch, settings).await }
+        },
+    )
+    .await
+}
 
-    if dest_path.exists() {
-        return Err("An item with this name already exists in destination".to_string());
+/// Move objects, and every object below the given prefixes, to another
+/// storage class. Returns how many objects were changed.
+#[tauri::command]
+async fn change_storage_class(
+    paths: Vec<String>,
+    storage_class: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<usize, String> {
+    validate_storage_class(&storage_class)?;
+
+    let mut objects = Vec::new();
+    for path in &paths {
+        let s3 = storage.s3_for(path).await?;
+        objects.extend(
+            s3.objects_below(path)
+                .await?
+                .into_iter()
+                .map(|(path, _)| path),
+        );
     }
+    let total = objects.len();
 
-    match fs::rename(source, &dest_path) {
-        Ok(_) => Ok(dest_path.to_string_lossy().to_string()),
-        Err(e) => Err(format!("Failed to move item: {}", e)),
+    let patch = Arc::new(ObjectPatch {
+        storage_class: Some(storage_class),
+        ..Default::default()
+    });
+    let settings = transfer_settings(&settings)?;
+    for_each_object(
+        &storage,
+        objects,
+        settings.concurrency,
+        "change",
+        move |s3, path| {
+            let patch = patch.clone();
+            let settings = settings.clone();
+            async move { s3.update_object(&path, &patch, settings).await }
+        },
+    )
+    .await?;
+
+    Ok(total)
+}
+
+/// Request restores of archived objects, including the archived objects
+/// below the given prefixes, and keep checking until they are readable.
+/// Returns how many restores were requested.
+#[tauri::command]
+async fn restore_objects(
+    paths: Vec<String>,
+    days: i32,
+    tier: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+    pending: State<'_, PendingRestores>,
+) -> Result<usize, String> {
+    validate_restore(days, &tier)?;
+
+    let mut objects = Vec::new();
+    for path in &paths {
+        let s3 = storage.s3_for(path).await?;
+        objects.extend(
+            s3.objects_below(path)
+                .await?
+                .into_iter()
+                // A selected object has no class yet; S3 refuses it if it is not archived
+                .filter(|(_, class)| class.as_deref().is_none_or(is_archive_class))
+                .map(|(path, _)| path),
+        );
+    }
+    if objects.is_empty() {
+        return Err("Nothing there is archived".to_string());
     }
+    let total = objects.len();
+
+    // Restores that fail are dropped by the first check, as nothing is running
+    pending.add(objects.clone())?;
+
+    let tier = Arc::new(tier);
+    for_each_object(
+        &storage,
+        objects,
+        transfer_settings(&settings)?.concurrency,
+        "restore",
+        move |s3, path| {
+            let tier = tier.clone();
+            async move { s3.restore_object(&path, days, &tier).await }
+        },
+    )
+    .await?;
+
+    Ok(total)
 }
 
+/// Sign a GET or PUT link to an S3 object and keep it in the history.
 #[tauri::command]
-async fn get_item_info(path: String) -> Result<FileItem, String> {
-    let item_path = Path::new(&path);
+async fn presign_url(
+    path: String,
+    method: String,
+    expires_in_secs: u64,
+    storage: State<'_, StorageManager>,
+    history: State<'_, PresignHistory>,
+) -> Result<PresignedUrl, String> {
+    let link = storage
+        .s3_for(&path)
+        .await?
+        .presign_url(&path, &method, expires_in_secs)
+        .await?;
+
+    history.add(link.clone())?;
+
+    Ok(link)
+}
 
-    if !item_path.exists() {
-        return Err("Item does not exist".to_string());
-    }
+#[tauri::command]
+fn list_presigned_urls(history: State<'_, PresignHistory>) -> Result<Vec<PresignedUrl>, String> {
+    history.list()
+}
 
-    let metadata = item_path
-        .metadata()
-        .map_err(|e| format!("Failed to get metadata: {}", e))?;
+#[tauri::command]
+fn clear_presigned_urls(history: State<'_, PresignHistory>) -> Result<(), String> {
+    history.clear()
+}
 
-    let name = item_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("Unknown")
-        .to_string();
+#[tauri::command]
+async fn get_bucket_properties(
+    path: String,
+    storage: State<'_, StorageManager>,
+) -> Result<BucketProperties, String> {
+    storage.s3_for(&path).await?.bucket_properties(&path).await
+}
 
-    let is_dir = item_path.is_dir();
-    let size = if !is_dir { Some(metadata.len()) } else { None };
+/// Validate edited bucket properties and show how they differ from the bucket.
+#[tauri::command]
+async fn preview_bucket_properties(
+    path: String,
+    properties: BucketProperties,
+    storage: State<'_, StorageManager>,
+) -> Result<Vec<PropertyDiff>, String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .preview_bucket_properties(&path, &properties)
+        .await
+}
 
-    let modified = metadata.modified().ok().and_then(|time| {
-        let datetime: DateTime<Utc> = time.into();
-        Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-    });
+#[tauri::command]
+async fn put_bucket_properties(
+    path: String,
+    properties: BucketProperties,
+    storage: State<'_, StorageManager>,
+) -> Result<(), String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .put_bucket_properties(&path, &properties)
+        .await
+}
 
-    let icon = if is_dir {
-        "folder".to_string()
-    } else {
-        get_file_icon(&name)
-    };
+#[tauri::command]
+async fn get_bucket_lifecycle(
+    path: String,
+    storage: State<'_, StorageManager>,
+) -> Result<LifecycleConfiguration, String> {
+    storage.s3_for(&path).await?.bucket_lifecycle(&path).await
+}
 
-    Ok(FileItem {
-        name,
-        path: path.clone(),
-        is_dir,
-        size,
-        modified,
-        icon,
-    })
+/// Replace the lifecycle rules of a bucket after validating them.
+#[tauri::command]
+async fn put_bucket_lifecycle(
+    path: String,
+    configuration: LifecycleConfiguration,
+    storage: State<'_, StorageManager>,
+) -> Result<(), String> {
+    storage
+        .s3_for(&path)
+        .await?
+        .put_bucket_lifecycle(&path, &configuration)
+        .await
 }
 
+/// Render lifecycle rules as `json` or `xml` for the raw editor.
 #[tauri::command]
-async fn search_files(directory: String, query: String) -> Result<Vec<FileItem>, String> {
-    // Validate directory path for security
-    let dir_path = validate_path(&directory)?;
+fn format_lifecycle(
+    configuration: LifecycleConfiguration,
+    format: String,
+) -> Result<String, String> {
+    configuration.format(&format)
+}
 
-    if !dir_path.is_dir() {
-        return Err("Path is not a directory".to_string());
-    }
+#[tauri::command]
+fn parse_lifecycle(text: String, format: String) -> Result<LifecycleConfiguration, String> {
+    LifecycleConfiguration::parse(&text, &format)
+}
 
+#[tauri::command]
+async fn search_files(
+    directory: String,
+    query: String,
+    storage: State<'_, StorageManager>,
+) -> Result<Vec<FileItem>, String> {
     if query.trim().is_empty() {
         return Err("Search query cannot be empty".to_string());
     }
 
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
-
-    // Search recursively in the directory
-    search_directory_recursive(&dir_path, &query_lower, &mut results)?;
-
-    // Sort results: directories first, then files, both alphabetically
-    results.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
-
-    Ok(results)
+    match StorageUri::parse(&directory)? {
+        StorageUri::Local(_) => storage.local().search(&directory, &query),
+        _ => Err("Search is only supported for local folders".to_string()),
+    }
 }
 
 #[tauri::command]
-async fn preview_file(path: String) -> Result<FilePreview, String> {
-    // Validate path for security
-    let file_path = validate_path(&path)?;
-
-    if !file_path.is_file() {
+async fn preview_file(
+    path: String,
+    storage: State<'_, StorageManager>,
+) -> Result<FilePreview, String> {
+    let provider = storage.provider(&path).await?;
+    let item = provider.stat(&path).await?;
+
+    if item.is_dir {
         return Err("Path is not a file".to_string());
     }
 
-    let metadata = file_path
-        .metadata()
-        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    if item.archived {
+        return Err(format!(
+            "{} is archived in {} and has to be restored before it can be previewed",
+            item.name,
+            item.storage_class.as_deref().unwrap_or("an archive tier")
+        ));
+    }
 
-    let size = metadata.len();
+    let size = item.size.unwrap_or(0);
 
     // Limit file size for preview (10MB max)
     if size > 10 * 1024 * 1024 {
         return Err("File too large for preview (max 10MB)".to_string());
     }
 
-    let filename = file_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("Unknown");
-
-    let extension = filename.split('.').last().unwrap_or("").to_lowercase();
+    let extension = item
+        .name
+        .split('.')
+        .next_back()
+        .unwrap_or("")
+        .to_lowercase();
     let file_type = get_file_type(&extension);
 
     match file_type.as_str() {
         "text" => {
-            // Read as text file
-            match fs::read_to_string(&file_path) {
+            let bytes = provider.read_range(&path, 0, None).await?;
+
+            match String::from_utf8(bytes) {
                 Ok(content) => Ok(FilePreview {
                     file_type,
                     content,
                     size,
                     encoding: "text".to_string(),
                 }),
-                Err(_) => {
-                    // If UTF-8 reading fails, try reading as binary and show hex preview
-                    let bytes =
-                        fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
-
-                    let hex_content = bytes
+                Err(e) => {
+                    // If UTF-8 decoding fails, show hex preview instead
+                    let hex_content = e
+                        .as_bytes()
                         .iter()
                         .take(1024) // Show first 1KB as hex
                         .map(|b| format!("{:02x}", b))
@@ -426,8 +723,10 @@ async fn preview_file(path: String) -> Result<FilePreview, String> {
         }
         "image" => {
             // Read as binary and encode to base64
-            let bytes =
-                fs::read(&file_path).map_err(|e| format!("Failed to read image file: {}", e))?;
+            let bytes = provider
+                .read_range(&path, 0, None)
+                .await
+                .map_err(|e| format!("Failed to read image file: {}", e))?;
 
             let base64_content = general_purpose::STANDARD.encode(&bytes);
 
@@ -442,152 +741,620 @@ async fn preview_file(path: String) -> Result<FilePreview, String> {
     }
 }
 
-fn search_directory_recursive(
-    dir: &Path,
-    query: &str,
-    results: &mut Vec<FileItem>,
+#[tauri::command]
+async fn get_transfer_settings(
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<TransferSettings, String> {
+    transfer_settings(&settings)
+}
+
+#[tauri::command]
+async fn update_transfer_settings(
+    new_settings: TransferSettings,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<TransferSettings, String> {
+    let new_settings = new_settings.normalized();
+    new_settings.save()?;
+
+    *settings.lock().map_err(|e| e.to_string())? = new_settings.clone();
+
+    Ok(new_settings)
+}
+
+// Recorded uploads and downloads remember their account, and have to be resumed
+// or aborted through that account's connection
+async fn uploader_for(
+    id: &str,
+    storage: &StorageManager,
+    settings: &Mutex<TransferSettings>,
+) -> Result<MultipartUploader, String> {
+    let state = MultipartUploader::open_store()?
+        .load(id)
+        .ok_or("Upload not found")?;
+    let s3 = storage.s3(state.account.as_deref()).await?;
+
+    MultipartUploader::new(&s3, transfer_settings(settings)?)
+}
+
+async fn downloader_for(
+    id: &str,
+    storage: &StorageManager,
+    settings: &Mutex<TransferSettings>,
+) -> Result<RangedDownloader, String> {
+    let state = RangedDownloader::open_store()?
+        .load(id)
+        .ok_or("Download not found")?;
+    let s3 = storage.s3(state.account.as_deref()).await?;
+
+    RangedDownloader::new(&s3, transfer_settings(settings)?)
+}
+
+#[tauri::command]
+async fn list_incomplete_uploads() -> Result<Vec<UploadState>, String> {
+    MultipartUploader::open_store()?.list()
+}
+
+/// Queue the rest of a recorded upload. It runs like any other transfer and
+/// picks up from the parts S3 already has.
+#[tauri::command]
+async fn resume_upload(
+    id: String,
+    storage: State<'_, StorageManager>,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    let state = MultipartUploader::open_store()?
+        .load(&id)
+        .ok_or("Upload not found")?;
+    let dest = s3_uri(state.account.as_deref(), &state.bucket, &state.key);
+    let dest_dir = storage
+        .provider(&dest)
+        .await?
+        .parent(&dest)
+        .ok_or("Upload has no destination")?;
+
+    transfers.enqueue_to(state.source, None, dest_dir, dest, state.encryption)
+}
+
+#[tauri::command]
+async fn abort_upload(
+    id: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
 ) -> Result<(), String> {
-    match fs::read_dir(dir) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    let metadata = entry.metadata().ok();
-
-                    let name = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("Unknown")
-                        .to_string();
-
-                    // Check if filename contains the search query
-                    if name.to_lowercase().contains(query) {
-                        let is_dir = path.is_dir();
-                        let size =
-                            metadata
-                                .as_ref()
-                                .and_then(|m| if !is_dir { Some(m.len()) } else { None });
-
-                        let modified =
-                            metadata
-                                .as_ref()
-                                .and_then(|m| m.modified().ok())
-                                .and_then(|time| {
-                                    let datetime: DateTime<Utc> = time.into();
-                                    Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-                                });
-
-                        let icon = if is_dir {
-                            "folder".to_string()
-                        } else {
-                            get_file_icon(&name)
-                        };
-
-                        results.push(FileItem {
-                            name,
-                            path: path.to_string_lossy().to_string(),
-                            is_dir,
-                            size,
-                            modified,
-                            icon,
-                        });
-                    }
-
-                    // Recursively search subdirectories
-                    if path.is_dir() {
-                        // Limit recursion depth to prevent infinite loops and performance issues
-                        if results.len() < 1000 {
-                            // Limit results to prevent memory issues
-                            let _ = search_directory_recursive(&path, query, results);
-                        }
-                    }
-                }
-            }
-        }
-        Err(_) => {
-            // Silently ignore directories we can't read (permission issues, etc.)
-        }
-    }
+    let uploader = uploader_for(&id, &storage, &settings).await?;
+
+    uploader.abort(&id).await
+}
+
+#[tauri::command]
+async fn list_incomplete_downloads() -> Result<Vec<DownloadState>, String> {
+    RangedDownloader::open_store()?.list()
+}
+
+/// Queue the rest of a recorded download, which picks up from the chunks
+/// already in its `.part` file.
+#[tauri::command]
+async fn resume_download(
+    id: String,
+    storage: State<'_, StorageManager>,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    let state = RangedDownloader::open_store()?
+        .load(&id)
+        .ok_or("Download not found")?;
+    let source = s3_uri(state.account.as_deref(), &state.bucket, &state.key);
+    let dest_dir = storage
+        .provider(&state.dest)
+        .await?
+        .parent(&state.dest)
+        .ok_or("Download has no destination")?;
+
+    transfers.enqueue_to(
+        source,
+        state.version_id,
+        dest_dir,
+        state.dest,
+        Encryption::default(),
+    )
+}
+
+#[tauri::command]
+async fn abort_download(
+    id: String,
+    storage: State<'_, StorageManager>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<(), String> {
+    let downloader = downloader_for(&id, &storage, &settings).await?;
+
+    downloader.abort(&id)
+}
+
+#[tauri::command]
+async fn queue_transfer(
+    source_path: String,
+    dest_dir: String,
+    kind: TransferKind,
+    version_id: Option<String>,
+    encryption: Option<Encryption>,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    let encryption = match encryption {
+        Some(encryption) => encryption,
+        None => Encryption::default_at(&dest_dir)?,
+    };
+
+    transfers.enqueue(kind, source_path, version_id, dest_dir, encryption, false)
+}
+
+#[tauri::command]
+async fn list_transfers(transfers: State<'_, TransferManager>) -> Result<Vec<TransferJob>, String> {
+    transfers.jobs()
+}
+
+#[tauri::command]
+async fn pause_transfer(
+    id: u64,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    transfers.pause(id)
+}
+
+#[tauri::command]
+async fn resume_transfer(
+    id: u64,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    transfers.resume(id)
+}
+
+#[tauri::command]
+async fn cancel_transfer(
+    id: u64,
+    transfers: State<'_, TransferManager>,
+) -> Result<TransferJob, String> {
+    transfers.cancel(id).await
+}
+
+/// Compare a local folder with an S3 prefix and list what a sync would do,
+/// without changing anything.
+#[tauri::command]
+async fn plan_sync(
+    options: SyncOptions,
+    storage: State<'_, StorageManager>,
+    state: State<'_, SyncState>,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<SyncPlan, String> {
+    let concurrency = transfer_settings(&settings)?.concurrency;
+    sync::plan(&storage, &state, options, concurrency).await
+}
+
+/// Queue the transfers and deletes of a reviewed plan. The pair's state is
+/// recorded once they have finished.
+#[tauri::command]
+async fn run_sync(
+    plan: SyncPlan,
+    app: AppHandle,
+    settings: State<'_, Mutex<TransferSettings>>,
+) -> Result<SyncRun, String> {
+    let concurrency = transfer_settings(&settings)?.concurrency;
+    sync::run(&app, &plan, concurrency).await
+}
+
+#[tauri::command]
+fn list_watch_folders(watches: State<'_, WatchManager>) -> Result<Vec<WatchStatus>, String> {
+    watches.list()
+}
+
+/// Start uploading new and changed files of a local folder to an S3 prefix as
+/// they are written. `ignore` holds `.gitignore` style patterns.
+#[tauri::command]
+fn add_watch_folder(
+    local: String,
+    remote: String,
+    ignore: String,
+    watches: State<'_, WatchManager>,
+) -> Result<WatchStatus, String> {
+    watches.add(local, remote, ignore)
+}
+
+#[tauri::command]
+fn set_watch_paused(
+    id: String,
+    paused: bool,
+    watches: State<'_, WatchManager>,
+) -> Result<WatchStatus, String> {
+    watches.set_paused(&id, paused)
+}
+
+/// Stop watching a folder. What was uploaded stays in S3.
+#[tauri::command]
+fn remove_watch_folder(id: String, watches: State<'_, WatchManager>) -> Result<(), String> {
+    watches.remove(&id)
+}
+
+/// Delete the given items in the background; S3 folders are deleted with
+/// everything below them. Local items are deleted for good, not trashed.
+#[tauri::command]
+async fn delete_items(
+    paths: Vec<String>,
+    deletes: State<'_, DeleteManager>,
+) -> Result<DeleteJob, String> {
+    deletes.start(paths)
+}
+
+#[tauri::command]
+async fn list_deletes(deletes: State<'_, DeleteManager>) -> Result<Vec<DeleteJob>, String> {
+    deletes.jobs()
+}
+
+#[tauri::command]
+async fn cancel_delete(id: u64, deletes: State<'_, DeleteManager>) -> Result<DeleteJob, String> {
+    deletes.cancel(id)
+}
+
+#[tauri::command]
+async fn clear_finished_deletes(
+    deletes: State<'_, DeleteManager>,
+) -> Result<Vec<DeleteJob>, String> {
+    deletes.clear_finished()
+}
+
+#[tauri::command]
+async fn clear_finished_transfers(
+    transfers: State<'_, TransferManager>,
+) -> Result<Vec<TransferJob>, String> {
+    transfers.clear_finished()
+}
+
+#[tauri::command]
+async fn list_accounts(storage: State<'_, StorageManager>) -> Result<Vec<AccountInfo>, String> {
+    storage.accounts().list()
+}
+
+#[tauri::command]
+async fn save_account(
+    account: AccountInput,
+    storage: State<'_, StorageManager>,
+) -> Result<AccountInfo, String> {
+    let saved = storage.accounts().upsert(account)?;
+    storage.forget_account(&saved.id).await;
+
+    Ok(saved)
+}
+
+#[tauri::command]
+async fn delete_account(id: String, storage: State<'_, StorageManager>) -> Result<(), String> {
+    storage.accounts().remove(&id)?;
+    storage.forget_account(&id).await;
 
     Ok(())
 }
 
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
-    fs::create_dir_all(dst).map_err(|e| format!("Failed to create directory: {}", e))?;
+/// Try the settings as entered in the dialog, before or without saving them.
+/// Roles with MFA need a token code to be tested.
+#[tauri::command]
+async fn test_account(
+    account: AccountInput,
+    mfa_code: Option<String>,
+    storage: State<'_, StorageManager>,
+) -> Result<String, String> {
+    let account = storage.accounts().resolve(account)?;
+
+    // A throwaway session, the settings may differ from the saved ones
+    let sessions = Arc::new(RoleSessions::default());
+    if account
+        .role
+        .as_ref()
+        .is_some_and(|role| role.mfa_serial.is_some())
+    {
+        sessions.start(&account, mfa_code.as_deref()).await?;
+    }
 
-    for entry in fs::read_dir(src).map_err(|e| format!("Failed to read directory: {}", e))? {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+    accounts::test_connection(&account, &sessions).await
+}
 
-        if src_path.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path).map_err(|e| format!("Failed to copy file: {}", e))?;
-        }
+/// Assume the role of an account with an MFA token code, returning when the
+/// session expires.
+#[tauri::command]
+async fn start_role_session(
+    id: String,
+    mfa_code: String,
+    storage: State<'_, StorageManager>,
+) -> Result<String, String> {
+    let accounts = storage.accounts();
+    let account = accounts.get(&id)?;
+
+    let expiry = accounts.sessions().start(&account, Some(&mfa_code)).await?;
+
+    Ok(chrono::DateTime::<chrono::Utc>::from(expiry).to_rfc3339())
+}
+
+// The customer key of the account `path` is in
+fn customer_keys(path: &str, storage: &StorageManager) -> Result<CustomerKeys, String> {
+    let uri = StorageUri::parse(path)?;
+    if uri.scheme() != "s3" {
+        return Err("Encryption settings only apply to S3".to_string());
     }
 
-    Ok(())
+    Ok(CustomerKeys::new(
+        storage.accounts().vault(),
+        uri.account().map(str::to_string),
+    ))
 }
 
-fn get_file_icon(filename: &str) -> String {
-    let extension = filename.split('.').last().unwrap_or("").to_lowercase();
-
-    match extension.as_str() {
-        "txt" | "md" | "rtf" => "document-text".to_string(),
-        "pdf" => "document".to_string(),
-        "doc" | "docx" => "document".to_string(),
-        "xls" | "xlsx" => "table".to_string(),
-        "ppt" | "pptx" => "presentation".to_string(),
-        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" => "photo".to_string(),
-        "mp4" | "avi" | "mov" | "wmv" | "flv" => "film".to_string(),
-        "mp3" | "wav" | "flac" | "aac" => "musical-note".to_string(),
-        "zip" | "rar" | "7z" | "tar" | "gz" => "archive-box".to_string(),
-        "exe" | "app" | "dmg" => "cog".to_string(),
-        "html" | "css" | "js" | "ts" | "json" => "code-bracket".to_string(),
-        "rs" | "py" | "java" | "cpp" | "c" => "code-bracket".to_string(),
-        _ => "document".to_string(),
+/// The encryption settings of the account `path` is in.
+#[tauri::command]
+async fn get_encryption_settings(
+    path: String,
+    storage: State<'_, StorageManager>,
+) -> Result<EncryptionSettings, String> {
+    let keys = customer_keys(&path, &storage)?;
+
+    Ok(EncryptionSettings {
+        default_encryption: Encryption::default_at(&path)?,
+        customer_key: keys.is_set(),
+    })
+}
+
+/// Set how uploads and copies to the account `path` is in are encrypted.
+#[tauri::command]
+async fn set_default_encryption(
+    path: String,
+    encryption: Encryption,
+    storage: State<'_, StorageManager>,
+) -> Result<EncryptionSettings, String> {
+    let keys = customer_keys(&path, &storage)?;
+    if encryption == Encryption::SseC && !keys.is_set() {
+        return Err("Store a customer key before making SSE-C the default".to_string());
     }
+
+    Encryption::set_default_for(StorageUri::parse(&path)?.account(), encryption)?;
+
+    Ok(EncryptionSettings {
+        default_encryption: Encryption::default_at(&path)?,
+        customer_key: keys.is_set(),
+    })
 }
 
-fn get_file_type(extension: &str) -> String {
-    match extension {
-        // Text files
-        "txt" | "md" | "rtf" | "log" | "csv" | "xml" | "yaml" | "yml" | "toml" | "ini" | "conf" => {
-            "text".to_string()
-        }
-        "html" | "css" | "js" | "ts" | "json" | "jsx" | "tsx" => "text".to_string(),
-        "rs" | "py" | "java" | "cpp" | "c" | "h" | "hpp" | "go" | "php" | "rb" | "swift" => {
-            "text".to_string()
+/// Store the customer key of the account `path` is in, or remove it with
+/// `None`. The key is kept in the vault.
+#[tauri::command]
+async fn set_customer_key(
+    path: String,
+    key: Option<String>,
+    storage: State<'_, StorageManager>,
+) -> Result<EncryptionSettings, String> {
+    let keys = customer_keys(&path, &storage)?;
+    let account = StorageUri::parse(&path)?.account().map(str::to_string);
+
+    match key.map(Zeroizing::new) {
+        Some(key) => keys.set(&key)?,
+        None => {
+            keys.remove()?;
+            // Nothing could be written with SSE-C any more
+            if Encryption::default_for(account.as_deref()) == Encryption::SseC {
+                Encryption::set_default_for(account.as_deref(), Encryption::BucketDefault)?;
+            }
         }
-        "sh" | "bash" | "zsh" | "fish" | "ps1" | "bat" | "cmd" => "text".to_string(),
+    }
+
+    Ok(EncryptionSettings {
+        default_encryption: Encryption::default_at(&path)?,
+        customer_key: keys.is_set(),
+    })
+}
+
+#[tauri::command]
+async fn generate_customer_key() -> Result<String, String> {
+    Ok(CustomerKey::generate()?.to_string())
+}
+
+#[tauri::command]
+async fn get_vault_status(storage: State<'_, StorageManager>) -> Result<VaultStatus, String> {
+    storage.accounts().vault().status()
+}
 
-        // Image files
-        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "svg" | "ico" => "image".to_string(),
-        "tiff" | "tif" | "raw" | "cr2" | "nef" | "arw" => "image".to_string(),
+/// Set up the vault, with a master passphrase or, without one, with a key kept
+/// in the OS secret store.
+#[tauri::command]
+async fn create_vault(
+    passphrase: Option<String>,
+    storage: State<'_, StorageManager>,
+) -> Result<VaultStatus, String> {
+    let passphrase = passphrase.map(Zeroizing::new);
+    let accounts = storage.accounts();
+
+    accounts
+        .vault()
+        .create(passphrase.as_deref().map(String::as_str))?;
+    accounts.migrate_secrets()?;
+
+    accounts.vault().status()
+}
 
-        // Other types not supported for preview
-        _ => "unsupported".to_string(),
+#[tauri::command]
+async fn unlock_vault(
+    passphrase: Option<String>,
+    storage: State<'_, StorageManager>,
+) -> Result<VaultStatus, String> {
+    let passphrase = passphrase.map(Zeroizing::new);
+    let accounts = storage.accounts();
+
+    // Key derivation is deliberately slow, keep it off the async workers
+    let vault = accounts.vault();
+    tokio::task::spawn_blocking(move || vault.unlock(passphrase.as_deref().map(String::as_str)))
+        .await
+        .map_err(|e| format!("Failed to unlock vault: {}", e))??;
+    accounts.migrate_secrets()?;
+
+    accounts.vault().status()
+}
+
+#[tauri::command]
+async fn lock_vault(storage: State<'_, StorageManager>) -> Result<VaultStatus, String> {
+    storage.accounts().vault().lock()?;
+    storage.forget_accounts().await;
+
+    storage.accounts().vault().status()
+}
+
+#[tauri::command]
+async fn set_vault_timeout(
+    seconds: u64,
+    storage: State<'_, StorageManager>,
+) -> Result<VaultStatus, String> {
+    storage.accounts().vault().set_idle_timeout(seconds)?;
+
+    storage.accounts().vault().status()
+}
+
+/// Delete the vault with all stored secrets, for when the passphrase is lost.
+#[tauri::command]
+async fn reset_vault(storage: State<'_, StorageManager>) -> Result<VaultStatus, String> {
+    storage.accounts().vault().reset()?;
+    storage.forget_accounts().await;
+
+    storage.accounts().vault().status()
+}
+
+// Check requested restores every minute and tell the webview about each
+// object whose restored copy can be read
+async fn poll_restores(app: AppHandle) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        let storage = app.state::<StorageManager>();
+        let pending = app.state::<PendingRestores>();
+        for path in pending.paths().unwrap_or_default() {
+            let item = match storage.provider(&path).await {
+                Ok(provider) => provider.stat(&path).await,
+                Err(e) => Err(e),
+            };
+
+            match item {
+                Ok(item) if item.restoring => continue,
+                Ok(item) if !item.archived => {
+                    let _ = app.emit(RESTORE_EVENT, &item);
+                }
+                _ => {}
+            }
+            let _ = pending.remove(&path);
+        }
+    }
+}
+
+// Lock the vault once it has been idle for its timeout and tell the webview
+async fn lock_idle_vault(app: AppHandle) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(15)).await;
+
+        let storage = app.state::<StorageManager>();
+        let vault = storage.accounts().vault();
+        if vault.lock_if_idle() == Ok(true) {
+            storage.forget_accounts().await;
+            if let Ok(status) = vault.status() {
+                let _ = app.emit(VAULT_EVENT, status);
+            }
+        }
     }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(StorageManager::new())
+        .manage(Mutex::new(TransferSettings::load()))
+        .manage(PresignHistory::default())
+        .manage(PendingRestores::default())
+        .setup(|app| {
+            app.manage(TransferManager::new(app.handle().clone()));
+            app.manage(DeleteManager::new(app.handle().clone()));
+            app.manage(SyncState::open()?);
+            app.manage(WatchManager::new(app.handle().clone()));
+            app.manage(DirectoryWatcher::new(app.handle().clone()));
+            tauri::async_runtime::spawn(lock_idle_vault(app.handle().clone()));
+            tauri::async_runtime::spawn(poll_restores(app.handle().clone()));
+            Ok(())
+        })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             read_directory,
+            watch_directories,
+            read_versions,
+            restore_version,
+            delete_version,
             get_home_directory,
             create_folder,
             delete_item,
+            move_to_trash,
+            list_trash,
+            restore_from_trash,
+            delete_from_trash,
+            empty_trash,
+            create_bucket,
+            delete_bucket,
+            empty_and_delete_bucket,
             rename_item,
             copy_item,
             move_item,
             get_item_info,
+            get_object_metadata,
+            update_objects,
+            change_storage_class,
+            restore_objects,
+            presign_url,
+            list_presigned_urls,
+            clear_presigned_urls,
+            get_bucket_properties,
+            preview_bucket_properties,
+            put_bucket_properties,
+            get_bucket_lifecycle,
+            put_bucket_lifecycle,
+            format_lifecycle,
+            parse_lifecycle,
             search_files,
-            preview_file
+            preview_file,
+            get_transfer_settings,
+            update_transfer_settings,
+            list_incomplete_uploads,
+            resume_upload,
+            abort_upload,
+            list_incomplete_downloads,
+            resume_download,
+            abort_download,
+            queue_transfer,
+            list_transfers,
+            pause_transfer,
+            resume_transfer,
+            cancel_transfer,
+            clear_finished_transfers,
+            plan_sync,
+            run_sync,
+            list_watch_folders,
+            add_watch_folder,
+            set_watch_paused,
+            remove_watch_folder,
+            delete_items,
+            list_deletes,
+            cancel_delete,
+            clear_finished_deletes,
+            list_accounts,
+            save_account,
+            delete_account,
+            test_account,
+            start_role_session,
+            get_encryption_settings,
+            set_default_encryption,
+            set_customer_key,
+            generate_customer_key,
+            get_vault_status,
+            create_vault,
+            unlock_vault,
+            lock_vault,
+            set_vault_timeout,
+            reset_vault
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
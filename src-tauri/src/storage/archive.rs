@@ -0,0 +1,122 @@
+// Storage classes and restores from the archive tiers. Objects in Glacier
+// Flexible Retrieval and Deep Archive cannot be read until a temporary copy
+// has been restored, which takes minutes to hours, so the restores requested
+// in a session are checked until their copy is ready.
+
+use aws_sdk_s3::types::RestoreStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use super::s3::format_s3_datetime;
+
+/// Event emitted with the [`FileItem`](crate::types::FileItem) of an object
+/// whose restore has finished.
+pub const RESTORE_EVENT: &str = "restore-updated";
+
+pub const STORAGE_CLASSES: [&str; 7] = [
+    "STANDARD",
+    "STANDARD_IA",
+    "ONEZONE_IA",
+    "INTELLIGENT_TIERING",
+    "GLACIER_IR",
+    "GLACIER",
+    "DEEP_ARCHIVE",
+];
+
+// Classes whose objects have to be restored before they can be read
+const ARCHIVE_CLASSES: [&str; 2] = ["GLACIER", "DEEP_ARCHIVE"];
+
+const RESTORE_TIERS: [&str; 3] = ["Expedited", "Standard", "Bulk"];
+
+/// Where the restore of an archived object stands.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreState {
+    /// The restore was requested and the copy is not ready yet
+    pub ongoing: bool,
+    /// When the restored copy is removed again
+    pub expiry: Option<String>,
+}
+
+impl RestoreState {
+    /// Read the `x-amz-restore` header, such as
+    /// `ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT"`.
+    pub fn parse(header: &str) -> Self {
+        let field = |name: &str| {
+            let start = header.find(&format!("{}=\"", name))? + name.len() + 2;
+            let end = header[start..].find('"')? + start;
+            Some(&header[start..end])
+        };
+
+        Self {
+            ongoing: field("ongoing-request") == Some("true"),
+            expiry: field("expiry-date").map(|date| {
+                chrono::DateTime::parse_from_rfc2822(date)
+                    .map(|date| date.to_rfc3339())
+                    .unwrap_or_else(|_| date.to_string())
+            }),
+        }
+    }
+
+    pub fn from_sdk(status: &RestoreStatus) -> Self {
+        Self {
+            ongoing: status.is_restore_in_progress().unwrap_or(false),
+            expiry: status.restore_expiry_date().and_then(format_s3_datetime),
+        }
+    }
+
+    /// Whether a restored copy can be read now.
+    pub fn is_available(&self) -> bool {
+        !self.ongoing && self.expiry.is_some()
+    }
+}
+
+pub fn is_archive_class(class: &str) -> bool {
+    ARCHIVE_CLASSES.contains(&class)
+}
+
+pub fn validate_storage_class(class: &str) -> Result<(), String> {
+    if STORAGE_CLASSES.contains(&class) {
+        Ok(())
+    } else {
+        Err(format!("Unknown storage class: {}", class))
+    }
+}
+
+pub fn validate_restore(days: i32, tier: &str) -> Result<(), String> {
+    if days < 1 {
+        return Err("A restored copy must be kept for at least one day".to_string());
+    }
+
+    if !RESTORE_TIERS.contains(&tier) {
+        return Err(format!("Unknown restore tier: {}", tier));
+    }
+
+    Ok(())
+}
+
+/// Objects whose restore was requested in this session and is not done yet.
+#[derive(Default)]
+pub struct PendingRestores(Mutex<BTreeSet<String>>);
+
+impl PendingRestores {
+    pub fn add(&self, paths: impl IntoIterator<Item = String>) -> Result<(), String> {
+        self.0.lock().map_err(|e| e.to_string())?.extend(paths);
+        Ok(())
+    }
+
+    pub fn remove(&self, path: &str) -> Result<(), String> {
+        self.0.lock().map_err(|e| e.to_string())?.remove(path);
+        Ok(())
+    }
+
+    pub fn paths(&self) -> Result<Vec<String>, String> {
+        Ok(self
+            .0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,689 @@
+// Bucket-level settings: the bucket policy, CORS rules and public access
+// block. Edits are validated and compared with what the bucket has now, so
+// the change can be reviewed before it is saved. Also holds the naming rules
+// new buckets must follow.
+
+use aws_sdk_s3::types::{
+    CorsConfiguration, CorsRule as SdkCorsRule, PublicAccessBlockConfiguration,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Limits S3 puts on bucket policies and CORS configurations
+const MAX_POLICY_BYTES: usize = 20 * 1024;
+const MAX_CORS_RULES: usize = 100;
+
+const POLICY_VERSIONS: [&str; 2] = ["2012-10-17", "2008-10-17"];
+const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+// Names S3 keeps for its own access points and endpoints
+const RESERVED_BUCKET_PREFIXES: [&str; 3] = ["xn--", "sthree-", "amzn-s3-demo-"];
+const RESERVED_BUCKET_SUFFIXES: [&str; 4] = ["-s3alias", "--ol-s3", ".mrap", "--x-s3"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketProperties {
+    /// Policy document as written, `None` when the bucket has no policy
+    pub policy: Option<String>,
+    pub cors_rules: Vec<CorsRule>,
+    pub public_access_block: PublicAccessBlock,
+}
+
+/// A CORS rule, in the shape the AWS CLI uses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CorsRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i32>,
+}
+
+/// Settings a bucket is created with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewBucket {
+    /// Region to create the bucket in, the account region when empty
+    pub region: Option<String>,
+    pub object_lock: bool,
+    pub versioning: bool,
+}
+
+/// Public access block of a bucket; a bucket without one blocks nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicAccessBlock {
+    pub block_public_acls: bool,
+    pub ignore_public_acls: bool,
+    pub block_public_policy: bool,
+    pub restrict_public_buckets: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// How one setting changes when the edited properties are saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyDiff {
+    pub setting: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Which settings differ between two sets of properties.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PropertyChanges {
+    pub policy: bool,
+    pub cors: bool,
+    pub public_access_block: bool,
+}
+
+impl BucketProperties {
+    /// Check the edited settings, dropping a blank policy.
+    pub fn validated(&self) -> Result<Self, String> {
+        let policy = self
+            .policy
+            .as_deref()
+            .map(str::trim)
+            .filter(|policy| !policy.is_empty());
+
+        if let Some(policy) = policy {
+            parse_policy(policy)?;
+        }
+        validate_cors(&self.cors_rules)?;
+
+        Ok(Self {
+            policy: policy.map(str::to_string),
+            cors_rules: self.cors_rules.clone(),
+            public_access_block: self.public_access_block.clone(),
+        })
+    }
+
+    /// Compare with edited properties. Policies are compared as documents,
+    /// so changes to their layout alone do not count.
+    pub fn changes(&self, edited: &Self) -> PropertyChanges {
+        let policy = |properties: &Self| {
+            properties
+                .policy
+                .as_deref()
+                .map(|policy| serde_json::from_str::<Value>(policy).ok())
+        };
+
+        PropertyChanges {
+            policy: policy(self) != policy(edited),
+            cors: self.cors_rules != edited.cors_rules,
+            public_access_block: self.public_access_block != edited.public_access_block,
+        }
+    }
+
+    /// Line diffs of every setting that `edited` changes.
+    pub fn diff(&self, edited: &Self) -> Vec<PropertyDiff> {
+        let changes = self.changes(edited);
+        let mut diffs = Vec::new();
+
+        if changes.public_access_block {
+            diffs.push(PropertyDiff {
+                setting: "Public Access".to_string(),
+                lines: diff_lines(
+                    &self.public_access_block.to_text(),
+                    &edited.public_access_block.to_text(),
+                ),
+            });
+        }
+        if changes.policy {
+            diffs.push(PropertyDiff {
+                setting: "Bucket Policy".to_string(),
+                lines: diff_lines(&policy_text(&self.policy), &policy_text(&edited.policy)),
+            });
+        }
+        if changes.cors {
+            diffs.push(PropertyDiff {
+                setting: "CORS".to_string(),
+                lines: diff_lines(&cors_text(&self.cors_rules), &cors_text(&edited.cors_rules)),
+            });
+        }
+
+        diffs
+    }
+}
+
+impl CorsRule {
+    pub fn from_sdk(rule: &SdkCorsRule) -> Self {
+        Self {
+            id: rule.id().map(str::to_string),
+            allowed_origins: rule.allowed_origins().to_vec(),
+            allowed_methods: rule.allowed_methods().to_vec(),
+            allowed_headers: rule.allowed_headers().to_vec(),
+            expose_headers: rule.expose_headers().to_vec(),
+            max_age_seconds: rule.max_age_seconds(),
+        }
+    }
+}
+
+/// Convert CORS rules for PutBucketCors.
+pub fn cors_to_sdk(rules: &[CorsRule]) -> Result<CorsConfiguration, String> {
+    let rules = rules
+        .iter()
+        .map(|rule| {
+            SdkCorsRule::builder()
+                .set_id(rule.id.clone())
+                .set_allowed_origins(Some(rule.allowed_origins.clone()))
+                .set_allowed_methods(Some(rule.allowed_methods.clone()))
+                .set_allowed_headers(
+                    (!rule.allowed_headers.is_empty()).then(|| rule.allowed_headers.clone()),
+                )
+                .set_expose_headers(
+                    (!rule.expose_headers.is_empty()).then(|| rule.expose_headers.clone()),
+                )
+                .set_max_age_seconds(rule.max_age_seconds)
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to build CORS rule: {}", e))?;
+
+    CorsConfiguration::builder()
+        .set_cors_rules(Some(rules))
+        .build()
+        .map_err(|e| format!("Failed to build CORS configuration: {}", e))
+}
+
+impl PublicAccessBlock {
+    pub fn from_sdk(configuration: &PublicAccessBlockConfiguration) -> Self {
+        Self {
+            block_public_acls: configuration.block_public_acls().unwrap_or(false),
+            ignore_public_acls: configuration.ignore_public_acls().unwrap_or(false),
+            block_public_policy: configuration.block_public_policy().unwrap_or(false),
+            restrict_public_buckets: configuration.restrict_public_buckets().unwrap_or(false),
+        }
+    }
+
+    pub fn to_sdk(&self) -> PublicAccessBlockConfiguration {
+        PublicAccessBlockConfiguration::builder()
+            .block_public_acls(self.block_public_acls)
+            .ignore_public_acls(self.ignore_public_acls)
+            .block_public_policy(self.block_public_policy)
+            .restrict_public_buckets(self.restrict_public_buckets)
+            .build()
+    }
+
+    fn to_text(&self) -> String {
+        [
+            ("BlockPublicAcls", self.block_public_acls),
+            ("IgnorePublicAcls", self.ignore_public_acls),
+            ("BlockPublicPolicy", self.block_public_policy),
+            ("RestrictPublicBuckets", self.restrict_public_buckets),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// Check a new bucket name against the S3 naming rules for general purpose
+/// buckets, so a bad name is caught before the request is sent.
+pub fn validate_bucket_name(name: &str) -> Result<(), String> {
+    if !(3..=63).contains(&name.len()) {
+        return Err("Bucket names must be between 3 and 63 characters long".to_string());
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-')
+    {
+        return Err(
+            "Bucket names may only contain lowercase letters, digits, dots and hyphens".to_string(),
+        );
+    }
+
+    let alphanumeric = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    if !alphanumeric(name.chars().next()) || !alphanumeric(name.chars().last()) {
+        return Err("Bucket names must begin and end with a letter or digit".to_string());
+    }
+
+    if name.contains("..") {
+        return Err("Bucket names must not contain two adjacent dots".to_string());
+    }
+
+    if name.parse::<std::net::Ipv4Addr>().is_ok() {
+        return Err("Bucket names must not be formatted as an IP address".to_string());
+    }
+
+    if let Some(prefix) = RESERVED_BUCKET_PREFIXES
+        .iter()
+        .find(|prefix| name.starts_with(*prefix))
+    {
+        return Err(format!("Bucket names must not start with \"{}\"", prefix));
+    }
+
+    if let Some(suffix) = RESERVED_BUCKET_SUFFIXES
+        .iter()
+        .find(|suffix| name.ends_with(*suffix))
+    {
+        return Err(format!("Bucket names must not end with \"{}\"", suffix));
+    }
+
+    Ok(())
+}
+
+// A policy must be a JSON document S3 could accept: statements with an
+// effect, a principal, actions and resources
+fn parse_policy(policy: &str) -> Result<Value, String> {
+    if policy.len() > MAX_POLICY_BYTES {
+        return Err(format!(
+            "The policy is larger than {} KB",
+            MAX_POLICY_BYTES / 1024
+        ));
+    }
+
+    let document = serde_json::from_str::<Value>(policy)
+        .map_err(|e| format!("The policy is not valid JSON: {}", e))?;
+    let document = document
+        .as_object()
+        .ok_or("The policy must be a JSON object")?;
+
+    if let Some(version) = document.get("Version") {
+        if !version
+            .as_str()
+            .is_some_and(|version| POLICY_VERSIONS.contains(&version))
+        {
+            return Err(format!(
+                "The policy Version must be {}",
+                POLICY_VERSIONS.join(" or ")
+            ));
+        }
+    }
+
+    let statements = match document.get("Statement") {
+        Some(Value::Array(statements)) if !statements.is_empty() => statements.iter().collect(),
+        Some(statement @ Value::Object(_)) => vec![statement],
+        _ => return Err("The policy has no Statement".to_string()),
+    };
+
+    for (index, statement) in statements.into_iter().enumerate() {
+        let statement = statement
+            .as_object()
+            .ok_or_else(|| format!("Statement {} must be a JSON object", index + 1))?;
+        let has_one_of = |names: [&str; 2]| names.iter().any(|name| statement.contains_key(*name));
+
+        if !matches!(
+            statement.get("Effect").and_then(Value::as_str),
+            Some("Allow" | "Deny")
+        ) {
+            return Err(format!(
+                "Statement {}: Effect must be Allow or Deny",
+                index + 1
+            ));
+        }
+        if !has_one_of(["Principal", "NotPrincipal"]) {
+            return Err(format!("Statement {} has no Principal", index + 1));
+        }
+        if !has_one_of(["Action", "NotAction"]) {
+            return Err(format!("Statement {} has no Action", index + 1));
+        }
+        if !has_one_of(["Resource", "NotResource"]) {
+            return Err(format!("Statement {} has no Resource", index + 1));
+        }
+    }
+
+    Ok(Value::Object(document.clone()))
+}
+
+fn validate_cors(rules: &[CorsRule]) -> Result<(), String> {
+    if rules.len() > MAX_CORS_RULES {
+        return Err(format!(
+            "A bucket can have at most {} CORS rules",
+            MAX_CORS_RULES
+        ));
+    }
+
+    for (index, rule) in rules.iter().enumerate() {
+        let label = format!("CORS rule {}", index + 1);
+
+        if rule.allowed_origins.is_empty() {
+            return Err(format!("{} has no allowed origins", label));
+        }
+        if let Some(origin) = rule
+            .allowed_origins
+            .iter()
+            .find(|origin| origin.matches('*').count() > 1)
+        {
+            return Err(format!(
+                "{}: the origin {} has more than one wildcard",
+                label, origin
+            ));
+        }
+        if rule.allowed_methods.is_empty() {
+            return Err(format!("{} has no allowed methods", label));
+        }
+        if let Some(method) = rule
+            .allowed_methods
+            .iter()
+            .find(|method| !CORS_METHODS.contains(&method.as_str()))
+        {
+            return Err(format!(
+                "{}: {} is not one of {}",
+                label,
+                method,
+                CORS_METHODS.join(", ")
+            ));
+        }
+        if rule.max_age_seconds.is_some_and(|seconds| seconds < 0) {
+            return Err(format!("{}: the max age cannot be negative", label));
+        }
+    }
+
+    Ok(())
+}
+
+// Policies are shown pretty-printed, so a diff shows the statements that
+// changed rather than one long line
+fn policy_text(policy: &Option<String>) -> String {
+    match policy {
+        Some(policy) => serde_json::from_str::<Value>(policy)
+            .and_then(|document| serde_json::to_string_pretty(&document))
+            .unwrap_or_else(|_| policy.clone()),
+        None => String::new(),
+    }
+}
+
+fn cors_text(rules: &[CorsRule]) -> String {
+    if rules.is_empty() {
+        return String::new();
+    }
+
+    serde_json::to_string_pretty(&serde_json::json!({ "CORSRules": rules })).unwrap_or_default()
+}
+
+// Line diff from the longest common subsequence of the two texts
+fn diff_lines(before: &str, after: &str) -> Vec<DiffLine> {
+    let before = before.lines().collect::<Vec<_>>();
+    let after = after.lines().collect::<Vec<_>>();
+
+    // common[i][j] is the length of the common subsequence of before[i..]
+    // and after[j..]
+    let mut common = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let line = |kind, text: &str| DiffLine {
+        kind,
+        text: text.to_string(),
+    };
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < before.len() && j < after.len() {
+        if before[i] == after[j] {
+            lines.push(line(DiffKind::Same, before[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(line(DiffKind::Removed, before[i]));
+            i += 1;
+        } else {
+            lines.push(line(DiffKind::Added, after[j]));
+            j += 1;
+        }
+    }
+    lines.extend(before[i..].iter().map(|text| line(DiffKind::Removed, text)));
+    lines.extend(after[j..].iter().map(|text| line(DiffKind::Added, text)));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"{
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "s3:GetObject",
+            "Resource": "arn:aws:s3:::example/*"
+        }]
+    }"#;
+
+    fn with_policy(policy: &str) -> BucketProperties {
+        BucketProperties {
+            policy: Some(policy.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn rule(origins: &[&str], methods: &[&str]) -> CorsRule {
+        CorsRule {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: methods.iter().map(|method| method.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn lines(diff: &[DiffLine]) -> Vec<(DiffKind, &str)> {
+        diff.iter()
+            .map(|line| (line.kind, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn policies_are_checked() {
+        assert!(with_policy(POLICY).validated().is_ok());
+        // A blank policy removes the policy
+        assert_eq!(with_policy("  \n").validated().unwrap().policy, None);
+
+        let rejected = [
+            ("{", "not valid JSON"),
+            ("[]", "must be a JSON object"),
+            (
+                r#"{"Version": "2020-01-01", "Statement": []}"#,
+                "Version must be",
+            ),
+            (r#"{"Version": "2012-10-17"}"#, "has no Statement"),
+            (r#"{"Statement": []}"#, "has no Statement"),
+            (
+                r#"{"Statement": ["Allow"]}"#,
+                "Statement 1 must be a JSON object",
+            ),
+            (
+                r#"{"Statement": {"Effect": "Permit", "Principal": "*", "Action": "s3:*", "Resource": "*"}}"#,
+                "Effect must be Allow or Deny",
+            ),
+            (
+                r#"{"Statement": {"Effect": "Deny", "Action": "s3:*", "Resource": "*"}}"#,
+                "has no Principal",
+            ),
+            (
+                r#"{"Statement": {"Effect": "Deny", "Principal": "*", "Resource": "*"}}"#,
+                "has no Action",
+            ),
+            (
+                r#"{"Statement": {"Effect": "Deny", "NotPrincipal": {"AWS": "x"}, "NotAction": "s3:*"}}"#,
+                "has no Resource",
+            ),
+        ];
+        for (policy, message) in rejected {
+            let error = with_policy(policy).validated().unwrap_err();
+            assert!(error.contains(message), "{}: {}", policy, error);
+        }
+
+        let large = format!("{{\"Sid\": \"{}\"}}", "x".repeat(MAX_POLICY_BYTES));
+        assert!(with_policy(&large)
+            .validated()
+            .unwrap_err()
+            .contains("larger than 20 KB"));
+    }
+
+    #[test]
+    fn cors_rules_are_checked() {
+        let properties = |rules: Vec<CorsRule>| BucketProperties {
+            cors_rules: rules,
+            ..Default::default()
+        };
+        let mut negative = rule(&["*"], &["GET"]);
+        negative.max_age_seconds = Some(-1);
+
+        assert!(
+            properties(vec![rule(&["https://*.example.com"], &["GET", "PUT"])])
+                .validated()
+                .is_ok()
+        );
+
+        let rejected = [
+            (
+                vec![rule(&[], &["GET"])],
+                "CORS rule 1 has no allowed origins",
+            ),
+            (
+                vec![rule(&["*"], &["GET"]), rule(&["https://*.*.com"], &["GET"])],
+                "CORS rule 2: the origin https://*.*.com has more than one wildcard",
+            ),
+            (
+                vec![rule(&["*"], &[])],
+                "CORS rule 1 has no allowed methods",
+            ),
+            (
+                vec![rule(&["*"], &["get"])],
+                "CORS rule 1: get is not one of",
+            ),
+            (
+                vec![rule(&["*"], &["PATCH"])],
+                "CORS rule 1: PATCH is not one of",
+            ),
+            (vec![negative], "the max age cannot be negative"),
+            (
+                vec![rule(&["*"], &["GET"]); MAX_CORS_RULES + 1],
+                "at most 100 CORS rules",
+            ),
+        ];
+        for (rules, message) in rejected {
+            let error = properties(rules).validated().unwrap_err();
+            assert!(error.contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn only_changed_settings_are_diffed() {
+        let current = with_policy(POLICY);
+
+        // The same policy laid out differently is no change
+        let compact = serde_json::to_string(&serde_json::from_str::<Value>(POLICY).unwrap());
+        assert!(current.diff(&with_policy(&compact.unwrap())).is_empty());
+
+        let mut edited = with_policy(&POLICY.replace("Allow", "Deny"));
+        edited.public_access_block.block_public_policy = true;
+        let diffs = current.diff(&edited);
+
+        let settings: Vec<&str> = diffs.iter().map(|diff| diff.setting.as_str()).collect();
+        assert_eq!(settings, ["Public Access", "Bucket Policy"]);
+        let access = lines(&diffs[0].lines);
+        assert!(access.contains(&(DiffKind::Removed, "BlockPublicPolicy: false")));
+        assert!(access.contains(&(DiffKind::Added, "BlockPublicPolicy: true")));
+        assert!(access.contains(&(DiffKind::Same, "BlockPublicAcls: false")));
+
+        let changed: Vec<_> = lines(&diffs[1].lines)
+            .into_iter()
+            .filter(|(kind, _)| *kind != DiffKind::Same)
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (DiffKind::Removed, "      \"Effect\": \"Allow\","),
+                (DiffKind::Added, "      \"Effect\": \"Deny\","),
+            ]
+        );
+
+        // Adding CORS rules to a bucket without any adds every line
+        let cors = BucketProperties {
+            cors_rules: vec![rule(&["*"], &["GET"])],
+            ..Default::default()
+        };
+        let diffs = BucketProperties::default().diff(&cors);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].setting, "CORS");
+        assert!(diffs[0]
+            .lines
+            .iter()
+            .all(|line| line.kind == DiffKind::Added));
+    }
+
+    #[test]
+    fn diff_keeps_common_lines() {
+        assert_eq!(
+            lines(&diff_lines("a\nb\nc\nd", "a\nc\nx\nd\ne")),
+            [
+                (DiffKind::Same, "a"),
+                (DiffKind::Removed, "b"),
+                (DiffKind::Same, "c"),
+                (DiffKind::Added, "x"),
+                (DiffKind::Same, "d"),
+                (DiffKind::Added, "e"),
+            ]
+        );
+        assert_eq!(lines(&diff_lines("", "a")), [(DiffKind::Added, "a")]);
+        assert_eq!(
+            lines(&diff_lines("a\nb", "")),
+            [(DiffKind::Removed, "a"), (DiffKind::Removed, "b")]
+        );
+    }
+
+    #[test]
+    fn bucket_names_follow_the_s3_rules() {
+        let name_of = |length: usize| "a".repeat(length);
+        for name in [
+            "abc",
+            "my-bucket.example",
+            "1.2.3",
+            "10.0.0.1.example",
+            "xn-bucket",
+            "s3alias",
+            "bucket-s3alias-logs",
+            &name_of(63),
+        ] {
+            assert_eq!(validate_bucket_name(name), Ok(()), "{}", name);
+        }
+
+        let rejected = [
+            (name_of(2), "between 3 and 63"),
+            (name_of(64), "between 3 and 63"),
+            ("My-Bucket".to_string(), "lowercase letters"),
+            ("my_bucket".to_string(), "lowercase letters"),
+            ("-bucket".to_string(), "begin and end"),
+            ("bucket.".to_string(), "begin and end"),
+            ("my..bucket".to_string(), "adjacent dots"),
+            ("192.168.5.4".to_string(), "IP address"),
+            ("xn--bucket".to_string(), "start with \"xn--\""),
+            ("sthree-bucket".to_string(), "start with \"sthree-\""),
+            ("bucket-s3alias".to_string(), "end with \"-s3alias\""),
+            ("bucket--ol-s3".to_string(), "end with \"--ol-s3\""),
+            ("bucket.mrap".to_string(), "end with \".mrap\""),
+            ("bucket--x-s3".to_string(), "end with \"--x-s3\""),
+        ];
+        for (name, message) in rejected {
+            let error = validate_bucket_name(&name).unwrap_err();
+            assert!(error.contains(message), "{}: {}", name, error);
+        }
+    }
+}
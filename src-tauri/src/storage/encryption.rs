@@ -0,0 +1,297 @@
+// Server-side encryption of the objects written by uploads and copies, and
+// the customer-provided keys (SSE-C) S3 needs on every read of an object
+// encrypted with one. Customer keys are kept per account in the vault.
+
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::types::ServerSideEncryption;
+use base64::{engine::general_purpose, Engine as _};
+use md5::{Digest, Md5};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+use super::StorageUri;
+use crate::utils::{app_data_dir, read_json, write_json};
+use crate::vault::Vault;
+
+const DEFAULTS_FILE: &str = "encryption.json";
+
+// SSE-C only supports AES-256, with a 256-bit key
+const CUSTOMER_KEY_LEN: usize = 32;
+const CUSTOMER_ALGORITHM: &str = "AES256";
+
+/// How the objects written by an upload or copy are encrypted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum Encryption {
+    /// Whatever default encryption the bucket has
+    #[default]
+    BucketDefault,
+    SseS3,
+    /// The AWS managed `aws/s3` key when no key ID is given
+    SseKms {
+        key_id: Option<String>,
+    },
+    /// The customer key stored for the account
+    SseC,
+}
+
+impl Encryption {
+    /// The mode uploads and copies to an account use unless told otherwise.
+    pub fn default_for(account: Option<&str>) -> Self {
+        load_defaults()
+            .remove(account.unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// The default mode for writes to `path`; local files are left alone.
+    pub fn default_at(path: &str) -> Result<Self, String> {
+        Ok(match StorageUri::parse(path)? {
+            uri @ StorageUri::S3 { .. } => Self::default_for(uri.account()),
+            StorageUri::Local(_) => Self::BucketDefault,
+        })
+    }
+
+    pub fn set_default_for(account: Option<&str>, encryption: Encryption) -> Result<(), String> {
+        let mut defaults = load_defaults();
+        let account = account.unwrap_or_default().to_string();
+
+        match encryption {
+            Encryption::BucketDefault => defaults.remove(&account),
+            Encryption::SseKms { key_id } => defaults.insert(
+                account,
+                Encryption::SseKms {
+                    key_id: key_id
+                        .map(|id| id.trim().to_string())
+                        .filter(|id| !id.is_empty()),
+                },
+            ),
+            encryption => defaults.insert(account, encryption),
+        };
+
+        write_json(&app_data_dir()?.join(DEFAULTS_FILE), &defaults)
+    }
+}
+
+// Default modes by account ID, the AWS environment being the empty string
+fn load_defaults() -> BTreeMap<String, Encryption> {
+    app_data_dir()
+        .and_then(|dir| read_json(&dir.join(DEFAULTS_FILE)))
+        .unwrap_or_default()
+}
+
+/// The encryption settings of an account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionSettings {
+    pub default_encryption: Encryption,
+    /// Whether a customer key is stored for the account
+    pub customer_key: bool,
+}
+
+/// A 256-bit key for SSE-C with the digest S3 checks it against, both base64.
+#[derive(Clone)]
+pub struct CustomerKey {
+    key: Zeroizing<String>,
+    key_md5: String,
+}
+
+impl CustomerKey {
+    /// Read a key given as base64 of its 32 bytes.
+    pub fn parse(encoded: &str) -> Result<Self, String> {
+        let bytes = Zeroizing::new(
+            general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|_| "The customer key must be base64".to_string())?,
+        );
+
+        if bytes.len() != CUSTOMER_KEY_LEN {
+            return Err(format!(
+                "The customer key must be {} bytes, not {}",
+                CUSTOMER_KEY_LEN,
+                bytes.len()
+            ));
+        }
+
+        Ok(Self {
+            key: Zeroizing::new(general_purpose::STANDARD.encode(&*bytes)),
+            key_md5: general_purpose::STANDARD.encode(Md5::digest(&*bytes)),
+        })
+    }
+
+    /// A new random key, as base64.
+    pub fn generate() -> Result<Zeroizing<String>, String> {
+        let mut bytes = Zeroizing::new([0u8; CUSTOMER_KEY_LEN]);
+        SystemRandom::new()
+            .fill(bytes.as_mut())
+            .map_err(|_| "Failed to generate a key".to_string())?;
+
+        Ok(Zeroizing::new(general_purpose::STANDARD.encode(*bytes)))
+    }
+
+    pub fn algorithm(&self) -> String {
+        CUSTOMER_ALGORITHM.to_string()
+    }
+
+    pub fn key(&self) -> String {
+        self.key.to_string()
+    }
+
+    pub fn key_md5(&self) -> String {
+        self.key_md5.clone()
+    }
+}
+
+/// Encryption headers for the requests that write an object.
+#[derive(Clone, Default)]
+pub enum EncryptionParams {
+    #[default]
+    BucketDefault,
+    Managed {
+        sse: ServerSideEncryption,
+        kms_key_id: Option<String>,
+    },
+    Customer(CustomerKey),
+}
+
+impl EncryptionParams {
+    pub fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+        match self {
+            EncryptionParams::Managed { sse, .. } => Some(sse.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn kms_key_id(&self) -> Option<String> {
+        match self {
+            EncryptionParams::Managed { kms_key_id, .. } => kms_key_id.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn customer_key(&self) -> Option<&CustomerKey> {
+        match self {
+            EncryptionParams::Customer(key) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+/// Vault entry holding the customer key of an account.
+pub fn customer_key_name(account: Option<&str>) -> String {
+    match account {
+        Some(account) => format!("sse-c/{}", account),
+        None => "sse-c".to_string(),
+    }
+}
+
+/// Whether S3 refused a read because the object is encrypted with a customer
+/// key that was not sent. HEAD responses have no body, so only the status is
+/// known for those.
+pub fn needs_customer_key<E: ProvideErrorMetadata>(e: &SdkError<E, HttpResponse>) -> bool {
+    e.raw_response()
+        .is_some_and(|response| response.status().as_u16() == 400)
+        && e.message()
+            .is_none_or(|message| message.contains("Server Side Encryption"))
+}
+
+/// The customer key of one account, read from the vault when it is needed so
+/// a key stored or replaced later is used without reconnecting.
+#[derive(Clone)]
+pub struct CustomerKeys {
+    vault: Arc<Vault>,
+    account: Option<String>,
+}
+
+impl CustomerKeys {
+    pub fn new(vault: Arc<Vault>, account: Option<String>) -> Self {
+        Self { vault, account }
+    }
+
+    fn name(&self) -> String {
+        customer_key_name(self.account.as_deref())
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.vault.contains(&self.name())
+    }
+
+    pub fn get(&self) -> Result<Option<CustomerKey>, String> {
+        if !self.is_set() {
+            return Ok(None);
+        }
+
+        self.vault
+            .get(&self.name())?
+            .map(|key| CustomerKey::parse(&key))
+            .transpose()
+    }
+
+    /// The key, for objects known to be encrypted with it.
+    pub fn require(&self) -> Result<CustomerKey, String> {
+        match self.get() {
+            Ok(Some(key)) => Ok(key),
+            Ok(None) => Err(
+                "This object is encrypted with a customer-provided key (SSE-C). Add the key in the account's encryption settings to read it."
+                    .to_string(),
+            ),
+            Err(e) => Err(format!(
+                "This object is encrypted with a customer-provided key (SSE-C): {}",
+                e
+            )),
+        }
+    }
+
+    pub fn set(&self, key: &str) -> Result<(), String> {
+        let key = CustomerKey::parse(key)?;
+        self.vault.set(&self.name(), &key.key)
+    }
+
+    pub fn remove(&self) -> Result<(), String> {
+        self.vault.remove(&self.name())
+    }
+
+    /// The headers for writing objects with `encryption`.
+    pub fn params(&self, encryption: &Encryption) -> Result<EncryptionParams, String> {
+        Ok(match encryption {
+            Encryption::BucketDefault => EncryptionParams::BucketDefault,
+            Encryption::SseS3 => EncryptionParams::Managed {
+                sse: ServerSideEncryption::Aes256,
+                kms_key_id: None,
+            },
+            Encryption::SseKms { key_id } => EncryptionParams::Managed {
+                sse: ServerSideEncryption::AwsKms,
+                kms_key_id: key_id.clone(),
+            },
+            Encryption::SseC => EncryptionParams::Customer(self.get()?.ok_or(
+                "No customer key is stored for this account; add one in its encryption settings",
+            )?),
+        })
+    }
+
+    /// Send a GET or HEAD request without a customer key, and again with the
+    /// account's key when S3 says the object needs one. Other errors go to
+    /// `recover`.
+    pub async fn send<T, E, F, Fut>(
+        &self,
+        send: F,
+        recover: impl FnOnce(SdkError<E, HttpResponse>) -> Result<T, String>,
+    ) -> Result<T, String>
+    where
+        E: ProvideErrorMetadata,
+        F: Fn(Option<&CustomerKey>) -> Fut,
+        Fut: Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+    {
+        match send(None).await {
+            Ok(output) => Ok(output),
+            Err(e) if needs_customer_key(&e) => {
+                let key = self.require()?;
+                send(Some(&key)).await.or_else(recover)
+            }
+            Err(e) => recover(e),
+        }
+    }
+}
@@ -0,0 +1,586 @@
+// An in-process stand-in for S3, speaking just enough of the REST API for the
+// tests of the S3 backend: path-style object reads and writes, ranged GETs,
+// copies, ListObjectsV2, ListObjectVersions of unversioned buckets, batch
+// deletes and bucket deletes. Each connection carries a single request.
+
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use percent_encoding::percent_decode_str;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+struct Object {
+    data: Vec<u8>,
+    content_type: Option<String>,
+    modified: DateTime<Utc>,
+}
+
+impl Object {
+    fn e_tag(&self) -> String {
+        format!("\"{}\"", hex::encode(Md5::digest(&self.data)))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    buckets: BTreeMap<String, BTreeMap<String, Object>>,
+    // Keys DeleteObjects refuses with AccessDenied
+    denied: HashSet<String>,
+}
+
+struct Request {
+    method: String,
+    bucket: String,
+    key: String,
+    query: BTreeMap<String, String>,
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn xml(status: u16, body: String) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/xml".to_string())],
+            body: format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{}", body).into_bytes(),
+        }
+    }
+
+    fn error(status: u16, code: &str, message: &str) -> Self {
+        Self::xml(
+            status,
+            format!(
+                "<Error><Code>{}</Code><Message>{}</Message></Error>",
+                code,
+                escape(message)
+            ),
+        )
+    }
+
+    fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+}
+
+pub struct FakeS3 {
+    pub endpoint: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeS3 {
+    /// Start serving the given, empty buckets on a free local port.
+    pub async fn start(buckets: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State {
+            buckets: buckets
+                .iter()
+                .map(|bucket| (bucket.to_string(), BTreeMap::new()))
+                .collect(),
+            ..Default::default()
+        }));
+
+        let shared = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = shared.clone();
+                tokio::spawn(async move { serve(stream, state).await });
+            }
+        });
+
+        Self { endpoint, state }
+    }
+
+    /// Make batch deletes refuse `key`, as a bucket policy or object lock would.
+    pub fn deny_delete(&self, key: &str) {
+        self.state.lock().unwrap().denied.insert(key.to_string());
+    }
+
+    pub fn has_bucket(&self, bucket: &str) -> bool {
+        self.state.lock().unwrap().buckets.contains_key(bucket)
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let head = request.method == "HEAD";
+    let response = handle(&mut state.lock().unwrap(), request);
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    // A HEAD response describes the object but carries none of it
+    if !head {
+        out.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+    out.push_str("Connection: close\r\n\r\n");
+
+    let mut data = out.into_bytes();
+    if !head {
+        data.extend_from_slice(&response.body);
+    }
+    let _ = stream.write_all(&data).await;
+    let _ = stream.shutdown().await;
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        404 => "Not Found",
+        409 => "Conflict",
+        416 => "Range Not Satisfiable",
+        _ => "Not Implemented",
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 8192];
+
+    let end = loop {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buffer[..read]);
+        if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    let headers: BTreeMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    if headers
+        .get("expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"))
+    {
+        stream
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await
+            .ok()?;
+    }
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = data[end + 4..].to_vec();
+    while body.len() < length {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        body.extend_from_slice(&buffer[..read]);
+    }
+    if headers
+        .get("content-encoding")
+        .is_some_and(|encoding| encoding.contains("aws-chunked"))
+    {
+        body = decode_chunks(&body);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = decode(path.trim_start_matches('/'));
+    let (bucket, key) = path.split_once('/').unwrap_or((&path, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect();
+
+    Some(Request {
+        method,
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        query,
+        headers,
+        body,
+    })
+}
+
+// Bodies sent with a trailing checksum come as `<hex size>\r\n<data>\r\n`
+// chunks, ended by an empty one and the trailers
+fn decode_chunks(body: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut rest = body;
+
+    while let Some(line_end) = rest.windows(2).position(|window| window == b"\r\n") {
+        let line = String::from_utf8_lossy(&rest[..line_end]);
+        let size_hex = line.split(';').next().unwrap_or("0");
+        let size = usize::from_str_radix(size_hex.trim(), 16).unwrap_or(0);
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        data.extend_from_slice(&rest[start..start + size]);
+        rest = &rest[(start + size + 2).min(rest.len())..];
+    }
+
+    data
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().to_string()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+fn handle(state: &mut State, request: Request) -> Response {
+    if !state.buckets.contains_key(&request.bucket) {
+        return Response::error(404, "NoSuchBucket", "The specified bucket does not exist");
+    }
+
+    match (request.method.as_str(), request.key.is_empty()) {
+        ("HEAD", true) => Response::new(200),
+        ("GET", true) if request.query.contains_key("versions") => list_versions(state, &request),
+        ("GET", true) => list_objects(state, &request),
+        ("POST", true) if request.query.contains_key("delete") => delete_objects(state, &request),
+        ("DELETE", true) => {
+            if !state.buckets[&request.bucket].is_empty() {
+                return Response::error(409, "BucketNotEmpty", "The bucket is not empty");
+            }
+            state.buckets.remove(&request.bucket);
+            Response::new(204)
+        }
+        ("PUT", false) if request.headers.contains_key("x-amz-copy-source") => {
+            copy_object(state, &request)
+        }
+        ("PUT", false) => {
+            let object = Object {
+                data: request.body,
+                content_type: request.headers.get("content-type").cloned(),
+                modified: Utc::now(),
+            };
+            let e_tag = object.e_tag();
+            bucket_mut(state, &request.bucket).insert(request.key, object);
+            Response::new(200).header("ETag", e_tag)
+        }
+        ("HEAD" | "GET", false) => get_object(state, &request),
+        ("DELETE", false) => {
+            bucket_mut(state, &request.bucket).remove(&request.key);
+            Response::new(204)
+        }
+        _ => Response::error(501, "NotImplemented", "Not supported by the stand-in"),
+    }
+}
+
+fn bucket_mut<'a>(state: &'a mut State, bucket: &str) -> &'a mut BTreeMap<String, Object> {
+    state.buckets.get_mut(bucket).unwrap()
+}
+
+fn get_object(state: &State, request: &Request) -> Response {
+    let Some(object) = state.buckets[&request.bucket].get(&request.key) else {
+        return Response::error(404, "NoSuchKey", "The specified key does not exist");
+    };
+
+    let total = object.data.len();
+    let range = request
+        .headers
+        .get("range")
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.split_once('-'))
+        .map(|(start, end)| {
+            let start: usize = start.parse().unwrap_or(0);
+            let end = end
+                .parse::<usize>()
+                .map_or(total, |end| (end + 1).min(total));
+            (start, end)
+        });
+
+    let response = match range {
+        Some((start, _)) if start >= total => {
+            return Response::error(416, "InvalidRange", "The range is not satisfiable")
+        }
+        Some((start, end)) => {
+            let mut response = Response::new(206).header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end - 1, total),
+            );
+            response.body = object.data[start..end].to_vec();
+            response
+        }
+        None => {
+            let mut response = Response::new(200);
+            response.body = object.data.clone();
+            response
+        }
+    };
+
+    let response = response
+        .header("ETag", object.e_tag())
+        .header(
+            "Last-Modified",
+            object
+                .modified
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        )
+        .header(
+            "Content-Type",
+            object
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+        );
+
+    if request.method == "HEAD" {
+        response.header("Content-Length", total.to_string())
+    } else {
+        response
+    }
+}
+
+fn copy_object(state: &mut State, request: &Request) -> Response {
+    let source = decode(&request.headers["x-amz-copy-source"]);
+    let source = source.split('?').next().unwrap_or_default();
+    let (bucket, key) = source
+        .trim_start_matches('/')
+        .split_once('/')
+        .unwrap_or_default();
+
+    let Some(object) = state
+        .buckets
+        .get(bucket)
+        .and_then(|objects| objects.get(key))
+    else {
+        return Response::error(404, "NoSuchKey", "The specified key does not exist");
+    };
+    let copy = Object {
+        data: object.data.clone(),
+        content_type: object.content_type.clone(),
+        modified: Utc::now(),
+    };
+    let body = format!(
+        "<CopyObjectResult><ETag>{}</ETag><LastModified>{}</LastModified></CopyObjectResult>",
+        escape(&copy.e_tag()),
+        timestamp(&copy.modified)
+    );
+    bucket_mut(state, &request.bucket).insert(request.key.clone(), copy);
+
+    Response::xml(200, body)
+}
+
+fn max_keys(request: &Request) -> usize {
+    request
+        .query
+        .get("max-keys")
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(1000)
+        .max(1)
+}
+
+fn contents_xml(tag: &str, key: &str, object: &Object, extra: &str) -> String {
+    format!(
+        "<{tag}><Key>{}</Key>{extra}<LastModified>{}</LastModified><ETag>{}</ETag>\
+         <Size>{}</Size><StorageClass>STANDARD</StorageClass></{tag}>",
+        escape(key),
+        timestamp(&object.modified),
+        escape(&object.e_tag()),
+        object.data.len()
+    )
+}
+
+fn list_objects(state: &State, request: &Request) -> Response {
+    let objects = &state.buckets[&request.bucket];
+    let prefix = request.query.get("prefix").cloned().unwrap_or_default();
+    let delimiter = request.query.get("delimiter").filter(|d| !d.is_empty());
+    let after = request
+        .query
+        .get("continuation-token")
+        .or_else(|| request.query.get("start-after"))
+        .cloned()
+        .unwrap_or_default();
+
+    // Keys and common prefixes share one sorted sequence that pages are cut from
+    let mut prefixes = BTreeSet::new();
+    let mut entries: BTreeMap<String, Option<&Object>> = BTreeMap::new();
+    for (key, object) in objects.range(prefix.clone()..) {
+        let Some(rest) = key.strip_prefix(&prefix) else {
+            break;
+        };
+        match delimiter.and_then(|delimiter| rest.find(delimiter.as_str()).map(|i| (delimiter, i)))
+        {
+            Some((delimiter, index)) => {
+                let common = format!("{}{}", prefix, &rest[..index + delimiter.len()]);
+                prefixes.insert(common.clone());
+                entries.insert(common, None);
+            }
+            None => {
+                entries.insert(key.clone(), Some(object));
+            }
+        }
+    }
+
+    let max = max_keys(request);
+    let remaining: Vec<_> = entries.iter().filter(|(name, _)| **name > after).collect();
+    let page = &remaining[..remaining.len().min(max)];
+    let truncated = remaining.len() > max;
+
+    let mut body = format!(
+        "<ListBucketResult xmlns=\"{}\"><Name>{}</Name><Prefix>{}</Prefix>\
+         <KeyCount>{}</KeyCount><MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+        XMLNS,
+        escape(&request.bucket),
+        escape(&prefix),
+        page.len(),
+        max,
+        truncated
+    );
+    if let (true, Some((last, _))) = (truncated, page.last()) {
+        body.push_str(&format!(
+            "<NextContinuationToken>{}</NextContinuationToken>",
+            escape(last)
+        ));
+    }
+    for (name, object) in page {
+        match object {
+            Some(object) => body.push_str(&contents_xml("Contents", name, object, "")),
+            None => body.push_str(&format!(
+                "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                escape(name)
+            )),
+        }
+    }
+    body.push_str("</ListBucketResult>");
+
+    Response::xml(200, body)
+}
+
+// Unversioned objects are listed as their single version, "null"
+fn list_versions(state: &State, request: &Request) -> Response {
+    let objects = &state.buckets[&request.bucket];
+    let after = request.query.get("key-marker").cloned().unwrap_or_default();
+
+    let max = max_keys(request);
+    let remaining: Vec<_> = objects.iter().filter(|(key, _)| **key > after).collect();
+    let page = &remaining[..remaining.len().min(max)];
+    let truncated = remaining.len() > max;
+
+    let mut body = format!(
+        "<ListVersionsResult xmlns=\"{}\"><Name>{}</Name><MaxKeys>{}</MaxKeys>\
+         <IsTruncated>{}</IsTruncated>",
+        XMLNS,
+        escape(&request.bucket),
+        max,
+        truncated
+    );
+    if let (true, Some((last, _))) = (truncated, page.last()) {
+        body.push_str(&format!(
+            "<NextKeyMarker>{}</NextKeyMarker><NextVersionIdMarker>null</NextVersionIdMarker>",
+            escape(last)
+        ));
+    }
+    for (key, object) in page {
+        body.push_str(&contents_xml(
+            "Version",
+            key,
+            object,
+            "<VersionId>null</VersionId><IsLatest>true</IsLatest>",
+        ));
+    }
+    body.push_str("</ListVersionsResult>");
+
+    Response::xml(200, body)
+}
+
+// The text of every `<tag>` element in `xml`
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else {
+            break;
+        };
+        found.push(&after[..end]);
+        rest = &after[end + close.len()..];
+    }
+
+    found
+}
+
+fn delete_objects(state: &mut State, request: &Request) -> Response {
+    let xml = String::from_utf8_lossy(&request.body).to_string();
+    let quiet = elements(&xml, "Quiet").first() == Some(&"true");
+
+    let mut body = format!("<DeleteResult xmlns=\"{}\">", XMLNS);
+    for object in elements(&xml, "Object") {
+        let Some(key) = elements(object, "Key").first().map(|key| unescape(key)) else {
+            continue;
+        };
+        if state.denied.contains(&key) {
+            body.push_str(&format!(
+                "<Error><Key>{}</Key><Code>AccessDenied</Code><Message>Access Denied</Message></Error>",
+                escape(&key)
+            ));
+            continue;
+        }
+
+        bucket_mut(state, &request.bucket).remove(&key);
+        if !quiet {
+            body.push_str(&format!("<Deleted><Key>{}</Key></Deleted>", escape(&key)));
+        }
+    }
+    body.push_str("</DeleteResult>");
+
+    Response::xml(200, body)
+}
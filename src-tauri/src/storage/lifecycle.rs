@@ -0,0 +1,1334 @@
+// Bucket lifecycle configurations. Rules are kept in the JSON shape the AWS
+// CLI uses, so a configuration can be pasted in or out of the raw view, and
+// are converted to SDK types only when they are sent to S3.
+
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload as SdkAbortIncompleteMultipartUpload,
+    BucketLifecycleConfiguration, ExpirationStatus, LifecycleExpiration,
+    LifecycleRule as SdkLifecycleRule, LifecycleRuleAndOperator, LifecycleRuleFilter,
+    NoncurrentVersionExpiration as SdkNoncurrentVersionExpiration,
+    NoncurrentVersionTransition as SdkNoncurrentVersionTransition, Tag,
+    Transition as SdkTransition, TransitionStorageClass,
+};
+use aws_smithy_xml::decode::{try_data, Document, ScopedDecoder};
+use chrono::{NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Limits S3 puts on lifecycle configurations
+const MAX_RULES: usize = 1000;
+const MAX_RULE_ID_LENGTH: usize = 255;
+const MAX_NEWER_NONCURRENT_VERSIONS: i32 = 100;
+
+// Infrequent access classes only take objects that are at least 30 days old
+const MIN_INFREQUENT_ACCESS_DAYS: i32 = 30;
+
+const XML_NAMESPACE: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleConfiguration {
+    #[serde(default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// `Enabled` or `Disabled`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<LifecycleFilter>,
+    // Older configurations put the prefix on the rule itself; it is moved
+    // into the filter when a configuration is read
+    #[serde(default, skip_serializing)]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<Expiration>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub noncurrent_version_transitions: Vec<NoncurrentVersionTransition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_version_expiration: Option<NoncurrentVersionExpiration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+/// Which objects a rule applies to. At most one field may be set; `And`
+/// combines several conditions, and an empty filter matches every object.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<LifecycleTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_less_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub and: Option<LifecycleAnd>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleAnd {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<LifecycleTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_less_than: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleTag {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Dates are `YYYY-MM-DD` or an ISO 8601 time at midnight UTC.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Transition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    pub storage_class: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Expiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expired_object_delete_marker: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NoncurrentVersionTransition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_days: Option<i32>,
+    pub storage_class: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_noncurrent_versions: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NoncurrentVersionExpiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_noncurrent_versions: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AbortIncompleteMultipartUpload {
+    pub days_after_initiation: i32,
+}
+
+impl LifecycleConfiguration {
+    /// Parse the raw view, either `json` or `xml`.
+    pub fn parse(text: &str, format: &str) -> Result<Self, String> {
+        let mut configuration = match format {
+            "json" => serde_json::from_str::<Self>(text)
+                .map_err(|e| format!("Failed to parse lifecycle JSON: {}", e))?,
+            "xml" => parse_xml(text)?,
+            other => return Err(format!("Unsupported lifecycle format: {}", other)),
+        };
+
+        for rule in &mut configuration.rules {
+            if let Some(prefix) = rule.prefix.take() {
+                if rule.filter.is_none() {
+                    rule.filter = Some(LifecycleFilter {
+                        prefix: Some(prefix),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        Ok(configuration)
+    }
+
+    /// Render the configuration for the raw view.
+    pub fn format(&self, format: &str) -> Result<String, String> {
+        match format {
+            "json" => serde_json::to_string_pretty(self)
+                .map_err(|e| format!("Failed to format lifecycle rules: {}", e)),
+            "xml" => Ok(self.to_xml()),
+            other => Err(format!("Unsupported lifecycle format: {}", other)),
+        }
+    }
+
+    /// Check the rules against what S3 accepts, so mistakes are reported
+    /// per rule instead of as one MalformedXML error.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rules.len() > MAX_RULES {
+            return Err(format!("A bucket can have at most {} rules", MAX_RULES));
+        }
+
+        let mut ids = HashSet::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let label = match &rule.id {
+                Some(id) => format!("Rule {} ({})", index + 1, id),
+                None => format!("Rule {}", index + 1),
+            };
+
+            if let Some(id) = &rule.id {
+                if !ids.insert(id.as_str()) {
+                    return Err(format!("{}: the ID is used by another rule", label));
+                }
+            }
+
+            validate_rule(rule).map_err(|e| format!("{}: {}", label, e))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn from_sdk(rules: &[SdkLifecycleRule]) -> Self {
+        Self {
+            rules: rules.iter().map(rule_from_sdk).collect(),
+        }
+    }
+
+    /// Validate the rules and convert them for PutBucketLifecycleConfiguration.
+    pub fn to_sdk(&self) -> Result<BucketLifecycleConfiguration, String> {
+        self.validate()?;
+
+        let rules = self
+            .rules
+            .iter()
+            .map(rule_to_sdk)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        BucketLifecycleConfiguration::builder()
+            .set_rules(Some(rules))
+            .build()
+            .map_err(|e| format!("Failed to build lifecycle configuration: {}", e))
+    }
+
+    fn to_xml(&self) -> String {
+        let mut out = XmlOut::default();
+        out.line(&format!(
+            "<LifecycleConfiguration xmlns=\"{}\">",
+            XML_NAMESPACE
+        ));
+        out.depth += 1;
+
+        for rule in &self.rules {
+            out.open("Rule");
+            if let Some(id) = &rule.id {
+                out.field("ID", id);
+            }
+            out.open("Filter");
+            if let Some(filter) = &rule.filter {
+                if let Some(prefix) = &filter.prefix {
+                    out.field("Prefix", prefix);
+                }
+                if let Some(tag) = &filter.tag {
+                    out.tag(tag);
+                }
+                out.sizes(
+                    filter.object_size_greater_than,
+                    filter.object_size_less_than,
+                );
+                if let Some(and) = &filter.and {
+                    out.open("And");
+                    if let Some(prefix) = &and.prefix {
+                        out.field("Prefix", prefix);
+                    }
+                    for tag in &and.tags {
+                        out.tag(tag);
+                    }
+                    out.sizes(and.object_size_greater_than, and.object_size_less_than);
+                    out.close("And");
+                }
+            }
+            out.close("Filter");
+            out.field("Status", &rule.status);
+
+            for transition in &rule.transitions {
+                out.open("Transition");
+                if let Some(date) = &transition.date {
+                    out.field("Date", &xml_date(date));
+                }
+                out.number("Days", transition.days);
+                out.field("StorageClass", &transition.storage_class);
+                out.close("Transition");
+            }
+            if let Some(expiration) = &rule.expiration {
+                out.open("Expiration");
+                if let Some(date) = &expiration.date {
+                    out.field("Date", &xml_date(date));
+                }
+                out.number("Days", expiration.days);
+                if let Some(marker) = expiration.expired_object_delete_marker {
+                    out.field("ExpiredObjectDeleteMarker", &marker.to_string());
+                }
+                out.close("Expiration");
+            }
+            for transition in &rule.noncurrent_version_transitions {
+                out.open("NoncurrentVersionTransition");
+                out.number("NoncurrentDays", transition.noncurrent_days);
+                out.field("StorageClass", &transition.storage_class);
+                out.number(
+                    "NewerNoncurrentVersions",
+                    transition.newer_noncurrent_versions,
+                );
+                out.close("NoncurrentVersionTransition");
+            }
+            if let Some(expiration) = &rule.noncurrent_version_expiration {
+                out.open("NoncurrentVersionExpiration");
+                out.number("NoncurrentDays", expiration.noncurrent_days);
+                out.number(
+                    "NewerNoncurrentVersions",
+                    expiration.newer_noncurrent_versions,
+                );
+                out.close("NoncurrentVersionExpiration");
+            }
+            if let Some(abort) = &rule.abort_incomplete_multipart_upload {
+                out.open("AbortIncompleteMultipartUpload");
+                out.number("DaysAfterInitiation", Some(abort.days_after_initiation));
+                out.close("AbortIncompleteMultipartUpload");
+            }
+            out.close("Rule");
+        }
+
+        out.close("LifecycleConfiguration");
+        out.doc
+    }
+}
+
+fn validate_rule(rule: &LifecycleRule) -> Result<(), String> {
+    if let Some(id) = &rule.id {
+        if id.chars().count() > MAX_RULE_ID_LENGTH {
+            return Err(format!(
+                "the ID is longer than {} characters",
+                MAX_RULE_ID_LENGTH
+            ));
+        }
+    }
+
+    if !matches!(rule.status.as_str(), "Enabled" | "Disabled") {
+        return Err("the status must be Enabled or Disabled".to_string());
+    }
+
+    if rule.transitions.is_empty()
+        && rule.expiration.is_none()
+        && rule.noncurrent_version_transitions.is_empty()
+        && rule.noncurrent_version_expiration.is_none()
+        && rule.abort_incomplete_multipart_upload.is_none()
+    {
+        return Err("the rule has no actions".to_string());
+    }
+
+    let has_tags = match &rule.filter {
+        Some(filter) => validate_filter(filter)?,
+        None => false,
+    };
+
+    let mut classes = HashSet::new();
+    let mut last_transition_day = None;
+    for transition in &rule.transitions {
+        validate_storage_class(&transition.storage_class)?;
+        if !classes.insert(transition.storage_class.as_str()) {
+            return Err(format!(
+                "more than one transition to {}",
+                transition.storage_class
+            ));
+        }
+
+        match (transition.days, &transition.date) {
+            (Some(days), None) => {
+                validate_transition_days(days, &transition.storage_class, "transition")?;
+                last_transition_day = last_transition_day.max(Some(days));
+            }
+            (None, Some(date)) => {
+                parse_date(date)?;
+            }
+            _ => return Err("a transition needs either days or a date".to_string()),
+        }
+    }
+
+    if rule.transitions.iter().any(|t| t.days.is_some())
+        && rule.transitions.iter().any(|t| t.date.is_some())
+    {
+        return Err("transitions cannot mix days and dates".to_string());
+    }
+
+    if let Some(expiration) = &rule.expiration {
+        match (
+            expiration.days,
+            &expiration.date,
+            expiration.expired_object_delete_marker,
+        ) {
+            (Some(days), None, None) => {
+                if days < 1 {
+                    return Err("objects must expire at least 1 day after creation".to_string());
+                }
+                if let Some(transition_day) = last_transition_day {
+                    if days <= transition_day {
+                        return Err(
+                            "the expiration must come after the last transition".to_string()
+                        );
+                    }
+                }
+            }
+            (None, Some(date), None) => {
+                parse_date(date)?;
+            }
+            (None, None, Some(_)) => {
+                if has_tags {
+                    return Err(
+                        "expired delete markers cannot be removed by a rule with a tag filter"
+                            .to_string(),
+                    );
+                }
+            }
+            _ => {
+                return Err(
+                    "an expiration needs exactly one of days, a date or expired delete markers"
+                        .to_string(),
+                )
+            }
+        }
+    }
+
+    let mut classes = HashSet::new();
+    for transition in &rule.noncurrent_version_transitions {
+        validate_storage_class(&transition.storage_class)?;
+        if !classes.insert(transition.storage_class.as_str()) {
+            return Err(format!(
+                "more than one noncurrent transition to {}",
+                transition.storage_class
+            ));
+        }
+
+        let days = transition
+            .noncurrent_days
+            .ok_or("a noncurrent transition needs a number of days")?;
+        validate_transition_days(days, &transition.storage_class, "noncurrent transition")?;
+        validate_newer_versions(transition.newer_noncurrent_versions)?;
+    }
+
+    if let Some(expiration) = &rule.noncurrent_version_expiration {
+        match expiration.noncurrent_days {
+            Some(days) if days >= 1 => {}
+            _ => {
+                return Err(
+                    "noncurrent versions must expire at least 1 day after they are replaced"
+                        .to_string(),
+                )
+            }
+        }
+        validate_newer_versions(expiration.newer_noncurrent_versions)?;
+    }
+
+    if let Some(abort) = &rule.abort_incomplete_multipart_upload {
+        if abort.days_after_initiation < 1 {
+            return Err("incomplete uploads must be kept for at least 1 day".to_string());
+        }
+        if has_tags {
+            return Err(
+                "incomplete uploads cannot be aborted by a rule with a tag filter".to_string(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Returns whether the filter selects by tag
+fn validate_filter(filter: &LifecycleFilter) -> Result<bool, String> {
+    let conditions = [
+        filter.prefix.is_some(),
+        filter.tag.is_some(),
+        filter.object_size_greater_than.is_some(),
+        filter.object_size_less_than.is_some(),
+        filter.and.is_some(),
+    ];
+    if conditions.iter().filter(|&&set| set).count() > 1 {
+        return Err("combine several filter conditions with And".to_string());
+    }
+
+    if let Some(tag) = &filter.tag {
+        validate_tag(tag)?;
+    }
+    validate_sizes(
+        filter.object_size_greater_than,
+        filter.object_size_less_than,
+    )?;
+
+    let Some(and) = &filter.and else {
+        return Ok(filter.tag.is_some());
+    };
+
+    let conditions = usize::from(and.prefix.is_some())
+        + and.tags.len()
+        + usize::from(and.object_size_greater_than.is_some())
+        + usize::from(and.object_size_less_than.is_some());
+    if conditions < 2 {
+        return Err("an And filter needs at least two conditions".to_string());
+    }
+
+    let mut keys = HashSet::new();
+    for tag in &and.tags {
+        validate_tag(tag)?;
+        if !keys.insert(tag.key.as_str()) {
+            return Err(format!("the tag {} is used twice in the filter", tag.key));
+        }
+    }
+    validate_sizes(and.object_size_greater_than, and.object_size_less_than)?;
+
+    Ok(!and.tags.is_empty())
+}
+
+fn validate_tag(tag: &LifecycleTag) -> Result<(), String> {
+    if tag.key.is_empty() {
+        return Err("a filter tag has no key".to_string());
+    }
+    if tag.key.chars().count() > 128 || tag.value.chars().count() > 256 {
+        return Err(format!(
+            "the filter tag {} is too long (128 characters for keys, 256 for values)",
+            tag.key
+        ));
+    }
+    Ok(())
+}
+
+fn validate_sizes(greater_than: Option<i64>, less_than: Option<i64>) -> Result<(), String> {
+    if greater_than.is_some_and(|size| size < 0) || less_than.is_some_and(|size| size < 1) {
+        return Err("object sizes in the filter must be positive".to_string());
+    }
+    if let (Some(greater_than), Some(less_than)) = (greater_than, less_than) {
+        if greater_than >= less_than {
+            return Err("the minimum object size must be below the maximum".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn validate_storage_class(storage_class: &str) -> Result<(), String> {
+    if TransitionStorageClass::values().contains(&storage_class) {
+        Ok(())
+    } else {
+        Err(format!(
+            "objects cannot transition to {:?}; use one of {}",
+            storage_class,
+            TransitionStorageClass::values().join(", ")
+        ))
+    }
+}
+
+fn validate_transition_days(days: i32, storage_class: &str, action: &str) -> Result<(), String> {
+    if days < 0 {
+        return Err(format!("a {} cannot have negative days", action));
+    }
+    if matches!(storage_class, "STANDARD_IA" | "ONEZONE_IA") && days < MIN_INFREQUENT_ACCESS_DAYS {
+        return Err(format!(
+            "a {} to {} must wait at least {} days",
+            action, storage_class, MIN_INFREQUENT_ACCESS_DAYS
+        ));
+    }
+    Ok(())
+}
+
+fn validate_newer_versions(versions: Option<i32>) -> Result<(), String> {
+    match versions {
+        Some(versions) if !(1..=MAX_NEWER_NONCURRENT_VERSIONS).contains(&versions) => Err(format!(
+            "the number of newer versions to keep must be between 1 and {}",
+            MAX_NEWER_NONCURRENT_VERSIONS
+        )),
+        _ => Ok(()),
+    }
+}
+
+// S3 only takes lifecycle dates at midnight UTC
+fn parse_date(date: &str) -> Result<chrono::DateTime<Utc>, String> {
+    let date = date.trim();
+    let time = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(date).map(|time| time.to_utc()))
+        .map_err(|_| format!("{:?} is not a date such as 2030-01-31", date))?;
+
+    if time.time() != NaiveTime::MIN {
+        return Err(format!("the date {} must be at midnight UTC", date));
+    }
+
+    Ok(time)
+}
+
+fn xml_date(date: &str) -> String {
+    parse_date(date)
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+fn date_from_sdk(date: &DateTime) -> String {
+    chrono::DateTime::from_timestamp(date.secs(), 0)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| date.to_string())
+}
+
+fn date_to_sdk(date: &Option<String>) -> Result<Option<DateTime>, String> {
+    date.as_deref()
+        .map(|date| parse_date(date).map(|time| DateTime::from_secs(time.timestamp())))
+        .transpose()
+}
+
+fn tag_from_sdk(tag: &Tag) -> LifecycleTag {
+    LifecycleTag {
+        key: tag.key().to_string(),
+        value: tag.value().to_string(),
+    }
+}
+
+fn tag_to_sdk(tag: &LifecycleTag) -> Result<Tag, String> {
+    Tag::builder()
+        .key(&tag.key)
+        .value(&tag.value)
+        .build()
+        .map_err(|e| format!("Failed to build tags: {}", e))
+}
+
+#[allow(deprecated)]
+fn rule_from_sdk(rule: &SdkLifecycleRule) -> LifecycleRule {
+    let filter = match (rule.filter(), rule.prefix()) {
+        (Some(filter), _) => Some(LifecycleFilter {
+            prefix: filter.prefix().map(str::to_string),
+            tag: filter.tag().map(tag_from_sdk),
+            object_size_greater_than: filter.object_size_greater_than(),
+            object_size_less_than: filter.object_size_less_than(),
+            and: filter.and().map(|and| LifecycleAnd {
+                prefix: and.prefix().map(str::to_string),
+                tags: and.tags().iter().map(tag_from_sdk).collect(),
+                object_size_greater_than: and.object_size_greater_than(),
+                object_size_less_than: and.object_size_less_than(),
+            }),
+        }),
+        (None, Some(prefix)) => Some(LifecycleFilter {
+            prefix: Some(prefix.to_string()),
+            ..Default::default()
+        }),
+        (None, None) => None,
+    };
+
+    LifecycleRule {
+        id: rule.id().map(str::to_string),
+        status: rule.status().as_str().to_string(),
+        filter,
+        prefix: None,
+        transitions: rule
+            .transitions()
+            .iter()
+            .map(|transition| Transition {
+                date: transition.date().map(date_from_sdk),
+                days: transition.days(),
+                storage_class: transition
+                    .storage_class()
+                    .map(|class| class.as_str().to_string())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        expiration: rule.expiration().map(|expiration| Expiration {
+            date: expiration.date().map(date_from_sdk),
+            days: expiration.days(),
+            expired_object_delete_marker: expiration.expired_object_delete_marker(),
+        }),
+        noncurrent_version_transitions: rule
+            .noncurrent_version_transitions()
+            .iter()
+            .map(|transition| NoncurrentVersionTransition {
+                noncurrent_days: transition.noncurrent_days(),
+                storage_class: transition
+                    .storage_class()
+                    .map(|class| class.as_str().to_string())
+                    .unwrap_or_default(),
+                newer_noncurrent_versions: transition.newer_noncurrent_versions(),
+            })
+            .collect(),
+        noncurrent_version_expiration: rule.noncurrent_version_expiration().map(|expiration| {
+            NoncurrentVersionExpiration {
+                noncurrent_days: expiration.noncurrent_days(),
+                newer_noncurrent_versions: expiration.newer_noncurrent_versions(),
+            }
+        }),
+        abort_incomplete_multipart_upload: rule.abort_incomplete_multipart_upload().and_then(
+            |abort| {
+                abort
+                    .days_after_initiation()
+                    .map(|days| AbortIncompleteMultipartUpload {
+                        days_after_initiation: days,
+                    })
+            },
+        ),
+    }
+}
+
+fn rule_to_sdk(rule: &LifecycleRule) -> Result<SdkLifecycleRule, String> {
+    // A rule without a filter applies to the whole bucket, which S3 wants
+    // spelled out as an empty filter
+    let filter = rule.filter.clone().unwrap_or_default();
+    let and = filter
+        .and
+        .as_ref()
+        .map(|and| -> Result<_, String> {
+            Ok(LifecycleRuleAndOperator::builder()
+                .set_prefix(and.prefix.clone())
+                .set_tags(Some(
+                    and.tags.iter().map(tag_to_sdk).collect::<Result<_, _>>()?,
+                ))
+                .set_object_size_greater_than(and.object_size_greater_than)
+                .set_object_size_less_than(and.object_size_less_than)
+                .build())
+        })
+        .transpose()?;
+    let filter = LifecycleRuleFilter::builder()
+        .set_prefix(filter.prefix.clone())
+        .set_tag(filter.tag.as_ref().map(tag_to_sdk).transpose()?)
+        .set_object_size_greater_than(filter.object_size_greater_than)
+        .set_object_size_less_than(filter.object_size_less_than)
+        .set_and(and)
+        .build();
+
+    let mut transitions = Vec::new();
+    for transition in &rule.transitions {
+        transitions.push(
+            SdkTransition::builder()
+                .set_date(date_to_sdk(&transition.date)?)
+                .set_days(transition.days)
+                .storage_class(TransitionStorageClass::from(
+                    transition.storage_class.as_str(),
+                ))
+                .build(),
+        );
+    }
+
+    let expiration = match &rule.expiration {
+        Some(expiration) => Some(
+            LifecycleExpiration::builder()
+                .set_date(date_to_sdk(&expiration.date)?)
+                .set_days(expiration.days)
+                .set_expired_object_delete_marker(expiration.expired_object_delete_marker)
+                .build(),
+        ),
+        None => None,
+    };
+
+    SdkLifecycleRule::builder()
+        .set_id(rule.id.clone())
+        .status(ExpirationStatus::from(rule.status.as_str()))
+        .filter(filter)
+        .set_transitions((!transitions.is_empty()).then_some(transitions))
+        .set_expiration(expiration)
+        .set_noncurrent_version_transitions((!rule.noncurrent_version_transitions.is_empty()).then(
+            || {
+                rule.noncurrent_version_transitions
+                    .iter()
+                    .map(|transition| {
+                        SdkNoncurrentVersionTransition::builder()
+                            .set_noncurrent_days(transition.noncurrent_days)
+                            .storage_class(TransitionStorageClass::from(
+                                transition.storage_class.as_str(),
+                            ))
+                            .set_newer_noncurrent_versions(transition.newer_noncurrent_versions)
+                            .build()
+                    })
+                    .collect()
+            },
+        ))
+        .set_noncurrent_version_expiration(rule.noncurrent_version_expiration.as_ref().map(
+            |expiration| {
+                SdkNoncurrentVersionExpiration::builder()
+                    .set_noncurrent_days(expiration.noncurrent_days)
+                    .set_newer_noncurrent_versions(expiration.newer_noncurrent_versions)
+                    .build()
+            },
+        ))
+        .set_abort_incomplete_multipart_upload(rule.abort_incomplete_multipart_upload.as_ref().map(
+            |abort| {
+                SdkAbortIncompleteMultipartUpload::builder()
+                    .days_after_initiation(abort.days_after_initiation)
+                    .build()
+            },
+        ))
+        .build()
+        .map_err(|e| format!("Failed to build lifecycle rule: {}", e))
+}
+
+// Indented XML for the raw view; S3 itself gets its request from the SDK
+#[derive(Default)]
+struct XmlOut {
+    doc: String,
+    depth: usize,
+}
+
+impl XmlOut {
+    fn line(&mut self, line: &str) {
+        self.doc.push_str(&"  ".repeat(self.depth));
+        self.doc.push_str(line);
+        self.doc.push('\n');
+    }
+
+    fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth = self.depth.saturating_sub(1);
+        self.line(&format!("</{}>", tag));
+    }
+
+    fn field(&mut self, tag: &str, value: &str) {
+        self.line(&format!("<{}>{}</{}>", tag, escape_xml(value), tag));
+    }
+
+    fn number<T: ToString>(&mut self, tag: &str, value: Option<T>) {
+        if let Some(value) = value {
+            self.field(tag, &value.to_string());
+        }
+    }
+
+    fn tag(&mut self, tag: &LifecycleTag) {
+        self.open("Tag");
+        self.field("Key", &tag.key);
+        self.field("Value", &tag.value);
+        self.close("Tag");
+    }
+
+    fn sizes(&mut self, greater_than: Option<i64>, less_than: Option<i64>) {
+        self.number("ObjectSizeGreaterThan", greater_than);
+        self.number("ObjectSizeLessThan", less_than);
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn parse_xml(text: &str) -> Result<LifecycleConfiguration, String> {
+    let mut document = Document::new(text.trim());
+    let mut root = document
+        .root_element()
+        .map_err(|e| format!("Failed to parse lifecycle XML: {}", e))?;
+
+    if root.start_el().local() != "LifecycleConfiguration" {
+        return Err("The XML must have a LifecycleConfiguration element".to_string());
+    }
+
+    let mut rules = Vec::new();
+    while let Some(mut tag) = root.next_tag() {
+        match tag_name(&tag).as_str() {
+            "Rule" => rules.push(parse_rule(&mut tag)?),
+            other => return Err(unexpected_element(other, "LifecycleConfiguration")),
+        }
+    }
+
+    Ok(LifecycleConfiguration { rules })
+}
+
+fn parse_rule(decoder: &mut ScopedDecoder) -> Result<LifecycleRule, String> {
+    let mut rule = LifecycleRule::default();
+
+    while let Some(mut tag) = decoder.next_tag() {
+        match tag_name(&tag).as_str() {
+            "ID" => rule.id = Some(text(&mut tag)?),
+            "Status" => rule.status = text(&mut tag)?,
+            "Prefix" => rule.prefix = Some(text(&mut tag)?),
+            "Filter" => rule.filter = Some(parse_filter(&mut tag)?),
+            "Transition" => {
+                let mut transition = Transition::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "Date" => transition.date = Some(text(&mut tag)?),
+                        "Days" => transition.days = Some(number(&mut tag)?),
+                        "StorageClass" => transition.storage_class = text(&mut tag)?,
+                        other => return Err(unexpected_element(other, "Transition")),
+                    }
+                }
+                rule.transitions.push(transition);
+            }
+            "Expiration" => {
+                let mut expiration = Expiration::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "Date" => expiration.date = Some(text(&mut tag)?),
+                        "Days" => expiration.days = Some(number(&mut tag)?),
+                        "ExpiredObjectDeleteMarker" => {
+                            expiration.expired_object_delete_marker = Some(number(&mut tag)?)
+                        }
+                        other => return Err(unexpected_element(other, "Expiration")),
+                    }
+                }
+                rule.expiration = Some(expiration);
+            }
+            "NoncurrentVersionTransition" => {
+                let mut transition = NoncurrentVersionTransition::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "NoncurrentDays" => transition.noncurrent_days = Some(number(&mut tag)?),
+                        "StorageClass" => transition.storage_class = text(&mut tag)?,
+                        "NewerNoncurrentVersions" => {
+                            transition.newer_noncurrent_versions = Some(number(&mut tag)?)
+                        }
+                        other => {
+                            return Err(unexpected_element(other, "NoncurrentVersionTransition"))
+                        }
+                    }
+                }
+                rule.noncurrent_version_transitions.push(transition);
+            }
+            "NoncurrentVersionExpiration" => {
+                let mut expiration = NoncurrentVersionExpiration::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "NoncurrentDays" => expiration.noncurrent_days = Some(number(&mut tag)?),
+                        "NewerNoncurrentVersions" => {
+                            expiration.newer_noncurrent_versions = Some(number(&mut tag)?)
+                        }
+                        other => {
+                            return Err(unexpected_element(other, "NoncurrentVersionExpiration"))
+                        }
+                    }
+                }
+                rule.noncurrent_version_expiration = Some(expiration);
+            }
+            "AbortIncompleteMultipartUpload" => {
+                let mut abort = AbortIncompleteMultipartUpload::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "DaysAfterInitiation" => abort.days_after_initiation = number(&mut tag)?,
+                        other => {
+                            return Err(unexpected_element(other, "AbortIncompleteMultipartUpload"))
+                        }
+                    }
+                }
+                rule.abort_incomplete_multipart_upload = Some(abort);
+            }
+            other => return Err(unexpected_element(other, "Rule")),
+        }
+    }
+
+    Ok(rule)
+}
+
+fn parse_filter(decoder: &mut ScopedDecoder) -> Result<LifecycleFilter, String> {
+    let mut filter = LifecycleFilter::default();
+
+    while let Some(mut tag) = decoder.next_tag() {
+        match tag_name(&tag).as_str() {
+            "Prefix" => filter.prefix = Some(text(&mut tag)?),
+            "Tag" => filter.tag = Some(parse_tag(&mut tag)?),
+            "ObjectSizeGreaterThan" => filter.object_size_greater_than = Some(number(&mut tag)?),
+            "ObjectSizeLessThan" => filter.object_size_less_than = Some(number(&mut tag)?),
+            "And" => {
+                let mut and = LifecycleAnd::default();
+                while let Some(mut tag) = tag.next_tag() {
+                    match tag_name(&tag).as_str() {
+                        "Prefix" => and.prefix = Some(text(&mut tag)?),
+                        "Tag" => and.tags.push(parse_tag(&mut tag)?),
+                        "ObjectSizeGreaterThan" => {
+                            and.object_size_greater_than = Some(number(&mut tag)?)
+                        }
+                        "ObjectSizeLessThan" => and.object_size_less_than = Some(number(&mut tag)?),
+                        other => return Err(unexpected_element(other, "And")),
+                    }
+                }
+                filter.and = Some(and);
+            }
+            other => return Err(unexpected_element(other, "Filter")),
+        }
+    }
+
+    Ok(filter)
+}
+
+fn parse_tag(decoder: &mut ScopedDecoder) -> Result<LifecycleTag, String> {
+    let mut tag = LifecycleTag::default();
+
+    while let Some(mut element) = decoder.next_tag() {
+        match tag_name(&element).as_str() {
+            "Key" => tag.key = text(&mut element)?,
+            "Value" => tag.value = text(&mut element)?,
+            other => return Err(unexpected_element(other, "Tag")),
+        }
+    }
+
+    Ok(tag)
+}
+
+fn tag_name(decoder: &ScopedDecoder) -> String {
+    decoder.start_el().local().to_string()
+}
+
+fn text(decoder: &mut ScopedDecoder) -> Result<String, String> {
+    try_data(decoder)
+        .map(|data| data.trim().to_string())
+        .map_err(|e| format!("Failed to parse lifecycle XML: {}", e))
+}
+
+fn number<T: std::str::FromStr>(decoder: &mut ScopedDecoder) -> Result<T, String> {
+    let name = tag_name(decoder);
+    let value = text(decoder)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn unexpected_element(name: &str, parent: &str) -> String {
+    format!("Unexpected element <{}> in <{}>", name, parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: &str = r#"{
+  "Rules": [
+    {
+      "ID": "archive logs",
+      "Status": "Enabled",
+      "Filter": {
+        "And": {
+          "Prefix": "logs/",
+          "Tags": [
+            {
+              "Key": "team",
+              "Value": "a&b"
+            }
+          ],
+          "ObjectSizeGreaterThan": 1024
+        }
+      },
+      "Transitions": [
+        {
+          "Days": 30,
+          "StorageClass": "STANDARD_IA"
+        },
+        {
+          "Days": 90,
+          "StorageClass": "GLACIER"
+        }
+      ],
+      "Expiration": {
+        "Days": 365
+      },
+      "NoncurrentVersionTransitions": [
+        {
+          "NoncurrentDays": 30,
+          "StorageClass": "GLACIER_IR",
+          "NewerNoncurrentVersions": 2
+        }
+      ],
+      "NoncurrentVersionExpiration": {
+        "NoncurrentDays": 60,
+        "NewerNoncurrentVersions": 5
+      }
+    },
+    {
+      "ID": "cleanup",
+      "Status": "Disabled",
+      "Filter": {
+        "Prefix": "tmp/"
+      },
+      "Expiration": {
+        "Date": "2030-01-31"
+      },
+      "AbortIncompleteMultipartUpload": {
+        "DaysAfterInitiation": 7
+      }
+    },
+    {
+      "Status": "Enabled",
+      "Filter": {},
+      "Expiration": {
+        "ExpiredObjectDeleteMarker": true
+      }
+    }
+  ]
+}"#;
+
+    fn parse(json: &str) -> LifecycleConfiguration {
+        LifecycleConfiguration::parse(json, "json").unwrap()
+    }
+
+    fn json(configuration: &LifecycleConfiguration) -> String {
+        configuration.format("json").unwrap()
+    }
+
+    // A configuration with one rule, given as JSON
+    fn rule(rule: &str) -> LifecycleConfiguration {
+        parse(&format!(r#"{{"Rules": [{}]}}"#, rule))
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let configuration = parse(FULL);
+        configuration.validate().unwrap();
+        assert_eq!(json(&configuration), FULL);
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let configuration = parse(FULL);
+        let xml = configuration.format("xml").unwrap();
+        assert!(xml.starts_with(&format!(
+            "<LifecycleConfiguration xmlns=\"{}\">",
+            XML_NAMESPACE
+        )));
+        assert!(xml.contains("<Value>a&amp;b</Value>"));
+        assert!(xml.contains("<Date>2030-01-31T00:00:00.000Z</Date>"));
+
+        let parsed = LifecycleConfiguration::parse(&xml, "xml").unwrap();
+        // Dates come back in the ISO 8601 form of the XML
+        assert_eq!(
+            json(&parsed),
+            FULL.replace("2030-01-31", "2030-01-31T00:00:00.000Z")
+        );
+        assert_eq!(parsed.format("xml").unwrap(), xml);
+    }
+
+    #[test]
+    fn sdk_round_trip() {
+        let configuration = parse(FULL);
+        let sdk = configuration.to_sdk().unwrap();
+        assert_eq!(json(&LifecycleConfiguration::from_sdk(sdk.rules())), FULL);
+    }
+
+    #[test]
+    fn legacy_prefix_moves_into_the_filter() {
+        let configuration =
+            rule(r#"{"Status": "Enabled", "Prefix": "old/", "Expiration": {"Days": 1}}"#);
+        let filter = configuration.rules[0].filter.as_ref().unwrap();
+        assert_eq!(filter.prefix.as_deref(), Some("old/"));
+        assert!(configuration.rules[0].prefix.is_none());
+
+        let xml = "<LifecycleConfiguration><Rule><Prefix>old/</Prefix><Status>Enabled</Status>\
+                   <Expiration><Days>1</Days></Expiration></Rule></LifecycleConfiguration>";
+        let configuration = LifecycleConfiguration::parse(xml, "xml").unwrap();
+        assert_eq!(
+            json(&configuration),
+            json(&rule(
+                r#"{"Status": "Enabled", "Filter": {"Prefix": "old/"}, "Expiration": {"Days": 1}}"#
+            ))
+        );
+    }
+
+    #[test]
+    fn empty_rules() {
+        for configuration in [
+            parse("{}"),
+            parse(r#"{"Rules": []}"#),
+            LifecycleConfiguration::parse("<LifecycleConfiguration/>", "xml").unwrap(),
+            LifecycleConfiguration::parse(
+                &format!(
+                    "<LifecycleConfiguration xmlns=\"{}\"></LifecycleConfiguration>",
+                    XML_NAMESPACE
+                ),
+                "xml",
+            )
+            .unwrap(),
+        ] {
+            assert!(configuration.rules.is_empty());
+            configuration.validate().unwrap();
+            assert_eq!(json(&configuration), "{\n  \"Rules\": []\n}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for (text, format) in [
+            ("{", "json"),
+            (r#"{"Rules": [{"ID": "no status"}]}"#, "json"),
+            ("<Rules/>", "xml"),
+            ("<LifecycleConfiguration><Bogus/></LifecycleConfiguration>", "xml"),
+            ("<LifecycleConfiguration><Rule><Expiration><Days>soon</Days></Expiration></Rule></LifecycleConfiguration>", "xml"),
+            ("{}", "yaml"),
+        ] {
+            assert!(LifecycleConfiguration::parse(text, format).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let configuration = parse(
+            r#"{"Rules": [
+                {"ID": "a", "Status": "Enabled", "Expiration": {"Days": 1}},
+                {"ID": "b", "Status": "Enabled", "Expiration": {"Days": 1}},
+                {"ID": "a", "Status": "Enabled", "Expiration": {"Days": 2}}
+            ]}"#,
+        );
+        assert_eq!(
+            configuration.validate().unwrap_err(),
+            "Rule 3 (a): the ID is used by another rule"
+        );
+        assert!(configuration.to_sdk().is_err());
+
+        // Rules without an ID do not clash
+        let configuration = parse(
+            r#"{"Rules": [
+                {"Status": "Enabled", "Expiration": {"Days": 1}},
+                {"Status": "Enabled", "Expiration": {"Days": 2}}
+            ]}"#,
+        );
+        configuration.validate().unwrap();
+    }
+
+    #[test]
+    fn expiration_after_transitions() {
+        let cases = [
+            (
+                30,
+                Some("the expiration must come after the last transition"),
+            ),
+            (
+                90,
+                Some("the expiration must come after the last transition"),
+            ),
+            (91, None),
+        ];
+        for (days, error) in cases {
+            let configuration = rule(&format!(
+                r#"{{"ID": "x", "Status": "Enabled",
+                    "Transitions": [{{"Days": 30, "StorageClass": "STANDARD_IA"}}, {{"Days": 90, "StorageClass": "GLACIER"}}],
+                    "Expiration": {{"Days": {}}}}}"#,
+                days
+            ));
+            assert_eq!(
+                configuration.validate().err(),
+                error.map(|error| format!("Rule 1 (x): {}", error)),
+                "{} days",
+                days
+            );
+        }
+    }
+
+    #[test]
+    fn rule_errors() {
+        let cases = [
+            (
+                r#"{"Status": "enabled", "Expiration": {"Days": 1}}"#,
+                "the status must be Enabled or Disabled",
+            ),
+            (r#"{"Status": "Enabled"}"#, "the rule has no actions"),
+            (
+                r#"{"Status": "Enabled", "Expiration": {"Days": 0}}"#,
+                "objects must expire at least 1 day after creation",
+            ),
+            (
+                r#"{"Status": "Enabled", "Expiration": {"Days": 1, "Date": "2030-01-31"}}"#,
+                "an expiration needs exactly one of days, a date or expired delete markers",
+            ),
+            (
+                r#"{"Status": "Enabled", "Expiration": {"Date": "2030-01-31T12:00:00Z"}}"#,
+                "the date 2030-01-31T12:00:00Z must be at midnight UTC",
+            ),
+            (
+                r#"{"Status": "Enabled", "Expiration": {"Date": "soon"}}"#,
+                "\"soon\" is not a date such as 2030-01-31",
+            ),
+            (
+                r#"{"Status": "Enabled", "Transitions": [{"Days": 10, "StorageClass": "STANDARD_IA"}]}"#,
+                "a transition to STANDARD_IA must wait at least 30 days",
+            ),
+            (
+                r#"{"Status": "Enabled", "Transitions": [{"Days": 10, "StorageClass": "GLACIER"}, {"Days": 20, "StorageClass": "GLACIER"}]}"#,
+                "more than one transition to GLACIER",
+            ),
+            (
+                r#"{"Status": "Enabled", "Transitions": [{"Days": 10, "StorageClass": "GLACIER"}, {"Date": "2030-01-31", "StorageClass": "DEEP_ARCHIVE"}]}"#,
+                "transitions cannot mix days and dates",
+            ),
+            (
+                r#"{"Status": "Enabled", "Transitions": [{"StorageClass": "GLACIER"}]}"#,
+                "a transition needs either days or a date",
+            ),
+            (
+                r#"{"Status": "Enabled", "NoncurrentVersionExpiration": {"NoncurrentDays": 1, "NewerNoncurrentVersions": 101}}"#,
+                "the number of newer versions to keep must be between 1 and 100",
+            ),
+            (
+                r#"{"Status": "Enabled", "NoncurrentVersionExpiration": {}}"#,
+                "noncurrent versions must expire at least 1 day after they are replaced",
+            ),
+            (
+                r#"{"Status": "Enabled", "AbortIncompleteMultipartUpload": {"DaysAfterInitiation": 0}}"#,
+                "incomplete uploads must be kept for at least 1 day",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"Tag": {"Key": "a", "Value": "b"}}, "AbortIncompleteMultipartUpload": {"DaysAfterInitiation": 1}}"#,
+                "incomplete uploads cannot be aborted by a rule with a tag filter",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"Tag": {"Key": "a"}}, "Expiration": {"ExpiredObjectDeleteMarker": true}}"#,
+                "expired delete markers cannot be removed by a rule with a tag filter",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"Prefix": "a/", "ObjectSizeLessThan": 10}, "Expiration": {"Days": 1}}"#,
+                "combine several filter conditions with And",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"And": {"Prefix": "a/"}}, "Expiration": {"Days": 1}}"#,
+                "an And filter needs at least two conditions",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"And": {"Tags": [{"Key": "a"}, {"Key": "a"}]}}, "Expiration": {"Days": 1}}"#,
+                "the tag a is used twice in the filter",
+            ),
+            (
+                r#"{"Status": "Enabled", "Filter": {"And": {"ObjectSizeGreaterThan": 10, "ObjectSizeLessThan": 10}}, "Expiration": {"Days": 1}}"#,
+                "the minimum object size must be below the maximum",
+            ),
+        ];
+
+        for (json, error) in cases {
+            assert_eq!(
+                rule(json).validate().unwrap_err(),
+                format!("Rule 1: {}", error),
+                "{}",
+                json
+            );
+        }
+
+        let error = rule(
+            r#"{"Status": "Enabled", "Transitions": [{"Days": 10, "StorageClass": "STANDARD"}]}"#,
+        )
+        .validate()
+        .unwrap_err();
+        assert!(error.starts_with("Rule 1: objects cannot transition to \"STANDARD\""));
+    }
+
+    #[test]
+    fn too_many_rules() {
+        let rules = vec![r#"{"Status": "Enabled", "Expiration": {"Days": 1}}"#; MAX_RULES + 1];
+        let configuration = parse(&format!(r#"{{"Rules": [{}]}}"#, rules.join(",")));
+        assert_eq!(
+            configuration.validate().unwrap_err(),
+            format!("A bucket can have at most {} rules", MAX_RULES)
+        );
+    }
+}
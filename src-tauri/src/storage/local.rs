@@ -0,0 +1,636 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{sort_items, StorageProvider};
+use crate::transfer::TransferSettings;
+use crate::types::{DirectoryContents, FileItem};
+use crate::utils::{format_modified, get_file_icon};
+
+// Listings that have not been paged through for this long are dropped
+const CURSOR_TTL: Duration = Duration::from_secs(300);
+
+/// A sorted snapshot of a directory's entries that later pages are served from.
+///
+/// Only names and types are collected up front; metadata is read per page, which
+/// is what keeps huge directories responsive.
+struct ListingCursor {
+    dir: PathBuf,
+    entries: Vec<(PathBuf, bool)>,
+    last_used: Instant,
+}
+
+/// Storage backend for the local file system.
+pub struct LocalStorage {
+    cursors: Mutex<HashMap<u64, ListingCursor>>,
+    next_cursor_id: AtomicU64,
+}
+
+impl LocalStorage {
+    pub fn new() -> Self {
+        Self {
+            cursors: Mutex::new(HashMap::new()),
+            next_cursor_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Recursively search a directory for items whose name contains `query`.
+    pub fn search(&self, directory: &str, query: &str) -> Result<Vec<FileItem>, String> {
+        // Validate directory path for security
+        let dir_path = validate_path(directory)?;
+
+        if !dir_path.is_dir() {
+            return Err("Path is not a directory".to_string());
+        }
+
+        let query_lower = query.to_lowercase();
+        let mut results = Vec::new();
+
+        // Search recursively in the directory
+        search_directory_recursive(&dir_path, &query_lower, &mut results)?;
+
+        sort_items(&mut results);
+
+        Ok(results)
+    }
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Accept both bare paths and file:// URIs
+fn strip_scheme(path: &str) -> &str {
+    path.strip_prefix("file://").unwrap_or(path)
+}
+
+// Security: Validate and sanitize file paths to prevent directory traversal attacks
+pub fn validate_path(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(strip_scheme(path));
+
+    // Resolve the canonical path to prevent directory traversal
+    let canonical = path
+        .canonicalize()
+        .map_err(|_| "Invalid or inaccessible path".to_string())?;
+
+    // Get home directory for validation
+    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory".to_string())?;
+
+    // Allow access to home directory and its subdirectories
+    if canonical.starts_with(&home_dir) {
+        return Ok(canonical);
+    }
+
+    // Allow access to common system directories (read-only)
+    let allowed_system_paths = [
+        "/Applications",
+        "/System/Applications",
+        "/usr/local",
+        "/opt",
+    ];
+
+    for allowed_path in &allowed_system_paths {
+        if canonical.starts_with(allowed_path) {
+            return Ok(canonical);
+        }
+    }
+
+    Err("Access denied: Path is outside allowed directories".to_string())
+}
+
+// Validate path for write operations (more restrictive)
+pub fn validate_write_path(path: &str) -> Result<PathBuf, String> {
+    let canonical = validate_path(path)?;
+
+    // Only allow write operations in home directory
+    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory".to_string())?;
+
+    if !canonical.starts_with(&home_dir) {
+        return Err("Write access denied: Only home directory is writable".to_string());
+    }
+
+    Ok(canonical)
+}
+
+// Validate a path that does not exist yet by checking its parent directory
+pub fn validate_new_path(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(strip_scheme(path));
+    let parent = path.parent().ok_or("Cannot determine parent directory")?;
+    let name = path.file_name().ok_or("Cannot determine file name")?;
+
+    let parent = validate_write_path(&parent.to_string_lossy())?;
+
+    Ok(parent.join(name))
+}
+
+// Read the names of all entries in a directory, sorted like the rest of the UI
+fn read_sorted_entries(dir: &Path) -> Result<Vec<(PathBuf, bool)>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    let mut entries: Vec<(PathBuf, bool)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            let is_dir = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => path.is_dir(),
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => false,
+            };
+            (path, is_dir)
+        })
+        .collect();
+
+    entries.sort_by_cached_key(|(path, is_dir)| {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        (!is_dir, name)
+    });
+
+    Ok(entries)
+}
+
+// Continuation tokens have the form "<cursor id>:<offset>"
+fn parse_token(token: &str) -> Result<(u64, usize), String> {
+    token
+        .split_once(':')
+        .and_then(|(id, offset)| Some((id.parse().ok()?, offset.parse().ok()?)))
+        .ok_or_else(|| "Invalid continuation token".to_string())
+}
+
+pub fn file_item_from_path(path: &Path, metadata: Option<&fs::Metadata>) -> FileItem {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    let is_dir = path.is_dir();
+    let size = metadata.and_then(|m| if !is_dir { Some(m.len()) } else { None });
+
+    let modified = metadata
+        .and_then(|m| m.modified().ok())
+        .map(format_modified);
+
+    let icon = if is_dir {
+        "folder".to_string()
+    } else {
+        get_file_icon(&name)
+    };
+
+    FileItem {
+        name,
+        path: path.to_string_lossy().to_string(),
+        is_dir,
+        size,
+        modified,
+        icon,
+        version_id: None,
+        is_latest: false,
+        is_delete_marker: false,
+        storage_class: None,
+        archived: false,
+        restoring: false,
+    }
+}
+
+#[async_trait]
+impl StorageProvider for LocalStorage {
+    async fn list_page(
+        &self,
+        path: &str,
+        token: Option<String>,
+        page_size: usize,
+    ) -> Result<DirectoryContents, String> {
+        // Validate path for security
+        let dir_path = validate_path(path)?;
+
+        if !dir_path.is_dir() {
+            return Err("Path is not a directory".to_string());
+        }
+
+        let (cursor_id, offset) = match token {
+            Some(token) => parse_token(&token)?,
+            None => (self.next_cursor_id.fetch_add(1, Ordering::Relaxed), 0),
+        };
+
+        // Reading a huge directory takes a while, so it is done off the async
+        // workers and without holding the lock other listings need
+        let cached = {
+            let mut cursors = self.cursors.lock().map_err(|e| e.to_string())?;
+            cursors.retain(|_, cursor| cursor.last_used.elapsed() < CURSOR_TTL);
+            cursors.get(&cursor_id).map(|c| &c.dir) == Some(&dir_path)
+        };
+        // Re-read the directory if the cursor expired or belongs to another path
+        if !cached {
+            let dir = dir_path.clone();
+            let entries = tokio::task::spawn_blocking(move || read_sorted_entries(&dir))
+                .await
+                .map_err(|e| format!("Failed to read directory: {}", e))??;
+            self.cursors.lock().map_err(|e| e.to_string())?.insert(
+                cursor_id,
+                ListingCursor {
+                    dir: dir_path.clone(),
+                    entries,
+                    last_used: Instant::now(),
+                },
+            );
+        }
+
+        let (page, next_token) = {
+            let mut cursors = self.cursors.lock().map_err(|e| e.to_string())?;
+            let cursor = cursors
+                .get_mut(&cursor_id)
+                .ok_or("Listing cursor disappeared")?;
+            cursor.last_used = Instant::now();
+
+            let start = offset.min(cursor.entries.len());
+            let end = (start + page_size).min(cursor.entries.len());
+            let page: Vec<PathBuf> = cursor.entries[start..end]
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect();
+
+            let next_token = if end < cursor.entries.len() {
+                Some(format!("{}:{}", cursor_id, end))
+            } else {
+                cursors.remove(&cursor_id);
+                None
+            };
+
+            (page, next_token)
+        };
+
+        let items = tokio::task::spawn_blocking(move || {
+            page.iter()
+                .map(|path| file_item_from_path(path, fs::metadata(path).ok().as_ref()))
+                .collect()
+        })
+        .await
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+        let parent_path = dir_path.parent().map(|p| p.to_string_lossy().to_string());
+
+        Ok(DirectoryContents {
+            current_path: dir_path.to_string_lossy().to_string(),
+            parent_path,
+            items,
+            next_token,
+        })
+    }
+
+    async fn stat(&self, path: &str) -> Result<FileItem, String> {
+        let item_path = validate_path(path)?;
+
+        let metadata = item_path
+            .metadata()
+            .map_err(|e| format!("Failed to get metadata: {}", e))?;
+
+        let mut item = file_item_from_path(&item_path, Some(&metadata));
+        item.path = strip_scheme(path).to_string();
+
+        Ok(item)
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, String> {
+        Ok(Path::new(strip_scheme(path)).exists())
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<Vec<u8>, String> {
+        let file_path = validate_path(path)?;
+
+        if !file_path.is_file() {
+            return Err("Path is not a file".to_string());
+        }
+
+        let mut file =
+            fs::File::open(&file_path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        let mut buffer = Vec::new();
+        match length {
+            Some(length) => file.take(length).read_to_end(&mut buffer),
+            None => file.read_to_end(&mut buffer),
+        }
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        Ok(buffer)
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        let file_path = validate_new_path(path)?;
+
+        fs::write(&file_path, data).map_err(|e| format!("Failed to write file: {}", e))
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), String> {
+        let dir_path = validate_new_path(path)?;
+
+        fs::create_dir(&dir_path).map_err(|e| format!("Failed to create folder: {}", e))
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        // Validate path for write access
+        let item_path = validate_write_path(path)?;
+
+        if item_path.is_dir() {
+            fs::remove_dir_all(&item_path).map_err(|e| format!("Failed to delete folder: {}", e))
+        } else {
+            fs::remove_file(&item_path).map_err(|e| format!("Failed to delete file: {}", e))
+        }
+    }
+
+    async fn copy(
+        &self,
+        source: &str,
+        dest: &str,
+        _settings: TransferSettings,
+    ) -> Result<(), String> {
+        let source = validate_path(source)?;
+        let dest = validate_new_path(dest)?;
+
+        if source.is_dir() {
+            copy_dir_recursive(&source, &dest)
+        } else {
+            fs::copy(&source, &dest)
+                .map(|_| ())
+                .map_err(|e| format!("Failed to copy file: {}", e))
+        }
+    }
+
+    async fn rename(
+        &self,
+        source: &str,
+        dest: &str,
+        _settings: TransferSettings,
+    ) -> Result<(), String> {
+        let source = validate_write_path(source)?;
+        let dest = validate_new_path(dest)?;
+
+        fs::rename(&source, &dest).map_err(|e| format!("Failed to move item: {}", e))
+    }
+
+    fn join(&self, parent: &str, name: &str) -> String {
+        Path::new(strip_scheme(parent))
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn parent(&self, path: &str) -> Option<String> {
+        Path::new(strip_scheme(path))
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+    }
+
+    fn file_name(&self, path: &str) -> Option<String> {
+        Path::new(strip_scheme(path))
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+    }
+}
+
+fn search_directory_recursive(
+    dir: &Path,
+    query: &str,
+    results: &mut Vec<FileItem>,
+) -> Result<(), String> {
+    // Silently ignore directories we can't read (permission issues, etc.)
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Unknown")
+            .to_lowercase();
+
+        // Check if filename contains the search query
+        if name.contains(query) {
+            results.push(file_item_from_path(&path, entry.metadata().ok().as_ref()));
+        }
+
+        // Recursively search subdirectories, limiting results to prevent memory issues
+        if path.is_dir() && results.len() < 1000 {
+            let _ = search_directory_recursive(&path, query, results);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
+    fs::create_dir_all(dst).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    for entry in fs::read_dir(src).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if src_path.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path)?;
+        } else {
+            fs::copy(&src_path, &dst_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only paths below the home directory can be listed
+    fn fixture(files: &[&str], dirs: &[&str]) -> tempfile::TempDir {
+        let root = tempfile::tempdir_in(dirs::home_dir().unwrap()).unwrap();
+        for name in files {
+            fs::write(root.path().join(name), name).unwrap();
+        }
+        for name in dirs {
+            fs::create_dir(root.path().join(name)).unwrap();
+        }
+        root
+    }
+
+    fn path(dir: &tempfile::TempDir) -> String {
+        dir.path().to_string_lossy().to_string()
+    }
+
+    fn names(items: &[FileItem]) -> Vec<&str> {
+        items.iter().map(|item| item.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_token_reads_cursor_and_offset() {
+        assert_eq!(parse_token("7:500"), Ok((7, 500)));
+        assert_eq!(parse_token("0:0"), Ok((0, 0)));
+
+        for token in [
+            "", "7", "7:", ":500", "a:500", "7:b", "7:-1", "-7:1", "7:5:0", "7 :5",
+        ] {
+            assert_eq!(
+                parse_token(token),
+                Err("Invalid continuation token".to_string()),
+                "{:?}",
+                token
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn list_page_pages_through_sorted_entries() {
+        let dir = fixture(&["b.txt", "A.txt", "c.txt", "d.txt"], &["zeta", "Alpha"]);
+        let storage = LocalStorage::new();
+
+        let mut items = Vec::new();
+        let mut token = None;
+        let mut pages = 0;
+        loop {
+            let page = storage.list_page(&path(&dir), token, 4).await.unwrap();
+            assert!(page.items.len() <= 4);
+            items.extend(page.items);
+            pages += 1;
+            token = page.next_token;
+            if token.is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(pages, 2);
+        assert_eq!(
+            names(&items),
+            ["Alpha", "zeta", "A.txt", "b.txt", "c.txt", "d.txt"]
+        );
+        assert!(items[0].is_dir && !items[2].is_dir);
+        assert_eq!(items[3].size, Some(5));
+        // The cursor is dropped once the last page was served
+        assert!(storage.cursors.lock().unwrap().is_empty());
+
+        let all = storage.list(&path(&dir)).await.unwrap();
+        assert_eq!(names(&all.items), names(&items));
+    }
+
+    #[tokio::test]
+    async fn later_pages_come_from_the_snapshot() {
+        let dir = fixture(&["a", "b", "c", "d"], &[]);
+        let storage = LocalStorage::new();
+
+        let first = storage.list_page(&path(&dir), None, 2).await.unwrap();
+        assert_eq!(names(&first.items), ["a", "b"]);
+
+        // Entries created after the first page do not shift the offsets
+        fs::write(dir.path().join("0"), "").unwrap();
+        let second = storage
+            .list_page(&path(&dir), first.next_token, 2)
+            .await
+            .unwrap();
+        assert_eq!(names(&second.items), ["c", "d"]);
+        assert_eq!(second.next_token, None);
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_read_the_directory_again() {
+        let dir = fixture(&["a", "b", "c", "d"], &[]);
+        let storage = LocalStorage::new();
+
+        let first = storage.list_page(&path(&dir), None, 2).await.unwrap();
+        let token = first.next_token.unwrap();
+        let (id, _) = parse_token(&token).unwrap();
+
+        let Some(long_ago) = Instant::now().checked_sub(CURSOR_TTL) else {
+            return;
+        };
+        storage
+            .cursors
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .unwrap()
+            .last_used = long_ago;
+        fs::write(dir.path().join("0"), "").unwrap();
+
+        // The offset now applies to a fresh listing that includes the new file
+        let second = storage
+            .list_page(&path(&dir), Some(token), 2)
+            .await
+            .unwrap();
+        assert_eq!(names(&second.items), ["b", "c"]);
+        assert!(second.next_token.is_some());
+    }
+
+    #[tokio::test]
+    async fn tokens_of_other_directories_are_not_reused() {
+        let one = fixture(&["a", "b", "c"], &[]);
+        let two = fixture(&["x", "y", "z"], &[]);
+        let storage = LocalStorage::new();
+
+        let first = storage.list_page(&path(&one), None, 1).await.unwrap();
+        let page = storage
+            .list_page(&path(&two), first.next_token, 1)
+            .await
+            .unwrap();
+        assert_eq!(names(&page.items), ["y"]);
+
+        // An unknown cursor starts a new listing at the given offset
+        let page = storage
+            .list_page(&path(&two), Some("9999:2".to_string()), 5)
+            .await
+            .unwrap();
+        assert_eq!(names(&page.items), ["z"]);
+        assert_eq!(page.next_token, None);
+
+        let page = storage
+            .list_page(&path(&two), Some("9998:10".to_string()), 5)
+            .await
+            .unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.next_token, None);
+    }
+
+    #[tokio::test]
+    async fn list_page_rejects_bad_input() {
+        let dir = fixture(&["a"], &[]);
+        let storage = LocalStorage::new();
+
+        assert_eq!(
+            storage
+                .list_page(&path(&dir), Some("garbage".to_string()), 5)
+                .await
+                .unwrap_err(),
+            "Invalid continuation token"
+        );
+        assert!(storage
+            .list_page(&dir.path().join("a").to_string_lossy(), None, 5)
+            .await
+            .is_err());
+        assert!(storage
+            .list_page(&dir.path().join("missing").to_string_lossy(), None, 5)
+            .await
+            .is_err());
+        assert!(storage.list_page("/etc", None, 5).await.is_err());
+
+        let uri = format!("file://{}", path(&dir));
+        let page = storage.list_page(&uri, None, 5).await.unwrap();
+        assert_eq!(names(&page.items), ["a"]);
+    }
+}
@@ -0,0 +1,304 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::accounts::AccountStore;
+use crate::transfer::TransferSettings;
+use crate::types::{DirectoryContents, FileItem};
+use crate::vault::Vault;
+
+pub mod archive;
+pub mod bucket;
+pub mod encryption;
+#[cfg(test)]
+mod fake_s3;
+pub mod lifecycle;
+pub mod local;
+pub mod presign;
+pub mod s3;
+pub mod trash;
+pub mod watch;
+
+use encryption::CustomerKeys;
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Number of entries returned per page when the caller does not ask for a size.
+pub const DEFAULT_PAGE_SIZE: usize = 500;
+
+// ListObjectsV2 never returns more than 1000 keys per request
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// A location the frontend can browse, parsed from the path strings it sends us.
+///
+/// Bare paths and `file://` URIs are local, `s3://bucket/key` addresses an object
+/// or prefix in a bucket. Objects reached through a configured account are written
+/// as `s3://account@bucket/key`, while paths without an account use the
+/// connection from the AWS environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageUri {
+    Local(String),
+    S3 {
+        account: Option<String>,
+        bucket: String,
+        key: String,
+    },
+}
+
+impl StorageUri {
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        if let Some(rest) = uri.strip_prefix("s3://") {
+            let (authority, key) = rest.split_once('/').unwrap_or((rest, ""));
+            // Bucket names cannot contain `@`, so it always ends the account ID
+            let (account, bucket) = match authority.split_once('@') {
+                Some((account, bucket)) => (Some(account.to_string()), bucket),
+                None => (None, authority),
+            };
+            return Ok(StorageUri::S3 {
+                account,
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            });
+        }
+
+        if let Some(rest) = uri.strip_prefix("file://") {
+            return Ok(StorageUri::Local(rest.to_string()));
+        }
+
+        if uri.contains("://") {
+            return Err(format!("Unsupported storage location: {}", uri));
+        }
+
+        Ok(StorageUri::Local(uri.to_string()))
+    }
+
+    /// The account an S3 location belongs to, `None` for local paths and for the
+    /// environment connection.
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            StorageUri::S3 { account, .. } => account.as_deref(),
+            StorageUri::Local(_) => None,
+        }
+    }
+
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            StorageUri::Local(_) => "file",
+            StorageUri::S3 { .. } => "s3",
+        }
+    }
+}
+
+/// Format an S3 location the way [`StorageUri::parse`] reads it back.
+pub fn s3_uri(account: Option<&str>, bucket: &str, key: &str) -> String {
+    match account {
+        Some(account) if bucket.is_empty() => format!("s3://{}@", account),
+        Some(account) => format!("s3://{}@{}/{}", account, bucket, key),
+        None if bucket.is_empty() => "s3://".to_string(),
+        None => format!("s3://{}/{}", bucket, key),
+    }
+}
+
+/// Common operations every storage backend has to support so the Tauri commands
+/// can stay agnostic of where an item actually lives.
+///
+/// All paths are passed exactly as the frontend knows them (a local path or an
+/// `s3://` URI), and every path a provider hands back must use the same form.
+#[async_trait]
+pub trait StorageProvider: Send + Sync {
+    /// List one page of the immediate children of a directory or prefix. Passing
+    /// the `next_token` of a page continues the listing where that page stopped.
+    async fn list_page(
+        &self,
+        path: &str,
+        token: Option<String>,
+        page_size: usize,
+    ) -> Result<DirectoryContents, String>;
+
+    /// List every child of a directory or prefix by following all pages.
+    async fn list(&self, path: &str) -> Result<DirectoryContents, String> {
+        let mut contents = self.list_page(path, None, MAX_PAGE_SIZE).await?;
+
+        while let Some(token) = contents.next_token.take() {
+            let page = self.list_page(path, Some(token), MAX_PAGE_SIZE).await?;
+            contents.items.extend(page.items);
+            contents.next_token = page.next_token;
+        }
+
+        Ok(contents)
+    }
+
+    async fn stat(&self, path: &str) -> Result<FileItem, String>;
+
+    async fn exists(&self, path: &str) -> Result<bool, String>;
+
+    /// Read `length` bytes starting at `offset`, or everything up to the end when
+    /// `length` is `None`.
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<Vec<u8>, String>;
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<(), String>;
+
+    async fn create_dir(&self, path: &str) -> Result<(), String>;
+
+    /// Delete a file, or a directory together with everything below it.
+    async fn delete(&self, path: &str) -> Result<(), String>;
+
+    /// Copy a file or directory tree to `dest`, which must not exist yet.
+    /// Large objects are copied in parts as `settings` say.
+    async fn copy(
+        &self,
+        source: &str,
+        dest: &str,
+        settings: TransferSettings,
+    ) -> Result<(), String>;
+
+    /// Move a file or directory to `dest`, which must not exist yet.
+    async fn rename(
+        &self,
+        source: &str,
+        dest: &str,
+        settings: TransferSettings,
+    ) -> Result<(), String>;
+
+    fn join(&self, parent: &str, name: &str) -> String;
+
+    fn parent(&self, path: &str) -> Option<String>;
+
+    fn file_name(&self, path: &str) -> Option<String>;
+}
+
+/// Owns one instance of every storage backend and picks the right one for a path.
+pub struct StorageManager {
+    local: Arc<LocalStorage>,
+    accounts: Arc<AccountStore>,
+    // One S3 backend per account, `None` being the AWS environment
+    s3: Mutex<HashMap<Option<String>, Arc<S3Storage>>>,
+}
+
+impl StorageManager {
+    pub fn new() -> Self {
+        Self {
+            local: Arc::new(LocalStorage::new()),
+            accounts: Arc::new(AccountStore::load(Arc::new(Vault::open()))),
+            s3: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn local(&self) -> Arc<LocalStorage> {
+        self.local.clone()
+    }
+
+    pub fn accounts(&self) -> Arc<AccountStore> {
+        self.accounts.clone()
+    }
+
+    /// The S3 backend for an account, created the first time one of its paths
+    /// is used. `None` selects the connection from the AWS environment.
+    pub async fn s3(&self, account: Option<&str>) -> Result<Arc<S3Storage>, String> {
+        let mut backends = self.s3.lock().await;
+        let id = account.map(str::to_string);
+
+        if let Some(storage) = backends.get(&id) {
+            return Ok(storage.clone());
+        }
+
+        let keys = CustomerKeys::new(self.accounts.vault(), id.clone());
+        let storage = Arc::new(match account {
+            Some(account) => {
+                S3Storage::from_account(
+                    &self.accounts.get(account)?,
+                    &self.accounts.sessions(),
+                    keys,
+                )
+                .await
+            }
+            None => S3Storage::from_env(keys).await?,
+        });
+        backends.insert(id, storage.clone());
+
+        Ok(storage)
+    }
+
+    /// The S3 backend serving an `s3://` path.
+    pub async fn s3_for(&self, path: &str) -> Result<Arc<S3Storage>, String> {
+        match StorageUri::parse(path)? {
+            StorageUri::S3 { account, .. } => self.s3(account.as_deref()).await,
+            StorageUri::Local(_) => Err(format!("Not an S3 location: {}", path)),
+        }
+    }
+
+    /// Drop the cached backend of an account so the next use picks up edited
+    /// settings.
+    pub async fn forget_account(&self, id: &str) {
+        self.s3.lock().await.remove(&Some(id.to_string()));
+    }
+
+    /// Drop the backends and role sessions of all accounts, whose clients hold
+    /// credentials from the vault, once it has been locked.
+    pub async fn forget_accounts(&self) {
+        self.s3.lock().await.retain(|account, _| account.is_none());
+        self.accounts.sessions().clear();
+    }
+
+    pub async fn provider(&self, uri: &str) -> Result<Arc<dyn StorageProvider>, String> {
+        match StorageUri::parse(uri)? {
+            StorageUri::Local(_) => Ok(self.local.clone()),
+            StorageUri::S3 { account, .. } => Ok(self.s3(account.as_deref()).await?),
+        }
+    }
+
+    /// Resolve the provider for an operation that involves two paths, which must
+    /// currently live on the same backend.
+    pub async fn provider_for_pair(
+        &self,
+        source: &str,
+        dest: &str,
+    ) -> Result<Arc<dyn StorageProvider>, String> {
+        let source_uri = StorageUri::parse(source)?;
+        let dest_uri = StorageUri::parse(dest)?;
+
+        if source_uri.scheme() != dest_uri.scheme() {
+            return Err(format!(
+                "Transfers from {} to {} storage are not supported",
+                source_uri.scheme(),
+                dest_uri.scheme()
+            ));
+        }
+
+        if source_uri.account() != dest_uri.account() {
+            return Err("Transfers between S3 accounts are not supported".to_string());
+        }
+
+        self.provider(source).await
+    }
+}
+
+impl Default for StorageManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Validate a file or folder name coming from the UI to prevent injection
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name == "." || name == ".." {
+        return Err("Invalid name".to_string());
+    }
+
+    Ok(())
+}
+
+// Sort items: directories first, then files, both alphabetically
+pub fn sort_items(items: &mut [FileItem]) {
+    items.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+}
@@ -0,0 +1,2259 @@
+use async_trait::async_trait;
+use aws_config::BehaviorVersion;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::config::SharedCredentialsProvider;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata};
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::types::{
+    BucketLocationConstraint, BucketVersioningStatus, ChecksumMode, CreateBucketConfiguration,
+    Delete, GlacierJobParameters, ObjectIdentifier, OptionalObjectAttributes, RestoreRequest,
+    ServerSideEncryption, StorageClass, Tag, Tagging, Tier, VersioningConfiguration,
+};
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinSet;
+
+use super::archive::{is_archive_class, validate_restore, validate_storage_class, RestoreState};
+use super::bucket::{
+    cors_to_sdk, validate_bucket_name, BucketProperties, CorsRule, NewBucket, PropertyDiff,
+    PublicAccessBlock,
+};
+use super::encryption::{needs_customer_key, CustomerKey, CustomerKeys};
+use super::lifecycle::LifecycleConfiguration;
+use super::presign::{presign, PresignRequest, PresignedUrl};
+use super::{s3_uri, sort_items, StorageProvider, StorageUri, MAX_PAGE_SIZE};
+use crate::accounts::session::RoleSessions;
+use crate::accounts::{build_client, Account, DEFAULT_REGION};
+use crate::transfer::delete::{DeleteFailure, DeleteProgress, DeleteProgressFn};
+use crate::transfer::{no_progress, ProgressFn, ServerSideCopier, TransferSettings};
+use crate::types::{DirectoryContents, FileItem, ObjectMetadata, ObjectPatch};
+use crate::utils::get_file_icon;
+
+// DeleteObjects accepts at most 1000 keys per request
+const DELETE_BATCH_SIZE: usize = 1000;
+
+// Limits S3 puts on user metadata and object tags
+const MAX_METADATA_BYTES: usize = 2048;
+const MAX_TAGS: usize = 10;
+const MAX_TAG_KEY_LENGTH: usize = 128;
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+/// Storage backend for S3 and S3-compatible object stores.
+///
+/// Buckets and common prefixes are presented as directories, so a location such as
+/// `s3://bucket/photos/` behaves like a folder. Directory paths always end with `/`.
+pub struct S3Storage {
+    client: Client,
+    account: Option<String>,
+    // What the client signs with and where it sends requests, needed to
+    // build presigned URLs
+    credentials: Option<SharedCredentialsProvider>,
+    endpoint_url: Option<String>,
+    path_style: bool,
+    keys: CustomerKeys,
+}
+
+impl S3Storage {
+    pub fn new(
+        client: Client,
+        account: Option<String>,
+        credentials: Option<SharedCredentialsProvider>,
+        endpoint_url: Option<String>,
+        path_style: bool,
+        keys: CustomerKeys,
+    ) -> Self {
+        Self {
+            client,
+            account,
+            credentials,
+            endpoint_url,
+            path_style,
+            keys,
+        }
+    }
+
+    /// Build a client for a configured account. Its paths are written as
+    /// `s3://account@bucket/key`.
+    pub async fn from_account(
+        account: &Account,
+        sessions: &Arc<RoleSessions>,
+        keys: CustomerKeys,
+    ) -> Self {
+        let (client, credentials) = build_client(account, sessions).await;
+
+        Self::new(
+            client,
+            Some(account.id.clone()),
+            credentials,
+            account.endpoint_url.clone(),
+            account.path_style,
+            keys,
+        )
+    }
+
+    /// Build a client from the standard AWS environment (env vars, shared config
+    /// files, IAM roles). Setting `AWS_ENDPOINT_URL` points it at MinIO or any
+    /// other S3-compatible service, in which case path-style addressing is used.
+    pub async fn from_env(keys: CustomerKeys) -> Result<Self, String> {
+        let sdk_config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+
+        let endpoint_url = sdk_config.endpoint_url().map(str::to_string);
+        let force_path_style = endpoint_url.is_some();
+        let config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(force_path_style)
+            .build();
+
+        Ok(Self::new(
+            Client::from_conf(config),
+            None,
+            sdk_config.credentials_provider(),
+            endpoint_url,
+            force_path_style,
+            keys,
+        ))
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The SSE-C key of the account, sent with reads of objects encrypted
+    /// with it.
+    pub fn customer_keys(&self) -> &CustomerKeys {
+        &self.keys
+    }
+
+    /// The account this backend connects with, `None` for the AWS environment.
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    fn uri(&self, bucket: &str, key: &str) -> String {
+        s3_uri(self.account(), bucket, key)
+    }
+
+    // Listings can say whether archived objects were restored, but only AWS
+    // is known to accept the attribute; other services get the class alone
+    fn restore_status_attribute(&self) -> Option<Vec<OptionalObjectAttributes>> {
+        self.endpoint_url
+            .is_none()
+            .then(|| vec![OptionalObjectAttributes::RestoreStatus])
+    }
+
+    async fn list_buckets(&self) -> Result<DirectoryContents, String> {
+        let output = self
+            .client
+            .list_buckets()
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list buckets: {}", DisplayErrorContext(&e)))?;
+
+        let mut items: Vec<FileItem> = output
+            .buckets()
+            .iter()
+            .filter_map(|bucket| bucket.name())
+            .map(|name| FileItem {
+                name: name.to_string(),
+                path: self.uri(name, ""),
+                is_dir: true,
+                size: None,
+                modified: None,
+                icon: "folder".to_string(),
+                version_id: None,
+                is_latest: false,
+                is_delete_marker: false,
+                storage_class: None,
+                archived: false,
+                restoring: false,
+            })
+            .collect();
+
+        sort_items(&mut items);
+
+        Ok(DirectoryContents {
+            current_path: self.uri("", ""),
+            parent_path: None,
+            items,
+            next_token: None,
+        })
+    }
+
+    /// Collect every object key below a prefix, without a delimiter.
+    async fn list_keys_recursive(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, String> {
+        let mut keys = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+            keys.extend(
+                page.contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(str::to_string)),
+            );
+        }
+
+        Ok(keys)
+    }
+
+    /// Copy an object or a whole prefix inside S3, without downloading it.
+    pub async fn copy_with_progress(
+        &self,
+        source: &str,
+        dest: &str,
+        settings: TransferSettings,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let (source_bucket, source_key) = require_bucket(source)?;
+        let (dest_bucket, dest_key) = require_bucket(dest)?;
+
+        if StorageUri::parse(dest)?.account() != self.account() {
+            return Err("Copies between S3 accounts are not supported".to_string());
+        }
+
+        let copier = ServerSideCopier::new(self, settings);
+
+        if self.is_prefix(&source_bucket, &source_key).await? {
+            if source_bucket == dest_bucket
+                && as_prefix(&dest_key).starts_with(&as_prefix(&source_key))
+            {
+                return Err("Cannot copy a folder into itself".to_string());
+            }
+
+            copier
+                .copy_prefix(
+                    &source_bucket,
+                    &as_prefix(&source_key),
+                    &dest_bucket,
+                    &as_prefix(&dest_key),
+                    progress,
+                )
+                .await
+        } else {
+            copier
+                .copy_object(
+                    &source_bucket,
+                    &source_key,
+                    &dest_bucket,
+                    &dest_key,
+                    progress,
+                )
+                .await
+        }
+    }
+
+    /// Total size in bytes of an object, or of every object below a prefix.
+    pub async fn total_size(&self, path: &str) -> Result<u64, String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        if !self.is_prefix(&bucket, &key).await? {
+            return Ok(self.stat(path).await?.size.unwrap_or(0));
+        }
+
+        let mut total = 0;
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&bucket)
+            .prefix(as_prefix(&key))
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+            total += page
+                .contents()
+                .iter()
+                .map(|object| object.size().unwrap_or(0).max(0) as u64)
+                .sum::<u64>();
+        }
+
+        Ok(total)
+    }
+
+    /// List one page of every version and delete marker below a prefix, with
+    /// the sub-prefixes as directories. Versions of a key are newest first.
+    pub async fn list_versions_page(
+        &self,
+        path: &str,
+        token: Option<String>,
+        page_size: usize,
+    ) -> Result<DirectoryContents, String> {
+        let (bucket, key) = require_bucket(path)?;
+        let prefix = as_prefix(&key);
+
+        // The token carries both markers ListObjectVersions continues from
+        let (key_marker, version_id_marker) = match token {
+            Some(token) => serde_json::from_str::<(String, Option<String>)>(&token)
+                .map(|(key, version)| (Some(key), version))
+                .map_err(|_| "Invalid continuation token".to_string())?,
+            None => (None, None),
+        };
+
+        let output = self
+            .client
+            .list_object_versions()
+            .bucket(&bucket)
+            .prefix(&prefix)
+            .delimiter("/")
+            .max_keys(page_size.min(MAX_PAGE_SIZE) as i32)
+            .set_key_marker(key_marker)
+            .set_version_id_marker(version_id_marker)
+            .set_optional_object_attributes(self.restore_status_attribute())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list versions: {}", DisplayErrorContext(&e)))?;
+
+        let mut items = Vec::new();
+
+        for common_prefix in output.common_prefixes() {
+            if let Some(sub_prefix) = common_prefix.prefix() {
+                items.push(directory_item(self.account(), &bucket, sub_prefix));
+            }
+        }
+        sort_items(&mut items);
+
+        // Versions and delete markers come back in separate lists
+        let mut versions = Vec::new();
+        for version in output.versions() {
+            match version.key() {
+                Some(object_key) if object_key != prefix => {
+                    let mut item = object_item(
+                        self.account(),
+                        &bucket,
+                        object_key,
+                        version.size(),
+                        version.last_modified(),
+                    );
+                    item.version_id = version.version_id().map(str::to_string);
+                    item.is_latest = version.is_latest().unwrap_or(false);
+                    set_storage(
+                        &mut item,
+                        version.storage_class().map(|class| class.as_str()),
+                        version.restore_status().map(RestoreState::from_sdk),
+                    );
+                    versions.push((object_key, version.last_modified(), item));
+                }
+                _ => {}
+            }
+        }
+        for marker in output.delete_markers() {
+            if let Some(object_key) = marker.key() {
+                let mut item = object_item(
+                    self.account(),
+                    &bucket,
+                    object_key,
+                    None,
+                    marker.last_modified(),
+                );
+                item.version_id = marker.version_id().map(str::to_string);
+                item.is_latest = marker.is_latest().unwrap_or(false);
+                item.is_delete_marker = true;
+                versions.push((object_key, marker.last_modified(), item));
+            }
+        }
+
+        versions.sort_by(|(key_a, time_a, _), (key_b, time_b, _)| {
+            key_a.cmp(key_b).then_with(|| {
+                let secs = |time: &Option<&DateTime>| time.map(|t| (t.secs(), t.subsec_nanos()));
+                secs(time_b).cmp(&secs(time_a))
+            })
+        });
+        items.extend(versions.into_iter().map(|(_, _, item)| item));
+
+        let next_token = if output.is_truncated().unwrap_or(false) {
+            output
+                .next_key_marker()
+                .map(|key| {
+                    serde_json::to_string(&(key, output.next_version_id_marker()))
+                        .map_err(|e| format!("Failed to encode continuation token: {}", e))
+                })
+                .transpose()?
+        } else {
+            None
+        };
+
+        let current_path = self.uri(&bucket, &prefix);
+
+        Ok(DirectoryContents {
+            parent_path: self.parent(&current_path),
+            current_path,
+            items,
+            next_token,
+        })
+    }
+
+    /// Metadata of one version of an object.
+    pub async fn stat_version(&self, path: &str, version_id: &str) -> Result<FileItem, String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        let output = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .head_object()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .version_id(version_id)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let mut item = object_item(
+            self.account(),
+            &bucket,
+            &key,
+            output.content_length(),
+            output.last_modified(),
+        );
+        item.version_id = Some(version_id.to_string());
+        set_head_storage(&mut item, &output);
+
+        Ok(item)
+    }
+
+    /// Copy one version of an object to `dest` inside the same account.
+    pub async fn copy_version_with_progress(
+        &self,
+        source: &str,
+        version_id: &str,
+        dest: &str,
+        settings: TransferSettings,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let (source_bucket, source_key) = require_bucket(source)?;
+        let (dest_bucket, dest_key) = require_bucket(dest)?;
+
+        if StorageUri::parse(dest)?.account() != self.account() {
+            return Err("Copies between S3 accounts are not supported".to_string());
+        }
+
+        ServerSideCopier::new(self, settings)
+            .copy_object_version(
+                &source_bucket,
+                &source_key,
+                Some(version_id),
+                &dest_bucket,
+                &dest_key,
+                progress,
+            )
+            .await
+    }
+
+    /// Make an older version current again by copying it over its own key.
+    /// The versions in between are kept.
+    pub async fn restore_version(
+        &self,
+        path: &str,
+        version_id: &str,
+        settings: TransferSettings,
+    ) -> Result<(), String> {
+        self.copy_version_with_progress(path, version_id, path, settings, no_progress())
+            .await
+    }
+
+    /// Remove one version of an object for good. Deleting a delete marker
+    /// brings the object back.
+    pub async fn delete_version(&self, path: &str, version_id: &str) -> Result<(), String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        self.client
+            .delete_object()
+            .bucket(&bucket)
+            .key(&key)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to delete version: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    /// Delete an object, or every object below a prefix. The listing feeds
+    /// DeleteObjects requests of up to 1000 keys, `concurrency` of them at a
+    /// time; keys S3 refuses are reported through `progress` with their error
+    /// code and do not stop the rest.
+    pub async fn delete_batch(
+        &self,
+        path: &str,
+        concurrency: usize,
+        progress: DeleteProgressFn,
+    ) -> Result<(), String> {
+        let (bucket, key) = require_bucket(path)?;
+        if key.is_empty() {
+            return Err("Deleting buckets is not supported here".to_string());
+        }
+        let mut requests = JoinSet::new();
+
+        if !self.is_prefix(&bucket, &key).await? {
+            progress(DeleteProgress::Found(1));
+            requests.spawn(self.delete_request(&bucket, vec![(key, None)], progress.clone()));
+        } else {
+            let mut pages = self
+                .client
+                .list_objects_v2()
+                .bucket(&bucket)
+                .prefix(as_prefix(&key))
+                .into_paginator()
+                .send();
+
+            while let Some(page) = pages.next().await {
+                let page = page
+                    .map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+                let keys: Vec<(String, Option<String>)> = page
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(|key| (key.to_string(), None)))
+                    .collect();
+                progress(DeleteProgress::Found(keys.len() as u64));
+
+                for batch in keys.chunks(DELETE_BATCH_SIZE) {
+                    while requests.len() >= concurrency.max(1) {
+                        requests.join_next().await;
+                    }
+                    requests.spawn(self.delete_request(&bucket, batch.to_vec(), progress.clone()));
+                }
+            }
+        }
+
+        while let Some(result) = requests.join_next().await {
+            result.map_err(|e| format!("Delete task failed: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    // One DeleteObjects request for keys, or versions of them. A request that
+    // fails as a whole counts as a failure of every key in it.
+    fn delete_request(
+        &self,
+        bucket: &str,
+        keys: Vec<(String, Option<String>)>,
+        progress: DeleteProgressFn,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let client = self.client.clone();
+        let bucket = bucket.to_string();
+        let account = self.account.clone();
+
+        async move {
+            let failed =
+                |keys: &[(String, Option<String>)], code: Option<&str>, message: String| {
+                    keys.iter()
+                        .map(|(key, _)| DeleteFailure {
+                            path: s3_uri(account.as_deref(), &bucket, key),
+                            code: code.map(str::to_string),
+                            message: message.clone(),
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+            let delete = keys
+                .iter()
+                .map(|(key, version_id)| {
+                    ObjectIdentifier::builder()
+                        .key(key)
+                        .set_version_id(version_id.clone())
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|objects| {
+                    Delete::builder()
+                        .set_objects(Some(objects))
+                        .quiet(true)
+                        .build()
+                });
+            let delete = match delete {
+                Ok(delete) => delete,
+                Err(e) => {
+                    let message = format!("Failed to build delete request: {}", e);
+                    progress(DeleteProgress::Failed(failed(&keys, None, message)));
+                    return;
+                }
+            };
+
+            match client
+                .delete_objects()
+                .bucket(&bucket)
+                .delete(delete)
+                .send()
+                .await
+            {
+                Ok(output) => {
+                    let failures: Vec<DeleteFailure> = output
+                        .errors()
+                        .iter()
+                        .map(|error| DeleteFailure {
+                            path: s3_uri(
+                                account.as_deref(),
+                                &bucket,
+                                error.key().unwrap_or_default(),
+                            ),
+                            code: error.code().map(str::to_string),
+                            message: error.message().unwrap_or("unknown error").to_string(),
+                        })
+                        .collect();
+                    progress(DeleteProgress::Deleted(
+                        (keys.len() - failures.len()) as u64,
+                    ));
+                    if !failures.is_empty() {
+                        progress(DeleteProgress::Failed(failures));
+                    }
+                }
+                Err(e) => {
+                    let message = format!("Failed to delete objects: {}", DisplayErrorContext(&e));
+                    progress(DeleteProgress::Failed(failed(&keys, e.code(), message)));
+                }
+            }
+        }
+    }
+
+    /// The objects a path stands for with their storage class: the object
+    /// itself, or every object below a prefix. A single object's class is
+    /// not looked up.
+    pub async fn objects_below(&self, path: &str) -> Result<Vec<(String, Option<String>)>, String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        if !key.is_empty() && !self.is_prefix(&bucket, &key).await? {
+            return Ok(vec![(path.to_string(), None)]);
+        }
+
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&bucket)
+            .prefix(as_prefix(&key))
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+            objects.extend(page.contents().iter().filter_map(|object| {
+                // Folder markers hold no data worth moving between classes
+                let key = object.key().filter(|key| !key.ends_with('/'))?;
+                Some((
+                    self.uri(&bucket, key),
+                    object
+                        .storage_class()
+                        .map(|class| class.as_str().to_string()),
+                ))
+            }));
+        }
+
+        Ok(objects)
+    }
+
+    /// Ask S3 to restore a temporary copy of an archived object for `days`.
+    /// Asking again while a restore is running is not an error.
+    pub async fn restore_object(&self, path: &str, days: i32, tier: &str) -> Result<(), String> {
+        let (bucket, key) = require_object(path)?;
+        validate_restore(days, tier)?;
+
+        let request = RestoreRequest::builder()
+            .days(days)
+            .glacier_job_parameters(
+                GlacierJobParameters::builder()
+                    .tier(Tier::from(tier))
+                    .build()
+                    .map_err(|e| format!("Failed to build restore request: {}", e))?,
+            )
+            .build();
+
+        match self
+            .client
+            .restore_object()
+            .bucket(&bucket)
+            .key(&key)
+            .restore_request(request)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some("RestoreAlreadyInProgress") => Ok(()),
+            Err(e) if e.code() == Some("InvalidObjectState") => {
+                Err("Only objects in an archive storage class can be restored".to_string())
+            }
+            Err(e) => Err(format!(
+                "Failed to restore object: {}",
+                DisplayErrorContext(&e)
+            )),
+        }
+    }
+
+    /// Headers, user metadata, encryption, checksum and tags of an object.
+    pub async fn object_metadata(
+        &self,
+        path: &str,
+        version_id: Option<&str>,
+    ) -> Result<ObjectMetadata, String> {
+        let (bucket, key) = require_object(path)?;
+
+        let head = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .head_object()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .set_version_id(version_id.map(str::to_string))
+                        .checksum_mode(ChecksumMode::Enabled)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let tags = self
+            .client
+            .get_object_tagging()
+            .bucket(&bucket)
+            .key(&key)
+            .set_version_id(version_id.map(str::to_string))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to read object tags: {}", DisplayErrorContext(&e)))?;
+
+        let mut metadata = head
+            .metadata()
+            .map(|metadata| metadata.clone().into_iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        metadata.sort();
+
+        let encryption = match head.server_side_encryption() {
+            _ if head.sse_customer_algorithm().is_some() => Some("SSE-C"),
+            Some(ServerSideEncryption::Aes256) => Some("SSE-S3"),
+            Some(ServerSideEncryption::AwsKms) => Some("SSE-KMS"),
+            Some(ServerSideEncryption::AwsKmsDsse) => Some("DSSE-KMS"),
+            Some(other) => Some(other.as_str()),
+            None => None,
+        };
+
+        Ok(ObjectMetadata {
+            path: path.to_string(),
+            version_id: head.version_id().map(str::to_string),
+            size: head.content_length().unwrap_or(0).max(0) as u64,
+            modified: head.last_modified().and_then(format_s3_datetime),
+            content_type: head.content_type().map(str::to_string),
+            cache_control: head.cache_control().map(str::to_string),
+            content_disposition: head.content_disposition().map(str::to_string),
+            content_encoding: head.content_encoding().map(str::to_string),
+            content_language: head.content_language().map(str::to_string),
+            metadata,
+            e_tag: head.e_tag().map(str::to_string),
+            storage_class: head.storage_class().map(|class| class.as_str().to_string()),
+            archived: head_archived(&head),
+            restore: head.restore().map(RestoreState::parse),
+            encryption: encryption.map(str::to_string),
+            kms_key_id: head.ssekms_key_id().map(str::to_string),
+            checksum: checksum(&head),
+            tags: tags
+                .tag_set()
+                .iter()
+                .map(|tag| (tag.key().to_string(), tag.value().to_string()))
+                .collect(),
+        })
+    }
+
+    /// Apply metadata and tag changes to the current version of an object.
+    /// Headers and user metadata can only be changed by copying the object
+    /// over itself; tags are replaced in place.
+    pub async fn update_object(
+        &self,
+        path: &str,
+        patch: &ObjectPatch,
+        settings: TransferSettings,
+    ) -> Result<(), String> {
+        let (bucket, key) = require_object(path)?;
+
+        if let Some(class) = &patch.storage_class {
+            validate_storage_class(class)?;
+        }
+
+        if patch.content_type.is_some()
+            || patch.cache_control.is_some()
+            || patch.content_disposition.is_some()
+            || patch.storage_class.is_some()
+            || !patch.metadata.is_empty()
+        {
+            let head = self
+                .keys
+                .send(
+                    |customer| {
+                        self.client
+                            .head_object()
+                            .bucket(&bucket)
+                            .key(&key)
+                            .checksum_mode(ChecksumMode::Enabled)
+                            .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                            .set_sse_customer_key(customer.map(CustomerKey::key))
+                            .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                            .send()
+                    },
+                    |e| {
+                        Err(format!(
+                            "Failed to get metadata: {}",
+                            DisplayErrorContext(&e)
+                        ))
+                    },
+                )
+                .await?;
+
+            ServerSideCopier::new(self, settings)
+                .rewrite_object(&bucket, &key, &patched_head(&head, patch)?)
+                .await?;
+        }
+
+        if !patch.tags.is_empty() {
+            self.update_tags(&bucket, &key, &patch.tags).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sign a GET or PUT URL for an object that stays valid for
+    /// `expires_in_secs`, or until temporary credentials run out.
+    pub async fn presign_url(
+        &self,
+        path: &str,
+        method: &str,
+        expires_in_secs: u64,
+    ) -> Result<PresignedUrl, String> {
+        let (bucket, key) = require_object(path)?;
+
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or("No credentials are configured")?
+            .provide_credentials()
+            .await
+            .map_err(|e| format!("Failed to load credentials: {}", DisplayErrorContext(&e)))?;
+
+        let region = self
+            .client
+            .config()
+            .region()
+            .map(|region| region.to_string())
+            .unwrap_or_else(|| DEFAULT_REGION.to_string());
+
+        let now = SystemTime::now();
+        let url = presign(&PresignRequest {
+            method,
+            endpoint_url: self.endpoint_url.as_deref(),
+            region: &region,
+            path_style: self.path_style,
+            bucket: &bucket,
+            key: &key,
+            access_key_id: credentials.access_key_id(),
+            secret_access_key: credentials.secret_access_key(),
+            session_token: credentials.session_token(),
+            time: now.into(),
+            expires_in_secs,
+        })?;
+
+        let mut expires_at = now + Duration::from_secs(expires_in_secs);
+        if let Some(expiry) = credentials.expiry() {
+            expires_at = expires_at.min(expiry);
+        }
+
+        Ok(PresignedUrl {
+            url,
+            path: path.to_string(),
+            method: method.to_string(),
+            created_at: chrono::DateTime::<Utc>::from(now).to_rfc3339(),
+            expires_at: chrono::DateTime::<Utc>::from(expires_at).to_rfc3339(),
+        })
+    }
+
+    /// Lifecycle rules of a bucket; a bucket without any has no rules.
+    pub async fn bucket_lifecycle(&self, path: &str) -> Result<LifecycleConfiguration, String> {
+        let (bucket, _) = require_bucket(path)?;
+
+        match self
+            .client
+            .get_bucket_lifecycle_configuration()
+            .bucket(&bucket)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(LifecycleConfiguration::from_sdk(output.rules())),
+            Err(e) if e.code() == Some("NoSuchLifecycleConfiguration") => {
+                Ok(LifecycleConfiguration::default())
+            }
+            Err(e) => Err(format!(
+                "Failed to read lifecycle rules: {}",
+                DisplayErrorContext(&e)
+            )),
+        }
+    }
+
+    /// Validate and replace the lifecycle rules of a bucket. S3 does not take
+    /// an empty configuration, so removing every rule deletes it instead.
+    pub async fn put_bucket_lifecycle(
+        &self,
+        path: &str,
+        configuration: &LifecycleConfiguration,
+    ) -> Result<(), String> {
+        let (bucket, _) = require_bucket(path)?;
+
+        if configuration.rules.is_empty() {
+            self.client
+                .delete_bucket_lifecycle()
+                .bucket(&bucket)
+                .send()
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to remove lifecycle rules: {}",
+                        DisplayErrorContext(&e)
+                    )
+                })?;
+            return Ok(());
+        }
+
+        self.client
+            .put_bucket_lifecycle_configuration()
+            .bucket(&bucket)
+            .lifecycle_configuration(configuration.to_sdk()?)
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to save lifecycle rules: {}",
+                    DisplayErrorContext(&e)
+                )
+            })?;
+
+        Ok(())
+    }
+
+    /// Policy, CORS rules and public access block of a bucket.
+    pub async fn bucket_properties(&self, path: &str) -> Result<BucketProperties, String> {
+        let (bucket, _) = require_bucket(path)?;
+
+        let policy = match self.client.get_bucket_policy().bucket(&bucket).send().await {
+            Ok(output) => output.policy().map(str::to_string),
+            Err(e) if e.code() == Some("NoSuchBucketPolicy") => None,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read the bucket policy: {}",
+                    DisplayErrorContext(&e)
+                ))
+            }
+        };
+
+        let cors_rules = match self.client.get_bucket_cors().bucket(&bucket).send().await {
+            Ok(output) => output.cors_rules().iter().map(CorsRule::from_sdk).collect(),
+            Err(e) if e.code() == Some("NoSuchCORSConfiguration") => Vec::new(),
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read CORS rules: {}",
+                    DisplayErrorContext(&e)
+                ))
+            }
+        };
+
+        let public_access_block = match self
+            .client
+            .get_public_access_block()
+            .bucket(&bucket)
+            .send()
+            .await
+        {
+            Ok(output) => output
+                .public_access_block_configuration()
+                .map(PublicAccessBlock::from_sdk)
+                .unwrap_or_default(),
+            Err(e) if e.code() == Some("NoSuchPublicAccessBlockConfiguration") => {
+                PublicAccessBlock::default()
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read the public access block: {}",
+                    DisplayErrorContext(&e)
+                ))
+            }
+        };
+
+        Ok(BucketProperties {
+            policy,
+            cors_rules,
+            public_access_block,
+        })
+    }
+
+    /// Validate edited bucket properties and diff them against the bucket.
+    pub async fn preview_bucket_properties(
+        &self,
+        path: &str,
+        edited: &BucketProperties,
+    ) -> Result<Vec<PropertyDiff>, String> {
+        let edited = edited.validated()?;
+        Ok(self.bucket_properties(path).await?.diff(&edited))
+    }
+
+    /// Save the bucket properties that differ from what the bucket has now.
+    pub async fn put_bucket_properties(
+        &self,
+        path: &str,
+        edited: &BucketProperties,
+    ) -> Result<(), String> {
+        let (bucket, _) = require_bucket(path)?;
+        let edited = edited.validated()?;
+        let changes = self.bucket_properties(path).await?.changes(&edited);
+
+        // The public access block goes first, so a public policy is only put
+        // once it is no longer blocked
+        if changes.public_access_block {
+            self.client
+                .put_public_access_block()
+                .bucket(&bucket)
+                .public_access_block_configuration(edited.public_access_block.to_sdk())
+                .send()
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to save the public access block: {}",
+                        DisplayErrorContext(&e)
+                    )
+                })?;
+        }
+
+        if changes.policy {
+            if let Some(policy) = &edited.policy {
+                self.client
+                    .put_bucket_policy()
+                    .bucket(&bucket)
+                    .policy(policy)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "Failed to save the bucket policy: {}",
+                            DisplayErrorContext(&e)
+                        )
+                    })?;
+            } else {
+                self.client
+                    .delete_bucket_policy()
+                    .bucket(&bucket)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "Failed to remove the bucket policy: {}",
+                            DisplayErrorContext(&e)
+                        )
+                    })?;
+            }
+        }
+
+        if changes.cors {
+            if edited.cors_rules.is_empty() {
+                self.client
+                    .delete_bucket_cors()
+                    .bucket(&bucket)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        format!("Failed to remove CORS rules: {}", DisplayErrorContext(&e))
+                    })?;
+            } else {
+                self.client
+                    .put_bucket_cors()
+                    .bucket(&bucket)
+                    .cors_configuration(cors_to_sdk(&edited.cors_rules)?)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        format!("Failed to save CORS rules: {}", DisplayErrorContext(&e))
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a bucket in `region`, or in the region of the client when none
+    /// is given. Returns the path of the new bucket.
+    pub async fn create_bucket(&self, name: &str, options: &NewBucket) -> Result<String, String> {
+        validate_bucket_name(name)?;
+
+        let region = match options.region.as_deref().map(str::trim) {
+            Some(region) if !region.is_empty() => region.to_string(),
+            _ => self
+                .client
+                .config()
+                .region()
+                .map(|region| region.to_string())
+                .unwrap_or_else(|| DEFAULT_REGION.to_string()),
+        };
+        // Requests for a bucket have to go to the region it lives in
+        let in_region = aws_sdk_s3::config::Builder::default().region(Region::new(region.clone()));
+
+        // us-east-1 is the default location and S3 rejects it as a constraint
+        let configuration = (region != DEFAULT_REGION).then(|| {
+            CreateBucketConfiguration::builder()
+                .location_constraint(BucketLocationConstraint::from(region.as_str()))
+                .build()
+        });
+
+        self.client
+            .create_bucket()
+            .bucket(name)
+            .set_create_bucket_configuration(configuration)
+            .object_lock_enabled_for_bucket(options.object_lock)
+            .customize()
+            .config_override(in_region.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create bucket: {}", DisplayErrorContext(&e)))?;
+
+        // Object lock switches versioning on by itself
+        if options.versioning && !options.object_lock {
+            self.client
+                .put_bucket_versioning()
+                .bucket(name)
+                .versioning_configuration(
+                    VersioningConfiguration::builder()
+                        .status(BucketVersioningStatus::Enabled)
+                        .build(),
+                )
+                .customize()
+                .config_override(in_region)
+                .send()
+                .await
+                .map_err(|e| {
+                    format!(
+                        "The bucket was created, but enabling versioning failed: {}",
+                        DisplayErrorContext(&e)
+                    )
+                })?;
+        }
+
+        Ok(self.uri(name, ""))
+    }
+
+    /// Delete the bucket `path` points into. S3 only deletes empty buckets,
+    /// see [`Self::empty_bucket`].
+    pub async fn delete_bucket(&self, path: &str) -> Result<(), String> {
+        let (bucket, _) = require_bucket(path)?;
+
+        match self.client.delete_bucket().bucket(&bucket).send().await {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some("BucketNotEmpty") => {
+                Err(format!("The bucket {} is not empty", bucket))
+            }
+            Err(e) => Err(format!(
+                "Failed to delete bucket: {}",
+                DisplayErrorContext(&e)
+            )),
+        }
+    }
+
+    /// Delete every object, version and delete marker in the bucket `path`
+    /// points into, reporting like [`Self::delete_batch`]. Versions and
+    /// delete markers are listed together, so unversioned buckets are emptied
+    /// by the same loop.
+    pub async fn empty_bucket(
+        &self,
+        path: &str,
+        concurrency: usize,
+        progress: DeleteProgressFn,
+    ) -> Result<(), String> {
+        let (bucket, _) = require_bucket(path)?;
+        let mut requests = JoinSet::new();
+
+        // ListObjectVersions has no paginator, it pages by key and version markers
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+
+        loop {
+            let page = self
+                .client
+                .list_object_versions()
+                .bucket(&bucket)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to list versions: {}", DisplayErrorContext(&e)))?;
+
+            let versions = page
+                .versions()
+                .iter()
+                .map(|version| (version.key(), version.version_id()));
+            let markers = page
+                .delete_markers()
+                .iter()
+                .map(|marker| (marker.key(), marker.version_id()));
+            let objects: Vec<(String, Option<String>)> = versions
+                .chain(markers)
+                .filter_map(|(key, version_id)| {
+                    key.map(|key| (key.to_string(), version_id.map(str::to_string)))
+                })
+                .collect();
+            progress(DeleteProgress::Found(objects.len() as u64));
+
+            for batch in objects.chunks(DELETE_BATCH_SIZE) {
+                while requests.len() >= concurrency.max(1) {
+                    requests.join_next().await;
+                }
+                requests.spawn(self.delete_request(&bucket, batch.to_vec(), progress.clone()));
+            }
+
+            if !page.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = page.next_key_marker().map(str::to_string);
+            version_id_marker = page.next_version_id_marker().map(str::to_string);
+        }
+
+        while let Some(result) = requests.join_next().await {
+            result.map_err(|e| format!("Delete task failed: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_tags(
+        &self,
+        bucket: &str,
+        key: &str,
+        changes: &[(String, Option<String>)],
+    ) -> Result<(), String> {
+        let output = self
+            .client
+            .get_object_tagging()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to read object tags: {}", DisplayErrorContext(&e)))?;
+
+        let mut tags = output
+            .tag_set()
+            .iter()
+            .map(|tag| (tag.key().to_string(), tag.value().to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        for (tag_key, value) in changes {
+            let tag_key = tag_key.trim();
+            match value {
+                Some(value) => tags.insert(tag_key.to_string(), value.trim().to_string()),
+                None => tags.remove(tag_key),
+            };
+        }
+
+        validate_tags(&tags)?;
+
+        if tags.is_empty() {
+            self.client
+                .delete_object_tagging()
+                .bucket(bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to remove tags: {}", DisplayErrorContext(&e)))?;
+            return Ok(());
+        }
+
+        let tag_set = tags
+            .into_iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to build tags: {}", e))?;
+        let tagging = Tagging::builder()
+            .set_tag_set(Some(tag_set))
+            .build()
+            .map_err(|e| format!("Failed to build tags: {}", e))?;
+
+        self.client
+            .put_object_tagging()
+            .bucket(bucket)
+            .key(key)
+            .tagging(tagging)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to update tags: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    async fn prefix_exists(&self, bucket: &str, prefix: &str) -> Result<bool, String> {
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .max_keys(1)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+
+        Ok(output.key_count().unwrap_or(0) > 0)
+    }
+
+    async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool, String> {
+        match self
+            .client
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            // Only an existing object can be encrypted with a customer key
+            Err(e) if needs_customer_key(&e) => Ok(true),
+            Err(e) => match e.as_service_error() {
+                Some(service_error) if service_error.is_not_found() => Ok(false),
+                _ => Err(format!(
+                    "Failed to get object metadata: {}",
+                    DisplayErrorContext(&e)
+                )),
+            },
+        }
+    }
+
+    async fn is_prefix(&self, bucket: &str, key: &str) -> Result<bool, String> {
+        if key.is_empty() || key.ends_with('/') {
+            return Ok(true);
+        }
+
+        if self.object_exists(bucket, key).await? {
+            return Ok(false);
+        }
+
+        self.prefix_exists(bucket, &format!("{}/", key)).await
+    }
+
+    // Every key S3 refuses is reported with its error code, not just the first
+    async fn delete_keys(&self, bucket: &str, keys: &[String]) -> Result<(), String> {
+        let objects = keys
+            .iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to build delete request: {}", e))?;
+
+        let mut failures = Vec::new();
+        for batch in objects.chunks(DELETE_BATCH_SIZE) {
+            let delete = Delete::builder()
+                .set_objects(Some(batch.to_vec()))
+                .quiet(true)
+                .build()
+                .map_err(|e| format!("Failed to build delete request: {}", e))?;
+
+            let output = self
+                .client
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to delete objects: {}", DisplayErrorContext(&e)))?;
+
+            failures.extend(output.errors().iter().map(|error| {
+                format!(
+                    "{} ({}): {}",
+                    error.key().unwrap_or("object"),
+                    error.code().unwrap_or("Error"),
+                    error.message().unwrap_or("unknown error")
+                )
+            }));
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Failed to delete {}", failures.join("; ")))
+        }
+    }
+}
+
+/// Split an `s3://` path into bucket and key.
+pub fn parse_s3_path(path: &str) -> Result<(String, String), String> {
+    match StorageUri::parse(path)? {
+        StorageUri::S3 { bucket, key, .. } => Ok((bucket, key)),
+        _ => Err(format!("Not an S3 location: {}", path)),
+    }
+}
+
+fn require_bucket(path: &str) -> Result<(String, String), String> {
+    let (bucket, key) = parse_s3_path(path)?;
+
+    if bucket.is_empty() {
+        return Err("No bucket specified".to_string());
+    }
+
+    Ok((bucket, key))
+}
+
+// Metadata and tags belong to objects, not to buckets or prefixes
+fn require_object(path: &str) -> Result<(String, String), String> {
+    let (bucket, key) = require_bucket(path)?;
+
+    if key.is_empty() || key.ends_with('/') {
+        return Err(format!("Not an S3 object: {}", path));
+    }
+
+    Ok((bucket, key))
+}
+
+fn checksum(head: &HeadObjectOutput) -> Option<String> {
+    [
+        ("CRC32", head.checksum_crc32()),
+        ("CRC32C", head.checksum_crc32_c()),
+        ("CRC64NVME", head.checksum_crc64_nvme()),
+        ("SHA1", head.checksum_sha1()),
+        ("SHA256", head.checksum_sha256()),
+    ]
+    .into_iter()
+    .find_map(|(algorithm, value)| value.map(|value| format!("{} {}", algorithm, value)))
+}
+
+// The object as it should look after the patch, ready for a self-copy
+fn patched_head(head: &HeadObjectOutput, patch: &ObjectPatch) -> Result<HeadObjectOutput, String> {
+    let header = |current: Option<&str>, change: &Option<String>| match change {
+        Some(value) if value.trim().is_empty() => None,
+        Some(value) => Some(value.trim().to_string()),
+        None => current.map(str::to_string),
+    };
+
+    let mut metadata = head
+        .metadata()
+        .map(|metadata| metadata.clone().into_iter().collect::<BTreeMap<_, _>>())
+        .unwrap_or_default();
+
+    for (key, value) in &patch.metadata {
+        let key = metadata_key(key)?;
+        match value {
+            Some(value) => metadata.insert(key, value.trim().to_string()),
+            None => metadata.remove(&key),
+        };
+    }
+
+    let metadata_size: usize = metadata
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum();
+    if metadata_size > MAX_METADATA_BYTES {
+        return Err(format!(
+            "User metadata is limited to {} bytes",
+            MAX_METADATA_BYTES
+        ));
+    }
+
+    Ok(HeadObjectOutput::builder()
+        .set_content_length(head.content_length())
+        .set_e_tag(head.e_tag().map(str::to_string))
+        .set_content_type(header(head.content_type(), &patch.content_type))
+        .set_cache_control(header(head.cache_control(), &patch.cache_control))
+        .set_content_disposition(header(
+            head.content_disposition(),
+            &patch.content_disposition,
+        ))
+        .set_content_encoding(head.content_encoding().map(str::to_string))
+        .set_content_language(head.content_language().map(str::to_string))
+        .set_storage_class(match &patch.storage_class {
+            Some(class) => Some(StorageClass::from(class.as_str())),
+            None => head.storage_class().cloned(),
+        })
+        .set_server_side_encryption(head.server_side_encryption().cloned())
+        .set_ssekms_key_id(head.ssekms_key_id().map(str::to_string))
+        .set_bucket_key_enabled(head.bucket_key_enabled())
+        .set_checksum_crc32(head.checksum_crc32().map(str::to_string))
+        .set_checksum_crc32_c(head.checksum_crc32_c().map(str::to_string))
+        .set_checksum_crc64_nvme(head.checksum_crc64_nvme().map(str::to_string))
+        .set_checksum_sha1(head.checksum_sha1().map(str::to_string))
+        .set_checksum_sha256(head.checksum_sha256().map(str::to_string))
+        .set_metadata(Some(metadata.into_iter().collect()))
+        .build())
+}
+
+// S3 stores user metadata keys in lower case as part of an HTTP header name
+fn metadata_key(key: &str) -> Result<String, String> {
+    let key = key.trim().to_ascii_lowercase();
+    let key = key.strip_prefix("x-amz-meta-").unwrap_or(&key);
+
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!("Invalid metadata key: {}", key));
+    }
+
+    Ok(key.to_string())
+}
+
+fn validate_tags(tags: &BTreeMap<String, String>) -> Result<(), String> {
+    if tags.len() > MAX_TAGS {
+        return Err(format!("An object can have at most {} tags", MAX_TAGS));
+    }
+
+    for (key, value) in tags {
+        if key.is_empty() || key.chars().count() > MAX_TAG_KEY_LENGTH {
+            return Err(format!(
+                "Tag keys must be 1 to {} characters long",
+                MAX_TAG_KEY_LENGTH
+            ));
+        }
+        if key.starts_with("aws:") {
+            return Err(format!("Tag keys starting with aws: are reserved: {}", key));
+        }
+        if value.chars().count() > MAX_TAG_VALUE_LENGTH {
+            return Err(format!(
+                "Tag values must be at most {} characters long",
+                MAX_TAG_VALUE_LENGTH
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn as_prefix(key: &str) -> String {
+    if key.is_empty() || key.ends_with('/') {
+        key.to_string()
+    } else {
+        format!("{}/", key)
+    }
+}
+
+pub fn format_s3_datetime(time: &DateTime) -> Option<String> {
+    chrono::DateTime::<Utc>::from_timestamp(time.secs(), time.subsec_nanos())
+        .map(|datetime| datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+// Record the storage class of a listed object, and whether it has to be
+// restored before it can be read
+fn set_storage(item: &mut FileItem, class: Option<&str>, restore: Option<RestoreState>) {
+    let restore = restore.unwrap_or_default();
+
+    item.storage_class = class.map(str::to_string);
+    item.restoring = restore.ongoing;
+    item.archived = class.is_some_and(is_archive_class) && !restore.is_available();
+    if item.archived {
+        item.icon = "archived".to_string();
+    }
+}
+
+fn set_head_storage(item: &mut FileItem, head: &HeadObjectOutput) {
+    set_storage(
+        item,
+        head.storage_class().map(|class| class.as_str()),
+        head.restore().map(RestoreState::parse),
+    );
+    if head_archived(head) {
+        item.archived = true;
+        item.icon = "archived".to_string();
+    }
+}
+
+// Intelligent-Tiering objects in its archive access tiers need a restore too
+fn head_archived(head: &HeadObjectOutput) -> bool {
+    let restored = head
+        .restore()
+        .map(RestoreState::parse)
+        .is_some_and(|restore| restore.is_available());
+    let archive_class = head
+        .storage_class()
+        .is_some_and(|class| is_archive_class(class.as_str()));
+
+    (archive_class || head.archive_status().is_some()) && !restored
+}
+
+fn key_name(key: &str) -> String {
+    key.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(key)
+        .to_string()
+}
+
+fn directory_item(account: Option<&str>, bucket: &str, prefix: &str) -> FileItem {
+    let name = if prefix.is_empty() {
+        bucket.to_string()
+    } else {
+        key_name(prefix)
+    };
+
+    FileItem {
+        name,
+        path: s3_uri(account, bucket, prefix),
+        is_dir: true,
+        size: None,
+        modified: None,
+        icon: "folder".to_string(),
+        version_id: None,
+        is_latest: false,
+        is_delete_marker: false,
+        storage_class: None,
+        archived: false,
+        restoring: false,
+    }
+}
+
+fn object_item(
+    account: Option<&str>,
+    bucket: &str,
+    key: &str,
+    size: Option<i64>,
+    modified: Option<&DateTime>,
+) -> FileItem {
+    let name = key_name(key);
+    let icon = get_file_icon(&name);
+
+    FileItem {
+        name,
+        path: s3_uri(account, bucket, key),
+        is_dir: false,
+        size: size.map(|size| size.max(0) as u64),
+        modified: modified.and_then(format_s3_datetime),
+        icon,
+        version_id: None,
+        is_latest: false,
+        is_delete_marker: false,
+        storage_class: None,
+        archived: false,
+        restoring: false,
+    }
+}
+
+#[async_trait]
+impl StorageProvider for S3Storage {
+    async fn list_page(
+        &self,
+        path: &str,
+        token: Option<String>,
+        page_size: usize,
+    ) -> Result<DirectoryContents, String> {
+        let (bucket, key) = parse_s3_path(path)?;
+
+        if bucket.is_empty() {
+            return self.list_buckets().await;
+        }
+
+        let prefix = as_prefix(&key);
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&bucket)
+            .prefix(&prefix)
+            .delimiter("/")
+            .max_keys(page_size.min(MAX_PAGE_SIZE) as i32)
+            .set_continuation_token(token)
+            .set_optional_object_attributes(self.restore_status_attribute())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+
+        let mut items = Vec::new();
+
+        for common_prefix in output.common_prefixes() {
+            if let Some(sub_prefix) = common_prefix.prefix() {
+                items.push(directory_item(self.account(), &bucket, sub_prefix));
+            }
+        }
+
+        for object in output.contents() {
+            match object.key() {
+                // Skip the zero-byte marker object that represents the folder itself
+                Some(object_key) if object_key != prefix => {
+                    let mut item = object_item(
+                        self.account(),
+                        &bucket,
+                        object_key,
+                        object.size(),
+                        object.last_modified(),
+                    );
+                    set_storage(
+                        &mut item,
+                        object.storage_class().map(|class| class.as_str()),
+                        object.restore_status().map(RestoreState::from_sdk),
+                    );
+                    items.push(item);
+                }
+                _ => {}
+            }
+        }
+
+        sort_items(&mut items);
+
+        let next_token = if output.is_truncated().unwrap_or(false) {
+            output.next_continuation_token().map(str::to_string)
+        } else {
+            None
+        };
+
+        let current_path = self.uri(&bucket, &prefix);
+
+        Ok(DirectoryContents {
+            parent_path: self.parent(&current_path),
+            current_path,
+            items,
+            next_token,
+        })
+    }
+
+    async fn stat(&self, path: &str) -> Result<FileItem, String> {
+        let (bucket, key) = parse_s3_path(path)?;
+
+        if bucket.is_empty() {
+            return Ok(FileItem {
+                name: self.account().unwrap_or("S3").to_string(),
+                path: self.uri("", ""),
+                is_dir: true,
+                size: None,
+                modified: None,
+                icon: "folder".to_string(),
+                version_id: None,
+                is_latest: false,
+                is_delete_marker: false,
+                storage_class: None,
+                archived: false,
+                restoring: false,
+            });
+        }
+
+        if self.is_prefix(&bucket, &key).await? {
+            return Ok(directory_item(self.account(), &bucket, &as_prefix(&key)));
+        }
+
+        let output = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .head_object()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let mut item = object_item(
+            self.account(),
+            &bucket,
+            &key,
+            output.content_length(),
+            output.last_modified(),
+        );
+        set_head_storage(&mut item, &output);
+
+        Ok(item)
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, String> {
+        let (bucket, key) = parse_s3_path(path)?;
+
+        if bucket.is_empty() {
+            return Ok(true);
+        }
+
+        if key.is_empty() {
+            return match self.client.head_bucket().bucket(&bucket).send().await {
+                Ok(_) => Ok(true),
+                Err(e) => match e.as_service_error() {
+                    Some(service_error) if service_error.is_not_found() => Ok(false),
+                    _ => Err(format!(
+                        "Failed to access bucket: {}",
+                        DisplayErrorContext(&e)
+                    )),
+                },
+            };
+        }
+
+        if !key.ends_with('/') && self.object_exists(&bucket, &key).await? {
+            return Ok(true);
+        }
+
+        self.prefix_exists(&bucket, &as_prefix(&key)).await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<Vec<u8>, String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        let range = match length {
+            Some(0) => return Ok(Vec::new()),
+            Some(length) => format!("bytes={}-{}", offset, offset + length - 1),
+            None => format!("bytes={}-", offset),
+        };
+
+        let output = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .get_object()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .range(&range)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to read object: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| format!("Failed to read object: {}", e))?;
+
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        self.client
+            .put_object()
+            .bucket(&bucket)
+            .key(&key)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to write object: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        if key.is_empty() {
+            return Err("Creating buckets is not supported here".to_string());
+        }
+
+        self.client
+            .put_object()
+            .bucket(&bucket)
+            .key(as_prefix(&key))
+            .body(ByteStream::from_static(b""))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create folder: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let (bucket, key) = require_bucket(path)?;
+
+        if key.is_empty() {
+            return Err("Deleting buckets is not supported here".to_string());
+        }
+
+        if self.is_prefix(&bucket, &key).await? {
+            let keys = self.list_keys_recursive(&bucket, &as_prefix(&key)).await?;
+            return self.delete_keys(&bucket, &keys).await;
+        }
+
+        self.client
+            .delete_object()
+            .bucket(&bucket)
+            .key(&key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to delete object: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    async fn copy(
+        &self,
+        source: &str,
+        dest: &str,
+        settings: TransferSettings,
+    ) -> Result<(), String> {
+        self.copy_with_progress(source, dest, settings, no_progress())
+            .await
+    }
+
+    async fn rename(
+        &self,
+        source: &str,
+        dest: &str,
+        settings: TransferSettings,
+    ) -> Result<(), String> {
+        // S3 has no rename, so move is a copy followed by a delete
+        self.copy(source, dest, settings).await?;
+        self.delete(source).await
+    }
+
+    fn join(&self, parent: &str, name: &str) -> String {
+        format!("{}/{}", parent.trim_end_matches('/'), name)
+    }
+
+    fn parent(&self, path: &str) -> Option<String> {
+        let (bucket, key) = parse_s3_path(path).ok()?;
+
+        if bucket.is_empty() {
+            return None;
+        }
+
+        let key = key.trim_end_matches('/');
+        if key.is_empty() {
+            return Some(self.uri("", ""));
+        }
+
+        match key.rsplit_once('/') {
+            Some((parent, _)) => Some(self.uri(&bucket, &format!("{}/", parent))),
+            None => Some(self.uri(&bucket, "")),
+        }
+    }
+
+    fn file_name(&self, path: &str) -> Option<String> {
+        let (bucket, key) = parse_s3_path(path).ok()?;
+
+        if key.trim_end_matches('/').is_empty() {
+            return (!bucket.is_empty()).then_some(bucket);
+        }
+
+        Some(key_name(&key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fake_s3::FakeS3;
+    use crate::vault::Vault;
+    use aws_credential_types::Credentials;
+
+    // These tests run against the in-process stand-in in fake_s3.rs. With
+    // S3_FINDER_TEST_ENDPOINT (e.g. http://localhost:9000 for MinIO) and
+    // S3_FINDER_TEST_BUCKET naming an existing scratch bucket they talk to a
+    // real S3-compatible service instead, with credentials from
+    // AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY. Each test works below its
+    // own prefix and deletes it when done.
+    async fn test_storage(name: &str) -> (S3Storage, String) {
+        let (endpoint, bucket) = match (
+            std::env::var("S3_FINDER_TEST_ENDPOINT"),
+            std::env::var("S3_FINDER_TEST_BUCKET"),
+        ) {
+            (Ok(endpoint), Ok(bucket)) => (endpoint, bucket),
+            _ => (
+                FakeS3::start(&["scratch"]).await.endpoint,
+                "scratch".to_string(),
+            ),
+        };
+
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = format!("s3://{}/s3-finder-test-{}-{}/", bucket, name, nanos);
+
+        (storage_at(endpoint), root)
+    }
+
+    fn storage_at(endpoint: String) -> S3Storage {
+        let credentials = SharedCredentialsProvider::new(Credentials::new(
+            std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_else(|_| "test".to_string()),
+            std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_else(|_| "test".to_string()),
+            None,
+            None,
+            "s3-finder-test",
+        ));
+        let region = std::env::var("AWS_REGION").unwrap_or_else(|_| DEFAULT_REGION.to_string());
+        let config = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(region))
+            .credentials_provider(credentials.clone())
+            .endpoint_url(&endpoint)
+            .force_path_style(true)
+            .build();
+
+        S3Storage::new(
+            Client::from_conf(config),
+            None,
+            Some(credentials),
+            Some(endpoint),
+            true,
+            CustomerKeys::new(Arc::new(Vault::open_at(None)), None),
+        )
+    }
+
+    // What a batch delete reported
+    #[derive(Default)]
+    struct Reported {
+        found: u64,
+        deleted: u64,
+        failures: Vec<DeleteFailure>,
+    }
+
+    fn recorder() -> (Arc<std::sync::Mutex<Reported>>, DeleteProgressFn) {
+        let reported = Arc::new(std::sync::Mutex::new(Reported::default()));
+        let record = reported.clone();
+        let progress: DeleteProgressFn = Arc::new(move |progress| {
+            let mut reported = record.lock().unwrap();
+            match progress {
+                DeleteProgress::Found(count) => reported.found += count,
+                DeleteProgress::Deleted(count) => reported.deleted += count,
+                DeleteProgress::Failed(failures) => reported.failures.extend(failures),
+            }
+        });
+        (reported, progress)
+    }
+
+    fn names(items: &[FileItem]) -> Vec<String> {
+        let mut names: Vec<String> = items.iter().map(|item| item.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn list_page_follows_tokens() {
+        let (storage, root) = test_storage("list").await;
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt", "sub/f.txt"] {
+            storage
+                .write(&format!("{}{}", root, name), name.as_bytes().to_vec())
+                .await
+                .unwrap();
+        }
+
+        let mut items = Vec::new();
+        let mut token = None;
+        let mut pages = 0;
+        loop {
+            let page = storage.list_page(&root, token, 2).await.unwrap();
+            assert!(page.items.len() <= 2);
+            assert_eq!(page.current_path, root);
+            items.extend(page.items);
+            pages += 1;
+            token = page.next_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        assert!(pages >= 3);
+        assert_eq!(
+            names(&items),
+            ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt", "sub"]
+        );
+        let sub = items.iter().find(|item| item.name == "sub").unwrap();
+        assert!(sub.is_dir);
+        assert_eq!(sub.path, format!("{}sub/", root));
+
+        let all = storage.list(&root).await.unwrap();
+        assert_eq!(names(&all.items), names(&items));
+        assert_eq!(all.next_token, None);
+
+        storage.delete(&root).await.unwrap();
+        assert!(storage.list(&root).await.unwrap().items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stat_and_read_range() {
+        let (storage, root) = test_storage("read").await;
+        let file = format!("{}dir/hello.txt", root);
+        storage.write(&file, b"hello world".to_vec()).await.unwrap();
+
+        let item = storage.stat(&file).await.unwrap();
+        assert!(!item.is_dir);
+        assert_eq!(item.name, "hello.txt");
+        assert_eq!(item.size, Some(11));
+        assert!(item.modified.is_some());
+
+        let dir = storage.stat(&format!("{}dir", root)).await.unwrap();
+        assert!(dir.is_dir);
+        assert_eq!(dir.path, format!("{}dir/", root));
+
+        assert!(storage.exists(&file).await.unwrap());
+        assert!(!storage.exists(&format!("{}missing", root)).await.unwrap());
+        assert!(storage.stat(&format!("{}missing", root)).await.is_err());
+
+        assert_eq!(
+            storage.read_range(&file, 0, Some(5)).await.unwrap(),
+            b"hello"
+        );
+        assert_eq!(storage.read_range(&file, 6, None).await.unwrap(), b"world");
+        assert_eq!(storage.read_range(&file, 4, Some(3)).await.unwrap(), b"o w");
+        assert!(storage
+            .read_range(&file, 3, Some(0))
+            .await
+            .unwrap()
+            .is_empty());
+
+        storage.write(&file, b"replaced".to_vec()).await.unwrap();
+        assert_eq!(
+            storage.read_range(&file, 0, None).await.unwrap(),
+            b"replaced"
+        );
+
+        storage.delete(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn copy_and_delete() {
+        let (storage, root) = test_storage("copy").await;
+        let file = format!("{}source/one.txt", root);
+        storage.write(&file, b"one".to_vec()).await.unwrap();
+        storage
+            .write(&format!("{}source/nested/two.txt", root), b"two".to_vec())
+            .await
+            .unwrap();
+
+        let copy = format!("{}copy.txt", root);
+        storage
+            .copy_with_progress(&file, &copy, TransferSettings::default(), no_progress())
+            .await
+            .unwrap();
+        assert_eq!(storage.read_range(&copy, 0, None).await.unwrap(), b"one");
+
+        let folder = format!("{}target/", root);
+        storage
+            .copy_with_progress(
+                &format!("{}source/", root),
+                &folder,
+                TransferSettings::default(),
+                no_progress(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .read_range(&format!("{}target/nested/two.txt", root), 0, None)
+                .await
+                .unwrap(),
+            b"two"
+        );
+        assert!(storage
+            .copy_with_progress(
+                &format!("{}source/", root),
+                &format!("{}source/inside/", root),
+                TransferSettings::default(),
+                no_progress(),
+            )
+            .await
+            .is_err());
+
+        storage.delete(&copy).await.unwrap();
+        assert!(!storage.exists(&copy).await.unwrap());
+        // The source is untouched by the copies
+        assert!(storage.exists(&file).await.unwrap());
+
+        storage.delete(&folder).await.unwrap();
+        assert!(!storage.exists(&folder).await.unwrap());
+        assert_eq!(names(&storage.list(&root).await.unwrap().items), ["source"]);
+
+        storage.delete(&root).await.unwrap();
+        assert!(!storage.exists(&root).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn delete_batch_reports_refused_keys() {
+        let s3 = FakeS3::start(&["scratch"]).await;
+        let storage = storage_at(s3.endpoint.clone());
+        for name in ["a.txt", "locked.txt", "sub/b.txt"] {
+            storage
+                .write(&format!("s3://scratch/dir/{}", name), b"x".to_vec())
+                .await
+                .unwrap();
+        }
+        s3.deny_delete("dir/locked.txt");
+
+        let (seen, progress) = recorder();
+        storage
+            .delete_batch("s3://scratch/dir/", 2, progress)
+            .await
+            .unwrap();
+
+        let reported = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!((reported.found, reported.deleted), (3, 2));
+        assert_eq!(reported.failures.len(), 1);
+        assert_eq!(reported.failures[0].path, "s3://scratch/dir/locked.txt");
+        assert_eq!(reported.failures[0].code.as_deref(), Some("AccessDenied"));
+        assert_eq!(
+            names(&storage.list("s3://scratch/dir/").await.unwrap().items),
+            ["locked.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_and_delete_bucket() {
+        let s3 = FakeS3::start(&["doomed"]).await;
+        let storage = storage_at(s3.endpoint.clone());
+        for index in 0..5 {
+            storage
+                .write(&format!("s3://doomed/{}/file.txt", index), vec![0; 10])
+                .await
+                .unwrap();
+        }
+
+        let error = storage.delete_bucket("s3://doomed/").await.unwrap_err();
+        assert!(error.contains("not empty"), "{}", error);
+        assert!(s3.has_bucket("doomed"));
+
+        let (seen, progress) = recorder();
+        storage
+            .empty_bucket("s3://doomed/", 4, progress)
+            .await
+            .unwrap();
+        let reported = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!((reported.found, reported.deleted), (5, 5));
+        assert!(reported.failures.is_empty());
+
+        storage.delete_bucket("s3://doomed/").await.unwrap();
+        assert!(!s3.has_bucket("doomed"));
+    }
+}
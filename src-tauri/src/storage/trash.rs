@@ -0,0 +1,415 @@
+// The home trash of the freedesktop.org Trash specification: a trashed item is
+// moved into `Trash/files` and described by a `.trashinfo` file of the same
+// name in `Trash/info`, recording where it came from and when it was trashed.
+// Other file managers on the desktop list and restore the same items.
+// https://specifications.freedesktop.org/trash-spec/latest/
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+
+use super::local::{copy_dir_recursive, validate_new_path, validate_write_path};
+
+const INFO_EXTENSION: &str = ".trashinfo";
+
+// Paths are escaped like URL paths, keeping their slashes
+const TRASH_PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// An item in the Trash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    /// The item's name inside the Trash, unique there
+    pub id: String,
+    pub name: String,
+    /// Where the item is put back when restored
+    pub original_path: String,
+    /// Local time the item was trashed, as `YYYY-MM-DDThh:mm:ss`
+    pub deleted: Option<String>,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+}
+
+/// Items moved to the Trash, and those that could not be moved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Trashed {
+    pub items: Vec<TrashItem>,
+    /// As `path: error`
+    pub errors: Vec<String>,
+}
+
+/// Where restored items were put back, and those that could not be.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Restored {
+    pub paths: Vec<String>,
+    /// As `id: error`
+    pub errors: Vec<String>,
+}
+
+// $XDG_DATA_HOME/Trash, usually ~/.local/share/Trash
+fn trash_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join("Trash"))
+        .ok_or_else(|| "Cannot determine the Trash folder".to_string())
+}
+
+fn files_and_info() -> Result<(PathBuf, PathBuf), String> {
+    let trash = trash_dir()?;
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    for dir in [&files, &info] {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create the Trash: {}", e))?;
+    }
+
+    Ok((files, info))
+}
+
+// IDs come from the frontend, so they must name an entry of the Trash itself
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || id == "." || id == ".." || id.contains('/') {
+        return Err(format!("Invalid Trash item: {}", id));
+    }
+
+    Ok(())
+}
+
+// "report.pdf", then "report.2.pdf", "report.3.pdf" and so on
+fn numbered(name: &str, n: usize) -> String {
+    if n == 1 {
+        return name.to_string();
+    }
+
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}.{}{}", &name[..dot], n, &name[dot..]),
+        _ => format!("{}.{}", name, n),
+    }
+}
+
+// Rename, or copy and remove when the item is on another filesystem
+fn move_item(source: &Path, dest: &Path) -> Result<(), String> {
+    match fs::rename(source, dest) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            let metadata =
+                fs::symlink_metadata(source).map_err(|e| format!("Failed to move item: {}", e))?;
+            if metadata.is_dir() {
+                copy_dir_recursive(source, dest)?;
+            } else {
+                fs::copy(source, dest).map_err(|e| format!("Failed to move item: {}", e))?;
+            }
+
+            if let Err(e) = remove(source) {
+                // Leave one copy rather than two
+                let _ = remove(dest);
+                return Err(e);
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to move item: {}", e)),
+    }
+}
+
+fn remove(path: &Path) -> Result<(), String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("Failed to delete: {}", e))?;
+
+    if metadata.is_dir() {
+        fs::remove_dir_all(path).map_err(|e| format!("Failed to delete folder: {}", e))
+    } else {
+        fs::remove_file(path).map_err(|e| format!("Failed to delete file: {}", e))
+    }
+}
+
+fn item(files: &Path, id: &str, original_path: PathBuf, deleted: Option<String>) -> TrashItem {
+    let metadata = fs::symlink_metadata(files.join(id)).ok();
+    let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+
+    TrashItem {
+        id: id.to_string(),
+        name: original_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| id.to_string()),
+        original_path: original_path.to_string_lossy().to_string(),
+        deleted,
+        is_dir,
+        size: metadata.filter(|_| !is_dir).map(|m| m.len()),
+    }
+}
+
+/// Move a local file or folder to the Trash. A symlink is trashed itself, not
+/// what it points to.
+pub fn move_to_trash(path: &str) -> Result<TrashItem, String> {
+    // The parent is checked, so that a symlink is not resolved to its target
+    let item_path = validate_new_path(path)?;
+    fs::symlink_metadata(&item_path).map_err(|_| "Invalid or inaccessible path".to_string())?;
+
+    let trash = trash_dir()?;
+    let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
+    if item_path == home || item_path.starts_with(&trash) || trash.starts_with(&item_path) {
+        return Err("This cannot be moved to the Trash".to_string());
+    }
+
+    let (files, info) = files_and_info()?;
+    let name = item_path
+        .file_name()
+        .ok_or("Cannot determine file name")?
+        .to_string_lossy()
+        .to_string();
+    let deleted = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        utf8_percent_encode(&item_path.to_string_lossy(), TRASH_PATH),
+        deleted
+    );
+
+    // Creating the info file claims the name, even against other programs
+    // trashing at the same time
+    let mut n = 1;
+    let (id, info_path) = loop {
+        let id = numbered(&name, n);
+        let info_path = info.join(format!("{}{}", id, INFO_EXTENSION));
+        n += 1;
+
+        if fs::symlink_metadata(files.join(&id)).is_ok() {
+            continue;
+        }
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(contents.as_bytes()) {
+                    let _ = fs::remove_file(&info_path);
+                    return Err(format!("Failed to write to the Trash: {}", e));
+                }
+                break (id, info_path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to write to the Trash: {}", e)),
+        }
+    };
+
+    if let Err(e) = move_item(&item_path, &files.join(&id)) {
+        let _ = fs::remove_file(&info_path);
+        return Err(e);
+    }
+
+    Ok(item(&files, &id, item_path, Some(deleted)))
+}
+
+// The original path and deletion date recorded for an item
+fn read_info(info_path: &Path) -> Result<(PathBuf, Option<String>), String> {
+    let contents =
+        fs::read_to_string(info_path).map_err(|e| format!("Failed to read the Trash: {}", e))?;
+
+    let mut path = None;
+    let mut deleted = None;
+    let mut in_section = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line == "[Trash Info]";
+        } else if let (true, Some((key, value))) = (in_section, line.split_once('=')) {
+            match key.trim() {
+                "Path" => path = Some(percent_decode_str(value.trim()).decode_utf8_lossy()),
+                "DeletionDate" => deleted = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let path = PathBuf::from(path.ok_or("Trash item has no original path")?.as_ref());
+    // Relative paths are relative to the folder the Trash is in
+    let path = if path.is_absolute() {
+        path
+    } else {
+        trash_dir()?
+            .parent()
+            .ok_or("Cannot determine the Trash folder")?
+            .join(path)
+    };
+
+    Ok((path, deleted))
+}
+
+/// Every item in the Trash, most recently trashed first.
+pub fn list() -> Result<Vec<TrashItem>, String> {
+    let (files, info) = files_and_info()?;
+    let entries = fs::read_dir(&info).map_err(|e| format!("Failed to read the Trash: {}", e))?;
+
+    let mut items: Vec<TrashItem> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let id = file_name.strip_suffix(INFO_EXTENSION)?;
+            // Info files whose item is gone are left for other programs to
+            // clean up
+            fs::symlink_metadata(files.join(id)).ok()?;
+            let (original_path, deleted) = read_info(&entry.path()).ok()?;
+
+            Some(item(&files, id, original_path, deleted))
+        })
+        .collect();
+
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(items)
+}
+
+/// Put an item back where it was trashed from and return its path there. An
+/// item whose place has been taken in the meantime stays in the Trash.
+pub fn restore(id: &str) -> Result<String, String> {
+    check_id(id)?;
+    let (files, info) = files_and_info()?;
+    let info_path = info.join(format!("{}{}", id, INFO_EXTENSION));
+    let (original_path, _) = read_info(&info_path)?;
+
+    // Any program can write the info file, so `..` must not lead out of home
+    if original_path
+        .components()
+        .any(|component| !matches!(component, Component::RootDir | Component::Normal(_)))
+    {
+        return Err(format!(
+            "Invalid original path in the Trash: {}",
+            original_path.to_string_lossy()
+        ));
+    }
+
+    let dest = validate_new_path(&original_path.to_string_lossy()).or_else(|_| {
+        // The folder it was in may have been deleted too; the part of it that
+        // is left must resolve below home before anything is created in it
+        let parent = original_path
+            .parent()
+            .ok_or("Cannot determine parent directory")?;
+        let existing = parent
+            .ancestors()
+            .find(|dir| dir.exists())
+            .ok_or("Cannot determine parent directory")?;
+        validate_write_path(&existing.to_string_lossy())?;
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        validate_new_path(&original_path.to_string_lossy())
+    })?;
+
+    if fs::symlink_metadata(&dest).is_ok() {
+        return Err(format!(
+            "Cannot restore {}: an item with that name already exists",
+            dest.to_string_lossy()
+        ));
+    }
+
+    move_item(&files.join(id), &dest)?;
+    let _ = fs::remove_file(&info_path);
+
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Delete an item in the Trash for good.
+pub fn delete(id: &str) -> Result<(), String> {
+    check_id(id)?;
+    let (files, info) = files_and_info()?;
+
+    remove(&files.join(id))?;
+    let _ = fs::remove_file(info.join(format!("{}{}", id, INFO_EXTENSION)));
+
+    Ok(())
+}
+
+/// Delete everything in the Trash for good. An entry that cannot be deleted
+/// does not keep the others; those are returned as `name: error`.
+pub fn empty() -> Result<Vec<String>, String> {
+    let (files, info) = files_and_info()?;
+
+    let mut errors = Vec::new();
+    for dir in [&files, &info] {
+        let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read the Trash: {}", e))?;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Err(e) = remove(&entry.path()) {
+                errors.push(format!("{}: {}", entry.file_name().to_string_lossy(), e));
+            }
+        }
+    }
+
+    // The cache of folder sizes describes nothing now
+    let _ = fs::remove_file(trash_dir()?.join("directorysizes"));
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info_file(dir: &tempfile::TempDir, contents: &str) -> PathBuf {
+        let path = dir.path().join(format!("item{}", INFO_EXTENSION));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn numbered_goes_before_the_extension() {
+        let cases = [
+            ("report.pdf", 1, "report.pdf"),
+            ("report.pdf", 2, "report.2.pdf"),
+            ("archive.tar.gz", 3, "archive.tar.3.gz"),
+            ("README", 2, "README.2"),
+            (".bashrc", 2, ".bashrc.2"),
+            (".config.toml", 2, ".config.2.toml"),
+            ("notes.", 2, "notes.2."),
+        ];
+        for (name, n, expected) in cases {
+            assert_eq!(numbered(name, n), expected, "{} #{}", name, n);
+        }
+    }
+
+    #[test]
+    fn read_info_decodes_path_and_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = info_file(
+            &dir,
+            "[Trash Info]\nPath=/home/me/My%20Files/r%C3%A9sum%C3%A9.pdf\nDeletionDate=2026-03-14T09:26:53\n",
+        );
+
+        let (original_path, deleted) = read_info(&path).unwrap();
+        assert_eq!(original_path, PathBuf::from("/home/me/My Files/résumé.pdf"));
+        assert_eq!(deleted.as_deref(), Some("2026-03-14T09:26:53"));
+    }
+
+    #[test]
+    fn read_info_resolves_relative_paths_against_the_trash_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = info_file(&dir, "[Trash Info]\nPath=Documents/a.txt\n");
+
+        let (original_path, deleted) = read_info(&path).unwrap();
+        let base = trash_dir().unwrap().parent().unwrap().to_path_buf();
+        assert_eq!(original_path, base.join("Documents/a.txt"));
+        assert_eq!(deleted, None);
+    }
+
+    #[test]
+    fn read_info_only_reads_the_trash_info_section() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Keys of other sections are not the item's
+        let path = info_file(
+            &dir,
+            "[Other]\nPath=/tmp/elsewhere\n[Trash Info]\nPath=/home/me/a.txt\n[More]\nPath=/tmp/later\n",
+        );
+        assert_eq!(read_info(&path).unwrap().0, PathBuf::from("/home/me/a.txt"));
+
+        for contents in [
+            "",
+            "Path=/home/me/a.txt\n",
+            "[Other]\nPath=/home/me/a.txt\n",
+        ] {
+            let path = info_file(&dir, contents);
+            assert!(read_info(&path).is_err(), "{:?}", contents);
+        }
+        assert!(read_info(&dir.path().join("missing.trashinfo")).is_err());
+    }
+}
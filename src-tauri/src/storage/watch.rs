@@ -0,0 +1,166 @@
+// Live listings: the local directories shown as columns are watched, and each
+// entry added, removed or modified in one is pushed to the frontend, which
+// updates the column in place. S3 has no change notifications, so only local
+// directories are watched.
+
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{async_runtime, AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+use super::local::{file_item_from_path, validate_path};
+use super::StorageUri;
+use crate::types::FileItem;
+
+/// Event carrying a [`DirectoryChange`] for every entry changed in a watched
+/// directory.
+pub const DIRECTORY_EVENT: &str = "directory-changed";
+
+// Events arriving this close together are sent as one, so a file being
+// written is reported once rather than for every write
+const COALESCE_TIME: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryChange {
+    /// The directory, as its listing reports its path
+    pub dir: String,
+    pub kind: ChangeKind,
+    pub path: String,
+    /// The entry as a listing shows it, unless it was removed
+    pub item: Option<FileItem>,
+}
+
+/// Watches the directories the frontend has open.
+pub struct DirectoryWatcher {
+    // Creating the watcher fails when the system is out of inotify instances,
+    // which only costs the live refresh
+    watcher: Mutex<Result<RecommendedWatcher, String>>,
+    dirs: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+impl DirectoryWatcher {
+    pub fn new(app: AppHandle) -> Self {
+        let dirs = Arc::new(Mutex::new(BTreeSet::new()));
+        let (sender, events) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(|e| format!("Failed to watch directories: {}", e));
+
+        async_runtime::spawn(forward(app, dirs.clone(), events));
+
+        Self {
+            watcher: Mutex::new(watcher),
+            dirs,
+        }
+    }
+
+    /// Watch exactly the given directories. S3 paths are skipped, and so are
+    /// directories that cannot be watched.
+    pub fn watch(&self, paths: &[String]) -> Result<(), String> {
+        let wanted: BTreeSet<PathBuf> = paths
+            .iter()
+            .filter_map(|path| match StorageUri::parse(path) {
+                Ok(StorageUri::Local(path)) => validate_path(&path).ok(),
+                _ => None,
+            })
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        let mut guard = self.watcher.lock().map_err(|e| e.to_string())?;
+        let watcher = guard.as_mut().map_err(|e| e.clone())?;
+        let mut dirs = self.dirs.lock().map_err(|e| e.to_string())?;
+
+        for dir in dirs.difference(&wanted) {
+            let _ = watcher.unwatch(dir);
+        }
+        let added: Vec<PathBuf> = wanted.difference(&dirs).cloned().collect();
+        dirs.retain(|dir| wanted.contains(dir));
+        for dir in added {
+            if watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok() {
+                dirs.insert(dir);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Turn the raw events into changes of the watched directories' entries. Each
+// path touched by a batch of events is looked at once the batch is over, so
+// the change reports what the entry is now.
+async fn forward(
+    app: AppHandle,
+    dirs: Arc<Mutex<BTreeSet<PathBuf>>>,
+    mut events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+) {
+    while let Some(first) = events.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::sleep(COALESCE_TIME);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                _ = &mut deadline => break,
+                event = events.recv() => match event {
+                    Some(event) => batch.push(event),
+                    None => break,
+                },
+            }
+        }
+
+        // Each path, and whether it appeared during the batch
+        let mut touched: BTreeMap<PathBuf, bool> = BTreeMap::new();
+        for event in batch.into_iter().flatten() {
+            let appeared = match event.kind {
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => true,
+                EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any => false,
+                EventKind::Access(_) | EventKind::Other => continue,
+            };
+            for path in event.paths {
+                *touched.entry(path).or_default() |= appeared;
+            }
+        }
+
+        let Ok(watched) = dirs.lock().map(|dirs| dirs.clone()) else {
+            continue;
+        };
+        for (path, appeared) in touched {
+            let Some(dir) = path.parent().filter(|dir| watched.contains(*dir)) else {
+                continue;
+            };
+
+            let (kind, item) = match fs::metadata(&path) {
+                Ok(metadata) => (
+                    if appeared {
+                        ChangeKind::Added
+                    } else {
+                        ChangeKind::Modified
+                    },
+                    Some(file_item_from_path(&path, Some(&metadata))),
+                ),
+                Err(_) => (ChangeKind::Removed, None),
+            };
+            let change = DirectoryChange {
+                dir: dir.to_string_lossy().to_string(),
+                kind,
+                path: path.to_string_lossy().to_string(),
+                item,
+            };
+            let _ = app.emit(DIRECTORY_EVENT, &change);
+        }
+    }
+}
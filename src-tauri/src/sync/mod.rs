@@ -0,0 +1,1462 @@
+// Sync between a local folder and an S3 prefix. A sync is planned first: both
+// sides are listed and compared, and the resulting uploads, downloads and
+// deletes are shown as a dry run. Running the plan hands the transfers to the
+// transfer queue and the deletes to the delete manager, and once they are done
+// the pair's state is recorded for the next two-way sync to compare against.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::storage::encryption::Encryption;
+use crate::storage::local::validate_write_path;
+use crate::storage::s3::as_prefix;
+use crate::storage::{s3_uri, StorageManager, StorageUri};
+use crate::transfer::manager::JobStatus;
+use crate::transfer::{DeleteManager, TransferKind, TransferManager};
+
+pub mod scan;
+pub mod state;
+pub mod watch;
+
+use scan::{Digests, Entry, Tree};
+use state::{SyncState, SyncedFile};
+
+// File systems and S3 keep times at different precisions
+const MTIME_TOLERANCE_SECS: i64 = 2;
+
+/// How a file and an object of the same size are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompareMode {
+    /// Sizes and modification times; fast but can miss an edit that kept the
+    /// size
+    SizeMtime,
+    /// The file's MD5 against the object's ETag, where the ETag is one
+    Etag,
+    /// SHA-256 of both, reading objects without a stored checksum in full
+    Checksum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncPolicy {
+    /// Make the destination match the source, deleting what the source lacks
+    Mirror,
+    /// Copy changes both ways, deletes included, going by what each side
+    /// looked like at the last sync
+    TwoWay,
+    /// Copy new and changed files from the source, never deleting
+    NeverDelete,
+}
+
+/// Which side is the source of a one-way sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    Upload,
+    Download,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOptions {
+    /// The local folder
+    pub local: String,
+    /// The S3 prefix, as `s3://[account@]bucket/prefix/`
+    pub remote: String,
+    pub policy: SyncPolicy,
+    /// Ignored by two-way syncs
+    pub direction: SyncDirection,
+    pub compare: CompareMode,
+    /// What a two-way sync does with paths changed on both sides
+    #[serde(default)]
+    pub resolution: ConflictResolution,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictResolution {
+    /// Keep the local copy under a new name and both versions on both sides
+    KeepBoth,
+    /// The copy modified last replaces the other; a changed copy wins over a
+    /// deletion
+    NewerWins,
+    /// The local folder is taken as it is
+    LocalWins,
+    /// Leave conflicts out until one is chosen in the plan
+    #[default]
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncOperation {
+    Upload,
+    Download,
+    DeleteLocal,
+    DeleteRemote,
+    /// Rename the local copy aside, then upload it and download the S3 copy
+    KeepBoth,
+    /// Leave both sides as they are
+    Skip,
+}
+
+/// How a path changed since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncChange {
+    New,
+    Modified,
+    Deleted,
+    /// Changed on both sides, or changed on one and deleted on the other
+    Conflict,
+}
+
+/// One step of a plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncAction {
+    /// Path relative to both roots, `/` separated
+    pub path: String,
+    pub operation: SyncOperation,
+    pub change: SyncChange,
+    /// Bytes to transfer, or to delete
+    pub size: u64,
+    pub reason: String,
+    /// Operations that can resolve a conflict, for the user to pick from
+    #[serde(default)]
+    pub choices: Vec<SyncOperation>,
+}
+
+/// What a sync would do, shown to the user before it runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub options: SyncOptions,
+    pub actions: Vec<SyncAction>,
+    /// Paths found identical on both sides
+    pub unchanged: u64,
+}
+
+/// What running a plan started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub transfers: usize,
+    pub deletes: usize,
+    /// Steps that could not be started, as `path: error`
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+// The two roots of a pair, resolved
+struct Roots {
+    local: PathBuf,
+    remote: String,
+    account: Option<String>,
+    bucket: String,
+    prefix: String,
+}
+
+impl Roots {
+    fn resolve(options: &SyncOptions) -> Result<Self, String> {
+        let local = validate_write_path(&options.local)?;
+        if !local.is_dir() {
+            return Err("The local side of a sync must be a folder".to_string());
+        }
+
+        let StorageUri::S3 {
+            account,
+            bucket,
+            key,
+        } = StorageUri::parse(&options.remote)?
+        else {
+            return Err("The remote side of a sync must be an S3 location".to_string());
+        };
+        if bucket.is_empty() {
+            return Err("Choose a bucket, or a folder in one, to sync with".to_string());
+        }
+
+        let prefix = as_prefix(&key);
+        Ok(Self {
+            local,
+            remote: s3_uri(account.as_deref(), &bucket, &prefix),
+            account,
+            bucket,
+            prefix,
+        })
+    }
+
+    fn local_path(&self, path: &str) -> PathBuf {
+        self.local.join(path)
+    }
+
+    fn remote_path(&self, path: &str) -> String {
+        format!("{}{}", self.remote, path)
+    }
+
+    fn pair_id(&self) -> String {
+        SyncState::pair_id(&path_string(&self.local), &self.remote)
+    }
+
+    async fn scan(&self, storage: &StorageManager) -> Result<(Tree, Tree), String> {
+        let s3 = storage.s3(self.account.as_deref()).await?;
+
+        let root = self.local.clone();
+        let local = tokio::task::spawn_blocking(move || scan::local_tree(&root))
+            .await
+            .map_err(|e| format!("Scan task failed: {}", e))??;
+        let remote = scan::remote_tree(&s3, &self.bucket, &self.prefix).await?;
+
+        Ok((local, remote))
+    }
+
+    async fn digests(&self, storage: &StorageManager) -> Result<Digests, String> {
+        Ok(Digests {
+            s3: storage.s3(self.account.as_deref()).await?,
+            bucket: self.bucket.clone(),
+            prefix: self.prefix.clone(),
+            root: self.local.clone(),
+        })
+    }
+}
+
+// Parent directory of a relative path, with its trailing slash
+fn parent_of(path: &str) -> &str {
+    path.rfind('/').map_or("", |slash| &path[..=slash])
+}
+
+/// List both sides of a pair and work out what a sync would do, comparing
+/// `concurrency` pairs of files at a time.
+pub async fn plan(
+    storage: &StorageManager,
+    state: &SyncState,
+    options: SyncOptions,
+    concurrency: usize,
+) -> Result<SyncPlan, String> {
+    let roots = Roots::resolve(&options)?;
+    let (local, remote) = roots.scan(storage).await?;
+    let records = state.snapshot(&roots.pair_id())?;
+
+    let digests = roots.digests(storage).await?;
+    let same = compare_contents(options.compare, &digests, &local, &remote, concurrency).await?;
+    let resaved = if options.policy == SyncPolicy::TwoWay {
+        resaved(&roots.local, &local, &records).await?
+    } else {
+        BTreeSet::new()
+    };
+
+    let mut actions = Vec::new();
+    let mut unchanged = 0;
+    let mut paths: Vec<&String> = local.keys().chain(remote.keys()).collect();
+    if options.policy == SyncPolicy::TwoWay {
+        paths.extend(records.keys());
+    }
+    paths.sort();
+    paths.dedup();
+
+    for path in paths {
+        let (file, object) = (local.get(path), remote.get(path));
+        let same = same.get(path).copied().flatten();
+
+        let decision = if options.policy == SyncPolicy::TwoWay {
+            let record = records.get(path);
+            let local_side = side(file, record, |file, record| {
+                record.matches_local(file) || resaved.contains(path)
+            });
+            let remote_side = side(object, record, |object, record| {
+                record.matches_remote(object)
+            });
+            two_way(&options, file, object, local_side, remote_side, same)
+        } else {
+            let step = match (file, object) {
+                (Some(_), None) => only_local(&options),
+                (None, Some(_)) => only_remote(&options),
+                (Some(file), Some(object)) => changed(&options, file, object, same),
+                (None, None) => None,
+            };
+            step.map(|(operation, reason)| Decision {
+                operation,
+                change: one_way_change(operation, file, object),
+                reason,
+                choices: Vec::new(),
+            })
+        };
+
+        match decision {
+            Some(decision) => actions.push(SyncAction {
+                path: path.clone(),
+                operation: decision.operation,
+                change: decision.change,
+                size: size_of(decision.operation, file, object),
+                reason: decision.reason.to_string(),
+                choices: decision.choices,
+            }),
+            None if file.is_some() && object.is_some() => unchanged += 1,
+            None => {}
+        }
+    }
+
+    Ok(SyncPlan {
+        options,
+        actions,
+        unchanged,
+    })
+}
+
+type Step = Option<(SyncOperation, &'static str)>;
+
+struct Decision {
+    operation: SyncOperation,
+    change: SyncChange,
+    reason: &'static str,
+    choices: Vec<SyncOperation>,
+}
+
+fn only_local(options: &SyncOptions) -> Step {
+    match (options.policy, options.direction) {
+        (_, SyncDirection::Upload) => Some((SyncOperation::Upload, "Only in the local folder")),
+        (SyncPolicy::Mirror, SyncDirection::Download) => {
+            Some((SyncOperation::DeleteLocal, "Not in S3"))
+        }
+        _ => None,
+    }
+}
+
+fn only_remote(options: &SyncOptions) -> Step {
+    match (options.policy, options.direction) {
+        (_, SyncDirection::Download) => Some((SyncOperation::Download, "Only in S3")),
+        (SyncPolicy::Mirror, SyncDirection::Upload) => {
+            Some((SyncOperation::DeleteRemote, "Not in the local folder"))
+        }
+        _ => None,
+    }
+}
+
+// What a one-way sync does with a path present on both sides; `same` is the
+// content comparison, when one was made and could tell
+fn changed(options: &SyncOptions, file: &Entry, object: &Entry, same: Option<bool>) -> Step {
+    if !differs(options, file, object, same) {
+        return None;
+    }
+
+    match options.direction {
+        SyncDirection::Upload => Some((SyncOperation::Upload, "Differs from S3")),
+        SyncDirection::Download => Some((SyncOperation::Download, "Differs from the local folder")),
+    }
+}
+
+fn differs(options: &SyncOptions, file: &Entry, object: &Entry, same: Option<bool>) -> bool {
+    match same {
+        Some(same) => !same,
+        None if file.size != object.size => true,
+        // An upload leaves the object newer than the file, and a download gives
+        // the file the object's time, so only the source side being newer
+        // counts as a change
+        None => match (options.policy, options.direction) {
+            (SyncPolicy::TwoWay, _) | (_, SyncDirection::Upload) => newer(file, object),
+            (_, SyncDirection::Download) => newer(object, file),
+        },
+    }
+}
+
+// What happens to the destination's copy in a one-way sync
+fn one_way_change(
+    operation: SyncOperation,
+    file: Option<&Entry>,
+    object: Option<&Entry>,
+) -> SyncChange {
+    match operation {
+        SyncOperation::DeleteLocal | SyncOperation::DeleteRemote => SyncChange::Deleted,
+        SyncOperation::Upload if object.is_none() => SyncChange::New,
+        SyncOperation::Download if file.is_none() => SyncChange::New,
+        _ => SyncChange::Modified,
+    }
+}
+
+fn size_of(operation: SyncOperation, file: Option<&Entry>, object: Option<&Entry>) -> u64 {
+    let file = file.map_or(0, |file| file.size);
+    let object = object.map_or(0, |object| object.size);
+
+    match operation {
+        SyncOperation::Upload | SyncOperation::DeleteLocal => file,
+        SyncOperation::Download | SyncOperation::DeleteRemote => object,
+        SyncOperation::KeepBoth => file + object,
+        SyncOperation::Skip => file.max(object),
+    }
+}
+
+// How one side of a path changed since the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// Neither there now nor at the last sync
+    Absent,
+    New,
+    Modified,
+    Deleted,
+    Unchanged,
+}
+
+fn side(
+    entry: Option<&Entry>,
+    record: Option<&SyncedFile>,
+    unchanged: impl Fn(&Entry, &SyncedFile) -> bool,
+) -> Side {
+    match (entry, record) {
+        (None, None) => Side::Absent,
+        (Some(_), None) => Side::New,
+        (None, Some(_)) => Side::Deleted,
+        (Some(entry), Some(record)) if unchanged(entry, record) => Side::Unchanged,
+        (Some(_), Some(_)) => Side::Modified,
+    }
+}
+
+fn two_way(
+    options: &SyncOptions,
+    file: Option<&Entry>,
+    object: Option<&Entry>,
+    local: Side,
+    remote: Side,
+    same: Option<bool>,
+) -> Option<Decision> {
+    let plain = |operation, change, reason| {
+        Some(Decision {
+            operation,
+            change,
+            reason,
+            choices: Vec::new(),
+        })
+    };
+    let conflict = |reason| {
+        let (operation, choices) = resolve(options.resolution, file, object);
+        Some(Decision {
+            operation,
+            change: SyncChange::Conflict,
+            reason,
+            choices,
+        })
+    };
+
+    match (local, remote) {
+        (Side::New, Side::Absent) => plain(
+            SyncOperation::Upload,
+            SyncChange::New,
+            "Only in the local folder",
+        ),
+        (Side::Absent, Side::New) => plain(SyncOperation::Download, SyncChange::New, "Only in S3"),
+        (Side::Modified, Side::Unchanged) => plain(
+            SyncOperation::Upload,
+            SyncChange::Modified,
+            "Changed locally",
+        ),
+        (Side::Unchanged, Side::Modified) => plain(
+            SyncOperation::Download,
+            SyncChange::Modified,
+            "Changed in S3",
+        ),
+        (Side::Unchanged, Side::Deleted) => plain(
+            SyncOperation::DeleteLocal,
+            SyncChange::Deleted,
+            "Deleted in S3",
+        ),
+        (Side::Deleted, Side::Unchanged) => plain(
+            SyncOperation::DeleteRemote,
+            SyncChange::Deleted,
+            "Deleted locally",
+        ),
+        (Side::Modified, Side::Deleted) => conflict("Changed locally, deleted in S3"),
+        (Side::Deleted, Side::Modified) => conflict("Deleted locally, changed in S3"),
+        (Side::New, Side::New) | (Side::Modified, Side::Modified) => {
+            let (Some(file), Some(object)) = (file, object) else {
+                return None;
+            };
+            if !differs(options, file, object, same) {
+                return None;
+            }
+            conflict(if local == Side::New {
+                "Created on both sides with different content"
+            } else {
+                "Changed on both sides"
+            })
+        }
+        _ => None,
+    }
+}
+
+// The operation a conflict resolves to, and the ones the user may pick instead
+fn resolve(
+    resolution: ConflictResolution,
+    file: Option<&Entry>,
+    object: Option<&Entry>,
+) -> (SyncOperation, Vec<SyncOperation>) {
+    use SyncOperation::*;
+
+    match (file, object) {
+        (Some(file), Some(object)) => {
+            let operation = match resolution {
+                ConflictResolution::KeepBoth => KeepBoth,
+                ConflictResolution::NewerWins if newer(object, file) => Download,
+                ConflictResolution::NewerWins | ConflictResolution::LocalWins => Upload,
+                ConflictResolution::Ask => Skip,
+            };
+            (operation, vec![Upload, Download, KeepBoth, Skip])
+        }
+        // Only the changed copy is left, so there is nothing to keep both of
+        (Some(_), None) => {
+            let operation = match resolution {
+                ConflictResolution::Ask => Skip,
+                _ => Upload,
+            };
+            (operation, vec![Upload, DeleteLocal, Skip])
+        }
+        (None, Some(_)) => {
+            let operation = match resolution {
+                ConflictResolution::LocalWins => DeleteRemote,
+                ConflictResolution::Ask => Skip,
+                _ => Download,
+            };
+            (operation, vec![Download, DeleteRemote, Skip])
+        }
+        (None, None) => (Skip, Vec::new()),
+    }
+}
+
+// Files whose time changed since the last sync but whose content did not, as
+// when a file is saved again unedited
+async fn resaved(
+    root: &Path,
+    local: &Tree,
+    records: &BTreeMap<String, SyncedFile>,
+) -> Result<BTreeSet<String>, String> {
+    let candidates: Vec<(String, String)> = local
+        .iter()
+        .filter_map(|(path, file)| {
+            let record = records.get(path)?;
+            let checksum = record.checksum.clone()?;
+            (file.size == record.size && file.modified != record.local_modified)
+                .then(|| (path.clone(), checksum))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        candidates
+            .into_iter()
+            .filter(|(path, checksum)| {
+                scan::sha256_file(&root.join(path)).is_ok_and(|actual| &actual == checksum)
+            })
+            .map(|(path, _)| path)
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))
+}
+
+// Whether `a` was modified after `b`, beyond the timestamp precision
+fn newer(a: &Entry, b: &Entry) -> bool {
+    match (a.modified, b.modified) {
+        (Some(a), Some(b)) => (a - b).num_seconds() > MTIME_TOLERANCE_SECS,
+        _ => false,
+    }
+}
+
+// Compare the contents of the files and objects of equal size, as the mode
+// says; different sizes already tell them apart
+async fn compare_contents(
+    compare: CompareMode,
+    digests: &Digests,
+    local: &Tree,
+    remote: &Tree,
+    concurrency: usize,
+) -> Result<BTreeMap<String, Option<bool>>, String> {
+    let mut results = BTreeMap::new();
+    if compare == CompareMode::SizeMtime {
+        return Ok(results);
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+
+    for (path, file) in local {
+        let Some(object) = remote.get(path).filter(|object| object.size == file.size) else {
+            continue;
+        };
+
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| e.to_string())?;
+        let (digests, path, object) = (digests.clone(), path.clone(), object.clone());
+
+        tasks.spawn(async move {
+            let _permit = permit;
+            let same = match compare {
+                CompareMode::Etag => digests.same_e_tag(&path, &object).await?,
+                _ => Some(digests.same_checksum(&path).await?),
+            };
+            Ok::<_, String>((path, same))
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        let (path, same) = result.map_err(|e| format!("Compare task failed: {}", e))??;
+        results.insert(path, same);
+    }
+
+    Ok(results)
+}
+
+/// Start the transfers and deletes of a plan. Every step is checked before
+/// anything is changed; steps that still fail to start are reported and their
+/// paths keep their previous state. Files are written over the older copies,
+/// and the deletes run as one batch. Once all of them have finished, the state
+/// of the pair is recorded in the background, comparing `concurrency` pairs of
+/// files at a time.
+pub async fn run(app: &AppHandle, plan: &SyncPlan, concurrency: usize) -> Result<SyncRun, String> {
+    let transfers = app.state::<TransferManager>();
+    let deletes = app.state::<DeleteManager>();
+    let roots = Roots::resolve(&plan.options)?;
+    let encryption = Encryption::default_at(&roots.remote)?;
+
+    let copies: BTreeMap<&str, String> = plan
+        .actions
+        .iter()
+        .filter(|action| action.operation == SyncOperation::KeepBoth)
+        .map(|action| (action.path.as_str(), conflict_copy(&action.path)))
+        .collect();
+    check(&roots, &plan.actions, &copies)?;
+
+    let since = Utc::now();
+    let mut started = Vec::new();
+    let mut skipped = BTreeSet::new();
+    let mut errors = Vec::new();
+    let mut doomed = Vec::new();
+
+    let upload = |path: &str| {
+        transfers
+            .enqueue(
+                TransferKind::Copy,
+                path_string(&roots.local_path(path)),
+                None,
+                roots.remote_path(parent_of(path)),
+                encryption.clone(),
+                true,
+            )
+            .map(|job| (job.id, path.to_string()))
+    };
+    let download = |path: &str| {
+        let dest_dir = roots.local_path(parent_of(path));
+        fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+        transfers
+            .enqueue(
+                TransferKind::Copy,
+                roots.remote_path(path),
+                None,
+                path_string(&dest_dir),
+                Encryption::BucketDefault,
+                true,
+            )
+            .map(|job| (job.id, path.to_string()))
+    };
+
+    for action in &plan.actions {
+        let path = action.path.as_str();
+        let result = match action.operation {
+            SyncOperation::Upload => upload(path).map(|job| started.push(job)),
+            SyncOperation::Download => download(path).map(|job| started.push(job)),
+            SyncOperation::KeepBoth => {
+                let copy = &copies[path];
+                fs::rename(roots.local_path(path), roots.local_path(copy))
+                    .map_err(|e| format!("Failed to rename: {}", e))
+                    .and_then(|()| {
+                        // The local copy is safe under its new name, so the
+                        // download goes ahead even if its upload fails
+                        match upload(copy) {
+                            Ok(job) => started.push(job),
+                            Err(e) => {
+                                skipped.insert(copy.clone());
+                                errors.push(format!("{}: {}", copy, e));
+                            }
+                        }
+                        download(path).map(|job| started.push(job))
+                    })
+            }
+            SyncOperation::DeleteLocal => {
+                doomed.push((path, path_string(&roots.local_path(path))));
+                Ok(())
+            }
+            SyncOperation::DeleteRemote => {
+                doomed.push((path, roots.remote_path(path)));
+                Ok(())
+            }
+            SyncOperation::Skip => {
+                skipped.insert(path.to_string());
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            skipped.insert(path.to_string());
+            errors.push(format!("{}: {}", path, e));
+        }
+    }
+
+    let mut deleting = doomed.len();
+    let delete = if doomed.is_empty() {
+        None
+    } else {
+        let targets = doomed.iter().map(|(_, target)| target.clone()).collect();
+        match deletes.start(targets) {
+            Ok(job) => Some(job.id),
+            Err(e) => {
+                for (path, _) in &doomed {
+                    skipped.insert(path.to_string());
+                    errors.push(format!("{}: {}", path, e));
+                }
+                deleting = 0;
+                None
+            }
+        }
+    };
+
+    let run = SyncRun {
+        transfers: started.len(),
+        deletes: deleting,
+        errors,
+    };
+
+    let app = app.clone();
+    let compare = plan.options.compare;
+    tauri::async_runtime::spawn(async move {
+        let _ = record(
+            app,
+            Ran {
+                roots,
+                compare,
+                concurrency,
+                since,
+                transfers: started,
+                delete,
+                skipped,
+            },
+        )
+        .await;
+    });
+
+    Ok(run)
+}
+
+// Check every step against the local folder as it is now, so a plan that no
+// longer fits, or one naming paths outside the pair, is refused before
+// anything is changed
+fn check(
+    roots: &Roots,
+    actions: &[SyncAction],
+    copies: &BTreeMap<&str, String>,
+) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    for action in actions {
+        let local = roots.local_path(&action.path);
+        // Downloads create the folders of the file, which a file in the way
+        // would stop
+        let blocked = || {
+            local
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != roots.local)
+                .any(Path::is_file)
+        };
+
+        let problem = if !is_relative(&action.path) {
+            Some("not a path inside the sync folders")
+        } else {
+            match action.operation {
+                SyncOperation::Upload if !local.is_file() => Some("the local file is gone"),
+                SyncOperation::KeepBoth if !local.is_file() => Some("the local file is gone"),
+                SyncOperation::KeepBoth
+                    if roots.local_path(&copies[action.path.as_str()]).exists() =>
+                {
+                    Some("the name for the conflict copy is taken")
+                }
+                SyncOperation::Download if local.is_dir() => Some("a folder has its name"),
+                SyncOperation::Download | SyncOperation::KeepBoth if blocked() => {
+                    Some("a file has the name of its folder")
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(problem) = problem {
+            problems.push(format!("{}: {}", action.path, problem));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The folders changed since the preview, preview again. {}",
+            problems.join("; ")
+        ))
+    }
+}
+
+// Whether a path from a plan stays below the roots: names separated by `/`,
+// none of them empty, `.` or `..`
+fn is_relative(path: &str) -> bool {
+    path.split('/').all(|part| !matches!(part, "" | "." | ".."))
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Name for the local copy set aside when keeping both sides of a conflict, as
+// `report (conflict 2026-01-31 14.05.09).pdf`
+fn conflict_copy(path: &str) -> String {
+    let (parent, name) = path.split_at(parent_of(path).len());
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    let stamp = chrono::Local::now().format("%Y-%m-%d %H.%M.%S");
+
+    format!("{}{} (conflict {}){}", parent, stem, stamp, extension)
+}
+
+// What a run started, for recording the pair once it is done
+struct Ran {
+    roots: Roots,
+    compare: CompareMode,
+    concurrency: usize,
+    since: DateTime<Utc>,
+    /// Transfer job IDs and the paths they write
+    transfers: Vec<(u64, String)>,
+    delete: Option<u64>,
+    skipped: BTreeSet<String>,
+}
+
+// Wait for the run's jobs, then record the paths the run transferred and those
+// whose two sides are verified to hold the same content, and forget the paths
+// gone from both. Paths whose transfer did not complete, conflicts left for
+// later, and files changed while the run went on keep their previous record.
+async fn record(app: AppHandle, ran: Ran) -> Result<(), String> {
+    let mut unsettled = ran.skipped;
+    let mut transferred = BTreeSet::new();
+
+    loop {
+        let jobs = app.state::<TransferManager>().jobs()?;
+        let status = |id: u64| jobs.iter().find(|job| job.id == id).map(|job| job.status);
+        let deleting = match ran.delete {
+            Some(id) => app
+                .state::<DeleteManager>()
+                .jobs()?
+                .iter()
+                .any(|job| job.id == id && !job.status.is_finished()),
+            None => false,
+        };
+
+        let transferring = ran
+            .transfers
+            .iter()
+            .any(|(id, _)| status(*id).is_some_and(|status| !status.is_finished()));
+        if !transferring && !deleting {
+            for (id, path) in &ran.transfers {
+                if status(*id) == Some(JobStatus::Completed) {
+                    transferred.insert(path.clone());
+                } else {
+                    unsettled.insert(path.clone());
+                }
+            }
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let storage = app.state::<StorageManager>();
+    let state = app.state::<SyncState>();
+    let pair = ran.roots.pair_id();
+    let (local, remote) = ran.roots.scan(&storage).await?;
+    let records = state.snapshot(&pair)?;
+
+    let root = ran.roots.local.clone();
+    let checksums: BTreeMap<String, String> = tokio::task::spawn_blocking(move || {
+        transferred
+            .into_iter()
+            .filter_map(|path| {
+                let checksum = scan::sha256_file(&root.join(&path)).ok()?;
+                Some((path, checksum))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))?;
+
+    // Equal sizes do not make the same file, so paths the run did not write
+    // are compared first, by ETag at least, unless their record already
+    // matches both sides
+    let touched = |entry: &Entry| entry.modified.is_some_and(|time| time > ran.since);
+    let unverified: Tree = local
+        .iter()
+        .filter(|(path, file)| {
+            let Some(object) = remote.get(*path) else {
+                return false;
+            };
+            let recorded = records
+                .get(*path)
+                .is_some_and(|record| record.matches_local(file) && record.matches_remote(object));
+            !unsettled.contains(*path)
+                && !checksums.contains_key(*path)
+                && !recorded
+                && file.size == object.size
+                && !touched(file)
+                && !touched(object)
+        })
+        .map(|(path, file)| (path.clone(), file.clone()))
+        .collect();
+    let compare = match ran.compare {
+        CompareMode::SizeMtime => CompareMode::Etag,
+        compare => compare,
+    };
+    let digests = ran.roots.digests(&storage).await?;
+    // A comparison that fails only leaves those paths unrecorded
+    let verified = compare_contents(compare, &digests, &unverified, &remote, ran.concurrency)
+        .await
+        .unwrap_or_default();
+
+    let mut paths: Vec<&String> = local.keys().chain(remote.keys()).collect();
+    paths.extend(records.keys());
+    paths.sort();
+    paths.dedup();
+
+    let mut changes = BTreeMap::new();
+    for path in paths {
+        if unsettled.contains(path) {
+            continue;
+        }
+
+        match (local.get(path), remote.get(path)) {
+            (Some(file), Some(object)) if file.size == object.size => {
+                let checksum = match checksums.get(path) {
+                    Some(checksum) => Some(checksum.clone()),
+                    None if verified.get(path) == Some(&Some(true)) => records
+                        .get(path)
+                        .filter(|record| record.matches_local(file))
+                        .and_then(|record| record.checksum.clone()),
+                    None => continue,
+                };
+                changes.insert(path.clone(), Some(SyncedFile::new(file, object, checksum)));
+            }
+            (None, None) => {
+                changes.insert(path.clone(), None);
+            }
+            _ => {}
+        }
+    }
+
+    state.update(&pair, &changes)
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use SyncDirection::{Download as Down, Upload as Up};
+    use SyncPolicy::{Mirror, NeverDelete};
+
+    fn options(policy: SyncPolicy, direction: SyncDirection) -> SyncOptions {
+        SyncOptions {
+            local: String::new(),
+            remote: String::new(),
+            policy,
+            direction,
+            compare: CompareMode::SizeMtime,
+            resolution: ConflictResolution::Ask,
+        }
+    }
+
+    // An entry modified `secs` seconds into 2026
+    fn entry(size: u64, secs: i64) -> Entry {
+        Entry {
+            size,
+            modified: Some(
+                Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+                    + chrono::Duration::seconds(secs),
+            ),
+            e_tag: None,
+        }
+    }
+
+    #[test]
+    fn one_way_missing_sides() {
+        use SyncOperation::*;
+
+        let cases = [
+            (Mirror, Up, Some(Upload), Some(DeleteRemote)),
+            (Mirror, Down, Some(DeleteLocal), Some(Download)),
+            (NeverDelete, Up, Some(Upload), None),
+            (NeverDelete, Down, None, Some(Download)),
+        ];
+        for (policy, direction, local_only, remote_only) in cases {
+            let options = options(policy, direction);
+            assert_eq!(
+                only_local(&options).map(|step| step.0),
+                local_only,
+                "{:?} {:?}",
+                policy,
+                direction
+            );
+            assert_eq!(
+                only_remote(&options).map(|step| step.0),
+                remote_only,
+                "{:?} {:?}",
+                policy,
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn one_way_changes() {
+        use SyncOperation::*;
+
+        // (direction, file, object, content comparison, expected step)
+        let cases = [
+            (Up, entry(10, 0), entry(10, 0), None, None),
+            (Up, entry(10, 0), entry(11, 0), None, Some(Upload)),
+            (Down, entry(10, 0), entry(11, 0), None, Some(Download)),
+            // Within the timestamp tolerance
+            (Up, entry(10, 2), entry(10, 0), None, None),
+            (Up, entry(10, 3), entry(10, 0), None, Some(Upload)),
+            // Only the source being newer counts
+            (Up, entry(10, 0), entry(10, 60), None, None),
+            (Down, entry(10, 0), entry(10, 60), None, Some(Download)),
+            (Down, entry(10, 60), entry(10, 0), None, None),
+            // A content comparison overrides sizes and times
+            (Up, entry(10, 60), entry(10, 0), Some(true), None),
+            (Up, entry(10, 0), entry(10, 0), Some(false), Some(Upload)),
+            (
+                Down,
+                entry(10, 0),
+                entry(10, 0),
+                Some(false),
+                Some(Download),
+            ),
+        ];
+        for (direction, file, object, same, expected) in cases {
+            for policy in [Mirror, NeverDelete] {
+                assert_eq!(
+                    changed(&options(policy, direction), &file, &object, same).map(|step| step.0),
+                    expected,
+                    "{:?} {:?} {:?} {:?} {:?}",
+                    policy,
+                    direction,
+                    file,
+                    object,
+                    same
+                );
+            }
+        }
+
+        // Without times only sizes tell
+        let undated = Entry {
+            modified: None,
+            ..entry(10, 0)
+        };
+        assert!(!differs(
+            &options(Mirror, Up),
+            &undated,
+            &entry(10, 60),
+            None
+        ));
+    }
+
+    #[test]
+    fn one_way_change_and_size() {
+        use SyncOperation::*;
+
+        let (file, object) = (entry(10, 0), entry(25, 0));
+        let cases = [
+            (Upload, Some(&file), None, SyncChange::New, 10),
+            (Upload, Some(&file), Some(&object), SyncChange::Modified, 10),
+            (Download, None, Some(&object), SyncChange::New, 25),
+            (
+                Download,
+                Some(&file),
+                Some(&object),
+                SyncChange::Modified,
+                25,
+            ),
+            (DeleteLocal, Some(&file), None, SyncChange::Deleted, 10),
+            (DeleteRemote, None, Some(&object), SyncChange::Deleted, 25),
+        ];
+        for (operation, file, object, change, size) in cases {
+            assert_eq!(
+                one_way_change(operation, file, object),
+                change,
+                "{:?}",
+                operation
+            );
+            assert_eq!(size_of(operation, file, object), size, "{:?}", operation);
+        }
+
+        assert_eq!(size_of(KeepBoth, Some(&file), Some(&object)), 35);
+        assert_eq!(size_of(Skip, Some(&file), Some(&object)), 25);
+        assert_eq!(size_of(Skip, None, None), 0);
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(parent_of("a/b/c.txt"), "a/b/");
+        assert_eq!(parent_of("c.txt"), "");
+
+        let stamp = chrono::Local::now().format("%Y-%m-%d").to_string();
+        for (path, start, end) in [
+            ("docs/report.pdf", "docs/report (conflict ", ").pdf"),
+            ("archive.tar.gz", "archive.tar (conflict ", ").gz"),
+            ("notes", "notes (conflict ", ")"),
+            ("dir.d/.env", "dir.d/.env (conflict ", ")"),
+        ] {
+            let copy = conflict_copy(path);
+            assert!(copy.starts_with(&format!("{}{}", start, stamp)), "{}", copy);
+            assert!(copy.ends_with(end), "{}", copy);
+        }
+    }
+
+    #[test]
+    fn check_refuses_stale_plans() {
+        use SyncOperation::*;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("here.txt"), "").unwrap();
+        fs::write(root.join("file"), "").unwrap();
+        fs::create_dir(root.join("folder")).unwrap();
+        let roots = Roots {
+            local: root.to_path_buf(),
+            remote: "s3://bucket/".to_string(),
+            account: None,
+            bucket: "bucket".to_string(),
+            prefix: String::new(),
+        };
+        let action = |path: &str, operation| SyncAction {
+            path: path.to_string(),
+            operation,
+            change: SyncChange::Modified,
+            size: 0,
+            reason: String::new(),
+            choices: Vec::new(),
+        };
+        let check = |actions: &[SyncAction], copies: &[(&'static str, &str)]| {
+            let copies = copies
+                .iter()
+                .map(|(path, copy)| (*path, copy.to_string()))
+                .collect();
+            check(&roots, actions, &copies)
+        };
+
+        let fine = [
+            action("here.txt", Upload),
+            action("new/deep/down.txt", Download),
+            action("here.txt", Download),
+            action("gone.txt", DeleteLocal),
+            action("folder/gone.txt", DeleteRemote),
+            action("whatever", Skip),
+        ];
+        check(&fine, &[]).unwrap();
+        check(
+            &[action("here.txt", KeepBoth)],
+            &[("here.txt", "here (copy).txt")],
+        )
+        .unwrap();
+
+        let cases = [
+            (action("missing.txt", Upload), "the local file is gone"),
+            (action("folder", Upload), "the local file is gone"),
+            (action("folder", Download), "a folder has its name"),
+            (
+                action("file/inside.txt", Download),
+                "a file has the name of its folder",
+            ),
+            (
+                action("../outside.txt", Download),
+                "not a path inside the sync folders",
+            ),
+            (
+                action("/etc/passwd", Upload),
+                "not a path inside the sync folders",
+            ),
+            (
+                action("a/./b", Upload),
+                "not a path inside the sync folders",
+            ),
+            (
+                action("", DeleteLocal),
+                "not a path inside the sync folders",
+            ),
+        ];
+        for (action, problem) in cases {
+            let error = check(std::slice::from_ref(&action), &[]).unwrap_err();
+            assert!(
+                error.ends_with(&format!("{}: {}", action.path, problem)),
+                "{}",
+                error
+            );
+        }
+
+        let error = check(&[action("here.txt", KeepBoth)], &[("here.txt", "file")]).unwrap_err();
+        assert!(error.ends_with("here.txt: the name for the conflict copy is taken"));
+        let error = check(
+            &[action("gone.txt", KeepBoth)],
+            &[("gone.txt", "gone (copy).txt")],
+        )
+        .unwrap_err();
+        assert!(error.ends_with("gone.txt: the local file is gone"));
+
+        // Every problem is listed, and nothing was touched
+        let error = check(
+            &[action("missing.txt", Upload), action("folder", Download)],
+            &[],
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("missing.txt: the local file is gone; folder: a folder has its name")
+        );
+        assert!(!root.join("new").exists());
+    }
+
+    fn synced(file: &Entry, object: &Entry) -> SyncedFile {
+        SyncedFile::new(file, object, None)
+    }
+
+    #[test]
+    fn sides_against_the_record() {
+        let (file, object) = (entry(10, 0), entry(10, 5));
+        let record = synced(&file, &object);
+        let same = |entry: &Entry, record: &SyncedFile| record.matches_local(entry);
+
+        assert_eq!(side(None, None, same), Side::Absent);
+        assert_eq!(side(Some(&file), None, same), Side::New);
+        assert_eq!(side(None, Some(&record), same), Side::Deleted);
+        assert_eq!(side(Some(&file), Some(&record), same), Side::Unchanged);
+        assert_eq!(
+            side(Some(&entry(11, 0)), Some(&record), same),
+            Side::Modified
+        );
+        assert_eq!(
+            side(Some(&entry(10, 1)), Some(&record), same),
+            Side::Modified
+        );
+    }
+
+    #[test]
+    fn two_way_decisions() {
+        use Side::*;
+        use SyncOperation::*;
+
+        let conflict = Some((Skip, SyncChange::Conflict));
+        // (local side, remote side, content comparison, expected decision)
+        let cases = [
+            (New, Absent, None, Some((Upload, SyncChange::New))),
+            (Absent, New, None, Some((Download, SyncChange::New))),
+            (
+                Modified,
+                Unchanged,
+                None,
+                Some((Upload, SyncChange::Modified)),
+            ),
+            (
+                Unchanged,
+                Modified,
+                None,
+                Some((Download, SyncChange::Modified)),
+            ),
+            (
+                Unchanged,
+                Deleted,
+                None,
+                Some((DeleteLocal, SyncChange::Deleted)),
+            ),
+            (
+                Deleted,
+                Unchanged,
+                None,
+                Some((DeleteRemote, SyncChange::Deleted)),
+            ),
+            (Modified, Deleted, None, conflict),
+            (Deleted, Modified, None, conflict),
+            (New, New, None, conflict),
+            (New, New, Some(false), conflict),
+            (New, New, Some(true), None),
+            (Modified, Modified, None, conflict),
+            (Modified, Modified, Some(true), None),
+            (Unchanged, Unchanged, None, None),
+            (Deleted, Deleted, None, None),
+            (Absent, Absent, None, None),
+        ];
+
+        for (local, remote, same, expected) in cases {
+            // Sizes differ unless the comparison says otherwise
+            let file = matches!(local, New | Modified | Unchanged).then(|| entry(10, 0));
+            let object = matches!(remote, New | Modified | Unchanged).then(|| entry(20, 0));
+            let decision = two_way(
+                &options(SyncPolicy::TwoWay, Up),
+                file.as_ref(),
+                object.as_ref(),
+                local,
+                remote,
+                same,
+            );
+
+            assert_eq!(
+                decision.as_ref().map(|d| (d.operation, d.change)),
+                expected,
+                "{:?} {:?} {:?}",
+                local,
+                remote,
+                same
+            );
+            if let Some(decision) = decision {
+                assert_eq!(
+                    decision.choices.is_empty(),
+                    decision.change != SyncChange::Conflict
+                );
+            }
+        }
+
+        // Both created with the same size and time are taken as the same
+        let decision = two_way(
+            &options(SyncPolicy::TwoWay, Up),
+            Some(&entry(10, 0)),
+            Some(&entry(10, 1)),
+            New,
+            New,
+            None,
+        );
+        assert!(decision.is_none());
+    }
+
+    #[test]
+    fn conflict_resolutions() {
+        use ConflictResolution as Resolution;
+        use SyncOperation::*;
+
+        let (old, new) = (entry(10, 0), entry(10, 60));
+        let both = vec![Upload, Download, KeepBoth, Skip];
+        let file_only = vec![Upload, DeleteLocal, Skip];
+        let object_only = vec![Download, DeleteRemote, Skip];
+
+        // (resolution, file, object, expected operation, choices)
+        let cases = [
+            (
+                Resolution::KeepBoth,
+                Some(&old),
+                Some(&new),
+                KeepBoth,
+                &both,
+            ),
+            (
+                Resolution::NewerWins,
+                Some(&old),
+                Some(&new),
+                Download,
+                &both,
+            ),
+            (Resolution::NewerWins, Some(&new), Some(&old), Upload, &both),
+            (Resolution::NewerWins, Some(&old), Some(&old), Upload, &both),
+            (Resolution::LocalWins, Some(&old), Some(&new), Upload, &both),
+            (Resolution::Ask, Some(&old), Some(&new), Skip, &both),
+            (Resolution::KeepBoth, Some(&old), None, Upload, &file_only),
+            (Resolution::NewerWins, Some(&old), None, Upload, &file_only),
+            (Resolution::LocalWins, Some(&old), None, Upload, &file_only),
+            (Resolution::Ask, Some(&old), None, Skip, &file_only),
+            (
+                Resolution::KeepBoth,
+                None,
+                Some(&new),
+                Download,
+                &object_only,
+            ),
+            (
+                Resolution::NewerWins,
+                None,
+                Some(&new),
+                Download,
+                &object_only,
+            ),
+            (
+                Resolution::LocalWins,
+                None,
+                Some(&new),
+                DeleteRemote,
+                &object_only,
+            ),
+            (Resolution::Ask, None, Some(&new), Skip, &object_only),
+        ];
+        for (resolution, file, object, operation, choices) in cases {
+            assert_eq!(
+                resolve(resolution, file, object),
+                (operation, choices.clone()),
+                "{:?} {:?} {:?}",
+                resolution,
+                file,
+                object
+            );
+            // The chosen operation is always one the user could pick
+            assert!(choices.contains(&operation));
+        }
+
+        for resolution in [
+            Resolution::KeepBoth,
+            Resolution::NewerWins,
+            Resolution::LocalWins,
+            Resolution::Ask,
+        ] {
+            assert_eq!(resolve(resolution, None, None), (Skip, Vec::new()));
+        }
+    }
+
+    #[test]
+    fn resolution_applies_to_conflicts_only() {
+        let mut options = options(SyncPolicy::TwoWay, Up);
+        options.resolution = ConflictResolution::KeepBoth;
+        let (file, object) = (entry(10, 0), entry(20, 0));
+
+        let decision = two_way(
+            &options,
+            Some(&file),
+            Some(&object),
+            Side::Modified,
+            Side::Modified,
+            None,
+        )
+        .unwrap();
+        assert_eq!(decision.operation, SyncOperation::KeepBoth);
+        assert_eq!(decision.reason, "Changed on both sides");
+
+        let decision = two_way(
+            &options,
+            Some(&file),
+            Some(&object),
+            Side::Modified,
+            Side::Unchanged,
+            None,
+        )
+        .unwrap();
+        assert_eq!(decision.operation, SyncOperation::Upload);
+    }
+}
@@ -0,0 +1,253 @@
+// Listings of both sides of a sync pair, and the digests used to tell whether
+// a file and an object of the same size hold the same data.
+
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::types::ChecksumMode;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::storage::encryption::CustomerKey;
+use crate::storage::S3Storage;
+use crate::transfer::download::{local_e_tag, local_path_for_key, opaque_e_tag, trim_e_tag};
+
+/// A file or object as a sync sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// Only known for objects
+    pub e_tag: Option<String>,
+}
+
+/// Entries by their path relative to the pair's root, `/` separated.
+pub type Tree = BTreeMap<String, Entry>;
+
+/// Every file below `root`. Symbolic links are not followed, and the partial
+/// files of unfinished downloads are left out.
+pub fn local_tree(root: &Path) -> Result<Tree, String> {
+    let mut tree = Tree::new();
+    collect_local(root, "", &mut tree)?;
+    Ok(tree)
+}
+
+fn collect_local(dir: &Path, prefix: &str, tree: &mut Tree) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let metadata = entry
+            .metadata()
+            .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if metadata.is_dir() {
+            collect_local(&entry.path(), &format!("{}{}/", prefix, name), tree)?;
+        } else if metadata.is_file() && !name.ends_with(".part") {
+            tree.insert(
+                format!("{}{}", prefix, name),
+                Entry {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok().map(DateTime::from),
+                    e_tag: None,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Every object below `prefix`. Folder markers are left out, and so are keys
+/// that could not be a file below the local folder.
+pub async fn remote_tree(s3: &S3Storage, bucket: &str, prefix: &str) -> Result<Tree, String> {
+    let mut tree = Tree::new();
+    let mut pages = s3
+        .client()
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page =
+            page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+
+        for object in page.contents() {
+            let Some(relative) = object.key().and_then(|key| key.strip_prefix(prefix)) else {
+                continue;
+            };
+            if relative.is_empty()
+                || relative.ends_with('/')
+                || local_path_for_key(Path::new(""), relative).is_err()
+            {
+                continue;
+            }
+
+            tree.insert(
+                relative.to_string(),
+                Entry {
+                    size: object.size().unwrap_or(0).max(0) as u64,
+                    modified: object
+                        .last_modified()
+                        .and_then(|time| SystemTime::try_from(*time).ok())
+                        .map(DateTime::from),
+                    e_tag: object.e_tag().map(str::to_string),
+                },
+            );
+        }
+    }
+
+    Ok(tree)
+}
+
+/// Reads the digests of the files and objects of one pair.
+#[derive(Clone)]
+pub struct Digests {
+    pub s3: Arc<S3Storage>,
+    pub bucket: String,
+    pub prefix: String,
+    pub root: PathBuf,
+}
+
+impl Digests {
+    fn key(&self, path: &str) -> String {
+        format!("{}{}", self.prefix, path)
+    }
+
+    async fn head(&self, path: &str, part: Option<i32>) -> Result<HeadObjectOutput, String> {
+        let key = self.key(path);
+
+        self.s3
+            .customer_keys()
+            .send(
+                |customer| {
+                    self.s3
+                        .client()
+                        .head_object()
+                        .bucket(&self.bucket)
+                        .key(&key)
+                        .set_part_number(part)
+                        .checksum_mode(ChecksumMode::Enabled)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get object metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await
+    }
+
+    /// Whether the file's MD5 based ETag matches the object's. `None` when
+    /// the object's ETag is not a digest of its data, as with KMS and
+    /// customer keys.
+    pub async fn same_e_tag(&self, path: &str, remote: &Entry) -> Result<Option<bool>, String> {
+        let Some(expected) = remote.e_tag.as_deref().map(trim_e_tag) else {
+            return Ok(None);
+        };
+
+        // Multipart ETags depend on the part size, which S3 reports for part 1
+        let part_size = if expected.contains('-') {
+            let first_part = self.head(path, Some(1)).await?;
+            if opaque_e_tag(&first_part) {
+                return Ok(None);
+            }
+            match first_part.content_length() {
+                Some(length) if length > 0 => Some(length as u64),
+                _ => return Ok(None),
+            }
+        } else {
+            None
+        };
+
+        let file = self.root.join(path);
+        let actual = tokio::task::spawn_blocking(move || local_e_tag(&file, part_size))
+            .await
+            .map_err(|e| format!("Checksum task failed: {}", e))?
+            .map_err(|e| format!("Failed to checksum {}: {}", path, e))?;
+
+        if actual == expected {
+            return Ok(Some(true));
+        }
+
+        if part_size.is_none() && opaque_e_tag(&self.head(path, None).await?) {
+            return Ok(None);
+        }
+
+        Ok(Some(false))
+    }
+
+    /// Whether the file and the object have the same SHA-256. The object's
+    /// full-object checksum is used when S3 has one; otherwise the object is
+    /// read through and hashed.
+    pub async fn same_checksum(&self, path: &str) -> Result<bool, String> {
+        let file = self.root.join(path);
+        let local = tokio::task::spawn_blocking(move || sha256_file(&file))
+            .await
+            .map_err(|e| format!("Checksum task failed: {}", e))?
+            .map_err(|e| format!("Failed to checksum {}: {}", path, e))?;
+
+        // Composite checksums of multipart uploads end in the part count
+        let head = self.head(path, None).await?;
+        if let Some(checksum) = head.checksum_sha256().filter(|c| !c.contains('-')) {
+            return Ok(checksum == local);
+        }
+
+        Ok(self.sha256_object(path).await? == local)
+    }
+
+    async fn sha256_object(&self, path: &str) -> Result<String, String> {
+        let key = self.key(path);
+        let mut output = self
+            .s3
+            .customer_keys()
+            .send(
+                |customer| {
+                    self.s3
+                        .client()
+                        .get_object()
+                        .bucket(&self.bucket)
+                        .key(&key)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to read object: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let mut hasher = Sha256::new();
+        while let Some(bytes) = output.body.next().await {
+            hasher.update(bytes.map_err(|e| format!("Failed to read object: {}", e))?);
+        }
+
+        Ok(general_purpose::STANDARD.encode(hasher.finalize()))
+    }
+}
+
+/// SHA-256 of a file, base64 like S3 checksums.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(general_purpose::STANDARD.encode(hasher.finalize()))
+}
@@ -0,0 +1,273 @@
+// What each sync pair looked like after its last sync, so a two-way sync can
+// tell a file deleted on one side from a file created on the other. Records
+// live in an embedded database in the app data directory, keyed by pair and
+// relative path.
+
+use chrono::{DateTime, Utc};
+use redb::{Database, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::scan::Entry;
+use crate::transfer::state_id;
+use crate::utils::app_data_dir;
+
+const DATABASE_FILE: &str = "sync-state.redb";
+
+// (pair ID, relative path) to a JSON encoded SyncedFile
+const FILES: TableDefinition<(&str, &str), &[u8]> = TableDefinition::new("files");
+
+/// A path as it was on both sides when it was last synced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedFile {
+    pub size: u64,
+    pub local_modified: Option<DateTime<Utc>>,
+    pub remote_modified: Option<DateTime<Utc>>,
+    pub e_tag: Option<String>,
+    /// SHA-256 of the synced content, base64, when it was computed
+    pub checksum: Option<String>,
+}
+
+impl SyncedFile {
+    pub fn new(file: &Entry, object: &Entry, checksum: Option<String>) -> Self {
+        Self {
+            size: file.size,
+            local_modified: file.modified,
+            remote_modified: object.modified,
+            e_tag: object.e_tag.clone(),
+            checksum,
+        }
+    }
+
+    /// Whether the file is as it was synced, going by size and time.
+    pub fn matches_local(&self, file: &Entry) -> bool {
+        file.size == self.size && file.modified == self.local_modified
+    }
+
+    pub fn matches_remote(&self, object: &Entry) -> bool {
+        object.size == self.size && object.e_tag == self.e_tag
+    }
+}
+
+/// The last synced state of every pair.
+pub struct SyncState {
+    db: Database,
+}
+
+impl SyncState {
+    pub fn open() -> Result<Self, String> {
+        Self::open_at(&app_data_dir()?.join(DATABASE_FILE))
+    }
+
+    fn open_at(path: &Path) -> Result<Self, String> {
+        let db = Database::create(path).map_err(|e| format!("Failed to open sync state: {}", e))?;
+
+        Ok(Self { db })
+    }
+
+    /// Identifier of the pair of a local folder and an S3 prefix.
+    pub fn pair_id(local: &str, remote: &str) -> String {
+        state_id(&["sync", local, remote])
+    }
+
+    /// Every path recorded for a pair.
+    pub fn snapshot(&self, pair: &str) -> Result<BTreeMap<String, SyncedFile>, String> {
+        let txn = self
+            .db
+            .begin_read()
+            .map_err(|e| format!("Failed to read sync state: {}", e))?;
+        let table = match txn.open_table(FILES) {
+            Ok(table) => table,
+            // Nothing has been synced yet
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(BTreeMap::new()),
+            Err(e) => return Err(format!("Failed to read sync state: {}", e)),
+        };
+
+        let mut files = BTreeMap::new();
+        let range = table
+            .range((pair, "")..)
+            .map_err(|e| format!("Failed to read sync state: {}", e))?;
+        for row in range {
+            let (key, value) = row.map_err(|e| format!("Failed to read sync state: {}", e))?;
+            let (row_pair, path) = key.value();
+            if row_pair != pair {
+                break;
+            }
+
+            // A record that cannot be read is as good as none
+            if let Ok(file) = serde_json::from_slice(value.value()) {
+                files.insert(path.to_string(), file);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Record paths of a pair, or forget them with `None`.
+    pub fn update(
+        &self,
+        pair: &str,
+        changes: &BTreeMap<String, Option<SyncedFile>>,
+    ) -> Result<(), String> {
+        let txn = self
+            .db
+            .begin_write()
+            .map_err(|e| format!("Failed to write sync state: {}", e))?;
+        {
+            let mut table = txn
+                .open_table(FILES)
+                .map_err(|e| format!("Failed to write sync state: {}", e))?;
+
+            for (path, file) in changes {
+                let result = match file {
+                    Some(file) => {
+                        let value = serde_json::to_vec(file).map_err(|e| e.to_string())?;
+                        table
+                            .insert((pair, path.as_str()), value.as_slice())
+                            .map(|_| ())
+                    }
+                    None => table.remove((pair, path.as_str())).map(|_| ()),
+                };
+                result.map_err(|e| format!("Failed to write sync state: {}", e))?;
+            }
+        }
+
+        txn.commit()
+            .map_err(|e| format!("Failed to write sync state: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(size: u64, secs: i64, e_tag: Option<&str>) -> Entry {
+        Entry {
+            size,
+            modified: Some(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()),
+            e_tag: e_tag.map(str::to_string),
+        }
+    }
+
+    fn synced(size: u64) -> SyncedFile {
+        SyncedFile::new(
+            &entry(size, 0, None),
+            &entry(size, 5, Some("\"abc\"")),
+            Some("checksum".to_string()),
+        )
+    }
+
+    #[test]
+    fn matches_each_side() {
+        let file = entry(10, 0, None);
+        let object = entry(10, 5, Some("\"abc\""));
+        let record = SyncedFile::new(&file, &object, None);
+
+        assert_eq!(record.size, 10);
+        assert_eq!(record.local_modified, file.modified);
+        assert_eq!(record.remote_modified, object.modified);
+        assert_eq!(record.e_tag.as_deref(), Some("\"abc\""));
+
+        // (entry, matches as the file, matches as the object)
+        let cases = [
+            (file.clone(), true, false),
+            (object.clone(), false, true),
+            (entry(11, 0, None), false, false),
+            (entry(10, 1, None), false, false),
+            // Objects go by ETag, whatever their time
+            (entry(10, 99, Some("\"abc\"")), false, true),
+            (entry(10, 5, Some("\"def\"")), false, false),
+            (entry(11, 5, Some("\"abc\"")), false, false),
+        ];
+        for (entry, local, remote) in cases {
+            assert_eq!(record.matches_local(&entry), local, "{:?}", entry);
+            assert_eq!(record.matches_remote(&entry), remote, "{:?}", entry);
+        }
+    }
+
+    #[test]
+    fn pair_ids() {
+        let id = SyncState::pair_id("/home/me/site", "s3://bucket/site/");
+        assert_eq!(id, SyncState::pair_id("/home/me/site", "s3://bucket/site/"));
+        assert_ne!(
+            id,
+            SyncState::pair_id("/home/me/site", "s3://bucket/other/")
+        );
+        assert_ne!(
+            id,
+            SyncState::pair_id("/home/me/other", "s3://bucket/site/")
+        );
+    }
+
+    #[test]
+    fn records_per_pair() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = SyncState::open_at(&dir.path().join(DATABASE_FILE)).unwrap();
+        assert!(state.snapshot("pair").unwrap().is_empty());
+
+        let changes = BTreeMap::from([
+            ("a.txt".to_string(), Some(synced(1))),
+            ("dir/b.txt".to_string(), Some(synced(2))),
+        ]);
+        state.update("pair", &changes).unwrap();
+        // Pairs whose IDs share a prefix stay apart
+        state
+            .update(
+                "pair2",
+                &BTreeMap::from([("c.txt".to_string(), Some(synced(3)))]),
+            )
+            .unwrap();
+        state
+            .update(
+                "pai",
+                &BTreeMap::from([("d.txt".to_string(), Some(synced(4)))]),
+            )
+            .unwrap();
+
+        let snapshot = state.snapshot("pair").unwrap();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), ["a.txt", "dir/b.txt"]);
+        assert_eq!(snapshot["dir/b.txt"], synced(2));
+        assert_eq!(state.snapshot("pair2").unwrap().len(), 1);
+        assert_eq!(state.snapshot("pai").unwrap().len(), 1);
+
+        // Updates replace and forget single paths
+        let changes = BTreeMap::from([
+            ("a.txt".to_string(), Some(synced(5))),
+            ("dir/b.txt".to_string(), None),
+            ("never-there".to_string(), None),
+        ]);
+        state.update("pair", &changes).unwrap();
+        let snapshot = state.snapshot("pair").unwrap();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), ["a.txt"]);
+        assert_eq!(snapshot["a.txt"].size, 5);
+    }
+
+    #[test]
+    fn survives_reopening_and_bad_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DATABASE_FILE);
+        {
+            let state = SyncState::open_at(&path).unwrap();
+            state
+                .update(
+                    "pair",
+                    &BTreeMap::from([("a.txt".to_string(), Some(synced(1)))]),
+                )
+                .unwrap();
+
+            let txn = state.db.begin_write().unwrap();
+            txn.open_table(FILES)
+                .unwrap()
+                .insert(("pair", "broken"), b"not json".as_slice())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+
+        let state = SyncState::open_at(&path).unwrap();
+        let snapshot = state.snapshot("pair").unwrap();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), ["a.txt"]);
+        assert_eq!(snapshot["a.txt"], synced(1));
+    }
+}
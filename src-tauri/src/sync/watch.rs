@@ -0,0 +1,505 @@
+// Watch folders: local folders whose new and changed files are uploaded to an
+// S3 prefix as they appear. Changes are picked up from file system
+// notifications, and a file is only uploaded once it has stopped changing, so
+// files still being written are not sent half done. Starting a watch, on
+// adding it or when the app starts, first rescans the folder and uploads what
+// changed while nothing was watching. Files are never deleted from S3.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+
+use super::state::SyncState;
+use super::{
+    parent_of, path_string, scan, CompareMode, ConflictResolution, Roots, SyncDirection,
+    SyncOperation, SyncOptions, SyncPolicy,
+};
+use crate::storage::encryption::Encryption;
+use crate::storage::StorageManager;
+use crate::transfer::{state_id, TransferKind, TransferManager, TransferSettings};
+use crate::utils::{app_data_dir, read_json, write_json};
+
+/// Event carrying a [`WatchStatus`] whenever a watch folder's status changes.
+pub const WATCH_EVENT: &str = "watch-updated";
+
+const WATCH_FILE: &str = "watch-folders.json";
+
+// A changed file is uploaded once its size and time have held this long
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+const TICK: Duration = Duration::from_millis(500);
+
+/// A local folder mirrored to an S3 prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub id: String,
+    pub local: String,
+    /// The S3 prefix, as `s3://[account@]bucket/prefix/`
+    pub remote: String,
+    /// Files not to upload, in `.gitignore` syntax, one pattern per line
+    #[serde(default)]
+    pub ignore: String,
+    #[serde(default)]
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchState {
+    /// Looking for changes made while the folder was not watched
+    Scanning,
+    Watching,
+    Paused,
+    Failed,
+}
+
+/// A watch folder as the frontend sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub folder: WatchFolder,
+    pub state: WatchState,
+    /// Changed files waiting for their writes to settle
+    pub pending: usize,
+    /// Files handed to the transfer queue since the watch started
+    pub uploads: u64,
+    pub last_upload: Option<String>,
+    pub error: Option<String>,
+}
+
+struct WatchEntry {
+    status: WatchStatus,
+    task: Option<JoinHandle<()>>,
+}
+
+/// Runs every watch folder and keeps the list of them on disk.
+#[derive(Clone)]
+pub struct WatchManager {
+    app: AppHandle,
+    watches: Arc<Mutex<BTreeMap<String, WatchEntry>>>,
+}
+
+impl WatchManager {
+    /// Load the saved watch folders and start those not paused.
+    pub fn new(app: AppHandle) -> Self {
+        let folders: Vec<WatchFolder> = app_data_dir()
+            .and_then(|dir| read_json(&dir.join(WATCH_FILE)))
+            .unwrap_or_default();
+        let ids: Vec<String> = folders.iter().map(|folder| folder.id.clone()).collect();
+        let watches = folders
+            .into_iter()
+            .map(|folder| {
+                let entry = WatchEntry {
+                    status: WatchStatus::new(folder),
+                    task: None,
+                };
+                (entry.status.folder.id.clone(), entry)
+            })
+            .collect();
+        let manager = Self {
+            app,
+            watches: Arc::new(Mutex::new(watches)),
+        };
+
+        // Rescanning on start uploads what changed while the app was closed
+        for id in ids {
+            let _ = manager.start(&id);
+        }
+
+        manager
+    }
+
+    pub fn list(&self) -> Result<Vec<WatchStatus>, String> {
+        let watches = self.watches.lock().map_err(|e| e.to_string())?;
+
+        Ok(watches.values().map(|entry| entry.status.clone()).collect())
+    }
+
+    pub fn add(
+        &self,
+        local: String,
+        remote: String,
+        ignore: String,
+    ) -> Result<WatchStatus, String> {
+        let roots = Roots::resolve(&options(&local, &remote))?;
+        matcher(&ignore)?;
+
+        let local = path_string(&roots.local);
+        let folder = WatchFolder {
+            id: state_id(&["watch", &local, &roots.remote]),
+            local,
+            remote: roots.remote,
+            ignore,
+            paused: false,
+        };
+
+        {
+            let mut watches = self.watches.lock().map_err(|e| e.to_string())?;
+            if watches.contains_key(&folder.id) {
+                return Err("This folder is already watched".to_string());
+            }
+            watches.insert(
+                folder.id.clone(),
+                WatchEntry {
+                    status: WatchStatus::new(folder.clone()),
+                    task: None,
+                },
+            );
+        }
+        self.save()?;
+
+        self.start(&folder.id)
+    }
+
+    /// Pause or resume a watch. Resuming rescans the folder.
+    pub fn set_paused(&self, id: &str, paused: bool) -> Result<WatchStatus, String> {
+        self.update(id, |entry| {
+            entry.status.folder.paused = paused;
+            Ok(())
+        })?;
+        self.save()?;
+
+        self.start(id)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        let entry = self
+            .watches
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(id)
+            .ok_or("Watch folder not found")?;
+        if let Some(task) = entry.task {
+            task.abort();
+        }
+
+        self.save()
+    }
+
+    // (Re)start the watch's task, or stop it when the watch is paused
+    fn start(&self, id: &str) -> Result<WatchStatus, String> {
+        let manager = self.clone();
+        let owned = id.to_string();
+
+        self.update(id, move |entry| {
+            if let Some(task) = entry.task.take() {
+                task.abort();
+            }
+            entry.status.pending = 0;
+            entry.status.error = None;
+
+            if entry.status.folder.paused {
+                entry.status.state = WatchState::Paused;
+            } else {
+                entry.status.state = WatchState::Scanning;
+                let folder = entry.status.folder.clone();
+                entry.task = Some(async_runtime::spawn(async move {
+                    if let Err(e) = manager.watch(folder).await {
+                        let _ = manager.update(&owned, |entry| {
+                            entry.status.state = WatchState::Failed;
+                            entry.status.error = Some(e);
+                            Ok(())
+                        });
+                    }
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn watch(&self, folder: WatchFolder) -> Result<(), String> {
+        let roots = Roots::resolve(&options(&folder.local, &folder.remote))?;
+        let ignore = matcher(&folder.ignore)?;
+        let encryption = Encryption::default_at(&roots.remote)?;
+
+        // Watch before scanning, so nothing written during the scan is missed
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(|e| format!("Failed to watch {}: {}", folder.local, e))?;
+        watcher
+            .watch(&roots.local, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", folder.local, e))?;
+
+        let upload = |path: &str| -> Result<(), String> {
+            self.app.state::<TransferManager>().enqueue(
+                TransferKind::Copy,
+                path_string(&roots.local_path(path)),
+                None,
+                roots.remote_path(parent_of(path)),
+                encryption.clone(),
+                true,
+            )?;
+            self.update(&folder.id, |entry| {
+                entry.status.uploads += 1;
+                entry.status.last_upload = Some(chrono::Utc::now().to_rfc3339());
+                Ok(())
+            })?;
+            Ok(())
+        };
+
+        for path in self.rescan(&folder, &ignore).await? {
+            upload(&path)?;
+        }
+
+        let mut pending: BTreeMap<String, Pending> = BTreeMap::new();
+        let mut ticks = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        return Err("File notifications stopped".to_string());
+                    };
+                    match event {
+                        Ok(event) if event.need_rescan() => {
+                            // The kernel dropped events, so look at everything
+                            for path in self.rescan(&folder, &ignore).await? {
+                                upload(&path)?;
+                            }
+                        }
+                        Ok(event) => changed(&roots.local, &ignore, &event, &mut pending),
+                        Err(e) => return Err(format!("Failed to watch {}: {}", folder.local, e)),
+                    }
+                }
+                _ = ticks.tick() => {
+                    for path in settled(&roots.local, &mut pending) {
+                        upload(&path)?;
+                    }
+                }
+            }
+
+            let count = pending.len();
+            if self.status(&folder.id)?.pending != count {
+                self.update(&folder.id, |entry| {
+                    entry.status.pending = count;
+                    Ok(())
+                })?;
+            }
+        }
+    }
+
+    // Files that are new or changed since S3 last got them
+    async fn rescan(
+        &self,
+        folder: &WatchFolder,
+        ignore: &Gitignore,
+    ) -> Result<Vec<String>, String> {
+        self.update(&folder.id, |entry| {
+            entry.status.state = WatchState::Scanning;
+            Ok(())
+        })?;
+
+        let concurrency = self
+            .app
+            .state::<Mutex<TransferSettings>>()
+            .lock()
+            .map_err(|e| e.to_string())?
+            .concurrency;
+        let plan = super::plan(
+            &self.app.state::<StorageManager>(),
+            &self.app.state::<SyncState>(),
+            options(&folder.local, &folder.remote),
+            concurrency,
+        )
+        .await?;
+
+        let paths = plan
+            .actions
+            .into_iter()
+            .filter(|action| action.operation == SyncOperation::Upload)
+            .map(|action| action.path)
+            .filter(|path| !ignored(ignore, path, false))
+            .collect();
+
+        self.update(&folder.id, |entry| {
+            entry.status.state = WatchState::Watching;
+            Ok(())
+        })?;
+
+        Ok(paths)
+    }
+
+    fn status(&self, id: &str) -> Result<WatchStatus, String> {
+        let watches = self.watches.lock().map_err(|e| e.to_string())?;
+
+        watches
+            .get(id)
+            .map(|entry| entry.status.clone())
+            .ok_or_else(|| "Watch folder not found".to_string())
+    }
+
+    fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut WatchEntry) -> Result<(), String>,
+    ) -> Result<WatchStatus, String> {
+        let status = {
+            let mut watches = self.watches.lock().map_err(|e| e.to_string())?;
+            let entry = watches.get_mut(id).ok_or("Watch folder not found")?;
+            change(entry)?;
+            entry.status.clone()
+        };
+
+        let _ = self.app.emit(WATCH_EVENT, &status);
+
+        Ok(status)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let folders: Vec<WatchFolder> = self
+            .list()?
+            .into_iter()
+            .map(|status| status.folder)
+            .collect();
+
+        write_json(&app_data_dir()?.join(WATCH_FILE), &folders)
+    }
+}
+
+impl WatchStatus {
+    fn new(folder: WatchFolder) -> Self {
+        Self {
+            state: if folder.paused {
+                WatchState::Paused
+            } else {
+                WatchState::Scanning
+            },
+            folder,
+            pending: 0,
+            uploads: 0,
+            last_upload: None,
+            error: None,
+        }
+    }
+}
+
+// A watch uploads new and changed files and never deletes
+fn options(local: &str, remote: &str) -> SyncOptions {
+    SyncOptions {
+        local: local.to_string(),
+        remote: remote.to_string(),
+        policy: SyncPolicy::NeverDelete,
+        direction: SyncDirection::Upload,
+        compare: CompareMode::SizeMtime,
+        resolution: ConflictResolution::default(),
+    }
+}
+
+fn matcher(patterns: &str) -> Result<Gitignore, String> {
+    let mut builder = GitignoreBuilder::new("");
+    for line in patterns.lines() {
+        builder
+            .add_line(None, line)
+            .map_err(|e| format!("Invalid ignore pattern: {}", e))?;
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Invalid ignore patterns: {}", e))
+}
+
+// Whether a path relative to the folder, or a folder above it, is ignored
+fn ignored(ignore: &Gitignore, path: &str, is_dir: bool) -> bool {
+    ignore.matched_path_or_any_parents(path, is_dir).is_ignore()
+}
+
+// A changed file, and its size and time when last looked at
+struct Pending {
+    size: u64,
+    modified: Option<SystemTime>,
+    since: Instant,
+}
+
+// Note the files an event touched. A folder created or moved in may arrive
+// with files already in it, which get no events of their own.
+fn changed(
+    root: &Path,
+    ignore: &Gitignore,
+    event: &Event,
+    pending: &mut BTreeMap<String, Pending>,
+) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+
+    for path in &event.paths {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if ignored(ignore, &relative, true) {
+                continue;
+            }
+            let Ok(tree) = scan::local_tree(path) else {
+                continue;
+            };
+            for (inner, entry) in tree {
+                let inner = format!("{}/{}", relative, inner);
+                if !ignored(ignore, &inner, false) {
+                    pending.insert(
+                        inner,
+                        Pending {
+                            size: entry.size,
+                            modified: entry.modified.map(SystemTime::from),
+                            since: Instant::now(),
+                        },
+                    );
+                }
+            }
+        } else if metadata.is_file()
+            && !relative.ends_with(".part")
+            && !ignored(ignore, &relative, false)
+        {
+            pending.insert(
+                relative,
+                Pending {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok(),
+                    since: Instant::now(),
+                },
+            );
+        }
+    }
+}
+
+// Take the files that stayed the same for the settle time; files that changed
+// since the last look start waiting again, and files gone are dropped
+fn settled(root: &Path, pending: &mut BTreeMap<String, Pending>) -> Vec<String> {
+    let mut ready = Vec::new();
+
+    pending.retain(|path, file| {
+        let Ok(metadata) = fs::metadata(root.join(path)) else {
+            return false;
+        };
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+
+        if size != file.size || modified != file.modified {
+            *file = Pending {
+                size,
+                modified,
+                since: Instant::now(),
+            };
+            true
+        } else if file.since.elapsed() >= SETTLE_TIME {
+            ready.push(path.clone());
+            false
+        } else {
+            true
+        }
+    });
+
+    ready
+}
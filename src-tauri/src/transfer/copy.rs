@@ -0,0 +1,496 @@
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::types::{
+    ChecksumAlgorithm, CompletedMultipartUpload, CompletedPart, MetadataDirective, TaggingDirective,
+};
+use aws_sdk_s3::Client;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::storage::encryption::{CustomerKey, CustomerKeys, Encryption, EncryptionParams};
+use crate::storage::S3Storage;
+
+use super::{no_progress, Progress, ProgressFn, TransferSettings};
+
+// Characters that must be escaped in the `x-amz-copy-source` header
+const COPY_SOURCE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+// CopyObject refuses sources larger than 5 GiB, those are copied in parts
+const MAX_SINGLE_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+fn copy_source(bucket: &str, key: &str, version_id: Option<&str>) -> String {
+    let source = format!("{}/{}", bucket, utf8_percent_encode(key, COPY_SOURCE));
+
+    match version_id {
+        Some(version_id) => format!(
+            "{}?versionId={}",
+            source,
+            utf8_percent_encode(version_id, NON_ALPHANUMERIC)
+        ),
+        None => source,
+    }
+}
+
+// Encode tags the way the `x-amz-tagging` header expects them
+fn encode_tags(tags: &[aws_sdk_s3::types::Tag]) -> String {
+    tags.iter()
+        .map(|tag| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(tag.key(), NON_ALPHANUMERIC),
+                utf8_percent_encode(tag.value(), NON_ALPHANUMERIC)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Keep the checksum algorithm of the source, CopyObject drops it otherwise
+fn checksum_algorithm(head: &HeadObjectOutput) -> Option<ChecksumAlgorithm> {
+    if head.checksum_crc32().is_some() {
+        Some(ChecksumAlgorithm::Crc32)
+    } else if head.checksum_crc32_c().is_some() {
+        Some(ChecksumAlgorithm::Crc32C)
+    } else if head.checksum_crc64_nvme().is_some() {
+        Some(ChecksumAlgorithm::Crc64Nvme)
+    } else if head.checksum_sha1().is_some() {
+        Some(ChecksumAlgorithm::Sha1)
+    } else if head.checksum_sha256().is_some() {
+        Some(ChecksumAlgorithm::Sha256)
+    } else {
+        None
+    }
+}
+
+// The object a copy reads from
+struct CopySource {
+    /// Value of the `x-amz-copy-source` header
+    header: String,
+    head: HeadObjectOutput,
+    /// Customer key the object is encrypted with, needed to read it
+    customer_key: Option<CustomerKey>,
+}
+
+/// Copies objects between S3 locations without the data leaving S3, keeping
+/// their metadata, tags and storage class.
+///
+/// Copies are encrypted as the settings say. With the bucket default, objects
+/// encrypted with the customer key stay encrypted with it.
+pub struct ServerSideCopier {
+    client: Client,
+    keys: CustomerKeys,
+    settings: TransferSettings,
+}
+
+impl ServerSideCopier {
+    pub fn new(s3: &S3Storage, settings: TransferSettings) -> Self {
+        Self {
+            client: s3.client().clone(),
+            keys: s3.customer_keys().clone(),
+            settings,
+        }
+    }
+
+    /// Copy every object below `source_prefix` to the same relative key below
+    /// `dest_prefix`. Both prefixes are expected to end with `/`.
+    pub async fn copy_prefix(
+        &self,
+        source_bucket: &str,
+        source_prefix: &str,
+        dest_bucket: &str,
+        dest_prefix: &str,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(source_bucket)
+            .prefix(source_prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+
+            for key in page.contents().iter().filter_map(|object| object.key()) {
+                let relative = &key[source_prefix.len()..];
+                self.copy_object(
+                    source_bucket,
+                    key,
+                    dest_bucket,
+                    &format!("{}{}", dest_prefix, relative),
+                    progress.clone(),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn copy_object(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        self.copy_object_version(
+            source_bucket,
+            source_key,
+            None,
+            dest_bucket,
+            dest_key,
+            progress,
+        )
+        .await
+    }
+
+    /// Copy a specific version of an object, or the current one without a
+    /// version ID. Copying an old version over its own key restores it.
+    pub async fn copy_object_version(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        version_id: Option<&str>,
+        dest_bucket: &str,
+        dest_key: &str,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let head = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .head_object()
+                        .bucket(source_bucket)
+                        .key(source_key)
+                        .set_version_id(version_id.map(str::to_string))
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get object metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let source = self.source(copy_source(source_bucket, source_key, version_id), head)?;
+        let params = match (&self.settings.encryption, &source.customer_key) {
+            (Encryption::BucketDefault, Some(key)) => EncryptionParams::Customer(key.clone()),
+            (encryption, _) => self.keys.params(encryption)?,
+        };
+        let size = source.head.content_length().unwrap_or(0).max(0) as u64;
+
+        progress(Progress::File {
+            path: format!("s3://{}/{}", source_bucket, source_key),
+            size,
+        });
+
+        if size > MAX_SINGLE_COPY_SIZE {
+            let tagging = self.tagging(source_bucket, source_key, version_id).await?;
+
+            return self
+                .copy_multipart(&source, dest_bucket, dest_key, tagging, &params, progress)
+                .await;
+        }
+
+        let source_customer = source.customer_key.as_ref();
+        let dest_customer = params.customer_key();
+
+        // Metadata and tags are copied by default, the storage class is not
+        self.client
+            .copy_object()
+            .copy_source(&source.header)
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .metadata_directive(MetadataDirective::Copy)
+            .tagging_directive(TaggingDirective::Copy)
+            .set_storage_class(source.head.storage_class().cloned())
+            .set_copy_source_sse_customer_algorithm(source_customer.map(CustomerKey::algorithm))
+            .set_copy_source_sse_customer_key(source_customer.map(CustomerKey::key))
+            .set_copy_source_sse_customer_key_md5(source_customer.map(CustomerKey::key_md5))
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .set_sse_customer_algorithm(dest_customer.map(CustomerKey::algorithm))
+            .set_sse_customer_key(dest_customer.map(CustomerKey::key))
+            .set_sse_customer_key_md5(dest_customer.map(CustomerKey::key_md5))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to copy object: {}", DisplayErrorContext(&e)))?;
+
+        progress(Progress::Bytes(size));
+
+        Ok(())
+    }
+
+    /// Copy an object over itself with the headers and user metadata of
+    /// `head`, which is how S3 changes metadata. Tags, storage class,
+    /// encryption and checksum algorithm are kept.
+    pub async fn rewrite_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        head: &HeadObjectOutput,
+    ) -> Result<(), String> {
+        let source = self.source(copy_source(bucket, key, None), head.clone())?;
+        let params = match &source.customer_key {
+            Some(key) => EncryptionParams::Customer(key.clone()),
+            None => match head.server_side_encryption() {
+                Some(sse) => EncryptionParams::Managed {
+                    sse: sse.clone(),
+                    kms_key_id: head.ssekms_key_id().map(str::to_string),
+                },
+                None => EncryptionParams::BucketDefault,
+            },
+        };
+
+        if head.content_length().unwrap_or(0).max(0) as u64 > MAX_SINGLE_COPY_SIZE {
+            let tagging = self.tagging(bucket, key, None).await?;
+            return self
+                .copy_multipart(&source, bucket, key, tagging, &params, no_progress())
+                .await;
+        }
+
+        let customer = source.customer_key.as_ref();
+
+        self.client
+            .copy_object()
+            .copy_source(&source.header)
+            .bucket(bucket)
+            .key(key)
+            .metadata_directive(MetadataDirective::Replace)
+            .tagging_directive(TaggingDirective::Copy)
+            .set_metadata(head.metadata().cloned())
+            .set_content_type(head.content_type().map(str::to_string))
+            .set_cache_control(head.cache_control().map(str::to_string))
+            .set_content_disposition(head.content_disposition().map(str::to_string))
+            .set_content_encoding(head.content_encoding().map(str::to_string))
+            .set_content_language(head.content_language().map(str::to_string))
+            .set_storage_class(head.storage_class().cloned())
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .set_bucket_key_enabled(head.bucket_key_enabled())
+            .set_checksum_algorithm(checksum_algorithm(head))
+            .set_copy_source_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+            .set_copy_source_sse_customer_key(customer.map(CustomerKey::key))
+            .set_copy_source_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+            .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+            .set_sse_customer_key(customer.map(CustomerKey::key))
+            .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to update metadata: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+
+    // Objects encrypted with a customer key can only be read with that key
+    fn source(&self, header: String, head: HeadObjectOutput) -> Result<CopySource, String> {
+        let customer_key = match head.sse_customer_algorithm() {
+            Some(_) => Some(self.keys.require()?),
+            None => None,
+        };
+
+        Ok(CopySource {
+            header,
+            head,
+            customer_key,
+        })
+    }
+
+    // Tags of the source in `x-amz-tagging` form, for copies made in parts
+    async fn tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<String, String> {
+        let tags = self
+            .client
+            .get_object_tagging()
+            .bucket(bucket)
+            .key(key)
+            .set_version_id(version_id.map(str::to_string))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to read object tags: {}", DisplayErrorContext(&e)))?;
+
+        Ok(encode_tags(tags.tag_set()))
+    }
+
+    // Multipart uploads start empty, so everything CopyObject would carry over
+    // has to be read from the source and set on the new upload
+    async fn copy_multipart(
+        &self,
+        source: &CopySource,
+        dest_bucket: &str,
+        dest_key: &str,
+        tagging: String,
+        params: &EncryptionParams,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let head = &source.head;
+        let customer = params.customer_key();
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .set_metadata(head.metadata().cloned())
+            .set_content_type(head.content_type().map(str::to_string))
+            .set_cache_control(head.cache_control().map(str::to_string))
+            .set_content_disposition(head.content_disposition().map(str::to_string))
+            .set_content_encoding(head.content_encoding().map(str::to_string))
+            .set_content_language(head.content_language().map(str::to_string))
+            .set_storage_class(head.storage_class().cloned())
+            .set_tagging((!tagging.is_empty()).then_some(tagging))
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+            .set_sse_customer_key(customer.map(CustomerKey::key))
+            .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to start copy: {}", DisplayErrorContext(&e)))?;
+
+        let upload_id = output
+            .upload_id()
+            .ok_or("S3 did not return an upload ID")?
+            .to_string();
+
+        let result = self
+            .copy_parts(source, dest_bucket, dest_key, &upload_id, params, progress)
+            .await;
+
+        if result.is_err() {
+            // Do not leave the copied parts behind, they are billed until aborted
+            let _ = self
+                .client
+                .abort_multipart_upload()
+                .bucket(dest_bucket)
+                .key(dest_key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+        }
+
+        result
+    }
+
+    async fn copy_parts(
+        &self,
+        source: &CopySource,
+        dest_bucket: &str,
+        dest_key: &str,
+        upload_id: &str,
+        params: &EncryptionParams,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let size = source.head.content_length().unwrap_or(0).max(0) as u64;
+        let part_size = self.settings.part_size_for(size);
+        let total_parts = size.div_ceil(part_size) as i32;
+        let e_tag = source.head.e_tag().map(str::to_string);
+        let source_customer = source.customer_key.as_ref();
+        let dest_customer = params.customer_key();
+
+        let semaphore = Arc::new(Semaphore::new(self.settings.concurrency));
+        let mut tasks = JoinSet::new();
+        let mut parts = Vec::new();
+
+        for part_number in 1..=total_parts {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // Stop scheduling new parts as soon as one has failed
+            if let Some(result) = tasks.try_join_next() {
+                parts.push(result.map_err(|e| format!("Copy task failed: {}", e))??);
+            }
+
+            let offset = (part_number as u64 - 1) * part_size;
+            let length = part_size.min(size - offset);
+            let request = self
+                .client
+                .upload_part_copy()
+                .copy_source(&source.header)
+                .copy_source_range(format!("bytes={}-{}", offset, offset + length - 1))
+                // Fail instead of mixing parts of two versions if the source changes
+                .set_copy_source_if_match(e_tag.clone())
+                .set_copy_source_sse_customer_algorithm(source_customer.map(CustomerKey::algorithm))
+                .set_copy_source_sse_customer_key(source_customer.map(CustomerKey::key))
+                .set_copy_source_sse_customer_key_md5(source_customer.map(CustomerKey::key_md5))
+                .set_sse_customer_algorithm(dest_customer.map(CustomerKey::algorithm))
+                .set_sse_customer_key(dest_customer.map(CustomerKey::key))
+                .set_sse_customer_key_md5(dest_customer.map(CustomerKey::key_md5))
+                .bucket(dest_bucket)
+                .key(dest_key)
+                .upload_id(upload_id)
+                .part_number(part_number);
+            let progress = progress.clone();
+
+            tasks.spawn(async move {
+                let _permit = permit;
+                let output = request.send().await.map_err(|e| {
+                    format!(
+                        "Failed to copy part {}: {}",
+                        part_number,
+                        DisplayErrorContext(&e)
+                    )
+                })?;
+
+                let e_tag = output
+                    .copy_part_result()
+                    .and_then(|result| result.e_tag())
+                    .ok_or_else(|| format!("S3 did not return an ETag for part {}", part_number))?
+                    .to_string();
+                progress(Progress::Bytes(length));
+
+                Ok::<_, String>(
+                    CompletedPart::builder()
+                        .part_number(part_number)
+                        .e_tag(e_tag)
+                        .build(),
+                )
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            parts.push(result.map_err(|e| format!("Copy task failed: {}", e))??);
+        }
+
+        parts.sort_by_key(|part| part.part_number());
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| format!("Failed to complete copy: {}", DisplayErrorContext(&e)))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::manager::JobStatus;
+use super::TransferSettings;
+use crate::storage::{StorageManager, StorageProvider, StorageUri};
+
+/// Event emitted with a [`DeleteJob`] payload whenever a delete changes.
+pub const DELETE_EVENT: &str = "delete-updated";
+
+// Progress events are throttled to keep the webview responsive
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An object that could not be deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFailure {
+    pub path: String,
+    /// S3 error code, such as `AccessDenied`
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// What a batch delete reports while it works.
+#[derive(Debug, Clone)]
+pub enum DeleteProgress {
+    /// The listing found this many more objects
+    Found(u64),
+    /// This many more objects are gone
+    Deleted(u64),
+    Failed(Vec<DeleteFailure>),
+}
+
+/// Callback receiving progress updates from a running delete.
+pub type DeleteProgressFn = Arc<dyn Fn(DeleteProgress) + Send + Sync>;
+
+/// A delete of one or more items as the frontend sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteJob {
+    pub id: u64,
+    pub paths: Vec<String>,
+    /// The bucket in `paths` is emptied, then deleted itself
+    #[serde(default)]
+    pub bucket: bool,
+    pub status: JobStatus,
+    /// Objects found so far; the listing runs while deleting
+    pub found: u64,
+    pub deleted: u64,
+    pub failures: Vec<DeleteFailure>,
+    pub error: Option<String>,
+    pub created: String,
+}
+
+struct DeleteEntry {
+    job: DeleteJob,
+    task: Option<JoinHandle<()>>,
+    emitted: Instant,
+}
+
+/// Runs deletes of selections and whole prefixes in the background and pushes
+/// their progress to the frontend as [`DELETE_EVENT`] events.
+///
+/// Cancelling stops the listing and the requests in flight; whatever was
+/// deleted by then stays deleted.
+#[derive(Clone)]
+pub struct DeleteManager {
+    app: AppHandle,
+    jobs: Arc<Mutex<BTreeMap<u64, DeleteEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl DeleteManager {
+    pub fn new(app: AppHandle) -> Self {
+        Self {
+            app,
+            jobs: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn jobs(&self) -> Result<Vec<DeleteJob>, String> {
+        let jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+
+        Ok(jobs.values().map(|entry| entry.job.clone()).collect())
+    }
+
+    pub fn start(&self, paths: Vec<String>) -> Result<DeleteJob, String> {
+        if paths.is_empty() {
+            return Err("Nothing to delete".to_string());
+        }
+        for path in &paths {
+            StorageUri::parse(path)?;
+        }
+
+        self.spawn(paths, false)
+    }
+
+    /// Delete every object, version and delete marker in a bucket, and then
+    /// the bucket. The bucket is kept if any of them could not be deleted.
+    pub fn start_bucket(&self, path: String) -> Result<DeleteJob, String> {
+        match StorageUri::parse(&path)? {
+            StorageUri::S3 { bucket, key, .. } if !bucket.is_empty() && key.is_empty() => {}
+            _ => return Err(format!("Not a bucket: {}", path)),
+        }
+
+        self.spawn(vec![path], true)
+    }
+
+    fn spawn(&self, paths: Vec<String>, bucket: bool) -> Result<DeleteJob, String> {
+        let job = DeleteJob {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            paths,
+            bucket,
+            status: JobStatus::Running,
+            found: 0,
+            deleted: 0,
+            failures: Vec::new(),
+            error: None,
+            created: chrono::Utc::now().to_rfc3339(),
+        };
+
+        let manager = self.clone();
+        let id = job.id;
+        self.jobs.lock().map_err(|e| e.to_string())?.insert(
+            job.id,
+            DeleteEntry {
+                job: job.clone(),
+                task: Some(async_runtime::spawn(async move { manager.run(id).await })),
+                emitted: Instant::now(),
+            },
+        );
+
+        self.emit(&job);
+
+        Ok(job)
+    }
+
+    pub fn cancel(&self, id: u64) -> Result<DeleteJob, String> {
+        self.update(id, |entry| {
+            if entry.job.status.is_finished() {
+                return Err("Delete has already finished".to_string());
+            }
+            if let Some(task) = entry.task.take() {
+                task.abort();
+            }
+            entry.job.status = JobStatus::Cancelled;
+            Ok(())
+        })
+    }
+
+    /// Forget every delete that has completed, failed or been cancelled.
+    pub fn clear_finished(&self) -> Result<Vec<DeleteJob>, String> {
+        let mut jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+        jobs.retain(|_, entry| !entry.job.status.is_finished());
+
+        Ok(jobs.values().map(|entry| entry.job.clone()).collect())
+    }
+
+    fn update(
+        &self,
+        id: u64,
+        change: impl FnOnce(&mut DeleteEntry) -> Result<(), String>,
+    ) -> Result<DeleteJob, String> {
+        let job = {
+            let mut jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+            let entry = jobs.get_mut(&id).ok_or("Delete not found")?;
+            change(entry)?;
+            entry.job.clone()
+        };
+
+        self.emit(&job);
+
+        Ok(job)
+    }
+
+    fn emit(&self, job: &DeleteJob) {
+        // Nothing useful can be done if the window is gone
+        let _ = self.app.emit(DELETE_EVENT, job);
+    }
+
+    async fn run(self, id: u64) {
+        let result = self.execute(id).await;
+
+        let _ = self.update(id, |entry| {
+            // A cancelled job keeps that status
+            if entry.job.status != JobStatus::Running {
+                return Ok(());
+            }
+
+            entry.task = None;
+            let failed = entry.job.failures.len();
+            match result {
+                Ok(()) if failed == 0 => entry.job.status = JobStatus::Completed,
+                Ok(()) => {
+                    entry.job.status = JobStatus::Failed;
+                    entry.job.error = Some(format!(
+                        "{} of {} objects could not be deleted",
+                        failed, entry.job.found
+                    ));
+                }
+                Err(e) => {
+                    entry.job.status = JobStatus::Failed;
+                    entry.job.error = Some(e);
+                }
+            }
+            Ok(())
+        });
+    }
+
+    async fn execute(&self, id: u64) -> Result<(), String> {
+        let (paths, bucket) = {
+            let jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+            let job = &jobs.get(&id).ok_or("Delete not found")?.job;
+            (job.paths.clone(), job.bucket)
+        };
+
+        let storage = self.app.state::<StorageManager>();
+        let concurrency = self
+            .app
+            .state::<Mutex<TransferSettings>>()
+            .lock()
+            .map(|s| s.concurrency)
+            .map_err(|e| e.to_string())?;
+
+        let manager = self.clone();
+        let progress: DeleteProgressFn = Arc::new(move |progress| manager.record(id, progress));
+
+        if !bucket {
+            return delete_paths(&storage, &paths, concurrency, progress).await;
+        }
+
+        for path in &paths {
+            let s3 = storage.s3_for(path).await?;
+            s3.empty_bucket(path, concurrency, progress.clone()).await?;
+
+            // The failures are reported once the job ends
+            let failed = self
+                .jobs
+                .lock()
+                .map_err(|e| e.to_string())?
+                .get(&id)
+                .is_some_and(|entry| !entry.job.failures.is_empty());
+            if !failed {
+                s3.delete_bucket(path).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record(&self, id: u64, progress: DeleteProgress) {
+        let job = {
+            let Ok(mut jobs) = self.jobs.lock() else {
+                return;
+            };
+            let Some(entry) = jobs.get_mut(&id) else {
+                return;
+            };
+            let job = &mut entry.job;
+
+            match progress {
+                DeleteProgress::Found(count) => job.found += count,
+                DeleteProgress::Deleted(count) => job.deleted += count,
+                DeleteProgress::Failed(failures) => job.failures.extend(failures),
+            }
+
+            if entry.emitted.elapsed() < EMIT_INTERVAL {
+                return;
+            }
+            entry.emitted = Instant::now();
+
+            job.clone()
+        };
+
+        self.emit(&job);
+    }
+}
+
+/// Delete every path, S3 objects and prefixes through batched DeleteObjects
+/// requests. A local item that cannot be deleted is reported as a failure.
+pub async fn delete_paths(
+    storage: &StorageManager,
+    paths: &[String],
+    concurrency: usize,
+    progress: DeleteProgressFn,
+) -> Result<(), String> {
+    for path in paths {
+        match StorageUri::parse(path)? {
+            StorageUri::S3 { .. } => {
+                storage
+                    .s3_for(path)
+                    .await?
+                    .delete_batch(path, concurrency, progress.clone())
+                    .await?
+            }
+            StorageUri::Local(_) => {
+                progress(DeleteProgress::Found(1));
+                match storage.local().delete(path).await {
+                    Ok(()) => progress(DeleteProgress::Deleted(1)),
+                    Err(message) => progress(DeleteProgress::Failed(vec![DeleteFailure {
+                        path: path.clone(),
+                        code: None,
+                        message,
+                    }])),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
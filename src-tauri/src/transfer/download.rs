@@ -0,0 +1,621 @@
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata};
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::types::ServerSideEncryption;
+use aws_sdk_s3::Client;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::storage::encryption::{CustomerKey, CustomerKeys};
+use crate::storage::S3Storage;
+
+use super::{scoped_bucket, state_id, Progress, ProgressFn, StateStore, TransferSettings};
+
+/// Everything needed to pick up a ranged download where it stopped, persisted
+/// after every chunk that reached the disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadState {
+    pub id: String,
+    /// Account the download runs under, `None` for the AWS environment
+    #[serde(default)]
+    pub account: Option<String>,
+    pub bucket: String,
+    pub key: String,
+    /// Set when an older version of the object is downloaded
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// Final local path; data is written to `<dest>.part` until verified
+    pub dest: String,
+    /// ETag of the object when the download started, sent as `If-Match`
+    pub e_tag: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    /// Indexes of the chunks already written and synced to the part file
+    pub completed_chunks: BTreeSet<u64>,
+    pub started: String,
+}
+
+impl DownloadState {
+    pub fn total_chunks(&self) -> u64 {
+        self.size.div_ceil(self.chunk_size)
+    }
+
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.completed_chunks
+            .iter()
+            .map(|&chunk| self.chunk_length(chunk))
+            .sum()
+    }
+
+    fn chunk_offset(&self, chunk: u64) -> u64 {
+        chunk * self.chunk_size
+    }
+
+    fn chunk_length(&self, chunk: u64) -> u64 {
+        self.chunk_size.min(self.size - self.chunk_offset(chunk))
+    }
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+pub fn trim_e_tag(e_tag: &str) -> &str {
+    e_tag.trim_matches('"')
+}
+
+/// Whether an object's ETag is something other than a digest of its data, as
+/// with KMS and customer keys.
+pub fn opaque_e_tag(head: &HeadObjectOutput) -> bool {
+    matches!(
+        head.server_side_encryption(),
+        Some(ServerSideEncryption::AwsKms | ServerSideEncryption::AwsKmsDsse)
+    ) || head.sse_customer_algorithm().is_some()
+}
+
+/// Compute the ETag S3 would give this file: the MD5 of the content for a
+/// single PUT, or the MD5 of the part digests followed by the part count for
+/// multipart.
+pub fn local_e_tag(path: &Path, part_size: Option<u64>) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+
+    let Some(part_size) = part_size else {
+        let mut hasher = Md5::new();
+        io::copy(&mut file, &mut hasher)?;
+        return Ok(hex::encode(hasher.finalize()));
+    };
+
+    let mut digests = Vec::new();
+    let mut parts = 0;
+
+    loop {
+        let mut hasher = Md5::new();
+        let copied = io::copy(&mut (&mut file).take(part_size), &mut hasher)?;
+        if copied == 0 && parts > 0 {
+            break;
+        }
+        digests.extend_from_slice(&hasher.finalize());
+        parts += 1;
+        if copied < part_size {
+            break;
+        }
+    }
+
+    Ok(format!("{}-{}", hex::encode(Md5::digest(&digests)), parts))
+}
+
+// Give a download the object's modification time, so comparing the two later
+// tells whether either changed since
+fn set_modified(dest: &Path, head: &HeadObjectOutput) {
+    let Some(time) = head
+        .last_modified()
+        .and_then(|time| SystemTime::try_from(*time).ok())
+    else {
+        return;
+    };
+
+    // The download itself succeeded; a file system without timestamps is fine
+    let _ = fs::File::options()
+        .write(true)
+        .open(dest)
+        .and_then(|file| file.set_modified(time));
+}
+
+// Whether a file is a finished download of an object. Downloads get the
+// object's modification time, so a file with its size and time is one, while
+// a file edited or left from an older object is not.
+fn is_downloaded(path: &Path, size: u64, modified: Option<&DateTime>) -> bool {
+    let seconds = |time: SystemTime| {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs())
+    };
+    let Some(object_time) = modified
+        .and_then(|time| SystemTime::try_from(*time).ok())
+        .and_then(seconds)
+    else {
+        return false;
+    };
+
+    fs::metadata(path).is_ok_and(|metadata| {
+        metadata.len() == size && metadata.modified().ok().and_then(seconds) == Some(object_time)
+    })
+}
+
+/// Join an object key below a local directory, refusing keys that would escape
+/// it.
+pub fn local_path_for_key(dest: &Path, relative_key: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative_key);
+
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Refusing to download unsafe key: {}", relative_key));
+    }
+
+    Ok(dest.join(relative))
+}
+
+/// Downloads S3 objects to local disk with parallel ranged GETs, resuming from
+/// the chunks already on disk and verifying the result before it is renamed
+/// into place.
+pub struct RangedDownloader {
+    client: Client,
+    account: Option<String>,
+    keys: CustomerKeys,
+    store: Arc<StateStore<DownloadState>>,
+    settings: TransferSettings,
+}
+
+impl RangedDownloader {
+    pub fn new(s3: &S3Storage, settings: TransferSettings) -> Result<Self, String> {
+        Ok(Self {
+            client: s3.client().clone(),
+            account: s3.account().map(str::to_string),
+            keys: s3.customer_keys().clone(),
+            store: Arc::new(Self::open_store()?),
+            settings,
+        })
+    }
+
+    /// The recorded downloads, readable without connecting to S3.
+    pub fn open_store() -> Result<StateStore<DownloadState>, String> {
+        StateStore::open("downloads")
+    }
+
+    pub fn store(&self) -> &StateStore<DownloadState> {
+        &self.store
+    }
+
+    /// Whether an unfinished download exists at or below `dest`.
+    pub fn has_pending(&self, dest: &Path) -> Result<bool, String> {
+        Ok(self
+            .store
+            .list()?
+            .iter()
+            .any(|state| Path::new(&state.dest).starts_with(dest)))
+    }
+
+    /// Download an object, or every object below `key` when it is a prefix.
+    ///
+    /// Files already downloaded from the same object, with its size and
+    /// modification time, are skipped, so re-running an interrupted folder
+    /// download only fetches what is missing or has changed.
+    pub async fn download_path(
+        &self,
+        bucket: &str,
+        key: &str,
+        dest: &Path,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        if !key.is_empty() && !key.ends_with('/') {
+            return self.download_file(bucket, key, dest, progress).await;
+        }
+
+        fs::create_dir_all(dest).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(key)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| format!("Failed to list objects: {}", DisplayErrorContext(&e)))?;
+
+            for object in page.contents() {
+                let Some(object_key) = object.key() else {
+                    continue;
+                };
+                let relative_key = &object_key[key.len()..];
+                if relative_key.is_empty() {
+                    continue;
+                }
+
+                let path = local_path_for_key(dest, relative_key.trim_end_matches('/'))?;
+
+                // Folder markers only need their directory
+                if relative_key.ends_with('/') {
+                    fs::create_dir_all(&path)
+                        .map_err(|e| format!("Failed to create directory: {}", e))?;
+                    continue;
+                }
+
+                let size = object.size().unwrap_or(0).max(0) as u64;
+                if is_downloaded(&path, size, object.last_modified()) {
+                    progress(Progress::Bytes(size));
+                    continue;
+                }
+
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create directory: {}", e))?;
+                }
+
+                self.download_file(bucket, object_key, &path, progress.clone())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn download_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        dest: &Path,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        self.download_version(bucket, key, None, dest, progress)
+            .await
+    }
+
+    /// Download a specific version of an object, or the current one without a
+    /// version ID.
+    pub async fn download_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        dest: &Path,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let head = self
+            .keys
+            .send(
+                |customer| {
+                    self.client
+                        .head_object()
+                        .bucket(bucket)
+                        .key(key)
+                        .set_version_id(version_id.map(str::to_string))
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+                        .send()
+                },
+                |e| {
+                    Err(format!(
+                        "Failed to get object metadata: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                },
+            )
+            .await?;
+
+        let size = head.content_length().unwrap_or(0).max(0) as u64;
+        let customer = match head.sse_customer_algorithm() {
+            Some(_) => Some(self.keys.require()?),
+            None => None,
+        };
+
+        progress(Progress::File {
+            path: format!("s3://{}/{}", bucket, key),
+            size,
+        });
+
+        if size == 0 {
+            fs::File::create(dest)
+                .map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+            set_modified(dest, &head);
+            return Ok(());
+        }
+
+        let state = self.resume_or_start(bucket, key, version_id, dest, &head, size)?;
+        progress(Progress::Bytes(state.downloaded_bytes()));
+
+        let state = self.download_chunks(state, customer, progress).await?;
+        let part = part_path(dest);
+
+        if let Err(e) = self.verify(&state, &head, &part).await {
+            // Corrupt data cannot be resumed, start over next time
+            let _ = fs::remove_file(&part);
+            self.store.remove(&state.id)?;
+            return Err(e);
+        }
+
+        fs::rename(&part, dest)
+            .map_err(|e| format!("Failed to move download into place: {}", e))?;
+        set_modified(dest, &head);
+
+        self.store.remove(&state.id)
+    }
+
+    /// Resume a recorded download by its state ID.
+    pub async fn resume(&self, id: &str, progress: ProgressFn) -> Result<(), String> {
+        let state = self.store.load(id).ok_or("Download not found")?;
+
+        self.download_version(
+            &state.bucket,
+            &state.key,
+            state.version_id.as_deref(),
+            Path::new(&state.dest),
+            progress,
+        )
+        .await
+    }
+
+    /// Forget an unfinished download and remove its partial file.
+    pub fn abort(&self, id: &str) -> Result<(), String> {
+        let state = self.store.load(id).ok_or("Download not found")?;
+
+        match fs::remove_file(part_path(Path::new(&state.dest))) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to remove partial download: {}", e)),
+        }
+
+        self.store.remove(id)
+    }
+
+    /// Pick up the recorded download if the object and the part file are still
+    /// the ones it describes, otherwise start over with an empty part file.
+    fn resume_or_start(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        dest: &Path,
+        head: &HeadObjectOutput,
+        size: u64,
+    ) -> Result<DownloadState, String> {
+        let scoped_key = match version_id {
+            Some(version_id) => format!("{}?versionId={}", key, version_id),
+            None => key.to_string(),
+        };
+        let id = state_id(&[
+            &scoped_bucket(self.account.as_deref(), bucket),
+            &scoped_key,
+            &dest.to_string_lossy(),
+        ]);
+        let e_tag = head.e_tag().ok_or("S3 did not return an ETag")?.to_string();
+        let part = part_path(dest);
+
+        if let Some(state) = self.store.load(&id) {
+            let part_size = fs::metadata(&part).map(|m| m.len()).ok();
+
+            if state.e_tag == e_tag && state.size == size && part_size == Some(size) {
+                return Ok(state);
+            }
+
+            self.store.remove(&id)?;
+        }
+
+        let file = fs::File::create(&part)
+            .map_err(|e| format!("Failed to create {}: {}", part.display(), e))?;
+        file.set_len(size)
+            .map_err(|e| format!("Failed to allocate {}: {}", part.display(), e))?;
+
+        let state = DownloadState {
+            id,
+            account: self.account.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            version_id: version_id.map(str::to_string),
+            dest: dest.to_string_lossy().to_string(),
+            e_tag,
+            size,
+            chunk_size: self.settings.part_size,
+            completed_chunks: BTreeSet::new(),
+            started: chrono::Utc::now().to_rfc3339(),
+        };
+
+        self.store.save(&state.id, &state)?;
+
+        Ok(state)
+    }
+
+    async fn download_chunks(
+        &self,
+        state: DownloadState,
+        customer: Option<CustomerKey>,
+        progress: ProgressFn,
+    ) -> Result<DownloadState, String> {
+        let pending: Vec<u64> = (0..state.total_chunks())
+            .filter(|chunk| !state.completed_chunks.contains(chunk))
+            .collect();
+
+        let part = part_path(Path::new(&state.dest));
+        let state = Arc::new(Mutex::new(state));
+        let semaphore = Arc::new(Semaphore::new(self.settings.concurrency));
+        let mut tasks = JoinSet::new();
+
+        for chunk in pending {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // Stop scheduling new chunks as soon as one has failed
+            if let Some(result) = tasks.try_join_next() {
+                result.map_err(|e| format!("Download task failed: {}", e))??;
+            }
+
+            let client = self.client.clone();
+            let store = self.store.clone();
+            let state = state.clone();
+            let part = part.clone();
+            let customer = customer.clone();
+            let progress = progress.clone();
+
+            tasks.spawn(async move {
+                let _permit = permit;
+                let (bucket, key, version_id, e_tag, offset, length) = {
+                    let state = state.lock().await;
+                    (
+                        state.bucket.clone(),
+                        state.key.clone(),
+                        state.version_id.clone(),
+                        state.e_tag.clone(),
+                        state.chunk_offset(chunk),
+                        state.chunk_length(chunk),
+                    )
+                };
+
+                // If-Match makes S3 refuse the range if the object was replaced
+                // since the download started, so chunks never mix versions
+                let output = client
+                    .get_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .set_version_id(version_id)
+                    .range(format!("bytes={}-{}", offset, offset + length - 1))
+                    .if_match(e_tag)
+                    .set_sse_customer_algorithm(customer.as_ref().map(CustomerKey::algorithm))
+                    .set_sse_customer_key(customer.as_ref().map(CustomerKey::key))
+                    .set_sse_customer_key_md5(customer.as_ref().map(CustomerKey::key_md5))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        if e.code() == Some("PreconditionFailed") {
+                            "The object changed while it was being downloaded".to_string()
+                        } else {
+                            format!(
+                                "Failed to download range at {}: {}",
+                                offset,
+                                DisplayErrorContext(&e)
+                            )
+                        }
+                    })?;
+
+                let data = output
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|e| format!("Failed to read range at {}: {}", offset, e))?
+                    .into_bytes();
+
+                if data.len() as u64 != length {
+                    return Err(format!(
+                        "S3 returned {} bytes for a {} byte range",
+                        data.len(),
+                        length
+                    ));
+                }
+
+                let mut file = tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&part)
+                    .await
+                    .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+
+                file.seek(SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+                file.write_all(&data)
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+                // Only record the chunk once it is really on disk
+                file.sync_data()
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+
+                let mut state = state.lock().await;
+                state.completed_chunks.insert(chunk);
+                store.save(&state.id, &state)?;
+                progress(Progress::Bytes(length));
+
+                Ok::<(), String>(())
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            result.map_err(|e| format!("Download task failed: {}", e))??;
+        }
+
+        let state = state.lock().await.clone();
+        Ok(state)
+    }
+
+    /// Compare the part file against the object's ETag when the ETag is an MD5
+    /// based digest. Objects encrypted with KMS or customer keys have opaque
+    /// ETags; for those the `If-Match` on every range is the only guarantee.
+    async fn verify(
+        &self,
+        state: &DownloadState,
+        head: &HeadObjectOutput,
+        part: &Path,
+    ) -> Result<(), String> {
+        if opaque_e_tag(head) {
+            return Ok(());
+        }
+
+        let expected = trim_e_tag(&state.e_tag).to_string();
+
+        // Multipart ETags depend on the part size, which S3 reports for part 1
+        let part_size = if expected.contains('-') {
+            let first_part = self
+                .client
+                .head_object()
+                .bucket(&state.bucket)
+                .key(&state.key)
+                .set_version_id(state.version_id.clone())
+                .part_number(1)
+                .if_match(&state.e_tag)
+                .send()
+                .await
+                .map_err(|e| {
+                    format!("Failed to get object metadata: {}", DisplayErrorContext(&e))
+                })?;
+
+            match first_part.content_length() {
+                Some(length) if length > 0 => Some(length as u64),
+                _ => return Ok(()),
+            }
+        } else {
+            None
+        };
+
+        let path = part.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || local_e_tag(&path, part_size))
+            .await
+            .map_err(|e| format!("Checksum task failed: {}", e))?
+            .map_err(|e| format!("Failed to checksum download: {}", e))?;
+
+        if actual != expected {
+            return Err(format!(
+                "Downloaded data does not match the object's ETag ({} != {})",
+                actual, expected
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::{Progress, ProgressFn};
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Total size in bytes of a file, or of every file below a directory.
+pub fn total_size(path: &Path) -> Result<u64, String> {
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0;
+    for entry in fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        total += total_size(&entry.path())?;
+    }
+
+    Ok(total)
+}
+
+/// Copy a file or directory tree on the local disk, reporting every buffer
+/// written.
+///
+/// Copies get the modification time of their source, and files already present
+/// at the destination with the same size and time are skipped, so copying again
+/// after an interruption only copies what is missing or has changed.
+pub async fn copy_path(source: &Path, dest: &Path, progress: ProgressFn) -> Result<(), String> {
+    if dest.starts_with(source) {
+        return Err("Cannot copy a folder into itself".to_string());
+    }
+
+    if !source.is_dir() {
+        return copy_file(source, dest, progress).await;
+    }
+
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    for entry in fs::read_dir(source).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let dest_path = dest.join(entry.file_name());
+
+        Box::pin(copy_path(&entry.path(), &dest_path, progress.clone())).await?;
+    }
+
+    Ok(())
+}
+
+async fn copy_file(source: &Path, dest: &Path, progress: ProgressFn) -> Result<(), String> {
+    let metadata =
+        fs::metadata(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let size = metadata.len();
+    let modified = metadata.modified().ok();
+
+    let copied = fs::metadata(dest).is_ok_and(|dest| {
+        dest.len() == size && modified.is_some() && dest.modified().ok() == modified
+    });
+    if copied {
+        progress(Progress::Bytes(size));
+        return Ok(());
+    }
+
+    progress(Progress::File {
+        path: source.to_string_lossy().to_string(),
+        size,
+    });
+
+    let mut reader = tokio::fs::File::open(source)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let mut writer = tokio::fs::File::create(dest)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        if read == 0 {
+            break;
+        }
+
+        writer
+            .write_all(&buffer[..read])
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+        progress(Progress::Bytes(read as u64));
+    }
+
+    writer
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    // Keep the executable bit and the like, as fs::copy does
+    writer
+        .set_permissions(metadata.permissions())
+        .await
+        .map_err(|e| format!("Failed to set permissions of {}: {}", dest.display(), e))?;
+
+    // The copy itself succeeded; a file system without timestamps is fine
+    if let Some(modified) = modified {
+        let _ = writer.into_std().await.set_modified(modified);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::no_progress;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copies_keep_permissions_and_modification_time() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (source, dest) = (dir.path().join("run.sh"), dir.path().join("copy.sh"));
+        fs::write(&source, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o750)).unwrap();
+
+        copy_path(&source, &dest, no_progress()).await.unwrap();
+
+        let copied = fs::metadata(&dest).unwrap();
+        assert_eq!(copied.permissions().mode() & 0o777, 0o750);
+        assert_eq!(
+            copied.modified().unwrap(),
+            fs::metadata(&source).unwrap().modified().unwrap()
+        );
+        assert_eq!(fs::read(&dest).unwrap(), b"#!/bin/sh\n");
+    }
+}
@@ -0,0 +1,707 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{local, MultipartUploader, Progress, ProgressFn, RangedDownloader, TransferSettings};
+use crate::storage::encryption::Encryption;
+use crate::storage::local::{validate_path, validate_write_path};
+use crate::storage::s3::parse_s3_path;
+use crate::storage::{StorageManager, StorageProvider, StorageUri};
+
+/// Event emitted with a [`TransferJob`] payload whenever a job changes.
+pub const TRANSFER_EVENT: &str = "transfer-updated";
+
+// Jobs beyond this wait in the queue until a running one finishes
+const MAX_RUNNING_JOBS: usize = 2;
+
+// Progress events are throttled to keep the webview responsive
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// A queued copy or move as the frontend sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferJob {
+    pub id: u64,
+    pub kind: TransferKind,
+    pub source: String,
+    /// Version of the source object to copy, `None` for the current one
+    pub version_id: Option<String>,
+    pub dest_dir: String,
+    /// Path of the new item, known once the job has started
+    pub dest: Option<String>,
+    /// Encryption of the objects written to S3
+    #[serde(default)]
+    pub encryption: Encryption,
+    /// Replace an existing item at the destination, as a sync does
+    #[serde(default)]
+    pub overwrite: bool,
+    pub status: JobStatus,
+    /// Size of everything to transfer, known once the source has been measured
+    pub total_bytes: Option<u64>,
+    pub transferred_bytes: u64,
+    pub current_file: Option<String>,
+    pub file_bytes: u64,
+    pub file_size: u64,
+    pub bytes_per_second: u64,
+    pub eta_seconds: Option<u64>,
+    pub error: Option<String>,
+    pub created: String,
+}
+
+// Bytes seen since the last throughput sample
+struct Meter {
+    since: Instant,
+    bytes: u64,
+}
+
+struct JobEntry {
+    job: TransferJob,
+    task: Option<JoinHandle<()>>,
+    // Set once the job ran, after which the destination may legitimately exist
+    started: bool,
+    meter: Meter,
+}
+
+/// Runs copy and move jobs in the background, a few at a time, and pushes their
+/// progress to the frontend as [`TRANSFER_EVENT`] events.
+///
+/// Pausing stops the job's task; resuming runs it again, and the resumable
+/// engines pick up from the parts, chunks and files already transferred.
+#[derive(Clone)]
+pub struct TransferManager {
+    app: AppHandle,
+    jobs: Arc<Mutex<BTreeMap<u64, JobEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl TransferManager {
+    pub fn new(app: AppHandle) -> Self {
+        Self {
+            app,
+            jobs: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn jobs(&self) -> Result<Vec<TransferJob>, String> {
+        let jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+
+        Ok(jobs.values().map(|entry| entry.job.clone()).collect())
+    }
+
+    pub fn enqueue(
+        &self,
+        kind: TransferKind,
+        source: String,
+        version_id: Option<String>,
+        dest_dir: String,
+        encryption: Encryption,
+        overwrite: bool,
+    ) -> Result<TransferJob, String> {
+        let source_uri = StorageUri::parse(&source)?;
+        StorageUri::parse(&dest_dir)?;
+
+        if version_id.is_some() {
+            if source_uri.scheme() != "s3" {
+                return Err("Only S3 objects have versions".to_string());
+            }
+            // Moving would have to delete the version, which is not undoable
+            if kind == TransferKind::Move {
+                return Err("Object versions can be copied but not moved".to_string());
+            }
+        }
+
+        self.queue(self.new_job(kind, source, version_id, dest_dir, encryption, overwrite))
+    }
+
+    /// Queue a copy to exactly `dest` inside `dest_dir`, replacing what is
+    /// there. Uploads and downloads an earlier session left unfinished are
+    /// resumed this way: the engines find their recorded state by the
+    /// destination, which need not carry the source's name.
+    pub fn enqueue_to(
+        &self,
+        source: String,
+        version_id: Option<String>,
+        dest_dir: String,
+        dest: String,
+        encryption: Encryption,
+    ) -> Result<TransferJob, String> {
+        StorageUri::parse(&source)?;
+        StorageUri::parse(&dest)?;
+
+        let mut job = self.new_job(
+            TransferKind::Copy,
+            source,
+            version_id,
+            dest_dir,
+            encryption,
+            true,
+        );
+        job.dest = Some(dest);
+
+        self.queue(job)
+    }
+
+    fn new_job(
+        &self,
+        kind: TransferKind,
+        source: String,
+        version_id: Option<String>,
+        dest_dir: String,
+        encryption: Encryption,
+        overwrite: bool,
+    ) -> TransferJob {
+        TransferJob {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            kind,
+            source,
+            version_id,
+            dest_dir,
+            dest: None,
+            encryption,
+            overwrite,
+            status: JobStatus::Queued,
+            total_bytes: None,
+            transferred_bytes: 0,
+            current_file: None,
+            file_bytes: 0,
+            file_size: 0,
+            bytes_per_second: 0,
+            eta_seconds: None,
+            error: None,
+            created: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    fn queue(&self, job: TransferJob) -> Result<TransferJob, String> {
+        self.jobs.lock().map_err(|e| e.to_string())?.insert(
+            job.id,
+            JobEntry {
+                job: job.clone(),
+                task: None,
+                started: false,
+                meter: Meter {
+                    since: Instant::now(),
+                    bytes: 0,
+                },
+            },
+        );
+
+        self.emit(&job);
+        self.schedule();
+
+        Ok(job)
+    }
+
+    pub fn pause(&self, id: u64) -> Result<TransferJob, String> {
+        let job = self.update(id, |entry| match entry.job.status {
+            JobStatus::Queued | JobStatus::Running => {
+                if let Some(task) = entry.task.take() {
+                    task.abort();
+                }
+                entry.job.status = JobStatus::Paused;
+                entry.job.bytes_per_second = 0;
+                entry.job.eta_seconds = None;
+                Ok(())
+            }
+            _ => Err("Only queued or running transfers can be paused".to_string()),
+        })?;
+
+        self.schedule();
+
+        Ok(job)
+    }
+
+    /// Put a paused or failed job back in the queue.
+    pub fn resume(&self, id: u64) -> Result<TransferJob, String> {
+        let job = self.update(id, |entry| match entry.job.status {
+            JobStatus::Paused | JobStatus::Failed => {
+                entry.job.status = JobStatus::Queued;
+                entry.job.error = None;
+                Ok(())
+            }
+            _ => Err("Only paused or failed transfers can be resumed".to_string()),
+        })?;
+
+        self.schedule();
+
+        Ok(job)
+    }
+
+    /// Stop a job for good and discard the partial uploads and downloads it left
+    /// behind. Files that were already complete are kept.
+    pub async fn cancel(&self, id: u64) -> Result<TransferJob, String> {
+        let job = self.update(id, |entry| {
+            if entry.job.status.is_finished() {
+                return Err("Transfer has already finished".to_string());
+            }
+            if let Some(task) = entry.task.take() {
+                task.abort();
+            }
+            entry.job.status = JobStatus::Cancelled;
+            entry.job.bytes_per_second = 0;
+            entry.job.eta_seconds = None;
+            Ok(())
+        })?;
+
+        self.schedule();
+
+        if let Some(dest) = &job.dest {
+            let storage = self.app.state::<StorageManager>();
+            let settings = self.settings()?;
+            discard_partial(&storage, settings, &job.source, dest).await?;
+        }
+
+        Ok(job)
+    }
+
+    /// Forget every job that has completed, failed or been cancelled.
+    pub fn clear_finished(&self) -> Result<Vec<TransferJob>, String> {
+        let mut jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+        jobs.retain(|_, entry| !entry.job.status.is_finished());
+
+        Ok(jobs.values().map(|entry| entry.job.clone()).collect())
+    }
+
+    fn settings(&self) -> Result<TransferSettings, String> {
+        self.app
+            .state::<Mutex<TransferSettings>>()
+            .lock()
+            .map(|s| s.clone())
+            .map_err(|e| e.to_string())
+    }
+
+    fn update(
+        &self,
+        id: u64,
+        change: impl FnOnce(&mut JobEntry) -> Result<(), String>,
+    ) -> Result<TransferJob, String> {
+        let job = {
+            let mut jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+            let entry = jobs.get_mut(&id).ok_or("Transfer not found")?;
+            change(entry)?;
+            entry.job.clone()
+        };
+
+        self.emit(&job);
+
+        Ok(job)
+    }
+
+    fn emit(&self, job: &TransferJob) {
+        // Nothing useful can be done if the window is gone
+        let _ = self.app.emit(TRANSFER_EVENT, job);
+    }
+
+    // Start queued jobs, oldest first, while there are free slots
+    fn schedule(&self) {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return;
+        };
+
+        let mut running = jobs
+            .values()
+            .filter(|entry| entry.job.status == JobStatus::Running)
+            .count();
+        let mut started = Vec::new();
+
+        for entry in jobs.values_mut() {
+            if running >= MAX_RUNNING_JOBS {
+                break;
+            }
+            if entry.job.status != JobStatus::Queued {
+                continue;
+            }
+
+            entry.job.status = JobStatus::Running;
+            entry.job.transferred_bytes = 0;
+            entry.meter = Meter {
+                since: Instant::now(),
+                bytes: 0,
+            };
+
+            let manager = self.clone();
+            let id = entry.job.id;
+            let check_dest = !entry.started && !entry.job.overwrite;
+            entry.started = true;
+            entry.task = Some(async_runtime::spawn(async move {
+                manager.run(id, check_dest).await;
+            }));
+
+            running += 1;
+            started.push(entry.job.clone());
+        }
+
+        drop(jobs);
+
+        for job in &started {
+            self.emit(job);
+        }
+    }
+
+    async fn run(self, id: u64, check_dest: bool) {
+        let result = self.execute(id, check_dest).await;
+
+        let finished = self.update(id, |entry| {
+            // A job paused or cancelled meanwhile keeps that status
+            if entry.job.status != JobStatus::Running {
+                return Ok(());
+            }
+
+            entry.task = None;
+            entry.job.bytes_per_second = 0;
+            entry.job.eta_seconds = None;
+            match &result {
+                Ok(()) => entry.job.status = JobStatus::Completed,
+                Err(e) => {
+                    entry.job.status = JobStatus::Failed;
+                    entry.job.error = Some(e.clone());
+                }
+            }
+            Ok(())
+        });
+
+        if finished.is_ok() {
+            self.schedule();
+        }
+    }
+
+    async fn execute(&self, id: u64, check_dest: bool) -> Result<(), String> {
+        let job = {
+            let jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+            jobs.get(&id).ok_or("Transfer not found")?.job.clone()
+        };
+
+        let storage = self.app.state::<StorageManager>();
+        let settings = TransferSettings {
+            encryption: job.encryption.clone(),
+            ..self.settings()?
+        };
+
+        let dest = match &job.dest {
+            Some(dest) => dest.clone(),
+            None => destination_for(&storage, &job.source, &job.dest_dir).await?,
+        };
+        let total_bytes = match &job.version_id {
+            Some(version_id) => storage
+                .s3_for(&job.source)
+                .await?
+                .stat_version(&job.source, version_id)
+                .await
+                .ok()
+                .and_then(|item| item.size),
+            None => measure(&storage, &job.source).await.ok(),
+        };
+        self.update(id, |entry| {
+            entry.job.dest = Some(dest.clone());
+            entry.job.total_bytes = total_bytes;
+            Ok(())
+        })?;
+
+        let manager = self.clone();
+        let progress: ProgressFn = Arc::new(move |progress| manager.record(id, progress));
+
+        if let Some(version_id) = &job.version_id {
+            return transfer_version(
+                &storage,
+                settings,
+                &job.source,
+                version_id,
+                &dest,
+                check_dest,
+                progress,
+            )
+            .await;
+        }
+
+        transfer_item(
+            &storage,
+            settings,
+            &job.source,
+            &dest,
+            job.kind,
+            check_dest,
+            progress,
+        )
+        .await
+    }
+
+    fn record(&self, id: u64, progress: Progress) {
+        let job = {
+            let Ok(mut jobs) = self.jobs.lock() else {
+                return;
+            };
+            let Some(entry) = jobs.get_mut(&id) else {
+                return;
+            };
+            let job = &mut entry.job;
+
+            match progress {
+                Progress::File { path, size } => {
+                    job.current_file = Some(path);
+                    job.file_size = size;
+                    job.file_bytes = 0;
+                }
+                Progress::Bytes(bytes) => {
+                    job.transferred_bytes += bytes;
+                    job.file_bytes = (job.file_bytes + bytes).min(job.file_size);
+                    entry.meter.bytes += bytes;
+                }
+            }
+
+            let elapsed = entry.meter.since.elapsed();
+            if elapsed < EMIT_INTERVAL {
+                return;
+            }
+
+            // Smooth the rate so the ETA does not jump around between samples
+            let rate = (entry.meter.bytes as f64 / elapsed.as_secs_f64()) as u64;
+            job.bytes_per_second = if job.bytes_per_second == 0 {
+                rate
+            } else {
+                (job.bytes_per_second * 7 + rate * 3) / 10
+            };
+            job.eta_seconds = match job.total_bytes {
+                Some(total) if job.bytes_per_second > 0 => {
+                    Some(total.saturating_sub(job.transferred_bytes) / job.bytes_per_second)
+                }
+                _ => None,
+            };
+            entry.meter = Meter {
+                since: Instant::now(),
+                bytes: 0,
+            };
+
+            job.clone()
+        };
+
+        self.emit(&job);
+    }
+}
+
+/// Path the copy of `source` gets inside `dest_dir`.
+pub async fn destination_for(
+    storage: &StorageManager,
+    source: &str,
+    dest_dir: &str,
+) -> Result<String, String> {
+    let name = storage
+        .provider(source)
+        .await?
+        .file_name(source)
+        .ok_or("Cannot determine file name")?;
+
+    match StorageUri::parse(dest_dir)? {
+        StorageUri::Local(_) => Ok(validate_write_path(dest_dir)?
+            .join(name)
+            .to_string_lossy()
+            .to_string()),
+        StorageUri::S3 { .. } => Ok(storage.s3_for(dest_dir).await?.join(dest_dir, &name)),
+    }
+}
+
+async fn measure(storage: &StorageManager, source: &str) -> Result<u64, String> {
+    match StorageUri::parse(source)? {
+        StorageUri::Local(_) => local::total_size(&validate_path(source)?),
+        StorageUri::S3 { .. } => storage.s3_for(source).await?.total_size(source).await,
+    }
+}
+
+/// Copy or move `source` to `dest`, picking the transfer engine for the pair of
+/// storages involved.
+///
+/// With `check_dest` the transfer refuses to overwrite an existing item, unless
+/// that item is the unfinished result of an earlier attempt.
+pub async fn transfer_item(
+    storage: &StorageManager,
+    settings: TransferSettings,
+    source: &str,
+    dest: &str,
+    kind: TransferKind,
+    check_dest: bool,
+    progress: ProgressFn,
+) -> Result<(), String> {
+    const DEST_EXISTS: &str = "An item with this name already exists in destination";
+
+    match (StorageUri::parse(source)?, StorageUri::parse(dest)?) {
+        (StorageUri::Local(_), StorageUri::S3 { .. }) => {
+            let source_path = validate_path(source)?;
+            let s3 = storage.s3_for(dest).await?;
+            let (bucket, key) = parse_s3_path(dest)?;
+            let uploader = MultipartUploader::new(&s3, settings)?;
+
+            if check_dest && s3.exists(dest).await? && !uploader.has_pending(&bucket, &key)? {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            uploader
+                .upload_path(&source_path, &bucket, &key, progress)
+                .await?;
+        }
+        (StorageUri::S3 { .. }, StorageUri::Local(_)) => {
+            let s3 = storage.s3_for(source).await?;
+            let item = s3.stat(source).await?;
+            let (bucket, key) = parse_s3_path(&item.path)?;
+            let dest_path = PathBuf::from(dest);
+            let downloader = RangedDownloader::new(&s3, settings)?;
+
+            if bucket.is_empty() {
+                return Err("Select a bucket or object to download".to_string());
+            }
+
+            if check_dest && dest_path.exists() && !downloader.has_pending(&dest_path)? {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            downloader
+                .download_path(&bucket, &key, &dest_path, progress)
+                .await?;
+        }
+        (StorageUri::Local(_), StorageUri::Local(_)) => {
+            let source_path = validate_path(source)?;
+            let dest_path = Path::new(dest);
+
+            if check_dest && dest_path.exists() {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            // A rename on the same disk is instant and needs no copying
+            if kind == TransferKind::Move
+                && storage.local().rename(source, dest, settings).await.is_ok()
+            {
+                progress(Progress::Bytes(local::total_size(dest_path)?));
+                return Ok(());
+            }
+
+            local::copy_path(&source_path, dest_path, progress).await?;
+        }
+        (source_uri @ StorageUri::S3 { .. }, dest_uri @ StorageUri::S3 { .. }) => {
+            if source_uri.account() != dest_uri.account() {
+                return Err("Transfers between S3 accounts are not supported".to_string());
+            }
+
+            let s3 = storage.s3_for(source).await?;
+
+            if check_dest && s3.exists(dest).await? {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            s3.copy_with_progress(source, dest, settings, progress)
+                .await?;
+        }
+    }
+
+    if kind == TransferKind::Move {
+        storage.provider(source).await?.delete(source).await?;
+    }
+
+    Ok(())
+}
+
+/// Copy one version of an S3 object to `dest`, either downloading it or
+/// copying it inside S3.
+pub async fn transfer_version(
+    storage: &StorageManager,
+    settings: TransferSettings,
+    source: &str,
+    version_id: &str,
+    dest: &str,
+    check_dest: bool,
+    progress: ProgressFn,
+) -> Result<(), String> {
+    const DEST_EXISTS: &str = "An item with this name already exists in destination";
+
+    let s3 = storage.s3_for(source).await?;
+
+    match StorageUri::parse(dest)? {
+        StorageUri::Local(_) => {
+            let (bucket, key) = parse_s3_path(source)?;
+            let dest_path = PathBuf::from(dest);
+            let downloader = RangedDownloader::new(&s3, settings)?;
+
+            if check_dest && dest_path.exists() && !downloader.has_pending(&dest_path)? {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            downloader
+                .download_version(&bucket, &key, Some(version_id), &dest_path, progress)
+                .await
+        }
+        StorageUri::S3 { .. } => {
+            if check_dest && s3.exists(dest).await? {
+                return Err(DEST_EXISTS.to_string());
+            }
+
+            s3.copy_version_with_progress(source, version_id, dest, settings, progress)
+                .await
+        }
+    }
+}
+
+// Abort the multipart uploads and remove the partial downloads of a transfer
+async fn discard_partial(
+    storage: &StorageManager,
+    settings: TransferSettings,
+    source: &str,
+    dest: &str,
+) -> Result<(), String> {
+    match (StorageUri::parse(source)?, StorageUri::parse(dest)?) {
+        (StorageUri::Local(_), StorageUri::S3 { .. }) => {
+            let (bucket, key) = parse_s3_path(dest)?;
+            let s3 = storage.s3_for(dest).await?;
+            let uploader = MultipartUploader::new(&s3, settings)?;
+
+            for state in uploader.store().list()? {
+                if state.account.as_deref() == s3.account()
+                    && state.bucket == bucket
+                    && state.is_at_or_below(&key)
+                {
+                    uploader.abort(&state.id).await?;
+                }
+            }
+        }
+        (StorageUri::S3 { .. }, StorageUri::Local(_)) => {
+            let downloader = RangedDownloader::new(&*storage.s3_for(source).await?, settings)?;
+
+            for state in downloader.store().list()? {
+                if Path::new(&state.dest).starts_with(dest) {
+                    downloader.abort(&state.id)?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
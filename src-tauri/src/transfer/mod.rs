@@ -0,0 +1,173 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::storage::encryption::Encryption;
+use crate::utils::{app_data_dir, read_json, write_json};
+
+pub mod copy;
+pub mod delete;
+pub mod download;
+pub mod local;
+pub mod manager;
+pub mod upload;
+
+pub use copy::ServerSideCopier;
+pub use delete::{DeleteJob, DeleteManager};
+pub use download::{DownloadState, RangedDownloader};
+pub use manager::{TransferJob, TransferKind, TransferManager};
+pub use upload::{MultipartUploader, UploadState};
+
+/// What a transfer engine reports while it works.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// A new file was started; `size` is its total length in bytes
+    File { path: String, size: u64 },
+    /// This many more bytes reached the destination
+    Bytes(u64),
+}
+
+/// Callback receiving progress updates from a running transfer.
+pub type ProgressFn = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Progress callback for callers that do not track progress.
+pub fn no_progress() -> ProgressFn {
+    Arc::new(|_| {})
+}
+
+// S3 rejects multipart parts smaller than 5 MiB (except the last one)
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+
+// S3 allows at most 10,000 parts per multipart upload
+pub const MAX_PARTS: u64 = 10_000;
+
+const MAX_CONCURRENCY: usize = 32;
+
+const SETTINGS_FILE: &str = "transfer-settings.json";
+
+/// User-tunable knobs shared by all transfers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferSettings {
+    /// Size of each part of a multipart transfer, in bytes
+    pub part_size: u64,
+    /// Number of parts transferred in parallel
+    pub concurrency: usize,
+    /// Encryption of the objects a transfer writes, chosen per job rather
+    /// than saved with the settings
+    #[serde(skip)]
+    pub encryption: Encryption,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self {
+            part_size: 8 * 1024 * 1024,
+            concurrency: 4,
+            encryption: Encryption::BucketDefault,
+        }
+    }
+}
+
+impl TransferSettings {
+    /// Load the saved settings, falling back to the defaults.
+    pub fn load() -> Self {
+        app_data_dir()
+            .and_then(|dir| read_json::<TransferSettings>(&dir.join(SETTINGS_FILE)))
+            .map(TransferSettings::normalized)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        write_json(&app_data_dir()?.join(SETTINGS_FILE), self)
+    }
+
+    /// Clamp values the user entered into the range S3 accepts.
+    pub fn normalized(self) -> Self {
+        Self {
+            part_size: self.part_size.max(MIN_PART_SIZE),
+            concurrency: self.concurrency.clamp(1, MAX_CONCURRENCY),
+            encryption: self.encryption,
+        }
+    }
+
+    /// Part size to use for a file, grown when needed so it fits in 10,000 parts.
+    pub fn part_size_for(&self, file_size: u64) -> u64 {
+        self.part_size.max(file_size.div_ceil(MAX_PARTS))
+    }
+}
+
+/// On-disk record of unfinished transfers, one JSON file per transfer, so they
+/// can be resumed after an app restart.
+pub struct StateStore<T> {
+    dir: PathBuf,
+    _state: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> StateStore<T> {
+    pub fn open(name: &str) -> Result<Self, String> {
+        let dir = app_data_dir()?.join(name);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create transfer state directory: {}", e))?;
+
+        Ok(Self {
+            dir,
+            _state: PhantomData,
+        })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn load(&self, id: &str) -> Option<T> {
+        read_json(&self.path(id)).ok()
+    }
+
+    pub fn save(&self, id: &str, state: &T) -> Result<(), String> {
+        write_json(&self.path(id), state)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(id)) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove transfer state: {}", e)),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<T>, String> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read transfer state directory: {}", e))?;
+
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| read_json(&entry.path()).ok())
+            .collect())
+    }
+}
+
+/// Stable identifier of a transfer, so the same source and destination resume
+/// the same state across app restarts.
+pub fn state_id(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// Bucket name qualified by its account for use in a state ID, since buckets of
+/// different accounts may share a name. Buckets of the environment connection
+/// keep the plain name, so their recorded transfers still resume.
+pub fn scoped_bucket(account: Option<&str>, bucket: &str) -> String {
+    match account {
+        Some(account) => format!("{}@{}", account, bucket),
+        None => bucket.to_string(),
+    }
+}
@@ -0,0 +1,698 @@
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata};
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::storage::encryption::{CustomerKey, CustomerKeys, Encryption, EncryptionParams};
+use crate::storage::S3Storage;
+
+use super::download::{local_e_tag, opaque_e_tag, trim_e_tag};
+use super::{scoped_bucket, state_id, Progress, ProgressFn, StateStore, TransferSettings};
+
+/// Everything needed to pick up a multipart upload where it stopped, persisted
+/// after every finished part.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadState {
+    pub id: String,
+    /// Account the upload runs under, `None` for the AWS environment
+    #[serde(default)]
+    pub account: Option<String>,
+    pub source: String,
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub part_size: u64,
+    pub file_size: u64,
+    /// Modification time of the source when the upload started, in seconds
+    pub file_modified: Option<u64>,
+    /// ETag of every part S3 has acknowledged, by part number
+    pub completed_parts: BTreeMap<i32, String>,
+    pub started: String,
+    /// Parts of an SSE-C upload have to be sent with the same key
+    #[serde(default)]
+    pub encryption: Encryption,
+}
+
+impl UploadState {
+    pub fn total_parts(&self) -> i32 {
+        self.file_size.div_ceil(self.part_size) as i32
+    }
+
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.completed_parts
+            .keys()
+            .map(|&part_number| self.part_length(part_number))
+            .sum()
+    }
+
+    /// Whether the upload is of the object `key`, or of one in the folder
+    /// `key` names; `report` covers neither `report.bak` nor `reports/a`.
+    pub fn is_at_or_below(&self, key: &str) -> bool {
+        let folder = key.trim_end_matches('/');
+        self.key == key
+            || folder.is_empty()
+            || self
+                .key
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    fn part_offset(&self, part_number: i32) -> u64 {
+        (part_number as u64 - 1) * self.part_size
+    }
+
+    fn part_length(&self, part_number: i32) -> u64 {
+        self.part_size
+            .min(self.file_size - self.part_offset(part_number))
+    }
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+}
+
+async fn read_part(path: &Path, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    file.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut buffer = vec![0; length as usize];
+    file.read_exact(&mut buffer)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    Ok(buffer)
+}
+
+// Collect every file below a directory together with its key relative to it
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut Vec<(PathBuf, String)>,
+) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            collect_files(&path, &format!("{}{}/", prefix, name), files)?;
+        } else {
+            files.push((path, format!("{}{}", prefix, name)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Uploads local files to S3, switching to resumable multipart uploads for
+/// anything larger than a single part.
+pub struct MultipartUploader {
+    client: Client,
+    account: Option<String>,
+    keys: CustomerKeys,
+    store: Arc<StateStore<UploadState>>,
+    settings: TransferSettings,
+}
+
+impl MultipartUploader {
+    pub fn new(s3: &S3Storage, settings: TransferSettings) -> Result<Self, String> {
+        Ok(Self {
+            client: s3.client().clone(),
+            account: s3.account().map(str::to_string),
+            keys: s3.customer_keys().clone(),
+            store: Arc::new(Self::open_store()?),
+            settings,
+        })
+    }
+
+    /// The recorded uploads, readable without connecting to S3.
+    pub fn open_store() -> Result<StateStore<UploadState>, String> {
+        StateStore::open("uploads")
+    }
+
+    pub fn store(&self) -> &StateStore<UploadState> {
+        &self.store
+    }
+
+    /// Whether an unfinished upload exists at or below `key`.
+    pub fn has_pending(&self, bucket: &str, key: &str) -> Result<bool, String> {
+        Ok(self.store.list()?.iter().any(|state| {
+            state.account == self.account && state.bucket == bucket && state.is_at_or_below(key)
+        }))
+    }
+
+    /// Upload a file, or a directory tree below `key` as a prefix, encrypted
+    /// as the settings say.
+    ///
+    /// Files whose object already has the same content, as far as its ETag
+    /// tells, are skipped, so re-running an interrupted folder upload only
+    /// sends what is missing or has changed.
+    pub async fn upload_path(
+        &self,
+        source: &Path,
+        bucket: &str,
+        key: &str,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let encryption = &self.settings.encryption;
+
+        if !source.is_dir() {
+            return self
+                .upload_file(source, bucket, key, encryption, progress)
+                .await;
+        }
+
+        let prefix = format!("{}/", key.trim_end_matches('/'));
+        let mut files = Vec::new();
+        collect_files(source, &prefix, &mut files)?;
+        let params = self.keys.params(encryption)?;
+
+        // Keep empty folders visible in S3 by creating the folder marker. It
+        // gets no customer key, so listing the folder never needs one.
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(&prefix)
+            .body(ByteStream::from_static(b""))
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create folder: {}", DisplayErrorContext(&e)))?;
+
+        for (path, file_key) in files {
+            if self.is_uploaded(&path, bucket, &file_key).await? {
+                progress(Progress::Bytes(
+                    fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                ));
+                continue;
+            }
+
+            self.upload_file(&path, bucket, &file_key, encryption, progress.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn upload_file(
+        &self,
+        source: &Path,
+        bucket: &str,
+        key: &str,
+        encryption: &Encryption,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let metadata = fs::metadata(source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let file_size = metadata.len();
+        let params = self.keys.params(encryption)?;
+
+        progress(Progress::File {
+            path: source.to_string_lossy().to_string(),
+            size: file_size,
+        });
+
+        if file_size <= self.settings.part_size {
+            return self
+                .put_single(source, bucket, key, file_size, &params, progress)
+                .await;
+        }
+
+        let state = self
+            .resume_or_start(source, bucket, key, &metadata, encryption, &params)
+            .await?;
+        progress(Progress::Bytes(state.uploaded_bytes()));
+
+        self.upload_parts(state, &params, progress).await
+    }
+
+    /// Resume a recorded upload by its state ID.
+    pub async fn resume(&self, id: &str, progress: ProgressFn) -> Result<(), String> {
+        let state = self.store.load(id).ok_or("Upload not found")?;
+
+        self.upload_file(
+            Path::new(&state.source),
+            &state.bucket,
+            &state.key,
+            &state.encryption,
+            progress,
+        )
+        .await
+    }
+
+    /// Abort an unfinished upload on the server and forget its local state.
+    pub async fn abort(&self, id: &str) -> Result<(), String> {
+        let state = self.store.load(id).ok_or("Upload not found")?;
+
+        self.abort_remote(&state).await?;
+        self.store.remove(id)
+    }
+
+    async fn abort_remote(&self, state: &UploadState) -> Result<(), String> {
+        match self
+            .client
+            .abort_multipart_upload()
+            .bucket(&state.bucket)
+            .key(&state.key)
+            .upload_id(&state.upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            // Already gone on the server, nothing left to clean up
+            Err(e) if e.code() == Some("NoSuchUpload") => Ok(()),
+            Err(e) => Err(format!(
+                "Failed to abort upload: {}",
+                DisplayErrorContext(&e)
+            )),
+        }
+    }
+
+    // Whether the object at `key` already holds the file: the same size and
+    // the same ETag. Objects whose ETag is no digest of their data, as with KMS
+    // and customer keys, count when they were written after the file changed.
+    async fn is_uploaded(&self, path: &Path, bucket: &str, key: &str) -> Result<bool, String> {
+        let Some(head) = self.head(bucket, key, None).await? else {
+            return Ok(false);
+        };
+        let metadata =
+            fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        if head.content_length().map(|size| size.max(0) as u64) != Some(metadata.len()) {
+            return Ok(false);
+        }
+
+        if opaque_e_tag(&head) {
+            let uploaded = head
+                .last_modified()
+                .and_then(|time| SystemTime::try_from(*time).ok());
+            return Ok(match (uploaded, metadata.modified()) {
+                (Some(uploaded), Ok(modified)) => uploaded >= modified,
+                _ => false,
+            });
+        }
+
+        let Some(expected) = head.e_tag().map(trim_e_tag) else {
+            return Ok(false);
+        };
+
+        // Multipart ETags depend on the part size, which S3 reports for part 1
+        let part_size = if expected.contains('-') {
+            match self
+                .head(bucket, key, Some(1))
+                .await?
+                .and_then(|part| part.content_length())
+            {
+                Some(length) if length > 0 => Some(length as u64),
+                _ => return Ok(false),
+            }
+        } else {
+            None
+        };
+
+        let file = path.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || local_e_tag(&file, part_size))
+            .await
+            .map_err(|e| format!("Checksum task failed: {}", e))?
+            .map_err(|e| format!("Failed to checksum {}: {}", path.display(), e))?;
+
+        Ok(actual == expected)
+    }
+
+    async fn head(
+        &self,
+        bucket: &str,
+        key: &str,
+        part_number: Option<i32>,
+    ) -> Result<Option<HeadObjectOutput>, String> {
+        self.keys
+            .send(
+                |customer| {
+                    let request = self
+                        .client
+                        .head_object()
+                        .bucket(bucket)
+                        .key(key)
+                        .set_part_number(part_number)
+                        .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+                        .set_sse_customer_key(customer.map(CustomerKey::key))
+                        .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5));
+                    async move { request.send().await.map(Some) }
+                },
+                |e| match e.as_service_error() {
+                    Some(service_error) if service_error.is_not_found() => Ok(None),
+                    _ => Err(format!(
+                        "Failed to get object metadata: {}",
+                        DisplayErrorContext(&e)
+                    )),
+                },
+            )
+            .await
+    }
+
+    async fn put_single(
+        &self,
+        source: &Path,
+        bucket: &str,
+        key: &str,
+        file_size: u64,
+        params: &EncryptionParams,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let body = ByteStream::from_path(source)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let customer = params.customer_key();
+
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(body)
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+            .set_sse_customer_key(customer.map(CustomerKey::key))
+            .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload file: {}", DisplayErrorContext(&e)))?;
+
+        progress(Progress::Bytes(file_size));
+
+        Ok(())
+    }
+
+    /// Pick up the recorded upload for this file if it is still valid, otherwise
+    /// abort whatever was left behind and start a new multipart upload.
+    async fn resume_or_start(
+        &self,
+        source: &Path,
+        bucket: &str,
+        key: &str,
+        metadata: &fs::Metadata,
+        encryption: &Encryption,
+        params: &EncryptionParams,
+    ) -> Result<UploadState, String> {
+        let id = state_id(&[
+            &source.to_string_lossy(),
+            &scoped_bucket(self.account.as_deref(), bucket),
+            key,
+        ]);
+        let file_size = metadata.len();
+        let file_modified = modified_secs(metadata);
+
+        if let Some(mut state) = self.store.load(&id) {
+            let unchanged = state.file_size == file_size
+                && state.file_modified == file_modified
+                && state.encryption == *encryption;
+
+            if unchanged {
+                if let Some(parts) = self.list_uploaded_parts(&state).await? {
+                    state.completed_parts = parts;
+                    self.store.save(&state.id, &state)?;
+                    return Ok(state);
+                }
+            } else {
+                // The source or its encryption changed, so the parts already
+                // sent are worthless
+                self.abort_remote(&state).await?;
+            }
+
+            self.store.remove(&id)?;
+        }
+
+        let customer = params.customer_key();
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .set_server_side_encryption(params.server_side_encryption())
+            .set_ssekms_key_id(params.kms_key_id())
+            .set_sse_customer_algorithm(customer.map(CustomerKey::algorithm))
+            .set_sse_customer_key(customer.map(CustomerKey::key))
+            .set_sse_customer_key_md5(customer.map(CustomerKey::key_md5))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to start upload: {}", DisplayErrorContext(&e)))?;
+
+        let state = UploadState {
+            id,
+            account: self.account.clone(),
+            source: source.to_string_lossy().to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            upload_id: output
+                .upload_id()
+                .ok_or("S3 did not return an upload ID")?
+                .to_string(),
+            part_size: self.settings.part_size_for(file_size),
+            file_size,
+            file_modified,
+            completed_parts: BTreeMap::new(),
+            started: chrono::Utc::now().to_rfc3339(),
+            encryption: encryption.clone(),
+        };
+
+        self.store.save(&state.id, &state)?;
+
+        Ok(state)
+    }
+
+    /// Ask S3 which parts it already has, so the local record can never claim a
+    /// part the server lost. Returns `None` when the upload no longer exists.
+    async fn list_uploaded_parts(
+        &self,
+        state: &UploadState,
+    ) -> Result<Option<BTreeMap<i32, String>>, String> {
+        let mut parts = BTreeMap::new();
+        let mut pages = self
+            .client
+            .list_parts()
+            .bucket(&state.bucket)
+            .key(&state.key)
+            .upload_id(&state.upload_id)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = match page {
+                Ok(page) => page,
+                Err(e) if e.code() == Some("NoSuchUpload") => return Ok(None),
+                Err(e) => {
+                    return Err(format!(
+                        "Failed to list uploaded parts: {}",
+                        DisplayErrorContext(&e)
+                    ))
+                }
+            };
+
+            for part in page.parts() {
+                if let (Some(part_number), Some(e_tag)) = (part.part_number(), part.e_tag()) {
+                    parts.insert(part_number, e_tag.to_string());
+                }
+            }
+        }
+
+        Ok(Some(parts))
+    }
+
+    async fn upload_parts(
+        &self,
+        state: UploadState,
+        params: &EncryptionParams,
+        progress: ProgressFn,
+    ) -> Result<(), String> {
+        let pending: Vec<i32> = (1..=state.total_parts())
+            .filter(|part_number| !state.completed_parts.contains_key(part_number))
+            .collect();
+
+        let state = Arc::new(Mutex::new(state));
+        let semaphore = Arc::new(Semaphore::new(self.settings.concurrency));
+        let mut tasks = JoinSet::new();
+
+        for part_number in pending {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // Stop scheduling new parts as soon as one has failed
+            if let Some(result) = tasks.try_join_next() {
+                result.map_err(|e| format!("Upload task failed: {}", e))??;
+            }
+
+            let client = self.client.clone();
+            let store = self.store.clone();
+            let state = state.clone();
+            let customer = params.customer_key().cloned();
+            let progress = progress.clone();
+
+            tasks.spawn(async move {
+                let _permit = permit;
+                let (source, bucket, key, upload_id, offset, length) = {
+                    let state = state.lock().await;
+                    (
+                        state.source.clone(),
+                        state.bucket.clone(),
+                        state.key.clone(),
+                        state.upload_id.clone(),
+                        state.part_offset(part_number),
+                        state.part_length(part_number),
+                    )
+                };
+
+                let data = read_part(Path::new(&source), offset, length).await?;
+
+                let output = client
+                    .upload_part()
+                    .bucket(bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(data))
+                    .set_sse_customer_algorithm(customer.as_ref().map(CustomerKey::algorithm))
+                    .set_sse_customer_key(customer.as_ref().map(CustomerKey::key))
+                    .set_sse_customer_key_md5(customer.as_ref().map(CustomerKey::key_md5))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "Failed to upload part {}: {}",
+                            part_number,
+                            DisplayErrorContext(&e)
+                        )
+                    })?;
+
+                let e_tag = output
+                    .e_tag()
+                    .ok_or_else(|| format!("S3 did not return an ETag for part {}", part_number))?
+                    .to_string();
+
+                let mut state = state.lock().await;
+                state.completed_parts.insert(part_number, e_tag);
+                store.save(&state.id, &state)?;
+                progress(Progress::Bytes(length));
+
+                Ok::<(), String>(())
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            result.map_err(|e| format!("Upload task failed: {}", e))??;
+        }
+
+        let state = state.lock().await.clone();
+        self.complete(&state).await
+    }
+
+    async fn complete(&self, state: &UploadState) -> Result<(), String> {
+        let parts = state
+            .completed_parts
+            .iter()
+            .map(|(&part_number, e_tag)| {
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .e_tag(e_tag)
+                    .build()
+            })
+            .collect();
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&state.bucket)
+            .key(&state.key)
+            .upload_id(&state.upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| format!("Failed to complete upload: {}", DisplayErrorContext(&e)))?;
+
+        self.store.remove(&state.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload_of(key: &str) -> UploadState {
+        UploadState {
+            id: key.to_string(),
+            account: None,
+            source: "/home/me/file".to_string(),
+            bucket: "bucket".to_string(),
+            key: key.to_string(),
+            upload_id: "upload".to_string(),
+            part_size: 8,
+            file_size: 20,
+            file_modified: None,
+            completed_parts: BTreeMap::new(),
+            started: String::new(),
+            encryption: Encryption::default(),
+        }
+    }
+
+    #[test]
+    fn pending_uploads_match_the_key_or_its_folder_only() {
+        let cases = [
+            ("report", "report", true),
+            ("report", "report.bak", false),
+            ("report", "reports/a.txt", false),
+            ("logs", "logs/2026/a.log", true),
+            ("logs/", "logs/2026/a.log", true),
+            ("logs", "logs2/a.log", false),
+            ("logs/", "logs2/a.log", false),
+            ("a/report", "a/report.bak", false),
+            ("a/report", "a/report/part", true),
+            ("", "anything", true),
+        ];
+        for (key, pending, expected) in cases {
+            assert_eq!(
+                upload_of(pending).is_at_or_below(key),
+                expected,
+                "{} for {}",
+                pending,
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn parts_cover_the_file() {
+        let mut state = upload_of("big");
+        assert_eq!(state.total_parts(), 3);
+        assert_eq!(state.part_length(3), 4);
+
+        state.completed_parts.insert(1, "a".to_string());
+        state.completed_parts.insert(3, "c".to_string());
+        assert_eq!(state.uploaded_bytes(), 12);
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage::archive::RestoreState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileItem {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<String>,
+    pub icon: String,
+    /// Version of an S3 object, only set when listing object versions
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// Whether the version is the current one
+    #[serde(default)]
+    pub is_latest: bool,
+    /// A delete marker hides the object without removing its older versions
+    #[serde(default)]
+    pub is_delete_marker: bool,
+    /// S3 storage class, such as `STANDARD_IA`, when the listing reports one
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    /// In an archive tier with no restored copy to read
+    #[serde(default)]
+    pub archived: bool,
+    /// A restore from the archive is under way
+    #[serde(default)]
+    pub restoring: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryContents {
+    pub current_path: String,
+    pub parent_path: Option<String>,
+    pub items: Vec<FileItem>,
+    /// Opaque token for fetching the next page, `None` once the listing is complete
+    pub next_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilePreview {
+    pub file_type: String,
+    pub content: String,
+    pub size: u64,
+    pub encoding: String, // "text" or "base64"
+}
+
+/// Headers, user metadata, encryption and tags of one S3 object, as shown in
+/// the inspector.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ObjectMetadata {
+    pub path: String,
+    pub version_id: Option<String>,
+    pub size: u64,
+    pub modified: Option<String>,
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+    pub content_language: Option<String>,
+    /// User metadata (`x-amz-meta-*`) without the prefix, sorted by key
+    pub metadata: Vec<(String, String)>,
+    pub e_tag: Option<String>,
+    pub storage_class: Option<String>,
+    /// Needs a restore before it can be read
+    pub archived: bool,
+    /// Set once a restore has been requested
+    pub restore: Option<RestoreState>,
+    /// "SSE-S3", "SSE-KMS", "DSSE-KMS" or "SSE-C"
+    pub encryption: Option<String>,
+    pub kms_key_id: Option<String>,
+    /// Algorithm and value, such as "SHA256 <base64>"
+    pub checksum: Option<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+/// Changes to apply to one or more S3 objects. A header left as `None` is
+/// kept and an empty one is removed; metadata and tag entries with a value
+/// are set, those without one are removed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ObjectPatch {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    /// Storage class to copy the object into
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    #[serde(default)]
+    pub metadata: Vec<(String, Option<String>)>,
+    #[serde(default)]
+    pub tags: Vec<(String, Option<String>)>,
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Directory where S3 Finder keeps its own state, created on first use.
+pub fn app_data_dir() -> Result<PathBuf, String> {
+    let dir = dirs::data_dir()
+        .ok_or("Cannot determine data directory")?
+        .join("s3-finder");
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+    Ok(dir)
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// Write through a temporary file so a crash never leaves a half-written file behind.
+// Only the user can read the file, as some hold secrets.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+
+    let tmp_path = path.with_extension("tmp");
+    // A file left by a crash keeps its permissions when opened, so start over
+    let _ = fs::remove_file(&tmp_path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn format_modified(time: SystemTime) -> String {
+    let datetime: DateTime<Utc> = time.into();
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn get_file_icon(filename: &str) -> String {
+    let extension = filename.split('.').next_back().unwrap_or("").to_lowercase();
+
+    match extension.as_str() {
+        "txt" | "md" | "rtf" => "document-text".to_string(),
+        "pdf" => "document".to_string(),
+        "doc" | "docx" => "document".to_string(),
+        "xls" | "xlsx" => "table".to_string(),
+        "ppt" | "pptx" => "presentation".to_string(),
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" => "photo".to_string(),
+        "mp4" | "avi" | "mov" | "wmv" | "flv" => "film".to_string(),
+        "mp3" | "wav" | "flac" | "aac" => "musical-note".to_string(),
+        "zip" | "rar" | "7z" | "tar" | "gz" => "archive-box".to_string(),
+        "exe" | "app" | "dmg" => "cog".to_string(),
+        "html" | "css" | "js" | "ts" | "json" => "code-bracket".to_string(),
+        "rs" | "py" | "java" | "cpp" | "c" => "code-bracket".to_string(),
+        _ => "document".to_string(),
+    }
+}
+
+pub fn get_file_type(extension: &str) -> String {
+    match extension {
+        // Text files
+        "txt" | "md" | "rtf" | "log" | "csv" | "xml" | "yaml" | "yml" | "toml" | "ini" | "conf" => {
+            "text".to_string()
+        }
+        "html" | "css" | "js" | "ts" | "json" | "jsx" | "tsx" => "text".to_string(),
+        "rs" | "py" | "java" | "cpp" | "c" | "h" | "hpp" | "go" | "php" | "rb" | "swift" => {
+            "text".to_string()
+        }
+        "sh" | "bash" | "zsh" | "fish" | "ps1" | "bat" | "cmd" => "text".to_string(),
+
+        // Image files
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "svg" | "ico" => "image".to_string(),
+        "tiff" | "tif" | "raw" | "cr2" | "nef" | "arw" => "image".to_string(),
+
+        // Other types not supported for preview
+        _ => "unsupported".to_string(),
+    }
+}
@@ -0,0 +1,178 @@
+// Keeps the vault key in the operating system's secret store through its
+// command line tools: `security` on macOS and `secret-tool` (libsecret) on
+// Linux. The key is always passed on stdin so it never shows up in `ps`.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+const SERVICE: &str = "s3-finder";
+const ACCOUNT: &str = "vault-key";
+
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Whether an OS secret store can be used on this machine.
+pub fn is_available() -> bool {
+    if cfg!(target_os = "macos") {
+        Path::new("/usr/bin/security").is_file()
+    } else if cfg!(target_os = "linux") {
+        // libsecret talks to the secret service over the session bus
+        in_path("secret-tool") && std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+    } else {
+        false
+    }
+}
+
+// Run a command with `input` on stdin and return its stdout
+fn run(program: &str, args: &[&str], input: &str) -> Result<Zeroizing<String>, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to talk to {}: {}", program, e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    let stdout = Zeroizing::new(String::from_utf8_lossy(&output.stdout).to_string());
+
+    if !output.status.success() {
+        return Err(format!(
+            "The system keychain refused the request: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(stdout)
+}
+
+// Quote one argument of a `security -i` command line, so that whatever it
+// holds stays a single argument. A line break would end the command, so none
+// is allowed.
+fn quote(arg: &str) -> Result<Zeroizing<String>, String> {
+    if arg.chars().any(char::is_control) {
+        return Err("The secret cannot be stored in the system keychain".to_string());
+    }
+
+    let mut quoted = Zeroizing::new(String::with_capacity(arg.len() + 2));
+    quoted.push('"');
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    Ok(quoted)
+}
+
+pub fn store(secret: &str) -> Result<(), String> {
+    if !is_available() {
+        return Err("No system keychain is available".to_string());
+    }
+
+    if cfg!(target_os = "macos") {
+        // In interactive mode `security` reads its commands from stdin
+        let command = Zeroizing::new(format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            quote(SERVICE)?.as_str(),
+            quote(ACCOUNT)?.as_str(),
+            quote(secret)?.as_str()
+        ));
+        run("/usr/bin/security", &["-i"], &command)?;
+    } else {
+        run(
+            "secret-tool",
+            &[
+                "store",
+                "--label=S3 Finder vault key",
+                "service",
+                SERVICE,
+                "account",
+                ACCOUNT,
+            ],
+            secret,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn load() -> Result<Zeroizing<String>, String> {
+    if !is_available() {
+        return Err("No system keychain is available".to_string());
+    }
+
+    let secret = if cfg!(target_os = "macos") {
+        run(
+            "/usr/bin/security",
+            &["find-generic-password", "-s", SERVICE, "-a", ACCOUNT, "-w"],
+            "",
+        )?
+    } else {
+        run(
+            "secret-tool",
+            &["lookup", "service", SERVICE, "account", ACCOUNT],
+            "",
+        )?
+    };
+
+    let secret = Zeroizing::new(secret.trim().to_string());
+    if secret.is_empty() {
+        return Err("The vault key is missing from the system keychain".to_string());
+    }
+
+    Ok(secret)
+}
+
+pub fn remove() -> Result<(), String> {
+    if !is_available() {
+        return Ok(());
+    }
+
+    // Removing a key that is already gone is not an error worth reporting
+    let _ = if cfg!(target_os = "macos") {
+        run(
+            "/usr/bin/security",
+            &["delete-generic-password", "-s", SERVICE, "-a", ACCOUNT],
+            "",
+        )
+    } else {
+        run(
+            "secret-tool",
+            &["clear", "service", SERVICE, "account", ACCOUNT],
+            "",
+        )
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_keeps_one_argument() {
+        assert_eq!(quote("plain").unwrap().as_str(), "\"plain\"");
+        assert_eq!(quote("a b").unwrap().as_str(), "\"a b\"");
+        assert_eq!(quote("x\" -w \"y").unwrap().as_str(), "\"x\\\" -w \\\"y\"");
+        assert_eq!(quote("back\\slash").unwrap().as_str(), "\"back\\\\slash\"");
+        assert!(quote("line\nbreak").is_err());
+        assert!(quote("nul\0").is_err());
+    }
+}
@@ -0,0 +1,563 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+use crate::utils::{app_data_dir, read_json, write_json};
+
+pub mod keychain;
+
+const VAULT_FILE: &str = "vault.json";
+
+/// Event emitted with the new [`VaultStatus`] when the vault locks by itself.
+pub const VAULT_EVENT: &str = "vault-updated";
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+// OWASP recommendation for PBKDF2-HMAC-SHA256
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// Keeps the tests quick; the count is stored with each vault
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+
+const DEFAULT_IDLE_TIMEOUT: u64 = 15 * 60;
+
+// Sealed into the vault file so a wrong passphrase is detected on unlock
+const VERIFIER: &[u8] = b"s3-finder-vault";
+
+/// Where the key that encrypts the vault comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum KeySource {
+    /// Derived from a master passphrase with PBKDF2-HMAC-SHA256
+    Passphrase { salt: String, iterations: u32 },
+    /// A random key kept in the operating system's secret store
+    Keychain,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultFile {
+    key_source: KeySource,
+    check: String,
+    idle_timeout_secs: u64,
+    /// Sealed secrets by name, as base64 of nonce and ciphertext
+    entries: BTreeMap<String, String>,
+}
+
+/// What the frontend may know about the vault.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub initialized: bool,
+    pub locked: bool,
+    /// "passphrase" or "keychain" once initialized
+    pub backend: Option<String>,
+    pub keychain_available: bool,
+    pub idle_timeout_secs: u64,
+}
+
+struct VaultState {
+    file: Option<VaultFile>,
+    key: Option<LessSafeKey>,
+    last_used: Instant,
+}
+
+/// Secrets encrypted at rest with AES-256-GCM. The key only lives in memory
+/// while the vault is unlocked and is dropped again after a period without use.
+pub struct Vault {
+    path: Option<PathBuf>,
+    state: Mutex<VaultState>,
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+
+    Ok(bytes)
+}
+
+fn make_key(bytes: &[u8]) -> Result<LessSafeKey, String> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| "Invalid vault key".to_string())
+}
+
+fn derive_key(passphrase: &str, salt: &str, iterations: u32) -> Result<LessSafeKey, String> {
+    let salt = general_purpose::STANDARD
+        .decode(salt)
+        .map_err(|e| format!("Failed to read vault salt: {}", e))?;
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid vault iteration count")?;
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        passphrase.as_bytes(),
+        key.as_mut(),
+    );
+
+    make_key(key.as_ref())
+}
+
+fn seal(key: &LessSafeKey, plaintext: &[u8]) -> Result<String, String> {
+    let nonce_bytes = random_bytes(NONCE_LEN)?;
+    let nonce = Nonce::try_assume_unique_for_key(&nonce_bytes)
+        .map_err(|_| "Failed to create nonce".to_string())?;
+
+    let mut data = plaintext.to_vec();
+    key.seal_in_place_append_tag(nonce, Aad::empty(), &mut data)
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    let mut sealed = nonce_bytes;
+    sealed.extend_from_slice(&data);
+
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+fn unseal(key: &LessSafeKey, sealed: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let sealed = general_purpose::STANDARD
+        .decode(sealed)
+        .map_err(|e| format!("Failed to read secret: {}", e))?;
+
+    if sealed.len() < NONCE_LEN {
+        return Err("Corrupt secret in vault".to_string());
+    }
+
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce_bytes)
+        .map_err(|_| "Corrupt secret in vault".to_string())?;
+
+    let mut data = Zeroizing::new(ciphertext.to_vec());
+    let plaintext_len = key
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| "Failed to decrypt secret".to_string())?
+        .len();
+    data.truncate(plaintext_len);
+
+    Ok(data)
+}
+
+impl Vault {
+    /// Open the vault file in the app data directory, locked.
+    pub fn open() -> Self {
+        Self::open_at(app_data_dir().ok().map(|dir| dir.join(VAULT_FILE)))
+    }
+
+    /// Open the vault file at `path`, locked. Without a path nothing can be
+    /// stored.
+    pub fn open_at(path: Option<PathBuf>) -> Self {
+        let file = path.as_ref().and_then(|path| read_json(path).ok());
+
+        Self {
+            path,
+            state: Mutex::new(VaultState {
+                file,
+                key: None,
+                last_used: Instant::now(),
+            }),
+        }
+    }
+
+    fn save(&self, file: &VaultFile) -> Result<(), String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or("Cannot determine data directory")?;
+        write_json(path, file)
+    }
+
+    fn lock_state(&self) -> Result<std::sync::MutexGuard<'_, VaultState>, String> {
+        self.state.lock().map_err(|e| e.to_string())
+    }
+
+    pub fn status(&self) -> Result<VaultStatus, String> {
+        let state = self.lock_state()?;
+
+        Ok(VaultStatus {
+            initialized: state.file.is_some(),
+            locked: state.key.is_none(),
+            backend: state.file.as_ref().map(|file| match file.key_source {
+                KeySource::Passphrase { .. } => "passphrase".to_string(),
+                KeySource::Keychain => "keychain".to_string(),
+            }),
+            keychain_available: keychain::is_available(),
+            idle_timeout_secs: state
+                .file
+                .as_ref()
+                .map(|file| file.idle_timeout_secs)
+                .unwrap_or(DEFAULT_IDLE_TIMEOUT),
+        })
+    }
+
+    /// Create an empty vault and leave it unlocked. Without a passphrase the
+    /// key is generated randomly and kept in the OS secret store.
+    pub fn create(&self, passphrase: Option<&str>) -> Result<(), String> {
+        let mut state = self.lock_state()?;
+
+        if state.file.is_some() {
+            return Err("The credential vault already exists".to_string());
+        }
+
+        let (key_source, key) = match passphrase {
+            Some(passphrase) => {
+                if passphrase.chars().count() < 8 {
+                    return Err("The passphrase must be at least 8 characters".to_string());
+                }
+                let salt = general_purpose::STANDARD.encode(random_bytes(SALT_LEN)?);
+                let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+                (
+                    KeySource::Passphrase {
+                        salt,
+                        iterations: PBKDF2_ITERATIONS,
+                    },
+                    key,
+                )
+            }
+            None => {
+                let bytes = Zeroizing::new(random_bytes(KEY_LEN)?);
+                keychain::store(&Zeroizing::new(general_purpose::STANDARD.encode(&*bytes)))?;
+                (KeySource::Keychain, make_key(&bytes)?)
+            }
+        };
+
+        let file = VaultFile {
+            key_source,
+            check: seal(&key, VERIFIER)?,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT,
+            entries: BTreeMap::new(),
+        };
+        self.save(&file)?;
+
+        state.file = Some(file);
+        state.key = Some(key);
+        state.last_used = Instant::now();
+
+        Ok(())
+    }
+
+    /// Unlock with the master passphrase, or from the OS secret store when the
+    /// vault was created without one.
+    pub fn unlock(&self, passphrase: Option<&str>) -> Result<(), String> {
+        let mut state = self.lock_state()?;
+        let file = state
+            .file
+            .as_ref()
+            .ok_or("The credential vault has not been set up")?;
+
+        let key = match (&file.key_source, passphrase) {
+            (KeySource::Passphrase { salt, iterations }, Some(passphrase)) => {
+                derive_key(passphrase, salt, *iterations)?
+            }
+            (KeySource::Passphrase { .. }, None) => {
+                return Err("Enter the master passphrase".to_string())
+            }
+            (KeySource::Keychain, _) => {
+                let encoded = keychain::load()?;
+                let bytes = Zeroizing::new(
+                    general_purpose::STANDARD
+                        .decode(encoded.as_bytes())
+                        .map_err(|e| format!("Failed to read vault key: {}", e))?,
+                );
+                make_key(&bytes)?
+            }
+        };
+
+        if unseal(&key, &file.check).map(|check| check.as_slice() == VERIFIER) != Ok(true) {
+            return Err("Wrong passphrase".to_string());
+        }
+
+        state.key = Some(key);
+        state.last_used = Instant::now();
+
+        Ok(())
+    }
+
+    pub fn lock(&self) -> Result<(), String> {
+        self.lock_state()?.key = None;
+        Ok(())
+    }
+
+    /// Lock the vault when it has not been used for its idle timeout. Returns
+    /// whether it was locked by this call.
+    pub fn lock_if_idle(&self) -> Result<bool, String> {
+        let mut state = self.lock_state()?;
+        let timeout = state
+            .file
+            .as_ref()
+            .map(|file| file.idle_timeout_secs)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT);
+
+        if state.key.is_some() && state.last_used.elapsed() >= Duration::from_secs(timeout) {
+            state.key = None;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    pub fn set_idle_timeout(&self, secs: u64) -> Result<(), String> {
+        let mut state = self.lock_state()?;
+        let file = state
+            .file
+            .as_mut()
+            .ok_or("The credential vault has not been set up")?;
+
+        // Between a minute and a day
+        file.idle_timeout_secs = secs.clamp(60, 24 * 60 * 60);
+        self.save(file)
+    }
+
+    /// Delete the vault and every secret in it, for when the passphrase is lost.
+    pub fn reset(&self) -> Result<(), String> {
+        let mut state = self.lock_state()?;
+
+        if matches!(
+            state.file.as_ref().map(|file| &file.key_source),
+            Some(KeySource::Keychain)
+        ) {
+            keychain::remove()?;
+        }
+
+        if let Some(path) = &self.path {
+            match fs::remove_file(path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to remove vault: {}", e)),
+            }
+        }
+
+        state.file = None;
+        state.key = None;
+
+        Ok(())
+    }
+
+    // Run `f` with the key and the vault file, counting it as use of the vault
+    fn with_key<T>(
+        &self,
+        f: impl FnOnce(&LessSafeKey, &mut VaultFile) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut state = self.lock_state()?;
+        let state = &mut *state;
+
+        let file = state
+            .file
+            .as_mut()
+            .ok_or("The credential vault has not been set up")?;
+        let key = state.key.as_ref().ok_or("The credential vault is locked")?;
+
+        state.last_used = Instant::now();
+        f(key, file)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Zeroizing<String>>, String> {
+        self.with_key(|key, file| {
+            let Some(sealed) = file.entries.get(name) else {
+                return Ok(None);
+            };
+
+            let plaintext = unseal(key, sealed)?;
+            let secret = String::from_utf8(plaintext.to_vec())
+                .map_err(|_| "Corrupt secret in vault".to_string())?;
+
+            Ok(Some(Zeroizing::new(secret)))
+        })
+    }
+
+    pub fn set(&self, name: &str, secret: &str) -> Result<(), String> {
+        self.with_key(|key, file| {
+            file.entries
+                .insert(name.to_string(), seal(key, secret.as_bytes())?);
+            self.save(file)
+        })
+    }
+
+    /// Whether a secret is stored under `name`. This works while locked.
+    pub fn contains(&self, name: &str) -> bool {
+        self.lock_state().is_ok_and(|state| {
+            state
+                .file
+                .as_ref()
+                .is_some_and(|file| file.entries.contains_key(name))
+        })
+    }
+
+    /// Forget a secret. This works while locked, as entries are found by name.
+    pub fn remove(&self, name: &str) -> Result<(), String> {
+        let mut state = self.lock_state()?;
+        let Some(file) = state.file.as_mut() else {
+            return Ok(());
+        };
+
+        if file.entries.remove(name).is_some() {
+            self.save(file)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(dir: &tempfile::TempDir) -> Vault {
+        Vault::open_at(Some(dir.path().join(VAULT_FILE)))
+    }
+
+    #[test]
+    fn seal_and_unseal() {
+        let key = make_key(&random_bytes(KEY_LEN).unwrap()).unwrap();
+        let other = make_key(&random_bytes(KEY_LEN).unwrap()).unwrap();
+
+        let sealed = seal(&key, b"secret").unwrap();
+        assert_eq!(unseal(&key, &sealed).unwrap().as_slice(), b"secret");
+        // A fresh nonce every time
+        assert_ne!(seal(&key, b"secret").unwrap(), sealed);
+
+        assert!(unseal(&other, &sealed).is_err());
+        assert!(unseal(&key, "AAAA").is_err());
+        assert!(unseal(&key, "not base64!").is_err());
+    }
+
+    #[test]
+    fn derived_keys_depend_on_passphrase_and_salt() {
+        let salt = general_purpose::STANDARD.encode([1u8; SALT_LEN]);
+        let other_salt = general_purpose::STANDARD.encode([2u8; SALT_LEN]);
+        let key = derive_key("correct horse", &salt, 1_000).unwrap();
+        let sealed = seal(&key, VERIFIER).unwrap();
+
+        let same = derive_key("correct horse", &salt, 1_000).unwrap();
+        assert_eq!(unseal(&same, &sealed).unwrap().as_slice(), VERIFIER);
+        assert!(unseal(&derive_key("wrong horse", &salt, 1_000).unwrap(), &sealed).is_err());
+        assert!(unseal(
+            &derive_key("correct horse", &other_salt, 1_000).unwrap(),
+            &sealed
+        )
+        .is_err());
+        assert!(derive_key("correct horse", &salt, 0).is_err());
+    }
+
+    #[test]
+    fn create_lock_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = vault(&dir);
+
+        assert!(vault.create(Some("short")).is_err());
+        vault.create(Some("a long passphrase")).unwrap();
+        assert!(vault.create(Some("a long passphrase")).is_err());
+        vault.set("account", "secret key").unwrap();
+        assert_eq!(
+            vault.get("account").unwrap().as_deref().map(String::as_str),
+            Some("secret key")
+        );
+
+        vault.lock().unwrap();
+        assert!(vault.status().unwrap().locked);
+        assert!(vault.get("account").is_err());
+        assert!(vault.set("other", "value").is_err());
+        // Names are known without the key
+        assert!(vault.contains("account"));
+
+        assert_eq!(
+            vault.unlock(Some("wrong passphrase")),
+            Err("Wrong passphrase".to_string())
+        );
+        assert!(vault.unlock(None).is_err());
+        assert!(vault.status().unwrap().locked);
+
+        vault.unlock(Some("a long passphrase")).unwrap();
+        assert_eq!(
+            vault.get("account").unwrap().as_deref().map(String::as_str),
+            Some("secret key")
+        );
+        assert_eq!(vault.get("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn reopen_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = vault(&dir);
+        first.create(Some("a long passphrase")).unwrap();
+        first.set("account", "secret key").unwrap();
+
+        let contents = fs::read_to_string(dir.path().join(VAULT_FILE)).unwrap();
+        assert!(!contents.contains("secret key"));
+
+        let second = vault(&dir);
+        let status = second.status().unwrap();
+        assert!(status.initialized && status.locked);
+        assert_eq!(status.backend.as_deref(), Some("passphrase"));
+        assert!(second.unlock(Some("wrong passphrase")).is_err());
+        second.unlock(Some("a long passphrase")).unwrap();
+        assert_eq!(
+            second
+                .get("account")
+                .unwrap()
+                .as_deref()
+                .map(String::as_str),
+            Some("secret key")
+        );
+
+        second.remove("account").unwrap();
+        assert!(!vault(&dir).contains("account"));
+    }
+
+    #[test]
+    fn locks_when_idle() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = vault(&dir);
+        vault.create(Some("a long passphrase")).unwrap();
+
+        assert!(!vault.lock_if_idle().unwrap());
+
+        let idle = Duration::from_secs(DEFAULT_IDLE_TIMEOUT);
+        let Some(long_ago) = Instant::now().checked_sub(idle) else {
+            return;
+        };
+        vault.state.lock().unwrap().last_used = long_ago;
+        assert!(vault.lock_if_idle().unwrap());
+        assert!(vault.status().unwrap().locked);
+        assert!(!vault.lock_if_idle().unwrap());
+    }
+
+    #[test]
+    fn reset_forgets_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = vault(&dir);
+        vault.create(Some("a long passphrase")).unwrap();
+        vault.set("account", "secret key").unwrap();
+
+        vault.reset().unwrap();
+        assert!(!vault.status().unwrap().initialized);
+        assert!(!dir.path().join(VAULT_FILE).exists());
+        assert!(!vault.contains("account"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn vault_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        vault(&dir).create(Some("a long passphrase")).unwrap();
+
+        let mode = fs::metadata(dir.path().join(VAULT_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
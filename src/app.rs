@@ -6,8 +6,31 @@ use leptos::{
 use wasm_bindgen::JsValue;
 
 // Import our modules
+use crate::components::accounts_dialog::AccountsDialog;
+use crate::components::bucket_dialogs::{CreateBucketDialog, DeleteBucketDialog};
+use crate::components::bucket_settings::BucketSettings;
+use crate::components::encryption_dialog::EncryptionDialog;
 use crate::components::file_icon::FileIcon;
+use crate::components::inspector_panel::InspectorPanel;
+use crate::components::mfa_dialog::MfaDialog;
+use crate::components::presign_dialog::PresignDialog;
+use crate::components::storage_class_dialog::{
+    storage_class_label, RestoreDialog, StorageClassDialog,
+};
+use crate::components::sync_dialog::SyncDialog;
+use crate::components::transfers_panel::{upsert_delete, upsert_job, TransfersPanel};
+use crate::components::trash::TrashDialog;
+use crate::components::vault_dialog::VaultDialog;
+use crate::components::watch_folders::{WatchFolderDialog, WatchFolders};
+use crate::services::account_service::{account_of, is_mfa_error, list_accounts};
 use crate::services::file_service::*;
+use crate::services::object_service::listen_restore_updates;
+use crate::services::transfer_service::{
+    delete_items, list_deletes, list_transfers, listen_delete_updates, listen_transfer_updates,
+    queue_transfer,
+};
+use crate::services::trash_service::{move_to_trash, restore_from_trash};
+use crate::services::vault_service::{get_vault_status, is_vault_error, listen_vault_updates};
 use crate::types::*;
 use crate::utils::format::format_file_size;
 use crate::utils::tauri::{invoke, is_tauri_available};
@@ -16,6 +39,7 @@ use crate::utils::tauri::{invoke, is_tauri_available};
 pub struct ColumnData {
     pub path: String,
     pub contents: DirectoryContents,
+    pub versions: bool, // listing every object version instead of the current objects
 }
 
 #[component]
@@ -23,6 +47,7 @@ pub fn App() -> impl IntoView {
     let (current_path, set_current_path) = signal(String::new());
     let (columns, set_columns) = signal(Vec::<ColumnData>::new());
     let (loading, set_loading) = signal(false);
+    let (loading_more, set_loading_more) = signal(false);
     let (error_msg, set_error_msg) = signal(Option::<String>::None);
     let (sidebar_collapsed, set_sidebar_collapsed) = signal(false);
     let (selected_item, set_selected_item) = signal(Option::<String>::None);
@@ -40,6 +65,51 @@ pub fn App() -> impl IntoView {
     // Copy/Move states
     let (clipboard_item, set_clipboard_item) = signal(Option::<String>::None);
     let (clipboard_operation, set_clipboard_operation) = signal(Option::<String>::None); // "copy" or "cut"
+    let (clipboard_version, set_clipboard_version) = signal(Option::<String>::None);
+
+    // Object versions states
+    let (selected_version, set_selected_version) = signal(Option::<String>::None);
+    let (confirm_delete_version, set_confirm_delete_version) =
+        signal(Option::<(usize, FileItem)>::None);
+
+    // Multi-selection within one column (Cmd/Ctrl-click) and the inspector
+    // opened on it: column, object paths and an older version to show
+    let (selected_paths, set_selected_paths) = signal(Vec::<String>::new());
+    let (inspector, set_inspector) = signal(Option::<(usize, Vec<String>, Option<String>)>::None);
+
+    // Object or folder to sign a share or upload link for
+    let (presign_target, set_presign_target) = signal(Option::<(String, bool)>::None);
+    let (bucket_settings, set_bucket_settings) = signal(Option::<String>::None);
+    // Bucket list a new bucket goes into, with the region it defaults to
+    let (new_bucket_root, set_new_bucket_root) = signal(Option::<(String, Option<String>)>::None);
+    let (delete_bucket_target, set_delete_bucket_target) = signal(Option::<String>::None);
+    // Account whose encryption settings are open: any path in it and its name
+    let (encryption_target, set_encryption_target) = signal(Option::<(String, String)>::None);
+    // Objects and folders to move to another storage class or restore
+    let (storage_class_target, set_storage_class_target) = signal(Option::<Vec<String>>::None);
+    let (restore_target, set_restore_target) = signal(Option::<Vec<String>>::None);
+    // Local folder and S3 prefix to sync, as far as they are known
+    let (sync_target, set_sync_target) = signal(Option::<(String, String)>::None);
+    // Local folder and S3 prefix to start watching
+    let (watch_target, set_watch_target) = signal(Option::<(String, String)>::None);
+
+    // Transfer queue states
+    let (transfers, set_transfers) = signal(Vec::<TransferJob>::new());
+    let (deletes, set_deletes) = signal(Vec::<DeleteJob>::new());
+    // Selection waiting for the user to confirm a batch delete
+    let (confirm_delete, set_confirm_delete) = signal(Option::<Vec<String>>::None);
+    let (show_transfers, set_show_transfers) = signal(false);
+    // Items the last delete moved to the Trash, until it is undone or dismissed
+    let (trashed, set_trashed) = signal(Option::<Vec<TrashItem>>::None);
+    let (show_trash, set_show_trash) = signal(false);
+
+    // S3 account states
+    let (accounts, set_accounts) = signal(Vec::<Account>::new());
+    let (show_accounts_dialog, set_show_accounts_dialog) = signal(false);
+    let (vault_status, set_vault_status) = signal(VaultStatus::default());
+    let (show_vault_dialog, set_show_vault_dialog) = signal(false);
+    // Path and column to load again once a role session has been started
+    let (mfa_retry, set_mfa_retry) = signal(Option::<(String, Option<usize>)>::None);
 
     // Search states
     let (search_query, set_search_query) = signal(String::new());
@@ -72,6 +142,7 @@ pub fn App() -> impl IntoView {
                     "read_directory",
                     serde_wasm_bindgen::to_value(&ReadDirArgs {
                         path: path_clone.clone(),
+                        continuation_token: None,
                     })
                     .unwrap(),
                 )
@@ -87,6 +158,7 @@ pub fn App() -> impl IntoView {
                                         cols.push(ColumnData {
                                             path: path_clone.clone(),
                                             contents,
+                                            versions: false,
                                         });
                                         index
                                     } else {
@@ -94,6 +166,7 @@ pub fn App() -> impl IntoView {
                                         cols.push(ColumnData {
                                             path: path_clone.clone(),
                                             contents,
+                                            versions: false,
                                         });
                                         cols.len() - 1
                                     }
@@ -126,7 +199,14 @@ pub fn App() -> impl IntoView {
                         }
                     }
                     Err(e) => {
-                        set_error_msg.set(Some(format!("Failed to load directory: {e:?}")));
+                        let message = format!("Failed to load directory: {e:?}");
+                        // Account secrets are unavailable until the vault is unlocked
+                        if is_vault_error(&message) {
+                            set_show_vault_dialog.set(true);
+                        } else if is_mfa_error(&message) {
+                            set_mfa_retry.set(Some((path_clone.clone(), column_index)));
+                        }
+                        set_error_msg.set(Some(message));
                     }
                 }
             } else {
@@ -146,6 +226,12 @@ pub fn App() -> impl IntoView {
                             size: None,
                             modified: Some("2024-01-15".to_string()),
                             icon: "folder".to_string(),
+                            version_id: None,
+                            is_latest: false,
+                            is_delete_marker: false,
+                            storage_class: None,
+                            archived: false,
+                            restoring: false,
                         },
                         FileItem {
                             name: "example.txt".to_string(),
@@ -154,8 +240,15 @@ pub fn App() -> impl IntoView {
                             size: Some(1024),
                             modified: Some("2024-01-15".to_string()),
                             icon: "text".to_string(),
+                            version_id: None,
+                            is_latest: false,
+                            is_delete_marker: false,
+                            storage_class: None,
+                            archived: false,
+                            restoring: false,
                         },
                     ],
+                    next_token: None,
                 };
 
                 let new_col_index = set_columns.update_untracked(|cols| {
@@ -164,12 +257,14 @@ pub fn App() -> impl IntoView {
                         cols.push(ColumnData {
                             path: path_clone.clone(),
                             contents: mock_contents,
+                            versions: false,
                         });
                         index
                     } else {
                         cols.push(ColumnData {
                             path: path_clone.clone(),
                             contents: mock_contents,
+                            versions: false,
                         });
                         cols.len() - 1
                     }
@@ -235,29 +330,39 @@ pub fn App() -> impl IntoView {
     Effect::new(move |_| {
         if let Some(selected_path) = selected_item.get() {
             // Check if the selected item is a file (not a directory)
-            let is_file = if let Some(col_index) = selected_column_index.get() {
-                if let Some(column) = columns.get().get(col_index) {
+            let file = if let Some(col_index) = selected_column_index.get() {
+                columns.get().get(col_index).and_then(|column| {
                     column
                         .contents
                         .items
                         .iter()
                         .find(|item| item.path == selected_path)
-                        .map(|item| !item.is_dir)
-                        .unwrap_or(false)
-                } else {
-                    false
-                }
+                        .filter(|item| !item.is_dir && item.version_id.is_none())
+                        .cloned()
+                })
             } else if let Some(search_results) = search_results.get() {
                 search_results
                     .iter()
                     .find(|item| item.path == selected_path)
-                    .map(|item| !item.is_dir)
-                    .unwrap_or(false)
+                    .filter(|item| !item.is_dir && item.version_id.is_none())
+                    .cloned()
             } else {
-                false
+                None
             };
 
-            if is_file {
+            if let Some(file) = file.as_ref().filter(|file| file.archived) {
+                // Archived objects cannot be read until a copy is restored
+                set_preview_content.set(None);
+                set_preview_loading.set(false);
+                set_preview_error.set(Some(if file.restoring {
+                    "This object is being restored from the archive. The preview is available once the restore finishes.".to_string()
+                } else {
+                    format!(
+                        "This object is archived in {}. Restore it to preview its contents.",
+                        storage_class_label(file.storage_class.as_deref().unwrap_or("GLACIER"))
+                    )
+                }));
+            } else if file.is_some() {
                 // Only preview files, not directories
                 spawn_local(async move {
                     preview_file(
@@ -313,6 +418,371 @@ pub fn App() -> impl IntoView {
         }
     };
 
+    // Every configured account gets its own sidebar section
+    spawn_local(async move {
+        match list_accounts().await {
+            Ok(list) => set_accounts.set(list),
+            Err(e) => set_error_msg.set(Some(e)),
+        }
+    });
+
+    // Follow the vault state, it locks itself after being idle
+    spawn_local(async move {
+        if let Ok(status) = get_vault_status().await {
+            set_vault_status.set(status);
+        }
+
+        if let Err(e) = listen_vault_updates(move |status| set_vault_status.set(status)).await {
+            set_error_msg.set(Some(e));
+        }
+    });
+
+    // Keep the transfers panel in sync with the backend queue
+    spawn_local(async move {
+        if let Ok(jobs) = list_transfers().await {
+            set_transfers.set(jobs);
+        }
+
+        let result = listen_transfer_updates(move |job| {
+            // Show the new item as soon as a transfer into the open folder is done
+            if job.status == "completed" && job.dest_dir == current_path.get_untracked() {
+                refresh_current_column();
+            }
+            set_transfers.update(|jobs| upsert_job(jobs, job));
+        })
+        .await;
+
+        if let Err(e) = result {
+            set_error_msg.set(Some(e));
+        }
+    });
+
+    // Deletes run in the background too; the open folder changes once one ends
+    spawn_local(async move {
+        if let Ok(jobs) = list_deletes().await {
+            set_deletes.set(jobs);
+        }
+
+        let result = listen_delete_updates(move |job| {
+            if job.status != "running" {
+                refresh_current_column();
+            }
+            set_deletes.update(|deletes| upsert_delete(deletes, job));
+        })
+        .await;
+
+        if let Err(e) = result {
+            set_error_msg.set(Some(e));
+        }
+    });
+
+    // Restores take hours; the backend reports each object once it is readable
+    spawn_local(async move {
+        let result = listen_restore_updates(move |item| {
+            let restored = columns.with_untracked(|cols| {
+                cols.iter()
+                    .any(|column| column.contents.items.iter().any(|i| i.path == item.path))
+            });
+            if restored {
+                refresh_current_column();
+            }
+        })
+        .await;
+
+        if let Err(e) = result {
+            set_error_msg.set(Some(e));
+        }
+    });
+
+    // Watch the local folders shown as columns; S3 columns are left as listed
+    let watched_dirs = Memo::new(move |_| {
+        // Loads replace columns without notifying, but always set the path
+        current_path.track();
+        columns.with(|cols| {
+            cols.iter()
+                .filter(|column| !column.versions && !column.contents.current_path.starts_with("s3://"))
+                .map(|column| column.contents.current_path.clone())
+                .collect::<Vec<_>>()
+        })
+    });
+    Effect::new(move |_| {
+        let paths = watched_dirs.get();
+        spawn_local(async move {
+            let _ = watch_directories(paths).await;
+        });
+    });
+
+    // Scroll offsets of the shown columns, by column path
+    let column_scroll_tops = move || -> Vec<(String, i32)> {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return Vec::new();
+        };
+        columns.with_untracked(|cols| {
+            cols.iter()
+                .enumerate()
+                .filter_map(|(index, column)| {
+                    let selector = format!(".file-column:nth-child({}) .file-list-body", index + 1);
+                    let body = document.query_selector(&selector).ok().flatten()?;
+                    Some((column.path.clone(), body.scroll_top()))
+                })
+                .collect()
+        })
+    };
+
+    // Put the columns that are still shown back where they were, once re-rendered
+    let restore_scroll_tops = move |scroll_tops: Vec<(String, i32)>| {
+        spawn_local(async move {
+            // Wait for the DOM to update
+            wasm_bindgen_futures::JsFuture::from(js_sys::Promise::new(&mut |resolve, _| {
+                web_sys::window()
+                    .unwrap()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 0)
+                    .unwrap();
+            }))
+            .await
+            .unwrap();
+
+            let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+                return;
+            };
+            let paths: Vec<String> =
+                columns.with_untracked(|cols| cols.iter().map(|column| column.path.clone()).collect());
+            for (path, scroll_top) in scroll_tops {
+                let Some(index) = paths.iter().position(|p| *p == path) else {
+                    continue;
+                };
+                let selector = format!(".file-column:nth-child({}) .file-list-body", index + 1);
+                if let Some(body) = document.query_selector(&selector).ok().flatten() {
+                    body.set_scroll_top(scroll_top);
+                }
+            }
+        });
+    };
+
+    // Update the watched columns in place as their folders change
+    spawn_local(async move {
+        let result = listen_directory_changes(move |change| {
+            let scroll_tops = column_scroll_tops();
+
+            let removed = change.kind == "removed";
+            let mut truncated = None;
+            set_columns.update(|cols| {
+                for column in cols.iter_mut() {
+                    if !column.versions && column.contents.current_path == change.dir {
+                        column.contents.apply_change(&change);
+                    }
+                }
+                // A removed folder takes the columns showing it along
+                if removed {
+                    if let Some(index) = cols.iter().position(|column| column.contents.current_path == change.path) {
+                        cols.truncate(index);
+                        truncated = cols.last().map(|column| (cols.len() - 1, column.path.clone()));
+                    }
+                }
+            });
+
+            if removed {
+                if selected_item.get_untracked().as_deref() == Some(change.path.as_str()) {
+                    set_selected_item.set(None);
+                }
+                set_selected_paths.update(|paths| paths.retain(|path| *path != change.path));
+            }
+            if let Some((last_index, path)) = truncated {
+                set_current_path.set(path);
+                set_selected_column_index.set(Some(last_index));
+            }
+
+            // The columns are re-rendered, so restore where the user was
+            restore_scroll_tops(scroll_tops);
+        })
+        .await;
+
+        if let Err(e) = result {
+            set_error_msg.set(Some(e));
+        }
+    });
+
+    // Fetch the next page of a column once the user scrolls near its end
+    let load_more_column = move |col_index: usize, scroll_top: i32| {
+        if loading_more.get_untracked() {
+            return;
+        }
+
+        let Some(column) = columns.get_untracked().get(col_index).cloned() else {
+            return;
+        };
+        let Some(token) = column.contents.next_token.clone() else {
+            return;
+        };
+
+        set_loading_more.set(true);
+        spawn_local(async move {
+            let page = if column.versions {
+                read_versions_page(column.path.clone(), Some(token)).await
+            } else {
+                read_directory_page(column.path.clone(), Some(token)).await
+            };
+            match page {
+                Ok(page) => {
+                    set_columns.update(|cols| {
+                        if let Some(col) = cols.get_mut(col_index) {
+                            // Ignore the page if the column was navigated away meanwhile
+                            if col.path == column.path {
+                                col.contents.append_page(page);
+                            }
+                        }
+                    });
+
+                    // The columns are re-rendered, so restore where the user was
+                    restore_scroll_tops(vec![(column.path.clone(), scroll_top)]);
+                }
+                Err(e) => {
+                    set_error_msg.set(Some(e));
+                }
+            }
+            set_loading_more.set(false);
+        });
+    };
+
+    // Switch a column between the current objects and every object version
+    let set_column_versions = move |col_index: usize, versions: bool| {
+        let Some(column) = columns.get_untracked().get(col_index).cloned() else {
+            return;
+        };
+
+        set_loading.set(true);
+        spawn_local(async move {
+            let result = if versions {
+                read_versions_page(column.path.clone(), None).await
+            } else {
+                read_directory_page(column.path.clone(), None).await
+            };
+
+            match result {
+                Ok(contents) => {
+                    set_columns.update(|cols| {
+                        cols.truncate(col_index + 1);
+                        if let Some(col) = cols.get_mut(col_index) {
+                            col.contents = contents;
+                            col.versions = versions;
+                        }
+                    });
+                    set_selected_item.set(None);
+                    set_selected_version.set(None);
+                }
+                Err(e) => set_error_msg.set(Some(e)),
+            }
+            set_loading.set(false);
+        });
+    };
+
+    // Run a version action, then list the versions of the column again
+    let run_version_action = move |col_index: usize, action: &'static str, item: FileItem| {
+        let Some(version_id) = item.version_id.clone() else {
+            return;
+        };
+
+        spawn_local(async move {
+            let result = match action {
+                "restore" => restore_version(item.path.clone(), version_id).await,
+                _ => delete_version(item.path.clone(), version_id).await,
+            };
+
+            match result {
+                Ok(()) => set_column_versions(col_index, true),
+                Err(e) => set_error_msg.set(Some(e)),
+            }
+        });
+    };
+
+    // The version the context menu acts on, with its column
+    let selected_version_item = move || {
+        let version_id = selected_version.get()?;
+        let path = selected_item.get()?;
+        let col_index = selected_column_index.get()?;
+        columns.with(|cols| {
+            cols.get(col_index)?
+                .contents
+                .items
+                .iter()
+                .find(|item| item.path == path && item.version_id.as_ref() == Some(&version_id))
+                .cloned()
+                .map(|item| (col_index, item))
+        })
+    };
+
+    // The selected item, looked up in the column it was picked in
+    let selected_entry = move || {
+        let path = selected_item.get()?;
+        let col_index = selected_column_index.get()?;
+        columns.with(|cols| {
+            cols.get(col_index)?
+                .contents
+                .items
+                .iter()
+                .find(|item| item.path == path)
+                .cloned()
+        })
+    };
+
+    // S3 objects the inspector works on: the multi-selection, or else the selected item
+    let inspectable_paths = move || {
+        let Some(col_index) = selected_column_index.get() else {
+            return Vec::new();
+        };
+        let mut paths = selected_paths.get();
+        if paths.is_empty() {
+            paths.extend(selected_item.get());
+        }
+
+        columns.with(|cols| {
+            let Some(column) = cols.get(col_index) else {
+                return Vec::new();
+            };
+            paths
+                .into_iter()
+                .filter(|path| {
+                    path.starts_with("s3://")
+                        && column.contents.items.iter().any(|item| {
+                            &item.path == path && !item.is_dir && !item.is_delete_marker
+                        })
+                })
+                .collect()
+        })
+    };
+
+    // S3 objects and folders in the selection, for storage class changes
+    let storage_class_paths = move || {
+        if selected_version.get().is_some() {
+            return Vec::new();
+        }
+        let mut paths = selected_paths.get();
+        if paths.is_empty() {
+            paths.extend(selected_item.get());
+        }
+
+        paths
+            .into_iter()
+            .filter(|path| is_s3_bucket_path(path) && !is_s3_bucket_root(path))
+            .collect::<Vec<_>>()
+    };
+
+    let open_inspector = move || {
+        let (Some(col_index), paths) = (selected_column_index.get_untracked(), inspectable_paths())
+        else {
+            return;
+        };
+        if paths.is_empty() {
+            return;
+        }
+
+        // Older versions are shown read-only, the latest one is the object itself
+        let version_id = selected_version_item()
+            .filter(|(_, item)| paths.len() == 1 && !item.is_latest)
+            .and_then(|(_, item)| item.version_id);
+        set_inspector.set(Some((col_index, paths, version_id)));
+    };
+
     // Navigation helper functions
     // Scroll to focused item
     let scroll_to_focused_item = move || {
@@ -552,6 +1022,7 @@ pub fn App() -> impl IntoView {
                         });
                     } else {
                         // Select file
+                        set_selected_version.set(item.version_id.clone());
                         set_selected_item.set(Some(path));
                         set_selected_column_index.set(Some(col_index));
                     }
@@ -560,11 +1031,48 @@ pub fn App() -> impl IntoView {
         }
     };
 
+    // The selection a context menu action applies to
+    let context_paths = move || {
+        let mut paths = selected_paths.get();
+        if paths.is_empty() {
+            paths.extend(selected_item.get());
+        }
+        paths
+    };
+    let local_selection = move || {
+        let paths = context_paths();
+        !paths.is_empty() && paths.iter().all(|path| !path.starts_with("s3://"))
+    };
+
+    // Put what the last delete moved to the Trash back
+    let undo_trash = move || {
+        let Some(items) = trashed.get_untracked() else {
+            return;
+        };
+        set_trashed.set(None);
+        spawn_local(async move {
+            let ids = items.into_iter().map(|item| item.id).collect();
+            match restore_from_trash(ids).await {
+                Ok(restored) if restored.errors.is_empty() => {}
+                Ok(restored) => set_error_msg.set(Some(format!(
+                    "Some items could not be put back: {}",
+                    restored.errors.join("; ")
+                ))),
+                Err(e) => set_error_msg.set(Some(e)),
+            }
+            refresh_current_column();
+        });
+    };
+
     // Keyboard navigation handlers
     let handle_keyboard_navigation = move |e: KeyboardEvent| {
         let key = e.key();
 
         match key.as_str() {
+            "z" if e.ctrl_key() || e.meta_key() => {
+                e.prevent_default();
+                undo_trash();
+            }
             "ArrowUp" => {
                 e.prevent_default();
                 navigate_up();
@@ -755,6 +1263,26 @@ pub fn App() -> impl IntoView {
                         }}
                     </button>
 
+                    <button
+                        class="toolbar-btn transfers-btn"
+                        class:active=move || show_transfers.get()
+                        on:click=move |_| set_show_transfers.update(|show| *show = !*show)
+                        title="Transfers"
+                    >
+                        <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                            <path d="M16 17.01V10h-2v7.01h-3L15 21l4-3.99h-3zM9 3L5 6.99h3V14h2V6.99h3L9 3z"/>
+                        </svg>
+                        {move || {
+                            let active = transfers
+                                .get()
+                                .iter()
+                                .filter(|job| job.status == "running" || job.status == "queued")
+                                .count()
+                                + deletes.with(|deletes| deletes.iter().filter(|job| job.status == "running").count());
+                            (active > 0).then(|| view! { <span class="transfers-badge">{active}</span> })
+                        }}
+                    </button>
+
                     <button class="toolbar-btn view-btn active">
                         <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
                             <path d="M4 6H2v14c0 1.1.9 2 2 2h14v-2H4V6zm16-4H8c-1.1 0-2 .9-2 2v12c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V4c0-1.1-.9-2-2-2zm-1 9H9V9h10v2zm-4 4H9v-2h6v2zm4-8H9V5h10v2z"/>
@@ -803,7 +1331,130 @@ pub fn App() -> impl IntoView {
                             </svg>
                             <span>"Users"</span>
                         </div>
+                        <div class="sidebar-item" on:click=move |_| set_show_trash.set(true)>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M6 19c0 1.1.9 2 2 2h8c1.1 0 2-.9 2-2V7H6v12zM19 4h-3.5l-1-1h-5l-1 1H5v2h14V4z"/>
+                            </svg>
+                            <span>"Trash"</span>
+                        </div>
                     </div>
+                    <div class="sidebar-section">
+                        <div class="sidebar-title">"S3"</div>
+                        <div class="sidebar-item" on:click=move |_| navigate_to("s3://".to_string())>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M19.35 10.04C18.67 6.59 15.64 4 12 4 9.11 4 6.6 5.64 5.35 8.04 2.34 8.36 0 10.91 0 14c0 3.31 2.69 6 6 6h13c2.76 0 5-2.24 5-5 0-2.64-2.05-4.78-4.65-4.96z"/>
+                            </svg>
+                            <span>"Buckets"</span>
+                        </div>
+                        <div class="sidebar-item" on:click=move |_| set_new_bucket_root.set(Some(("s3://".to_string(), None)))>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M20 6h-8l-2-2H4c-1.11 0-1.99.89-1.99 2L2 18c0 1.11.89 2 2 2h16c1.11 0 2-.89 2-2V8c0-1.11-.89-2-2-2zm-1 8h-3v3h-2v-3h-3v-2h3V9h2v3h3v2z"/>
+                            </svg>
+                            <span>"New Bucket…"</span>
+                        </div>
+                        <div class="sidebar-item" on:click=move |_| set_encryption_target.set(Some(("s3://".to_string(), "S3".to_string())))>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M12 1L3 5v6c0 5.55 3.84 10.74 9 12 5.16-1.26 9-6.45 9-12V5l-9-4zm0 10.99h7c-.53 4.12-3.28 7.79-7 8.94V12H5V6.3l7-3.11v8.8z"/>
+                            </svg>
+                            <span>"Encryption…"</span>
+                        </div>
+                        <div class="sidebar-item" on:click=move |_| set_show_accounts_dialog.set(true)>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                <path d="M19 13h-6v6h-2v-6H5v-2h6V5h2v6h6v2z"/>
+                            </svg>
+                            <span>"Accounts…"</span>
+                        </div>
+                        <div class="sidebar-item" on:click=move |_| set_show_vault_dialog.set(true)>
+                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                {move || if vault_status.with(|s| s.locked) {
+                                    view! { <path d="M18 8h-1V6c0-2.76-2.24-5-5-5S7 3.24 7 6v2H6c-1.1 0-2 .9-2 2v10c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V10c0-1.1-.9-2-2-2zM9 6c0-1.66 1.34-3 3-3s3 1.34 3 3v2H9V6zm3 11c-1.1 0-2-.9-2-2s.9-2 2-2 2 .9 2 2-.9 2-2 2z"/> }
+                                } else {
+                                    view! { <path d="M12 17c1.1 0 2-.9 2-2s-.9-2-2-2-2 .9-2 2 .9 2 2 2zm6-9h-1V6c0-2.76-2.24-5-5-5S7 3.24 7 6h2c0-1.66 1.34-3 3-3s3 1.34 3 3v2H6c-1.1 0-2 .9-2 2v10c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V10c0-1.1-.9-2-2-2zm0 12H6V10h12v10z"/> }
+                                }}
+                            </svg>
+                            <span>
+                                {move || vault_status.with(|s| {
+                                    if !s.initialized {
+                                        "Set Up Vault…"
+                                    } else if s.locked {
+                                        "Vault Locked"
+                                    } else {
+                                        "Vault Unlocked"
+                                    }
+                                })}
+                            </span>
+                        </div>
+                    </div>
+                    {move || accounts.get().into_iter().filter(|account| account.profile.is_none()).map(|account| {
+                        let root_path = account.root_path();
+                        let new_bucket = (root_path.clone(), account.region.clone());
+                        let encryption = (root_path.clone(), account.name.clone());
+                        let default_bucket = account.default_bucket.clone().map(|bucket| {
+                            let bucket_path = format!("{}{}/", root_path, bucket);
+                            view! {
+                                <div class="sidebar-item" on:click=move |_| navigate_to(bucket_path.clone())>
+                                    <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                        <path d="M10 4H4c-1.1 0-1.99.9-1.99 2L2 18c0 1.1.9 2 2 2h16c1.1 0 2-.9 2-2V8c0-1.1-.9-2-2-2h-8l-2-2z"/>
+                                    </svg>
+                                    <span>{bucket}</span>
+                                </div>
+                            }
+                        });
+                        view! {
+                            <div class="sidebar-section">
+                                <div class="sidebar-title" title=account.endpoint_url.clone().unwrap_or_default()>
+                                    {account.name.clone()}
+                                </div>
+                                <div class="sidebar-item" on:click=move |_| navigate_to(root_path.clone())>
+                                    <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                        <path d="M19.35 10.04C18.67 6.59 15.64 4 12 4 9.11 4 6.6 5.64 5.35 8.04 2.34 8.36 0 10.91 0 14c0 3.31 2.69 6 6 6h13c2.76 0 5-2.24 5-5 0-2.64-2.05-4.78-4.65-4.96z"/>
+                                    </svg>
+                                    <span>"Buckets"</span>
+                                </div>
+                                {default_bucket}
+                                <div class="sidebar-item" on:click=move |_| set_new_bucket_root.set(Some(new_bucket.clone()))>
+                                    <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                        <path d="M20 6h-8l-2-2H4c-1.11 0-1.99.89-1.99 2L2 18c0 1.11.89 2 2 2h16c1.11 0 2-.89 2-2V8c0-1.11-.89-2-2-2zm-1 8h-3v3h-2v-3h-3v-2h3V9h2v3h3v2z"/>
+                                    </svg>
+                                    <span>"New Bucket…"</span>
+                                </div>
+                                <div class="sidebar-item" on:click=move |_| set_encryption_target.set(Some(encryption.clone()))>
+                                    <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                        <path d="M12 1L3 5v6c0 5.55 3.84 10.74 9 12 5.16-1.26 9-6.45 9-12V5l-9-4zm0 10.99h7c-.53 4.12-3.28 7.79-7 8.94V12H5V6.3l7-3.11v8.8z"/>
+                                    </svg>
+                                    <span>"Encryption…"</span>
+                                </div>
+                            </div>
+                        }
+                    }).collect::<Vec<_>>()}
+                    // Profiles from ~/.aws can number in the dozens, so they share one section
+                    {move || {
+                        let profiles: Vec<Account> = accounts.get().into_iter().filter(|account| account.profile.is_some()).collect();
+                        (!profiles.is_empty()).then(|| view! {
+                            <div class="sidebar-section">
+                                <div class="sidebar-title">"AWS Profiles"</div>
+                                {profiles.into_iter().map(|account| {
+                                    let root_path = account.root_path();
+                                    view! {
+                                        <div
+                                            class="sidebar-item"
+                                            title=account.credential_source.clone().unwrap_or_default()
+                                            on:click=move |_| navigate_to(root_path.clone())
+                                        >
+                                            <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                                                <path d="M19.35 10.04C18.67 6.59 15.64 4 12 4 9.11 4 6.6 5.64 5.35 8.04 2.34 8.36 0 10.91 0 14c0 3.31 2.69 6 6 6h13c2.76 0 5-2.24 5-5 0-2.64-2.05-4.78-4.65-4.96z"/>
+                                            </svg>
+                                            <span>{account.name.clone()}</span>
+                                        </div>
+                                    }
+                                }).collect::<Vec<_>>()}
+                            </div>
+                        })
+                    }}
+                    <WatchFolders
+                        on_add=move || set_watch_target.set(Some((String::new(), String::new())))
+                        on_open=Callback::new(move |path: String| navigate_to(path))
+                    />
                 </div>
 
                 // Content area with file list and preview panel
@@ -872,6 +1523,7 @@ pub fn App() -> impl IntoView {
                                                         tabindex="0"
                                                         on:click=move |_| {
                                                             set_selected_item.set(Some(item_path_click.clone()));
+                                                            set_selected_version.set(None);
                                                             set_context_menu_visible.set(false);
                                                         }
                                                         on:dblclick=move |_| {
@@ -885,6 +1537,7 @@ pub fn App() -> impl IntoView {
                                                         on:contextmenu=move |e| {
                                                             e.prevent_default();
                                                             set_selected_item.set(Some(item_path_context.clone()));
+                                                            set_selected_version.set(None);
                                                             set_context_menu_pos.set((e.client_x(), e.client_y()));
                                                             set_context_menu_visible.set(true);
                                                         }
@@ -943,15 +1596,38 @@ pub fn App() -> impl IntoView {
                             view! {
                                 <div class="columns-container">
                                     {cols.into_iter().enumerate().map(|(col_index, column)| {
+                                        let versions_mode = column.versions;
+                                        let can_show_versions = is_s3_bucket_path(&column.path);
                                         view! {
                                             <div class="file-column">
                                                 <div class="file-list">
                                                     <div class="file-list-header">
-                                                        <div class="file-header-name">"Name"</div>
+                                                        <div class="file-header-name">
+                                                            "Name"
+                                                            {can_show_versions.then(|| view! {
+                                                                <button
+                                                                    class="column-versions-toggle"
+                                                                    class:active=versions_mode
+                                                                    title=if versions_mode { "Show current objects" } else { "Show versions" }
+                                                                    on:click=move |_| set_column_versions(col_index, !versions_mode)
+                                                                >
+                                                                    "Versions"
+                                                                </button>
+                                                            })}
+                                                        </div>
                                                         <div class="file-header-modified">"Date Modified"</div>
                                                         <div class="file-header-size">"Size"</div>
                                                     </div>
-                                                    <div class="file-list-body">
+                                                    <div
+                                                        class="file-list-body"
+                                                        on:scroll=move |e| {
+                                                            let body = event_target::<web_sys::Element>(&e);
+                                                            // Start fetching before the very end is reached
+                                                            if body.scroll_top() + body.client_height() >= body.scroll_height() - 200 {
+                                                                load_more_column(col_index, body.scroll_top());
+                                                            }
+                                                        }
+                                                    >
                                                         {column.contents.items.into_iter().map(|item| {
                                                             let item_path = item.path.clone();
                                                             let item_path_click = item_path.clone();
@@ -961,20 +1637,61 @@ pub fn App() -> impl IntoView {
                                                             let _item_name = item.name.clone();
                                                             let is_dir = item.is_dir;
                                                             let current_col_index = col_index;
+                                                            let item_version = item.version_id.clone();
+                                                            let item_version_click = item_version.clone();
+                                                            let item_version_context = item_version.clone();
+                                                            let class_badge = if item.restoring {
+                                                                Some("Restoring…".to_string())
+                                                            } else {
+                                                                item.storage_class.as_deref()
+                                                                    .filter(|class| *class != "STANDARD")
+                                                                    .map(|class| storage_class_label(class).to_string())
+                                                            };
+                                                            let version_badge = if item.is_delete_marker {
+                                                                Some("Delete marker".to_string())
+                                                            } else if item.is_latest {
+                                                                Some("Latest".to_string())
+                                                            } else {
+                                                                item.version_id.as_ref().map(|id| id.chars().take(8).collect())
+                                                            };
                                                             view! {
                                                                 <div
                                                                     class="file-item"
+                                                                    class:delete-marker=item.is_delete_marker
                                                                     class:selected=move || {
-                                                                        selected_item.get() == Some(item_path.clone()) &&
-                                                                        selected_column_index.get() == Some(current_col_index)
+                                                                        let in_column = selected_column_index.get() == Some(current_col_index);
+                                                                        (in_column && selected_paths.with(|paths| paths.contains(&item_path))) ||
+                                                                        (selected_item.get() == Some(item_path.clone()) && in_column &&
+                                                                        (item_version.is_none() || selected_version.get() == item_version))
                                                                     }
                                                                     class:focused=move || {
                                                                         focused_item.get() == Some(item_path_focused.clone()) &&
                                                                         focused_column_index.get() == Some(current_col_index)
                                                                     }
                                                                     tabindex="0"
-                                                                    on:click=move |_| {
+                                                                    on:click=move |e| {
+                                                                        // Cmd/Ctrl-click toggles the item in a multi-selection
+                                                                        if e.meta_key() || e.ctrl_key() {
+                                                                            let same_column = selected_column_index.get_untracked() == Some(current_col_index);
+                                                                            let previous = selected_item.get_untracked().filter(|_| same_column);
+                                                                            set_selected_paths.update(|paths| {
+                                                                                if !same_column {
+                                                                                    paths.clear();
+                                                                                }
+                                                                                if paths.is_empty() {
+                                                                                    paths.extend(previous);
+                                                                                }
+                                                                                if let Some(index) = paths.iter().position(|path| path == &item_path_click) {
+                                                                                    paths.remove(index);
+                                                                                } else {
+                                                                                    paths.push(item_path_click.clone());
+                                                                                }
+                                                                            });
+                                                                        } else {
+                                                                            set_selected_paths.set(Vec::new());
+                                                                        }
                                                                         set_selected_item.set(Some(item_path_click.clone()));
+                                                                        set_selected_version.set(item_version_click.clone());
                                                                         set_selected_column_index.set(Some(current_col_index));
                                                                         set_context_menu_visible.set(false);
 
@@ -1024,7 +1741,11 @@ pub fn App() -> impl IntoView {
                                                     }
                                                                     on:contextmenu=move |e| {
                                                                         e.prevent_default();
+                                                                        if !selected_paths.with_untracked(|paths| paths.contains(&item_path_context)) {
+                                                                            set_selected_paths.set(Vec::new());
+                                                                        }
                                                                         set_selected_item.set(Some(item_path_context.clone()));
+                                                                        set_selected_version.set(item_version_context.clone());
                                                                         set_selected_column_index.set(Some(current_col_index));
                                                                         set_context_menu_pos.set((e.client_x(), e.client_y()));
                                                                         set_context_menu_visible.set(true);
@@ -1033,6 +1754,12 @@ pub fn App() -> impl IntoView {
                                                                     <div class="file-item-name">
                                                                         <FileIcon icon=item.icon.clone() />
                                                                         <span class="file-name">{item.name}</span>
+                                                                        {class_badge.map(|badge| view! {
+                                                                            <span class="storage-class-badge" class:archived=item.archived>{badge}</span>
+                                                                        })}
+                                                                        {version_badge.map(|badge| view! {
+                                                                            <span class="version-badge">{badge}</span>
+                                                                        })}
                                                                     </div>
                                                                     <div class="file-item-modified">
                                                                         {item.modified.unwrap_or_else(|| "--".to_string())}
@@ -1061,7 +1788,7 @@ pub fn App() -> impl IntoView {
                                                 if let Some(column) = columns.get().get(col_index) {
                                                     column.contents.items.iter()
                                                         .find(|item| item.path == selected_path)
-                                                        .map(|item| !item.is_dir)
+                                                        .map(|item| !item.is_dir && item.version_id.is_none())
                                                         .unwrap_or(false)
                                                 } else {
                                                     false
@@ -1069,7 +1796,7 @@ pub fn App() -> impl IntoView {
                                             } else if let Some(search_results) = search_results.get() {
                                                 search_results.iter()
                                                     .find(|item| item.path == selected_path)
-                                                    .map(|item| !item.is_dir)
+                                                    .map(|item| !item.is_dir && item.version_id.is_none())
                                                     .unwrap_or(false)
                                             } else {
                                                 false
@@ -1321,13 +2048,189 @@ pub fn App() -> impl IntoView {
             }}
             </div>
 
+            {move || show_accounts_dialog.get().then(|| view! {
+                <AccountsDialog
+                    accounts=accounts
+                    set_accounts=set_accounts
+                    on_close=move || set_show_accounts_dialog.set(false)
+                    on_vault_locked=move || set_show_vault_dialog.set(true)
+                />
+            })}
+
+            {move || {
+                let (path, column_index) = mfa_retry.get()?;
+                let id = account_of(&path)?;
+                let account = accounts.with(|accounts| accounts.iter().find(|a| a.id == id).cloned())?;
+                Some(view! {
+                    <MfaDialog
+                        account=account
+                        on_success=move || {
+                            set_mfa_retry.set(None);
+                            load_directory_column(path.clone(), column_index);
+                        }
+                        on_close=move || set_mfa_retry.set(None)
+                    />
+                })
+            }}
+
+            {move || show_vault_dialog.get().then(|| view! {
+                <VaultDialog
+                    status=vault_status
+                    set_status=set_vault_status
+                    on_close=move || set_show_vault_dialog.set(false)
+                />
+            })}
+
+            {move || inspector.get().map(|(col_index, paths, version_id)| {
+                let read_only = version_id.is_some();
+                view! {
+                    <InspectorPanel
+                        paths=paths
+                        version_id=version_id
+                        read_only=read_only
+                        on_close=move || set_inspector.set(None)
+                        on_saved=move || {
+                            let versions = columns.with_untracked(|cols| {
+                                cols.get(col_index).is_some_and(|column| column.versions)
+                            });
+                            set_column_versions(col_index, versions);
+                        }
+                    />
+                }
+            })}
+
+            {move || presign_target.get().map(|(path, is_dir)| view! {
+                <PresignDialog
+                    path=path
+                    is_dir=is_dir
+                    on_close=move || set_presign_target.set(None)
+                />
+            })}
+
+            {move || sync_target.get().map(|(local, remote)| view! {
+                <SyncDialog
+                    local=local
+                    remote=remote
+                    on_started=move || {
+                        set_sync_target.set(None);
+                        set_show_transfers.set(true);
+                    }
+                    on_close=move || set_sync_target.set(None)
+                />
+            })}
+
+            {move || watch_target.get().map(|(local, remote)| view! {
+                <WatchFolderDialog
+                    local=local
+                    remote=remote
+                    on_close=move || set_watch_target.set(None)
+                />
+            })}
+
+            {move || bucket_settings.get().map(|path| view! {
+                <BucketSettings
+                    path=path
+                    on_close=move || set_bucket_settings.set(None)
+                />
+            })}
+
+            {move || new_bucket_root.get().map(|(root_path, default_region)| view! {
+                <CreateBucketDialog
+                    root_path=root_path
+                    default_region=default_region
+                    on_created=Callback::new(move |path: String| {
+                        set_new_bucket_root.set(None);
+                        navigate_to(path);
+                    })
+                    on_close=move || set_new_bucket_root.set(None)
+                />
+            })}
+
+            {move || encryption_target.get().map(|(root_path, title)| view! {
+                <EncryptionDialog
+                    root_path=root_path
+                    title=title
+                    on_close=move || set_encryption_target.set(None)
+                />
+            })}
+
+            {move || delete_bucket_target.get().map(|path| {
+                let root_path = account_of(&path).map_or_else(|| "s3://".to_string(), |id| format!("s3://{}@", id));
+                view! {
+                    <DeleteBucketDialog
+                        path=path
+                        on_deleted=Callback::new(move |job: Option<DeleteJob>| {
+                            set_delete_bucket_target.set(None);
+                            if let Some(job) = job {
+                                set_deletes.update(|deletes| upsert_delete(deletes, job));
+                                set_show_transfers.set(true);
+                            }
+                            navigate_to(root_path.clone());
+                        })
+                        on_close=move || set_delete_bucket_target.set(None)
+                    />
+                }
+            })}
+
+            {move || storage_class_target.get().map(|paths| view! {
+                <StorageClassDialog
+                    paths=paths
+                    on_close=move || {
+                        set_storage_class_target.set(None);
+                        refresh_current_column();
+                    }
+                />
+            })}
+
+            {move || restore_target.get().map(|paths| view! {
+                <RestoreDialog
+                    paths=paths
+                    on_close=move || {
+                        set_restore_target.set(None);
+                        refresh_current_column();
+                    }
+                />
+            })}
+
+            {move || show_transfers.get().then(|| view! {
+                <TransfersPanel
+                    jobs=transfers
+                    set_jobs=set_transfers
+                    deletes=deletes
+                    set_deletes=set_deletes
+                    on_close=move || set_show_transfers.set(false)
+                />
+            })}
+
+            {move || show_trash.get().then(|| view! {
+                <TrashDialog on_close=move || set_show_trash.set(false) />
+            })}
+
+            {move || trashed.get().map(|items| {
+                let message = match items.as_slice() {
+                    [item] => format!("{} moved to the Trash", item.name),
+                    items => format!("{} items moved to the Trash", items.len()),
+                };
+                view! {
+                    <div class="undo-toast">
+                        <span>{message}</span>
+                        <button on:click=move |_| undo_trash()>"Undo"</button>
+                        <button title="Dismiss" on:click=move |_| set_trashed.set(None)>"×"</button>
+                    </div>
+                }
+            })}
+
             // Status bar
             <div class="status-bar">
                 <div class="status-left">
                     {move || {
                         let cols = columns.get();
                         if let Some(last_col) = cols.last() {
-                            format!("{} items", last_col.contents.items.len())
+                            if last_col.contents.next_token.is_some() {
+                                format!("{}+ items", last_col.contents.items.len())
+                            } else {
+                                format!("{} items", last_col.contents.items.len())
+                            }
                         } else {
                             "".to_string()
                         }
@@ -1351,35 +2254,41 @@ pub fn App() -> impl IntoView {
                             <div class="context-menu-item" on:click=move |_| {
                                 if let Some(path) = selected_item.get() {
                                     set_clipboard_item.set(Some(path));
+                                    set_clipboard_version.set(selected_version.get());
                                     set_clipboard_operation.set(Some("copy".to_string()));
                                 }
                                 set_context_menu_visible.set(false);
                             }>
                                 "Copy"
                             </div>
-                            <div class="context-menu-item" on:click=move |_| {
-                                if let Some(path) = selected_item.get() {
-                                    set_clipboard_item.set(Some(path));
-                                    set_clipboard_operation.set(Some("cut".to_string()));
-                                }
-                                set_context_menu_visible.set(false);
-                            }>
-                                "Cut"
-                            </div>
+                            <Show when=move || selected_version.get().is_none()>
+                                <div class="context-menu-item" on:click=move |_| {
+                                    if let Some(path) = selected_item.get() {
+                                        set_clipboard_item.set(Some(path));
+                                        set_clipboard_version.set(None);
+                                        set_clipboard_operation.set(Some("cut".to_string()));
+                                    }
+                                    set_context_menu_visible.set(false);
+                                }>
+                                    "Cut"
+                                </div>
+                            </Show>
                             <div
                                 class="context-menu-item"
                                 class:disabled=move || clipboard_item.get().is_none()
                                 on:click=move |_| {
                                      if let (Some(source_path), Some(operation)) = (clipboard_item.get(), clipboard_operation.get()) {
                                          let dest_dir = current_path.get();
-                                         let operation_clone = operation.clone();
+                                         let kind = if operation == "cut" { "move" } else { "copy" };
                                          spawn_local(async move {
-                                             if operation_clone == "copy" {
-                                                 let _ = copy_selected_item(source_path, dest_dir.clone()).await;
-                                             } else if operation_clone == "cut" {
-                                                 let _ = move_selected_item(source_path, dest_dir.clone()).await;
+                                             // Transfers run in the background; the column refreshes when they finish
+                                             match queue_transfer(source_path, clipboard_version.get_untracked(), dest_dir, kind).await {
+                                                 Ok(job) => {
+                                                     set_transfers.update(|jobs| upsert_job(jobs, job));
+                                                     set_show_transfers.set(true);
+                                                 }
+                                                 Err(e) => set_error_msg.set(Some(e)),
                                              }
-                                             refresh_current_column();
                                          });
                                          if operation == "cut" {
                                              set_clipboard_item.set(None);
@@ -1392,6 +2301,124 @@ pub fn App() -> impl IntoView {
                                 "Paste"
                             </div>
                             <div class="context-menu-separator"></div>
+                            {move || (!inspectable_paths().is_empty()).then(|| view! {
+                                <div class="context-menu-item" on:click=move |_| {
+                                    open_inspector();
+                                    set_context_menu_visible.set(false);
+                                }>
+                                    "Get Info"
+                                </div>
+                            })}
+                            {move || selected_entry()
+                                .filter(|item| selected_version.get().is_none() && is_s3_bucket_path(&item.path))
+                                .map(|item| {
+                                    let path = item.path.clone();
+                                    let is_dir = item.is_dir;
+                                    view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_presign_target.set(Some((path.clone(), is_dir)));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            {if is_dir { "Upload Link…" } else { "Share Link…" }}
+                                        </div>
+                                    }
+                                })}
+                            {move || selected_entry()
+                                .filter(|item| selected_version.get().is_none() && is_s3_bucket_root(&item.path))
+                                .map(|item| {
+                                    let path = item.path.clone();
+                                    view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_bucket_settings.set(Some(path.clone()));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Bucket Settings…"
+                                        </div>
+                                    }
+                                })}
+                            {move || selected_entry()
+                                .filter(|item| {
+                                    item.is_dir
+                                        && selected_version.get().is_none()
+                                        && (!item.path.starts_with("s3://") || is_s3_bucket_path(&item.path))
+                                })
+                                .map(|item| {
+                                    let pair = if item.path.starts_with("s3://") {
+                                        (String::new(), item.path.clone())
+                                    } else {
+                                        (item.path.clone(), String::new())
+                                    };
+                                    view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_sync_target.set(Some(pair.clone()));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Sync…"
+                                        </div>
+                                    }
+                                })}
+                            {move || selected_entry()
+                                .filter(|item| item.is_dir && !item.path.starts_with("s3://"))
+                                .map(|item| {
+                                    let local = item.path.clone();
+                                    view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_watch_target.set(Some((local.clone(), String::new())));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Watch and Upload…"
+                                        </div>
+                                    }
+                                })}
+                            {move || {
+                                let paths = storage_class_paths();
+                                (!paths.is_empty()).then(|| {
+                                    let restore_paths = paths.clone();
+                                    view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_storage_class_target.set(Some(paths.clone()));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Storage Class…"
+                                        </div>
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            set_restore_target.set(Some(restore_paths.clone()));
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Restore from Archive…"
+                                        </div>
+                                    }
+                                })
+                            }}
+                            {move || selected_version_item().map(|(col_index, item)| {
+                                let restore_item = item.clone();
+                                let undelete_item = item.clone();
+                                view! {
+                                    {(!item.is_latest && !item.is_delete_marker).then(|| view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            run_version_action(col_index, "restore", restore_item.clone());
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Restore This Version"
+                                        </div>
+                                    })}
+                                    {(item.is_latest && item.is_delete_marker).then(|| view! {
+                                        <div class="context-menu-item" on:click=move |_| {
+                                            run_version_action(col_index, "delete", undelete_item.clone());
+                                            set_context_menu_visible.set(false);
+                                        }>
+                                            "Undelete"
+                                        </div>
+                                    })}
+                                    <div class="context-menu-item" on:click=move |_| {
+                                        set_confirm_delete_version.set(Some((col_index, item.clone())));
+                                        set_context_menu_visible.set(false);
+                                    }>
+                                        "Delete Version Permanently"
+                                    </div>
+                                }
+                            })}
+                            <Show when=move || selected_version.get().is_none()>
                             <div class="context-menu-item" on:click=move |_| {
                                 if let Some(path) = selected_item.get() {
                                     // Extract filename from path for initial value
@@ -1405,16 +2432,53 @@ pub fn App() -> impl IntoView {
                                 "Rename"
                             </div>
                             <div class="context-menu-item" on:click=move |_| {
-                                if let Some(path) = selected_item.get() {
-                                    spawn_local(async move {
-                                        delete_selected_item(path).await;
-                                        refresh_current_column();
-                                    });
+                                let paths = context_paths();
+                                match paths.as_slice() {
+                                    [] => {}
+                                    // Buckets are deleted through their own confirmation
+                                    [path] if is_s3_bucket_root(path) => {
+                                        set_delete_bucket_target.set(Some(path.clone()));
+                                    }
+                                    // Local items can be put back, so they go without asking
+                                    paths if paths.iter().all(|path| !path.starts_with("s3://")) => {
+                                        let paths = paths.to_vec();
+                                        spawn_local(async move {
+                                            match move_to_trash(paths).await {
+                                                Ok(trashed) => {
+                                                    set_selected_paths.set(Vec::new());
+                                                    // What did move can still be put back
+                                                    if !trashed.items.is_empty() {
+                                                        set_trashed.set(Some(trashed.items));
+                                                    }
+                                                    if !trashed.errors.is_empty() {
+                                                        set_error_msg.set(Some(format!(
+                                                            "Some items could not be moved to the Trash: {}",
+                                                            trashed.errors.join("; ")
+                                                        )));
+                                                    }
+                                                }
+                                                Err(e) => set_error_msg.set(Some(e)),
+                                            }
+                                            // In case the folder is not being watched
+                                            refresh_current_column();
+                                        });
+                                    }
+                                    // S3 folders and selections can hold any number of objects
+                                    _ => set_confirm_delete.set(Some(paths)),
                                 }
                                 set_context_menu_visible.set(false);
                             }>
-                                "Delete"
+                                {move || if local_selection() { "Move to Trash" } else { "Delete" }}
                             </div>
+                            <Show when=local_selection>
+                                <div class="context-menu-item" on:click=move |_| {
+                                    set_confirm_delete.set(Some(context_paths()));
+                                    set_context_menu_visible.set(false);
+                                }>
+                                    "Delete Permanently…"
+                                </div>
+                            </Show>
+                            </Show>
                         </div>
                     }.into_any()
                 } else {
@@ -1422,6 +2486,75 @@ pub fn App() -> impl IntoView {
                 }
             }}
 
+            // Batch delete confirmation
+            {move || confirm_delete.get().map(|paths| {
+                let message = match paths.as_slice() {
+                    [path] => format!(
+                        "{} will be deleted, with everything in it if it is a folder. This cannot be undone.",
+                        path.trim_end_matches('/').rsplit('/').next().unwrap_or(path)
+                    ),
+                    paths => format!(
+                        "{} items will be deleted, folders with everything in them. This cannot be undone.",
+                        paths.len()
+                    ),
+                };
+                view! {
+                    <div class="dialog-overlay" on:click=move |_| set_confirm_delete.set(None)>
+                        <div class="dialog" on:click=move |e| e.stop_propagation()>
+                            <h3>"Delete"</h3>
+                            <p class="dialog-message">{message}</p>
+                            <div class="dialog-buttons">
+                                <button class="danger" on:click=move |_| {
+                                    let paths = paths.clone();
+                                    set_confirm_delete.set(None);
+                                    set_selected_paths.set(Vec::new());
+                                    spawn_local(async move {
+                                        match delete_items(paths).await {
+                                            Ok(job) => {
+                                                set_deletes.update(|deletes| upsert_delete(deletes, job));
+                                                set_show_transfers.set(true);
+                                            }
+                                            Err(e) => set_error_msg.set(Some(e)),
+                                        }
+                                    });
+                                }>
+                                    "Delete"
+                                </button>
+                                <button on:click=move |_| set_confirm_delete.set(None)>
+                                    "Cancel"
+                                </button>
+                            </div>
+                        </div>
+                    </div>
+                }
+            })}
+
+            // Permanent version delete confirmation
+            {move || confirm_delete_version.get().map(|(col_index, item)| {
+                let version_id = item.version_id.clone().unwrap_or_default();
+                view! {
+                    <div class="dialog-overlay" on:click=move |_| set_confirm_delete_version.set(None)>
+                        <div class="dialog" on:click=move |e| e.stop_propagation()>
+                            <h3>"Delete Version Permanently"</h3>
+                            <p class="dialog-message">
+                                {format!("Version {} of {} will be deleted permanently. This cannot be undone.", version_id, item.name)}
+                            </p>
+                            <div class="dialog-buttons">
+                                <button class="danger" on:click=move |_| {
+                                    run_version_action(col_index, "delete", item.clone());
+                                    set_confirm_delete_version.set(None);
+                                }>
+                                    "Delete"
+                                </button>
+                                <button on:click=move |_| set_confirm_delete_version.set(None)>
+                                    "Cancel"
+                                </button>
+                            </div>
+                        </div>
+                    </div>
+                }
+            })}
+
             // New folder dialog
             {move || {
                 if show_new_folder_dialog.get() {
@@ -1443,7 +2576,7 @@ pub fn App() -> impl IntoView {
                                         let folder_name = new_folder_name.get();
                                         if !folder_name.is_empty() {
                                             spawn_local(async move {
-                                                create_new_folder(current_path.get(), folder_name).await;
+                                                create_new_folder(current_path.get_untracked(), folder_name).await;
                                                 refresh_current_column();
                                             });
                                             set_new_folder_name.set("".to_string());
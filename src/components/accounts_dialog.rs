@@ -0,0 +1,325 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::account_service::{delete_account, save_account, test_account};
+use crate::services::vault_service::is_vault_error;
+use crate::types::{Account, AccountInput, RoleConfig};
+
+// Optional text fields are sent as `None` when left blank
+fn optional(value: String) -> Option<String> {
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+// Role settings of the form, created on the first edit
+fn role(input: &mut AccountInput) -> &mut RoleConfig {
+    input.role.get_or_insert_with(RoleConfig::default)
+}
+
+fn role_field(input: &AccountInput, get: fn(&RoleConfig) -> Option<String>) -> String {
+    input.role.as_ref().and_then(get).unwrap_or_default()
+}
+
+#[component]
+pub fn AccountsDialog(
+    accounts: ReadSignal<Vec<Account>>,
+    set_accounts: WriteSignal<Vec<Account>>,
+    #[prop(into)] on_close: Callback<()>,
+    // Asks for the master passphrase when a secret cannot be read or stored
+    #[prop(into)] on_vault_locked: Callback<()>,
+) -> impl IntoView {
+    let (form, set_form) = signal(AccountInput::default());
+    let (status, set_status) = signal(Option::<Result<String, String>>::None);
+    let (busy, set_busy) = signal(false);
+    let (mfa_code, set_mfa_code) = signal(String::new());
+    // Imported AWS profiles are shown read-only
+    let (profile, set_profile) = signal(Option::<Account>::None);
+
+    let select = move |input: AccountInput| {
+        set_form.set(input);
+        set_profile.set(None);
+        set_status.set(None);
+    };
+
+    let select_profile = move |account: Account| {
+        set_form.set(AccountInput::from(&account));
+        set_profile.set(Some(account));
+        set_status.set(None);
+    };
+
+    let text_field = move |label: &'static str,
+                           placeholder: &'static str,
+                           get: fn(&AccountInput) -> String,
+                           set: fn(&mut AccountInput, String)| {
+        view! {
+            <label class="account-field">
+                <span>{label}</span>
+                <input
+                    type="text"
+                    placeholder=placeholder
+                    prop:value=move || form.with(get)
+                    on:input=move |e| set_form.update(|f| set(f, event_target_value(&e)))
+                />
+            </label>
+        }
+    };
+
+    let on_test = move |_| {
+        set_busy.set(true);
+        set_status.set(None);
+        spawn_local(async move {
+            let code = optional(mfa_code.get_untracked());
+            let result = test_account(form.get_untracked(), code).await;
+            if matches!(&result, Err(e) if is_vault_error(e)) {
+                on_vault_locked.run(());
+            }
+            set_status.set(Some(result));
+            set_busy.set(false);
+        });
+    };
+
+    let on_save = move |_| {
+        set_busy.set(true);
+        spawn_local(async move {
+            match save_account(form.get_untracked()).await {
+                Ok(account) => {
+                    set_form.set(AccountInput::from(&account));
+                    set_status.set(Some(Ok(format!("Saved {}", account.name))));
+                    set_accounts.update(|accounts| {
+                        match accounts.iter_mut().find(|a| a.id == account.id) {
+                            Some(existing) => *existing = account,
+                            None => accounts.push(account),
+                        }
+                    });
+                }
+                Err(e) => {
+                    if is_vault_error(&e) {
+                        on_vault_locked.run(());
+                    }
+                    set_status.set(Some(Err(e)));
+                }
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_delete = move |_| {
+        let Some(id) = form.get_untracked().id else {
+            return;
+        };
+        set_busy.set(true);
+        spawn_local(async move {
+            match delete_account(id.clone()).await {
+                Ok(()) => {
+                    set_accounts.update(|accounts| accounts.retain(|a| a.id != id));
+                    select(AccountInput::default());
+                }
+                Err(e) => set_status.set(Some(Err(e))),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog accounts-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"S3 Accounts"</h3>
+                <div class="accounts-layout">
+                    <div class="accounts-list">
+                        {move || accounts.get().into_iter().filter(|account| account.profile.is_none()).map(|account| {
+                            let id = account.id.clone();
+                            let name = account.name.clone();
+                            let input = AccountInput::from(&account);
+                            view! {
+                                <div
+                                    class="accounts-list-item"
+                                    class:selected=move || form.with(|f| f.id.as_deref() == Some(id.as_str()))
+                                    on:click=move |_| select(input.clone())
+                                >
+                                    {name}
+                                </div>
+                            }
+                        }).collect::<Vec<_>>()}
+                        <div
+                            class="accounts-list-item add"
+                            class:selected=move || form.with(|f| f.id.is_none())
+                            on:click=move |_| select(AccountInput::default())
+                        >
+                            "+ New Account"
+                        </div>
+                        {move || {
+                            let profiles: Vec<Account> = accounts.get().into_iter().filter(|account| account.profile.is_some()).collect();
+                            (!profiles.is_empty()).then(|| view! {
+                                <div class="accounts-list-group">"AWS Profiles"</div>
+                                {profiles.into_iter().map(|account| {
+                                    let id = account.id.clone();
+                                    let name = account.name.clone();
+                                    view! {
+                                        <div
+                                            class="accounts-list-item"
+                                            class:selected=move || form.with(|f| f.id.as_deref() == Some(id.as_str()))
+                                            on:click=move |_| select_profile(account.clone())
+                                        >
+                                            {name}
+                                        </div>
+                                    }
+                                }).collect::<Vec<_>>()}
+                            })
+                        }}
+                    </div>
+
+                    <div class="account-form">
+                        <Show
+                            when=move || profile.with(Option::is_none)
+                            fallback=move || profile.get().map(|account| view! {
+                                <div class="account-profile">
+                                    <div class="account-profile-row">
+                                        <span>"Profile"</span>
+                                        {account.name.clone()}
+                                    </div>
+                                    <div class="account-profile-row">
+                                        <span>"Credentials"</span>
+                                        {account.credential_source.clone().unwrap_or_default()}
+                                    </div>
+                                    <div class="account-profile-row">
+                                        <span>"Region"</span>
+                                        {account.region.clone().unwrap_or_else(|| "Default".to_string())}
+                                    </div>
+                                    <div class="account-profile-row">
+                                        <span>"Endpoint"</span>
+                                        {account.endpoint_url.clone().unwrap_or_else(|| "AWS".to_string())}
+                                    </div>
+                                    <div class="account-profile-note">
+                                        "Imported from ~/.aws/config and ~/.aws/credentials. Edit those files to change it."
+                                    </div>
+                                </div>
+                            })
+                        >
+                        {text_field("Name", "My MinIO", |f| f.name.clone(), |f, v| f.name = v)}
+                        {text_field(
+                            "Endpoint URL",
+                            "Leave empty for AWS",
+                            |f| f.endpoint_url.clone().unwrap_or_default(),
+                            |f, v| f.endpoint_url = optional(v),
+                        )}
+                        {text_field(
+                            "Region",
+                            "us-east-1",
+                            |f| f.region.clone().unwrap_or_default(),
+                            |f, v| f.region = optional(v),
+                        )}
+                        <label class="account-checkbox">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || form.with(|f| f.path_style)
+                                on:change=move |e| set_form.update(|f| f.path_style = event_target_checked(&e))
+                            />
+                            <span>"Path-style addressing (endpoint/bucket)"</span>
+                        </label>
+                        {text_field(
+                            "Access Key ID",
+                            "",
+                            |f| f.access_key_id.clone(),
+                            |f, v| f.access_key_id = v,
+                        )}
+                        <label class="account-field">
+                            <span>"Secret Access Key"</span>
+                            <input
+                                type="password"
+                                placeholder=move || if form.with(|f| f.id.is_some()) { "Unchanged" } else { "" }
+                                prop:value=move || form.with(|f| f.secret_access_key.clone().unwrap_or_default())
+                                on:input=move |e| set_form.update(|f| f.secret_access_key = optional(event_target_value(&e)))
+                            />
+                        </label>
+                        <label class="account-field">
+                            <span>"Session Token"</span>
+                            <input
+                                type="password"
+                                placeholder="Optional"
+                                prop:value=move || form.with(|f| f.session_token.clone().unwrap_or_default())
+                                on:input=move |e| set_form.update(|f| f.session_token = optional(event_target_value(&e)))
+                            />
+                        </label>
+                        {text_field(
+                            "Default Bucket",
+                            "Optional",
+                            |f| f.default_bucket.clone().unwrap_or_default(),
+                            |f, v| f.default_bucket = optional(v),
+                        )}
+
+                        <div class="account-form-group">"Assume Role"</div>
+                        {text_field(
+                            "Role ARN",
+                            "Optional, arn:aws:iam::123456789012:role/name",
+                            |f| role_field(f, |r| Some(r.role_arn.clone())),
+                            |f, v| role(f).role_arn = v,
+                        )}
+                        <Show when=move || form.with(|f| f.role.as_ref().is_some_and(|r| !r.role_arn.trim().is_empty()))>
+                            {text_field(
+                                "Session Name",
+                                "s3-finder-<account>",
+                                |f| role_field(f, |r| r.session_name.clone()),
+                                |f, v| role(f).session_name = optional(v),
+                            )}
+                            {text_field(
+                                "External ID",
+                                "Optional",
+                                |f| role_field(f, |r| r.external_id.clone()),
+                                |f, v| role(f).external_id = optional(v),
+                            )}
+                            {text_field(
+                                "MFA Device",
+                                "Optional, serial number or ARN",
+                                |f| role_field(f, |r| r.mfa_serial.clone()),
+                                |f, v| role(f).mfa_serial = optional(v),
+                            )}
+                            {text_field(
+                                "Session Duration (seconds)",
+                                "3600",
+                                |f| role_field(f, |r| r.duration_secs.map(|d| d.to_string())),
+                                |f, v| role(f).duration_secs = v.trim().parse().ok(),
+                            )}
+                            {text_field(
+                                "STS Endpoint URL",
+                                "Defaults to the endpoint above",
+                                |f| role_field(f, |r| r.sts_endpoint_url.clone()),
+                                |f, v| role(f).sts_endpoint_url = optional(v),
+                            )}
+                            <Show when=move || form.with(|f| f.role.as_ref().is_some_and(|r| r.mfa_serial.is_some()))>
+                                <label class="account-field">
+                                    <span>"MFA Code (for Test Connection)"</span>
+                                    <input
+                                        type="text"
+                                        inputmode="numeric"
+                                        autocomplete="one-time-code"
+                                        prop:value=move || mfa_code.get()
+                                        on:input=move |e| set_mfa_code.set(event_target_value(&e))
+                                    />
+                                </label>
+                            </Show>
+                        </Show>
+                        </Show>
+
+                        {move || status.get().map(|result| match result {
+                            Ok(message) => view! { <div class="account-status ok">{message}</div> },
+                            Err(message) => view! { <div class="account-status error">{message}</div> },
+                        })}
+                    </div>
+                </div>
+
+                <div class="dialog-buttons">
+                    {move || (form.with(|f| f.id.is_some()) && profile.with(Option::is_none)).then(|| view! {
+                        <button class="danger" disabled=move || busy.get() on:click=on_delete>
+                            "Delete"
+                        </button>
+                    })}
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() on:click=on_test>"Test Connection"</button>
+                    {move || profile.with(Option::is_none).then(|| view! {
+                        <button disabled=move || busy.get() on:click=on_save>"Save"</button>
+                    })}
+                </div>
+            </div>
+        </div>
+    }
+}
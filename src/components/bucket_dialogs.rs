@@ -0,0 +1,180 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::components::bucket_settings::bucket_name;
+use crate::services::file_service::{create_bucket, delete_bucket, empty_and_delete_bucket};
+use crate::types::{DeleteJob, NewBucket};
+
+/// Creates a bucket in the bucket list at `root_path`. The region defaults
+/// to the one the account is set up with.
+#[component]
+pub fn CreateBucketDialog(
+    root_path: String,
+    default_region: Option<String>,
+    on_created: Callback<String>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let root_path = StoredValue::new(root_path);
+    let (name, set_name) = signal(String::new());
+    let (options, set_options) = signal(NewBucket::default());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let on_create = move |_| {
+        let name = name.get_untracked().trim().to_string();
+        if name.is_empty() {
+            set_error.set(Some("Enter a bucket name".to_string()));
+            return;
+        }
+
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match create_bucket(root_path.get_value(), name, options.get_untracked()).await {
+                Ok(path) => on_created.run(path),
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"New Bucket"</h3>
+                <label class="account-field">
+                    <span>"Name"</span>
+                    <input
+                        type="text"
+                        placeholder="my-bucket"
+                        prop:value=move || name.get()
+                        on:input=move |e| set_name.set(event_target_value(&e))
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"Region"</span>
+                    <input
+                        type="text"
+                        placeholder=default_region.unwrap_or_else(|| "Account region".to_string())
+                        prop:value=move || options.with(|o| o.region.clone().unwrap_or_default())
+                        on:input=move |e| {
+                            let region = event_target_value(&e);
+                            set_options.update(|o| o.region = (!region.trim().is_empty()).then_some(region));
+                        }
+                    />
+                </label>
+                <label class="account-checkbox">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || options.with(|o| o.versioning || o.object_lock)
+                        prop:disabled=move || options.with(|o| o.object_lock)
+                        on:change=move |e| set_options.update(|o| o.versioning = event_target_checked(&e))
+                    />
+                    <span>"Keep every version of objects"</span>
+                </label>
+                <label class="account-checkbox">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || options.with(|o| o.object_lock)
+                        on:change=move |e| set_options.update(|o| o.object_lock = event_target_checked(&e))
+                    />
+                    <span>"Enable object lock"</span>
+                </label>
+                <p class="vault-note">
+                    "Object lock needs versioning and cannot be turned off once the bucket exists."
+                </p>
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Cancel"</button>
+                    <button disabled=move || busy.get() on:click=on_create>"Create"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Deletes a bucket. S3 only deletes empty buckets; emptying one first
+/// deletes every object and version in it, so the name has to be typed out.
+/// Emptying runs in the background and is passed on as a delete job.
+#[component]
+pub fn DeleteBucketDialog(
+    path: String,
+    #[prop(into)] on_deleted: Callback<Option<DeleteJob>>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let bucket = StoredValue::new(bucket_name(&path));
+    let path = StoredValue::new(path);
+    let (empty, set_empty) = signal(false);
+    let (typed_name, set_typed_name) = signal(String::new());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let confirmed =
+        move || !empty.get() || bucket.with_value(|bucket| typed_name.get().trim() == bucket);
+
+    let on_delete = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            let result = if empty.get_untracked() {
+                empty_and_delete_bucket(path.get_value()).await.map(Some)
+            } else {
+                delete_bucket(path.get_value()).await.map(|()| None)
+            };
+            match result {
+                Ok(job) => on_deleted.run(job),
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Delete Bucket"</h3>
+                <p class="dialog-message">
+                    {format!("The bucket {} will be deleted. Only empty buckets can be deleted.", bucket.get_value())}
+                </p>
+                <label class="account-checkbox">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || empty.get()
+                        on:change=move |e| set_empty.set(event_target_checked(&e))
+                    />
+                    <span>"Empty the bucket first"</span>
+                </label>
+                {move || empty.get().then(|| view! {
+                    <p class="vault-note">
+                        "Every object, version and delete marker in the bucket will be deleted permanently. Type the bucket name to confirm."
+                    </p>
+                    <input
+                        type="text"
+                        placeholder=bucket.get_value()
+                        prop:value=move || typed_name.get()
+                        on:input=move |e| set_typed_name.set(event_target_value(&e))
+                    />
+                })}
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+                <div class="dialog-buttons">
+                    <button
+                        class="danger"
+                        disabled=move || busy.get() || !confirmed()
+                        on:click=on_delete
+                    >
+                        {move || if empty.get() { "Empty and Delete" } else { "Delete" }}
+                    </button>
+                    <button on:click=move |_| on_close.run(())>"Cancel"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,362 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use wasm_bindgen::JsCast;
+
+use crate::services::bucket_service::{
+    get_bucket_properties, preview_bucket_properties, put_bucket_properties,
+};
+use crate::types::{BucketProperties, CorsRule, PropertyDiff, PublicAccessBlock};
+
+const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+type BlockField = fn(&mut PublicAccessBlock) -> &mut bool;
+
+const BLOCK_FIELDS: [(&str, BlockField); 4] = [
+    ("Block public ACLs", |block| &mut block.block_public_acls),
+    ("Ignore public ACLs", |block| &mut block.ignore_public_acls),
+    ("Block public bucket policies", |block| {
+        &mut block.block_public_policy
+    }),
+    ("Restrict public buckets", |block| {
+        &mut block.restrict_public_buckets
+    }),
+];
+
+// Syntax errors in the policy as the user types; the backend checks the rest
+fn json_error(text: &str) -> Option<String> {
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    js_sys::JSON::parse(text).err().map(|e| {
+        e.dyn_into::<js_sys::Error>()
+            .map(|error| String::from(error.message()))
+            .unwrap_or_else(|_| "Invalid JSON".to_string())
+    })
+}
+
+fn list_label(values: &[String]) -> String {
+    values.join(", ")
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Field edits do not re-render the form, so focus stays where it is
+fn edit_cors(
+    set_properties: WriteSignal<BucketProperties>,
+    index: usize,
+    edit: impl FnOnce(&mut CorsRule),
+) {
+    set_properties.update_untracked(|properties| {
+        if let Some(rule) = properties.cors_rules.get_mut(index) {
+            edit(rule);
+        }
+    });
+}
+
+fn list_field(
+    label: &'static str,
+    value: String,
+    placeholder: &'static str,
+    on_change: impl Fn(Vec<String>) + 'static,
+) -> impl IntoView {
+    view! {
+        <label class="account-field">
+            <span>{label}</span>
+            <input
+                type="text"
+                placeholder=placeholder
+                prop:value=value
+                on:change=move |e| on_change(parse_list(&event_target_value(&e)))
+            />
+        </label>
+    }
+}
+
+fn cors_rule_view(
+    index: usize,
+    rule: CorsRule,
+    set_properties: WriteSignal<BucketProperties>,
+) -> impl IntoView {
+    view! {
+        <div class="lifecycle-rule">
+            <div class="lifecycle-rule-header">
+                <input
+                    type="text"
+                    class="lifecycle-rule-id"
+                    placeholder="Rule ID (optional)"
+                    prop:value=rule.id.clone().unwrap_or_default()
+                    on:change=move |e| {
+                        let id = event_target_value(&e).trim().to_string();
+                        edit_cors(set_properties, index, |rule| rule.id = (!id.is_empty()).then_some(id));
+                    }
+                />
+                <button
+                    class="inspector-remove"
+                    title="Remove Rule"
+                    on:click=move |_| set_properties.update(|properties| {
+                        if index < properties.cors_rules.len() {
+                            properties.cors_rules.remove(index);
+                        }
+                    })
+                >
+                    "×"
+                </button>
+            </div>
+
+            {list_field("Allowed Origins", list_label(&rule.allowed_origins), "https://example.com, *", move |origins| {
+                edit_cors(set_properties, index, |rule| rule.allowed_origins = origins);
+            })}
+            <div class="property-methods">
+                {CORS_METHODS.iter().map(|&method| {
+                    let checked = rule.allowed_methods.iter().any(|allowed| allowed == method);
+                    view! {
+                        <label class="lifecycle-check">
+                            <input
+                                type="checkbox"
+                                prop:checked=checked
+                                on:change=move |e| {
+                                    let checked = event_target_checked(&e);
+                                    edit_cors(set_properties, index, |rule| {
+                                        rule.allowed_methods.retain(|allowed| allowed != method);
+                                        if checked {
+                                            rule.allowed_methods.push(method.to_string());
+                                        }
+                                        rule.allowed_methods.sort_by_key(|allowed| {
+                                            CORS_METHODS.iter().position(|known| known == allowed)
+                                        });
+                                    });
+                                }
+                            />
+                            {method}
+                        </label>
+                    }
+                }).collect::<Vec<_>>()}
+            </div>
+            <div class="lifecycle-grid">
+                {list_field("Allowed Headers", list_label(&rule.allowed_headers), "*", move |headers| {
+                    edit_cors(set_properties, index, |rule| rule.allowed_headers = headers);
+                })}
+                {list_field("Exposed Headers", list_label(&rule.expose_headers), "ETag", move |headers| {
+                    edit_cors(set_properties, index, |rule| rule.expose_headers = headers);
+                })}
+                <label class="account-field">
+                    <span>"Max Age (seconds)"</span>
+                    <input
+                        type="text"
+                        prop:value=rule.max_age_seconds.map(|seconds| seconds.to_string()).unwrap_or_default()
+                        on:change=move |e| {
+                            let seconds = event_target_value(&e).trim().parse().ok();
+                            edit_cors(set_properties, index, |rule| rule.max_age_seconds = seconds);
+                        }
+                    />
+                </label>
+            </div>
+        </div>
+    }
+}
+
+fn diff_view(diffs: Vec<PropertyDiff>) -> impl IntoView {
+    if diffs.is_empty() {
+        return view! { <p class="vault-note">"Nothing has changed."</p> }.into_any();
+    }
+
+    diffs
+        .into_iter()
+        .map(|diff| {
+            view! {
+                <div class="inspector-section">
+                    <div class="inspector-section-title">{diff.setting}</div>
+                    <pre class="property-diff">
+                        {diff.lines.into_iter().map(|line| {
+                            let marker = match line.kind.as_str() {
+                                "added" => "+ ",
+                                "removed" => "- ",
+                                _ => "  ",
+                            };
+                            view! {
+                                <div class=format!("diff-line diff-{}", line.kind)>
+                                    {format!("{}{}", marker, line.text)}
+                                </div>
+                            }
+                        }).collect::<Vec<_>>()}
+                    </pre>
+                </div>
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_any()
+}
+
+/// Edits the policy, CORS rules and public access block of a bucket. Saving
+/// first shows what changes against the bucket as it is now.
+#[component]
+pub fn BucketPropertiesPane(path: String) -> impl IntoView {
+    let path = StoredValue::new(path);
+    let (properties, set_properties) = signal(BucketProperties::default());
+    let (policy, set_policy) = signal(String::new());
+    let (review, set_review) = signal(Option::<Vec<PropertyDiff>>::None);
+    let (notice, set_notice) = signal(Option::<String>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let load = move || {
+        set_busy.set(true);
+        set_error.set(None);
+        set_review.set(None);
+        spawn_local(async move {
+            match get_bucket_properties(path.get_value()).await {
+                Ok(loaded) => {
+                    set_policy.set(loaded.policy.clone().unwrap_or_default());
+                    set_properties.set(loaded);
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    load();
+
+    let edited = move || {
+        let mut edited = properties.get_untracked();
+        edited.policy = Some(policy.get_untracked());
+        edited
+    };
+
+    let on_review = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        set_notice.set(None);
+        spawn_local(async move {
+            match preview_bucket_properties(path.get_value(), edited()).await {
+                Ok(diffs) => set_review.set(Some(diffs)),
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_save = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match put_bucket_properties(path.get_value(), edited()).await {
+                Ok(()) => {
+                    set_notice.set(Some("Bucket properties saved".to_string()));
+                    load();
+                }
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="lifecycle-editor">
+            {move || match review.get() {
+                Some(diffs) => {
+                    let unchanged = diffs.is_empty();
+                    view! {
+                        <div class="lifecycle-rules">{diff_view(diffs)}</div>
+                        {move || error.get().map(|message| view! {
+                            <div class="account-status error">{message}</div>
+                        })}
+                        <div class="dialog-buttons">
+                            <button on:click=move |_| set_review.set(None)>"Back"</button>
+                            <button disabled=move || busy.get() || unchanged on:click=on_save>
+                                "Save"
+                            </button>
+                        </div>
+                    }.into_any()
+                }
+                None => view! {
+                    <div class="lifecycle-rules">
+                        <div class="inspector-section-title">"Public Access"</div>
+                        <div class="property-block">
+                            {move || {
+                                let mut block = properties.get().public_access_block;
+                                BLOCK_FIELDS.iter().map(|&(label, field)| {
+                                    let checked = *field(&mut block);
+                                    view! {
+                                        <label class="lifecycle-check">
+                                            <input
+                                                type="checkbox"
+                                                prop:checked=checked
+                                                on:change=move |e| {
+                                                    let checked = event_target_checked(&e);
+                                                    set_properties.update_untracked(|properties| {
+                                                        *field(&mut properties.public_access_block) = checked;
+                                                    });
+                                                }
+                                            />
+                                            {label}
+                                        </label>
+                                    }
+                                }).collect::<Vec<_>>()
+                            }}
+                        </div>
+
+                        <div class="inspector-section-title">"Bucket Policy"</div>
+                        <textarea
+                            class="lifecycle-raw property-policy"
+                            spellcheck="false"
+                            placeholder="No policy"
+                            prop:value=move || policy.get()
+                            on:input=move |e| set_policy.set(event_target_value(&e))
+                        ></textarea>
+                        {move || json_error(&policy.get()).map(|message| view! {
+                            <div class="account-status error">{message}</div>
+                        })}
+
+                        <div class="inspector-section-title">"CORS"</div>
+                        {move || {
+                            let rules = properties.get().cors_rules;
+                            view! {
+                                {rules.is_empty().then(|| view! {
+                                    <p class="vault-note">"No cross-origin requests are allowed."</p>
+                                })}
+                                {rules.into_iter().enumerate()
+                                    .map(|(index, rule)| cors_rule_view(index, rule, set_properties))
+                                    .collect::<Vec<_>>()}
+                            }
+                        }}
+                        <button
+                            class="inspector-add"
+                            on:click=move |_| set_properties.update(|properties| {
+                                properties.cors_rules.push(CorsRule {
+                                    allowed_origins: vec!["*".to_string()],
+                                    allowed_methods: vec!["GET".to_string()],
+                                    ..Default::default()
+                                });
+                            })
+                        >
+                            "Add CORS Rule"
+                        </button>
+                    </div>
+
+                    {move || notice.get().map(|message| view! {
+                        <div class="account-status ok">{message}</div>
+                    })}
+                    {move || error.get().map(|message| view! {
+                        <div class="account-status error">{message}</div>
+                    })}
+
+                    <div class="dialog-buttons">
+                        <button disabled=move || busy.get() on:click=move |_| load()>"Reload"</button>
+                        <button disabled=move || busy.get() on:click=on_review>"Review Changes…"</button>
+                    </div>
+                }.into_any(),
+            }}
+        </div>
+    }
+}
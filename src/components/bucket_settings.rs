@@ -0,0 +1,47 @@
+use leptos::prelude::*;
+
+use crate::components::bucket_properties::BucketPropertiesPane;
+use crate::components::lifecycle_editor::LifecycleEditor;
+
+const TABS: [(&str, &str); 2] = [("properties", "Permissions"), ("lifecycle", "Lifecycle")];
+
+/// Name of the bucket an `s3://` path points into.
+pub fn bucket_name(path: &str) -> String {
+    path.trim_start_matches("s3://")
+        .split('/')
+        .next()
+        .map(|authority| authority.rsplit('@').next().unwrap_or(authority))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Settings of the bucket `path` points into. Switching tabs drops unsaved
+/// edits.
+#[component]
+pub fn BucketSettings(path: String, #[prop(into)] on_close: Callback<()>) -> impl IntoView {
+    let bucket = bucket_name(&path);
+    let path = StoredValue::new(path);
+    let (tab, set_tab) = signal("properties");
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog bucket-settings" on:click=move |e| e.stop_propagation()>
+                <div class="bucket-settings-header">
+                    <h3>{bucket}</h3>
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                </div>
+                <div class="settings-tabs">
+                    {TABS.iter().map(|&(name, label)| view! {
+                        <button class:active=move || tab.get() == name on:click=move |_| set_tab.set(name)>
+                            {label}
+                        </button>
+                    }).collect::<Vec<_>>()}
+                </div>
+                {move || match tab.get() {
+                    "lifecycle" => view! { <LifecycleEditor path=path.get_value() /> }.into_any(),
+                    _ => view! { <BucketPropertiesPane path=path.get_value() /> }.into_any(),
+                }}
+            </div>
+        </div>
+    }
+}
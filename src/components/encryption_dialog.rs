@@ -0,0 +1,183 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::encryption_service::{
+    generate_customer_key, get_encryption_settings, set_customer_key, set_default_encryption,
+};
+use crate::types::{Encryption, EncryptionSettings};
+
+const MODES: [(&str, &str); 4] = [
+    ("bucket-default", "Bucket default"),
+    ("sse-s3", "SSE-S3 (S3 managed keys)"),
+    ("sse-kms", "SSE-KMS (AWS KMS keys)"),
+    ("sse-c", "SSE-C (customer-provided key)"),
+];
+
+fn mode_of(encryption: &Encryption) -> &'static str {
+    match encryption {
+        Encryption::BucketDefault => "bucket-default",
+        Encryption::SseS3 => "sse-s3",
+        Encryption::SseKms { .. } => "sse-kms",
+        Encryption::SseC => "sse-c",
+    }
+}
+
+/// How uploads and copies to an account are encrypted, and the customer key
+/// used for SSE-C. `root_path` is any path in the account.
+#[component]
+pub fn EncryptionDialog(
+    root_path: String,
+    title: String,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let root_path = StoredValue::new(root_path);
+    let (settings, set_settings) = signal(Option::<EncryptionSettings>::None);
+    let (mode, set_mode) = signal("bucket-default".to_string());
+    let (kms_key_id, set_kms_key_id) = signal(String::new());
+    let (customer_key, set_customer_key_input) = signal(String::new());
+    let (status, set_status) = signal(Option::<Result<String, String>>::None);
+    let (busy, set_busy) = signal(false);
+
+    let apply = move |loaded: EncryptionSettings| {
+        set_mode.set(mode_of(&loaded.default_encryption).to_string());
+        if let Encryption::SseKms { key_id } = &loaded.default_encryption {
+            set_kms_key_id.set(key_id.clone().unwrap_or_default());
+        }
+        set_settings.set(Some(loaded));
+    };
+
+    spawn_local(async move {
+        match get_encryption_settings(root_path.get_value()).await {
+            Ok(loaded) => apply(loaded),
+            Err(e) => set_status.set(Some(Err(e))),
+        }
+    });
+
+    let on_save = move |_| {
+        let encryption = match mode.get_untracked().as_str() {
+            "sse-s3" => Encryption::SseS3,
+            "sse-kms" => Encryption::SseKms {
+                key_id: Some(kms_key_id.get_untracked()).filter(|id| !id.trim().is_empty()),
+            },
+            "sse-c" => Encryption::SseC,
+            _ => Encryption::BucketDefault,
+        };
+        set_busy.set(true);
+        set_status.set(None);
+        spawn_local(async move {
+            match set_default_encryption(root_path.get_value(), encryption).await {
+                Ok(saved) => {
+                    apply(saved);
+                    set_status.set(Some(Ok("Default encryption saved".to_string())));
+                }
+                Err(e) => set_status.set(Some(Err(e))),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_generate = move |_| {
+        spawn_local(async move {
+            match generate_customer_key().await {
+                Ok(key) => set_customer_key_input.set(key),
+                Err(e) => set_status.set(Some(Err(e))),
+            }
+        });
+    };
+
+    let store_key = move |key: Option<String>| {
+        let removing = key.is_none();
+        set_busy.set(true);
+        set_status.set(None);
+        spawn_local(async move {
+            match set_customer_key(root_path.get_value(), key).await {
+                Ok(saved) => {
+                    apply(saved);
+                    set_customer_key_input.set(String::new());
+                    set_status.set(Some(Ok(if removing {
+                        "Customer key removed".to_string()
+                    } else {
+                        "Customer key stored in the vault".to_string()
+                    })));
+                }
+                Err(e) => set_status.set(Some(Err(e))),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let has_key = move || settings.with(|s| s.as_ref().is_some_and(|s| s.customer_key));
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>{format!("Encryption — {}", title)}</h3>
+                <label class="account-field">
+                    <span>"Encrypt uploads and copies with"</span>
+                    <select on:change=move |e| set_mode.set(event_target_value(&e))>
+                        {MODES.iter().map(|&(value, label)| view! {
+                            <option value=value selected=move || mode.get() == value>{label}</option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+                <Show when=move || mode.get() == "sse-kms">
+                    <label class="account-field">
+                        <span>"KMS key ID or ARN (empty for aws/s3)"</span>
+                        <input
+                            type="text"
+                            placeholder="arn:aws:kms:…"
+                            prop:value=move || kms_key_id.get()
+                            on:input=move |e| set_kms_key_id.set(event_target_value(&e))
+                        />
+                    </label>
+                </Show>
+
+                <h4>"Customer-provided key"</h4>
+                <p class="dialog-message">
+                    {move || if has_key() {
+                        "A key is stored in the vault. Objects encrypted with it are read with it automatically."
+                    } else {
+                        "No key is stored. SSE-C objects cannot be read or written until one is."
+                    }}
+                </p>
+                <label class="account-field">
+                    <span>{move || if has_key() { "Replace with key (base64, 256-bit)" } else { "Key (base64, 256-bit)" }}</span>
+                    <input
+                        type="password"
+                        autocomplete="off"
+                        prop:value=move || customer_key.get()
+                        on:input=move |e| set_customer_key_input.set(event_target_value(&e))
+                    />
+                </label>
+                <div class="encryption-key-actions">
+                    <button on:click=on_generate>"Generate"</button>
+                    <button
+                        disabled=move || busy.get() || customer_key.with(|key| key.trim().is_empty())
+                        on:click=move |_| store_key(Some(customer_key.get_untracked()))
+                    >
+                        "Store Key"
+                    </button>
+                    <Show when=has_key>
+                        <button class="danger" disabled=move || busy.get() on:click=move |_| store_key(None)>
+                            "Remove Key"
+                        </button>
+                    </Show>
+                </div>
+                <p class="vault-note">
+                    "S3 does not keep customer keys. Back the key up somewhere safe: objects written with a lost key cannot be read by anyone."
+                </p>
+
+                {move || status.get().map(|status| match status {
+                    Ok(message) => view! { <div class="account-status">{message}</div> },
+                    Err(message) => view! { <div class="account-status error">{message}</div> },
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() || settings.with(Option::is_none) on:click=on_save>
+                        "Save Default"
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
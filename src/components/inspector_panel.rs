@@ -0,0 +1,326 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::object_service::{get_object_metadata, update_objects};
+use crate::types::{ObjectMetadata, ObjectPatch, RestoreState};
+use crate::utils::format::format_file_size;
+
+type Rows = Vec<(String, String)>;
+type Changes = Vec<(String, Option<String>)>;
+
+fn trimmed(rows: &Rows) -> Rows {
+    rows.iter()
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+// Where an archived object stands; objects that were never archived show nothing
+fn restore_label(archived: bool, restore: Option<RestoreState>) -> Option<String> {
+    match restore {
+        Some(restore) if restore.ongoing => Some("In progress".to_string()),
+        Some(RestoreState {
+            expiry: Some(expiry),
+            ..
+        }) => Some(format!("Restored copy until {}", expiry)),
+        _ if archived => Some("Archived, not restored".to_string()),
+        _ => None,
+    }
+}
+
+// Turn the original entries into the edited rows: rows that are new or
+// changed are set, entries without a row are removed
+fn row_changes(original: &Rows, rows: &Rows) -> Changes {
+    let rows = trimmed(rows);
+    let mut changes = original
+        .iter()
+        .filter(|(key, _)| !rows.iter().any(|(row_key, _)| row_key == key))
+        .map(|(key, _)| (key.clone(), None))
+        .collect::<Changes>();
+
+    changes.extend(
+        rows.into_iter()
+            .filter(|row| !original.contains(row))
+            .map(|(key, value)| (key, Some(value))),
+    );
+
+    changes
+}
+
+// In a batch every row is applied, and a row without a value removes its key
+fn batch_changes(rows: &Rows) -> Changes {
+    trimmed(rows)
+        .into_iter()
+        .map(|(key, value)| (key, (!value.is_empty()).then_some(value)))
+        .collect()
+}
+
+// In a batch only filled-in headers are applied, otherwise only changed ones
+fn header_change(original: Option<&String>, value: String, batch: bool) -> Option<String> {
+    let value = value.trim().to_string();
+
+    if batch {
+        (!value.is_empty()).then_some(value)
+    } else {
+        (original.map(String::as_str).unwrap_or("") != value).then_some(value)
+    }
+}
+
+fn info_row(label: &'static str, value: Option<String>) -> impl IntoView {
+    view! {
+        <div class="inspector-info-row">
+            <span class="inspector-info-label">{label}</span>
+            <span class="inspector-info-value">{value.unwrap_or_else(|| "--".to_string())}</span>
+        </div>
+    }
+}
+
+#[component]
+pub fn InspectorPanel(
+    paths: Vec<String>,
+    version_id: Option<String>,
+    read_only: bool,
+    #[prop(into)] on_close: Callback<()>,
+    #[prop(into)] on_saved: Callback<()>,
+) -> impl IntoView {
+    let batch = paths.len() > 1;
+    let title = if batch {
+        format!("{} Objects", paths.len())
+    } else {
+        paths
+            .first()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let paths = StoredValue::new(paths);
+    let version_id = StoredValue::new(version_id);
+
+    let (info, set_info) = signal(Option::<ObjectMetadata>::None);
+    let (content_type, set_content_type) = signal(String::new());
+    let (cache_control, set_cache_control) = signal(String::new());
+    let (content_disposition, set_content_disposition) = signal(String::new());
+    let (metadata_rows, set_metadata_rows) = signal(Rows::new());
+    let (tag_rows, set_tag_rows) = signal(Rows::new());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    // Fill the form from the object; a batch starts out empty
+    let load = move || {
+        set_content_type.set(String::new());
+        set_cache_control.set(String::new());
+        set_content_disposition.set(String::new());
+        set_metadata_rows.set(Rows::new());
+        set_tag_rows.set(Rows::new());
+
+        let Some(path) = paths.with_value(|paths| (!batch).then(|| paths[0].clone())) else {
+            return;
+        };
+
+        set_busy.set(true);
+        spawn_local(async move {
+            match get_object_metadata(path, version_id.get_value()).await {
+                Ok(metadata) => {
+                    set_content_type.set(metadata.content_type.clone().unwrap_or_default());
+                    set_cache_control.set(metadata.cache_control.clone().unwrap_or_default());
+                    set_content_disposition
+                        .set(metadata.content_disposition.clone().unwrap_or_default());
+                    set_metadata_rows.set(metadata.metadata.clone());
+                    set_tag_rows.set(metadata.tags.clone());
+                    set_info.set(Some(metadata));
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    load();
+
+    let on_save = move |_| {
+        let original = info.get_untracked().unwrap_or_default();
+        let patch = ObjectPatch {
+            content_type: header_change(
+                original.content_type.as_ref(),
+                content_type.get_untracked(),
+                batch,
+            ),
+            cache_control: header_change(
+                original.cache_control.as_ref(),
+                cache_control.get_untracked(),
+                batch,
+            ),
+            content_disposition: header_change(
+                original.content_disposition.as_ref(),
+                content_disposition.get_untracked(),
+                batch,
+            ),
+            storage_class: None,
+            metadata: if batch {
+                batch_changes(&metadata_rows.get_untracked())
+            } else {
+                row_changes(&original.metadata, &metadata_rows.get_untracked())
+            },
+            tags: if batch {
+                batch_changes(&tag_rows.get_untracked())
+            } else {
+                row_changes(&original.tags, &tag_rows.get_untracked())
+            },
+        };
+
+        if patch.content_type.is_none()
+            && patch.cache_control.is_none()
+            && patch.content_disposition.is_none()
+            && patch.metadata.is_empty()
+            && patch.tags.is_empty()
+        {
+            set_error.set(Some("Nothing has changed".to_string()));
+            return;
+        }
+
+        set_busy.set(true);
+        spawn_local(async move {
+            let result = update_objects(paths.get_value(), patch).await;
+            set_busy.set(false);
+            match result {
+                Ok(()) => {
+                    set_error.set(None);
+                    on_saved.run(());
+                    load();
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+        });
+    };
+
+    let placeholder = if batch { "Keep current values" } else { "" };
+
+    let header_field =
+        move |label: &'static str, value: ReadSignal<String>, set_value: WriteSignal<String>| {
+            view! {
+                <label class="account-field">
+                    <span>{label}</span>
+                    <input
+                        type="text"
+                        placeholder=placeholder
+                        disabled=read_only
+                        prop:value=move || value.get()
+                        on:input=move |e| set_value.set(event_target_value(&e))
+                    />
+                </label>
+            }
+        };
+
+    // Rows are only updated on change, so typing does not re-render them
+    let rows_editor =
+        move |label: &'static str, rows: ReadSignal<Rows>, set_rows: WriteSignal<Rows>| {
+            view! {
+                <div class="inspector-section">
+                    <div class="inspector-section-title">{label}</div>
+                    {move || rows.get().into_iter().enumerate().map(|(index, (key, value))| view! {
+                        <div class="inspector-row">
+                            <input
+                                type="text"
+                                placeholder="Key"
+                                disabled=read_only
+                                prop:value=key
+                                on:change=move |e| set_rows.update(|rows| {
+                                    if let Some(row) = rows.get_mut(index) {
+                                        row.0 = event_target_value(&e);
+                                    }
+                                })
+                            />
+                            <input
+                                type="text"
+                                placeholder="Value"
+                                disabled=read_only
+                                prop:value=value
+                                on:change=move |e| set_rows.update(|rows| {
+                                    if let Some(row) = rows.get_mut(index) {
+                                        row.1 = event_target_value(&e);
+                                    }
+                                })
+                            />
+                            {(!read_only).then(|| view! {
+                                <button
+                                    class="inspector-remove"
+                                    title="Remove"
+                                    on:click=move |_| set_rows.update(|rows| {
+                                        if index < rows.len() {
+                                            rows.remove(index);
+                                        }
+                                    })
+                                >
+                                    "×"
+                                </button>
+                            })}
+                        </div>
+                    }).collect::<Vec<_>>()}
+                    {(!read_only).then(|| view! {
+                        <button
+                            class="inspector-add"
+                            on:click=move |_| set_rows.update(|rows| rows.push(Default::default()))
+                        >
+                            "Add"
+                        </button>
+                    })}
+                </div>
+            }
+        };
+
+    view! {
+        <div class="inspector-panel">
+            <div class="inspector-header">
+                <span class="inspector-title" title=title.clone()>{title.clone()}</span>
+                <button on:click=move |_| on_close.run(())>"Close"</button>
+            </div>
+
+            <div class="inspector-body">
+                {move || info.get().map(|metadata| view! {
+                    <div class="inspector-section">
+                        {info_row("Size", Some(format_file_size(metadata.size)))}
+                        {info_row("Modified", metadata.modified)}
+                        {info_row("ETag", metadata.e_tag)}
+                        {info_row("Storage Class", metadata.storage_class.or(Some("STANDARD".to_string())))}
+                        {info_row("Restore", restore_label(metadata.archived, metadata.restore))}
+                        {info_row("Encryption", metadata.encryption)}
+                        {metadata.kms_key_id.map(|key| info_row("KMS Key", Some(key)))}
+                        {info_row("Checksum", metadata.checksum)}
+                        {info_row("Version ID", metadata.version_id)}
+                        {info_row("Content-Encoding", metadata.content_encoding)}
+                        {info_row("Content-Language", metadata.content_language)}
+                    </div>
+                })}
+
+                {batch.then(|| view! {
+                    <p class="vault-note">
+                        "Filled-in fields are applied to every selected object. "
+                        "A metadata or tag row without a value removes that key."
+                    </p>
+                })}
+                {read_only.then(|| view! {
+                    <p class="vault-note">"Only the current version of an object can be edited."</p>
+                })}
+
+                <div class="inspector-section">
+                    {header_field("Content-Type", content_type, set_content_type)}
+                    {header_field("Cache-Control", cache_control, set_cache_control)}
+                    {header_field("Content-Disposition", content_disposition, set_content_disposition)}
+                </div>
+
+                {rows_editor("Metadata (x-amz-meta-*)", metadata_rows, set_metadata_rows)}
+                {rows_editor("Tags", tag_rows, set_tag_rows)}
+
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+            </div>
+
+            {(!read_only).then(|| view! {
+                <div class="dialog-buttons inspector-buttons">
+                    <button disabled=move || busy.get() on:click=on_save>"Save"</button>
+                </div>
+            })}
+        </div>
+    }
+}
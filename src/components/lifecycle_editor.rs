@@ -0,0 +1,632 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::bucket_service::{
+    format_lifecycle, get_bucket_lifecycle, parse_lifecycle, put_bucket_lifecycle,
+};
+use crate::types::{
+    AbortIncompleteMultipartUpload, Expiration, LifecycleAnd, LifecycleConfiguration,
+    LifecycleFilter, LifecycleRule, LifecycleTag, NoncurrentVersionTransition, Transition,
+};
+
+// Classes lifecycle rules can move objects to, cheapest to retrieve first
+const STORAGE_CLASSES: [&str; 6] = [
+    "STANDARD_IA",
+    "ONEZONE_IA",
+    "INTELLIGENT_TIERING",
+    "GLACIER_IR",
+    "GLACIER",
+    "DEEP_ARCHIVE",
+];
+
+// Raw views; the form is "form"
+const FORMATS: [(&str, &str); 2] = [("json", "JSON"), ("xml", "XML")];
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    value.trim().parse().ok()
+}
+
+fn number_label<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+// Transitions and expirations take either a number of days or a date
+fn parse_when(value: &str) -> (Option<i32>, Option<String>) {
+    let value = value.trim();
+
+    if value.is_empty() {
+        (None, None)
+    } else if let Ok(days) = value.parse() {
+        (Some(days), None)
+    } else {
+        (None, Some(value.to_string()))
+    }
+}
+
+fn when_label(days: Option<i32>, date: Option<&String>) -> String {
+    days.map(|days| days.to_string())
+        .or_else(|| date.cloned())
+        .unwrap_or_default()
+}
+
+fn tags_label(tags: &[LifecycleTag]) -> String {
+    tags.iter()
+        .map(|tag| format!("{}={}", tag.key, tag.value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_tags(value: &str) -> Vec<LifecycleTag> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            LifecycleTag {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+// The form edits the conditions of a filter separately; S3 wants a single
+// condition on its own and several wrapped in And
+#[derive(Default)]
+struct FilterFields {
+    prefix: String,
+    tags: Vec<LifecycleTag>,
+    greater_than: Option<i64>,
+    less_than: Option<i64>,
+}
+
+fn filter_fields(filter: Option<&LifecycleFilter>) -> FilterFields {
+    let Some(filter) = filter else {
+        return FilterFields::default();
+    };
+
+    match &filter.and {
+        Some(and) => FilterFields {
+            prefix: and.prefix.clone().unwrap_or_default(),
+            tags: and.tags.clone(),
+            greater_than: and.object_size_greater_than,
+            less_than: and.object_size_less_than,
+        },
+        None => FilterFields {
+            prefix: filter.prefix.clone().unwrap_or_default(),
+            tags: filter.tag.clone().into_iter().collect(),
+            greater_than: filter.object_size_greater_than,
+            less_than: filter.object_size_less_than,
+        },
+    }
+}
+
+fn build_filter(fields: FilterFields) -> Option<LifecycleFilter> {
+    let prefix = (!fields.prefix.is_empty()).then_some(fields.prefix);
+    let conditions = usize::from(prefix.is_some())
+        + fields.tags.len()
+        + usize::from(fields.greater_than.is_some())
+        + usize::from(fields.less_than.is_some());
+
+    match conditions {
+        0 => None,
+        1 => Some(LifecycleFilter {
+            prefix,
+            tag: fields.tags.into_iter().next(),
+            object_size_greater_than: fields.greater_than,
+            object_size_less_than: fields.less_than,
+            and: None,
+        }),
+        _ => Some(LifecycleFilter {
+            and: Some(LifecycleAnd {
+                prefix,
+                tags: fields.tags,
+                object_size_greater_than: fields.greater_than,
+                object_size_less_than: fields.less_than,
+            }),
+            ..Default::default()
+        }),
+    }
+}
+
+fn new_rule(rules: &[LifecycleRule]) -> LifecycleRule {
+    let id = (1..)
+        .map(|number| format!("rule-{}", number))
+        .find(|id| !rules.iter().any(|rule| rule.id.as_ref() == Some(id)))
+        .unwrap_or_default();
+
+    LifecycleRule {
+        id: Some(id),
+        status: "Enabled".to_string(),
+        abort_incomplete_multipart_upload: Some(AbortIncompleteMultipartUpload {
+            days_after_initiation: 7,
+        }),
+        ..Default::default()
+    }
+}
+
+// Field edits do not re-render the form, so focus stays where it is; only
+// adding or removing parts of a rule does
+fn edit_rule(
+    set_rules: WriteSignal<Vec<LifecycleRule>>,
+    index: usize,
+    edit: impl FnOnce(&mut LifecycleRule),
+) {
+    set_rules.update_untracked(|rules| {
+        if let Some(rule) = rules.get_mut(index) {
+            edit(rule);
+        }
+    });
+}
+
+fn edit_filter(
+    set_rules: WriteSignal<Vec<LifecycleRule>>,
+    index: usize,
+    edit: impl FnOnce(&mut FilterFields),
+) {
+    edit_rule(set_rules, index, |rule| {
+        let mut fields = filter_fields(rule.filter.as_ref());
+        edit(&mut fields);
+        rule.filter = build_filter(fields);
+    });
+}
+
+fn text_field(
+    label: &'static str,
+    value: String,
+    placeholder: &'static str,
+    on_change: impl Fn(String) + 'static,
+) -> impl IntoView {
+    view! {
+        <label class="account-field">
+            <span>{label}</span>
+            <input
+                type="text"
+                placeholder=placeholder
+                prop:value=value
+                on:change=move |e| on_change(event_target_value(&e))
+            />
+        </label>
+    }
+}
+
+fn class_select(value: String, on_change: impl Fn(String) + 'static) -> impl IntoView {
+    view! {
+        <select on:change=move |e| on_change(event_target_value(&e))>
+            {STORAGE_CLASSES.iter().map(|&class| {
+                let selected = class == value;
+                view! { <option value=class selected=selected>{class}</option> }
+            }).collect::<Vec<_>>()}
+        </select>
+    }
+}
+
+fn rule_view(
+    index: usize,
+    rule: LifecycleRule,
+    set_rules: WriteSignal<Vec<LifecycleRule>>,
+) -> impl IntoView {
+    let fields = filter_fields(rule.filter.as_ref());
+    let expiration = rule.expiration.clone().unwrap_or_default();
+    let removes_markers = expiration.expired_object_delete_marker == Some(true);
+    let noncurrent_expiration = rule
+        .noncurrent_version_expiration
+        .clone()
+        .unwrap_or_default();
+
+    view! {
+        <div class="lifecycle-rule">
+            <div class="lifecycle-rule-header">
+                <input
+                    type="text"
+                    class="lifecycle-rule-id"
+                    placeholder="Rule ID"
+                    prop:value=rule.id.clone().unwrap_or_default()
+                    on:change=move |e| {
+                        let id = event_target_value(&e).trim().to_string();
+                        edit_rule(set_rules, index, |rule| rule.id = (!id.is_empty()).then_some(id));
+                    }
+                />
+                <label class="lifecycle-check">
+                    <input
+                        type="checkbox"
+                        prop:checked=rule.status == "Enabled"
+                        on:change=move |e| {
+                            let status = if event_target_checked(&e) { "Enabled" } else { "Disabled" };
+                            edit_rule(set_rules, index, |rule| rule.status = status.to_string());
+                        }
+                    />
+                    "Enabled"
+                </label>
+                <button
+                    class="inspector-remove"
+                    title="Remove Rule"
+                    on:click=move |_| set_rules.update(|rules| {
+                        if index < rules.len() {
+                            rules.remove(index);
+                        }
+                    })
+                >
+                    "×"
+                </button>
+            </div>
+
+            <div class="inspector-section-title">"Filter"</div>
+            <div class="lifecycle-grid">
+                {text_field("Prefix", fields.prefix, "Whole bucket", move |value| {
+                    edit_filter(set_rules, index, |fields| fields.prefix = value.trim().to_string());
+                })}
+                {text_field("Tags", tags_label(&fields.tags), "key=value, …", move |value| {
+                    edit_filter(set_rules, index, |fields| fields.tags = parse_tags(&value));
+                })}
+                {text_field("Larger Than (bytes)", number_label(fields.greater_than), "", move |value| {
+                    edit_filter(set_rules, index, |fields| fields.greater_than = parse_number(&value));
+                })}
+                {text_field("Smaller Than (bytes)", number_label(fields.less_than), "", move |value| {
+                    edit_filter(set_rules, index, |fields| fields.less_than = parse_number(&value));
+                })}
+            </div>
+
+            <div class="inspector-section-title">"Current Versions"</div>
+            {rule.transitions.iter().enumerate().map(|(position, transition)| view! {
+                <div class="lifecycle-row">
+                    <span>"Move to"</span>
+                    {class_select(transition.storage_class.clone(), move |class| {
+                        edit_rule(set_rules, index, |rule| {
+                            if let Some(transition) = rule.transitions.get_mut(position) {
+                                transition.storage_class = class;
+                            }
+                        });
+                    })}
+                    <span>"after"</span>
+                    <input
+                        type="text"
+                        placeholder="Days or YYYY-MM-DD"
+                        prop:value=when_label(transition.days, transition.date.as_ref())
+                        on:change=move |e| {
+                            let (days, date) = parse_when(&event_target_value(&e));
+                            edit_rule(set_rules, index, |rule| {
+                                if let Some(transition) = rule.transitions.get_mut(position) {
+                                    transition.days = days;
+                                    transition.date = date;
+                                }
+                            });
+                        }
+                    />
+                    <button
+                        class="inspector-remove"
+                        title="Remove"
+                        on:click=move |_| set_rules.update(|rules| {
+                            if let Some(rule) = rules.get_mut(index) {
+                                if position < rule.transitions.len() {
+                                    rule.transitions.remove(position);
+                                }
+                            }
+                        })
+                    >
+                        "×"
+                    </button>
+                </div>
+            }).collect::<Vec<_>>()}
+            <button
+                class="inspector-add"
+                on:click=move |_| set_rules.update(|rules| {
+                    if let Some(rule) = rules.get_mut(index) {
+                        rule.transitions.push(Transition {
+                            days: Some(30),
+                            storage_class: "STANDARD_IA".to_string(),
+                            ..Default::default()
+                        });
+                    }
+                })
+            >
+                "Add Transition"
+            </button>
+            <div class="lifecycle-grid">
+                {text_field(
+                    "Expire After",
+                    when_label(expiration.days, expiration.date.as_ref()),
+                    "Days or YYYY-MM-DD",
+                    move |value| set_rules.update(|rules| {
+                        if let Some(rule) = rules.get_mut(index) {
+                            let (days, date) = parse_when(&value);
+                            // An expiration either expires objects or removes
+                            // delete markers, never both
+                            rule.expiration = (days.is_some() || date.is_some()).then_some(Expiration {
+                                days,
+                                date,
+                                expired_object_delete_marker: None,
+                            });
+                        }
+                    }),
+                )}
+                <label class="lifecycle-check">
+                    <input
+                        type="checkbox"
+                        prop:checked=removes_markers
+                        on:change=move |e| {
+                            let checked = event_target_checked(&e);
+                            set_rules.update(|rules| {
+                                if let Some(rule) = rules.get_mut(index) {
+                                    rule.expiration = checked.then_some(Expiration {
+                                        expired_object_delete_marker: Some(true),
+                                        ..Default::default()
+                                    });
+                                }
+                            });
+                        }
+                    />
+                    "Remove expired delete markers"
+                </label>
+            </div>
+
+            <div class="inspector-section-title">"Noncurrent Versions"</div>
+            {rule.noncurrent_version_transitions.iter().enumerate().map(|(position, transition)| view! {
+                <div class="lifecycle-row">
+                    <span>"Move to"</span>
+                    {class_select(transition.storage_class.clone(), move |class| {
+                        edit_rule(set_rules, index, |rule| {
+                            if let Some(transition) = rule.noncurrent_version_transitions.get_mut(position) {
+                                transition.storage_class = class;
+                            }
+                        });
+                    })}
+                    <span>"after"</span>
+                    <input
+                        type="text"
+                        placeholder="Days"
+                        prop:value=number_label(transition.noncurrent_days)
+                        on:change=move |e| {
+                            let days = parse_number(&event_target_value(&e));
+                            edit_rule(set_rules, index, |rule| {
+                                if let Some(transition) = rule.noncurrent_version_transitions.get_mut(position) {
+                                    transition.noncurrent_days = days;
+                                }
+                            });
+                        }
+                    />
+                    <span>"keeping"</span>
+                    <input
+                        type="text"
+                        placeholder="Newest"
+                        prop:value=number_label(transition.newer_noncurrent_versions)
+                        on:change=move |e| {
+                            let versions = parse_number(&event_target_value(&e));
+                            edit_rule(set_rules, index, |rule| {
+                                if let Some(transition) = rule.noncurrent_version_transitions.get_mut(position) {
+                                    transition.newer_noncurrent_versions = versions;
+                                }
+                            });
+                        }
+                    />
+                    <button
+                        class="inspector-remove"
+                        title="Remove"
+                        on:click=move |_| set_rules.update(|rules| {
+                            if let Some(rule) = rules.get_mut(index) {
+                                if position < rule.noncurrent_version_transitions.len() {
+                                    rule.noncurrent_version_transitions.remove(position);
+                                }
+                            }
+                        })
+                    >
+                        "×"
+                    </button>
+                </div>
+            }).collect::<Vec<_>>()}
+            <button
+                class="inspector-add"
+                on:click=move |_| set_rules.update(|rules| {
+                    if let Some(rule) = rules.get_mut(index) {
+                        rule.noncurrent_version_transitions.push(NoncurrentVersionTransition {
+                            noncurrent_days: Some(30),
+                            storage_class: "STANDARD_IA".to_string(),
+                            newer_noncurrent_versions: None,
+                        });
+                    }
+                })
+            >
+                "Add Transition"
+            </button>
+            <div class="lifecycle-grid">
+                {text_field(
+                    "Expire After (days)",
+                    number_label(noncurrent_expiration.noncurrent_days),
+                    "Never",
+                    move |value| edit_rule(set_rules, index, |rule| {
+                        let mut expiration = rule.noncurrent_version_expiration.take().unwrap_or_default();
+                        expiration.noncurrent_days = parse_number(&value);
+                        rule.noncurrent_version_expiration = (expiration.noncurrent_days.is_some()
+                            || expiration.newer_noncurrent_versions.is_some())
+                            .then_some(expiration);
+                    }),
+                )}
+                {text_field(
+                    "Keep Newest Versions",
+                    number_label(noncurrent_expiration.newer_noncurrent_versions),
+                    "None",
+                    move |value| edit_rule(set_rules, index, |rule| {
+                        let mut expiration = rule.noncurrent_version_expiration.take().unwrap_or_default();
+                        expiration.newer_noncurrent_versions = parse_number(&value);
+                        rule.noncurrent_version_expiration = (expiration.noncurrent_days.is_some()
+                            || expiration.newer_noncurrent_versions.is_some())
+                            .then_some(expiration);
+                    }),
+                )}
+            </div>
+
+            <div class="inspector-section-title">"Incomplete Multipart Uploads"</div>
+            <div class="lifecycle-grid">
+                {text_field(
+                    "Abort After (days)",
+                    number_label(rule.abort_incomplete_multipart_upload.as_ref().map(|abort| abort.days_after_initiation)),
+                    "Never",
+                    move |value| edit_rule(set_rules, index, |rule| {
+                        rule.abort_incomplete_multipart_upload = parse_number(&value)
+                            .map(|days| AbortIncompleteMultipartUpload { days_after_initiation: days });
+                    }),
+                )}
+            </div>
+        </div>
+    }
+}
+
+// What is being edited, read back from the raw text when that is shown
+async fn current_configuration(
+    mode: String,
+    raw: String,
+    rules: Vec<LifecycleRule>,
+) -> Result<LifecycleConfiguration, String> {
+    if mode == "form" {
+        Ok(LifecycleConfiguration { rules })
+    } else {
+        parse_lifecycle(raw, mode).await
+    }
+}
+
+/// Reads, edits and saves the lifecycle rules of a bucket, as a form or as
+/// the JSON or XML S3 documents them in. Rules are validated when saved.
+#[component]
+pub fn LifecycleEditor(path: String) -> impl IntoView {
+    let path = StoredValue::new(path);
+    let (rules, set_rules) = signal(Vec::<LifecycleRule>::new());
+    let (mode, set_mode) = signal("form".to_string());
+    let (raw, set_raw) = signal(String::new());
+    let (notice, set_notice) = signal(Option::<String>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let load = move || {
+        set_busy.set(true);
+        set_error.set(None);
+        set_notice.set(None);
+        spawn_local(async move {
+            match get_bucket_lifecycle(path.get_value()).await {
+                Ok(configuration) => {
+                    set_mode.set("form".to_string());
+                    set_rules.set(configuration.rules);
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    load();
+
+    let switch_mode = move |next: &'static str| {
+        let current = mode.get_untracked();
+        if current == next {
+            return;
+        }
+
+        set_error.set(None);
+        set_notice.set(None);
+        spawn_local(async move {
+            let result = async {
+                let configuration =
+                    current_configuration(current, raw.get_untracked(), rules.get_untracked())
+                        .await?;
+                if next != "form" {
+                    set_raw.set(format_lifecycle(configuration.clone(), next.to_string()).await?);
+                }
+                set_rules.set(configuration.rules);
+                Ok::<_, String>(())
+            }
+            .await;
+
+            match result {
+                Ok(()) => set_mode.set(next.to_string()),
+                Err(e) => set_error.set(Some(e)),
+            }
+        });
+    };
+
+    let on_save = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        set_notice.set(None);
+        spawn_local(async move {
+            let result = async {
+                let configuration = current_configuration(
+                    mode.get_untracked(),
+                    raw.get_untracked(),
+                    rules.get_untracked(),
+                )
+                .await?;
+                let count = configuration.rules.len();
+                put_bucket_lifecycle(path.get_value(), configuration).await?;
+                Ok::<_, String>(count)
+            }
+            .await;
+
+            match result {
+                Ok(0) => set_notice.set(Some("Lifecycle rules removed".to_string())),
+                Ok(1) => set_notice.set(Some("Saved 1 rule".to_string())),
+                Ok(count) => set_notice.set(Some(format!("Saved {} rules", count))),
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="lifecycle-editor">
+            <div class="settings-tabs lifecycle-modes">
+                <button class:active=move || mode.get() == "form" on:click=move |_| switch_mode("form")>
+                    "Form"
+                </button>
+                {FORMATS.iter().map(|&(format, label)| view! {
+                    <button class:active=move || mode.get() == format on:click=move |_| switch_mode(format)>
+                        {label}
+                    </button>
+                }).collect::<Vec<_>>()}
+            </div>
+
+            {move || if mode.get() == "form" {
+                let rules = rules.get();
+                view! {
+                    <div class="lifecycle-rules">
+                        {rules.is_empty().then(|| view! {
+                            <p class="vault-note">"This bucket has no lifecycle rules."</p>
+                        })}
+                        {rules.into_iter().enumerate()
+                            .map(|(index, rule)| rule_view(index, rule, set_rules))
+                            .collect::<Vec<_>>()}
+                        <button
+                            class="inspector-add"
+                            on:click=move |_| set_rules.update(|rules| {
+                                let rule = new_rule(rules);
+                                rules.push(rule);
+                            })
+                        >
+                            "Add Rule"
+                        </button>
+                    </div>
+                }.into_any()
+            } else {
+                view! {
+                    <textarea
+                        class="lifecycle-raw"
+                        spellcheck="false"
+                        prop:value=move || raw.get()
+                        on:input=move |e| set_raw.set(event_target_value(&e))
+                    ></textarea>
+                }.into_any()
+            }}
+
+            {move || notice.get().map(|message| view! {
+                <div class="account-status ok">{message}</div>
+            })}
+            {move || error.get().map(|message| view! {
+                <div class="account-status error">{message}</div>
+            })}
+
+            <div class="dialog-buttons">
+                <button disabled=move || busy.get() on:click=move |_| load()>"Reload"</button>
+                <button disabled=move || busy.get() on:click=on_save>"Save"</button>
+            </div>
+        </div>
+    }
+}
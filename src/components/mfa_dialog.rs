@@ -0,0 +1,80 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::account_service::start_role_session;
+use crate::types::Account;
+
+/// Asks for an MFA token code to start a role session for an account.
+#[component]
+pub fn MfaDialog(
+    account: Account,
+    #[prop(into)] on_success: Callback<()>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (code, set_code) = signal(String::new());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let id = account.id.clone();
+    let submit = move || {
+        if busy.get_untracked() {
+            return;
+        }
+        let id = id.clone();
+        set_busy.set(true);
+        spawn_local(async move {
+            match start_role_session(id, code.get_untracked()).await {
+                Ok(_) => on_success.run(()),
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_code.set(String::new());
+                }
+            }
+            set_busy.set(false);
+        });
+    };
+    let on_click = {
+        let submit = submit.clone();
+        move |_| submit()
+    };
+
+    let role_arn = account
+        .role
+        .as_ref()
+        .map(|role| role.role_arn.clone())
+        .unwrap_or_default();
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog mfa-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"MFA Code Required"</h3>
+                <p class="vault-note">
+                    {format!("Enter a code from your MFA device to assume {} for {}.", role_arn, account.name)}
+                </p>
+                <label class="account-field">
+                    <span>"MFA Code"</span>
+                    <input
+                        type="text"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        autofocus
+                        prop:value=move || code.get()
+                        on:input=move |e| set_code.set(event_target_value(&e))
+                        on:keydown=move |e| {
+                            if e.key() == "Enter" {
+                                submit();
+                            }
+                        }
+                    />
+                </label>
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Cancel"</button>
+                    <button disabled=move || busy.get() on:click=on_click>"Continue"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
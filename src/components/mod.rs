@@ -1 +1,16 @@
+pub mod accounts_dialog;
+pub mod bucket_dialogs;
+pub mod bucket_properties;
+pub mod bucket_settings;
+pub mod encryption_dialog;
 pub mod file_icon;
+pub mod inspector_panel;
+pub mod lifecycle_editor;
+pub mod mfa_dialog;
+pub mod presign_dialog;
+pub mod storage_class_dialog;
+pub mod sync_dialog;
+pub mod transfers_panel;
+pub mod trash;
+pub mod vault_dialog;
+pub mod watch_folders;
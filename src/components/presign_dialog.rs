@@ -0,0 +1,212 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use wasm_bindgen::JsValue;
+
+use crate::services::object_service::{clear_presigned_urls, list_presigned_urls, presign_url};
+use crate::types::PresignedUrl;
+use crate::utils::clipboard::copy_to_clipboard;
+
+// Expiry choices, in seconds; S3 allows at most seven days
+const EXPIRIES: [(u64, &str); 5] = [
+    (900, "15 minutes"),
+    (3600, "1 hour"),
+    (43200, "12 hours"),
+    (86400, "1 day"),
+    (604800, "7 days"),
+];
+
+fn display_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+// Local time a link expires, or "Expired" once it has
+fn expiry_label(expires_at: &str) -> String {
+    let expiry = js_sys::Date::new(&JsValue::from_str(expires_at));
+
+    if expiry.get_time() <= js_sys::Date::now() {
+        "Expired".to_string()
+    } else {
+        let time: String = expiry
+            .to_locale_string("default", &JsValue::UNDEFINED)
+            .into();
+        format!("Expires {}", time)
+    }
+}
+
+/// Signs GET links to share an object, or PUT links that let someone upload
+/// to a key, and copies them to the clipboard.
+#[component]
+pub fn PresignDialog(
+    path: String,
+    is_dir: bool,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let folder = StoredValue::new(path.clone());
+    let (method, set_method) = signal(if is_dir { "PUT" } else { "GET" }.to_string());
+    let (file_name, set_file_name) = signal(String::new());
+    let (expires_in, set_expires_in) = signal(3600u64);
+    let (link, set_link) = signal(Option::<PresignedUrl>::None);
+    let (history, set_history) = signal(Vec::<PresignedUrl>::new());
+    let (notice, set_notice) = signal(Option::<String>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let load_history = move || {
+        spawn_local(async move {
+            match list_presigned_urls().await {
+                Ok(links) => set_history.set(links),
+                Err(e) => set_error.set(Some(e)),
+            }
+        });
+    };
+
+    load_history();
+
+    let copy = move |url: String| {
+        spawn_local(async move {
+            match copy_to_clipboard(&url).await {
+                Ok(()) => set_notice.set(Some("Copied to the clipboard".to_string())),
+                Err(e) => set_error.set(Some(e)),
+            }
+        });
+    };
+
+    // Upload links from a folder point at a new key inside it
+    let on_generate = move |_| {
+        let target = if is_dir {
+            let name = file_name.get_untracked().trim().to_string();
+            if name.is_empty() || name.contains('/') {
+                set_error.set(Some("Enter a file name for the upload".to_string()));
+                return;
+            }
+            format!("{}{}", folder.get_value(), name)
+        } else {
+            folder.get_value()
+        };
+
+        set_busy.set(true);
+        set_error.set(None);
+        set_notice.set(None);
+        spawn_local(async move {
+            match presign_url(target, method.get_untracked(), expires_in.get_untracked()).await {
+                Ok(generated) => {
+                    copy(generated.url.clone());
+                    set_link.set(Some(generated));
+                    load_history();
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_clear = move |_| {
+        spawn_local(async move {
+            match clear_presigned_urls().await {
+                Ok(()) => set_history.set(Vec::new()),
+                Err(e) => set_error.set(Some(e)),
+            }
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog presign-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>{if is_dir { "Upload Link" } else { "Share Link" }}</h3>
+                <p class="vault-note">{path.clone()}</p>
+
+                {if is_dir {
+                    view! {
+                        <label class="account-field">
+                            <span>"File Name"</span>
+                            <input
+                                type="text"
+                                autofocus
+                                prop:value=move || file_name.get()
+                                on:input=move |e| set_file_name.set(event_target_value(&e))
+                            />
+                        </label>
+                    }.into_any()
+                } else {
+                    view! {
+                        <label class="account-field">
+                            <span>"Method"</span>
+                            <select on:change=move |e| set_method.set(event_target_value(&e))>
+                                <option value="GET" selected=move || method.get() == "GET">
+                                    "GET (download)"
+                                </option>
+                                <option value="PUT" selected=move || method.get() == "PUT">
+                                    "PUT (upload, replaces the object)"
+                                </option>
+                            </select>
+                        </label>
+                    }.into_any()
+                }}
+
+                <label class="account-field">
+                    <span>"Expires After"</span>
+                    <select on:change=move |e| {
+                        if let Ok(seconds) = event_target_value(&e).parse() {
+                            set_expires_in.set(seconds);
+                        }
+                    }>
+                        {EXPIRIES.iter().map(|&(seconds, label)| view! {
+                            <option
+                                value=seconds.to_string()
+                                selected=move || expires_in.get() == seconds
+                            >
+                                {label}
+                            </option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+
+                {move || link.get().map(|generated| view! {
+                    <textarea class="presign-url" readonly prop:value=generated.url></textarea>
+                    <p class="vault-note">{expiry_label(&generated.expires_at)}</p>
+                })}
+                {move || notice.get().map(|message| view! {
+                    <div class="account-status ok">{message}</div>
+                })}
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+
+                <div class="presign-history">
+                    <div class="inspector-section-title">"Recent Links"</div>
+                    {move || {
+                        let links = history.get();
+                        if links.is_empty() {
+                            view! { <p class="vault-note">"No links generated yet."</p> }.into_any()
+                        } else {
+                            links.into_iter().map(|entry| {
+                                let url = entry.url.clone();
+                                let expired = expiry_label(&entry.expires_at) == "Expired";
+                                view! {
+                                    <div class="presign-history-item" class:expired=expired>
+                                        <span class="presign-method">{entry.method.clone()}</span>
+                                        <span class="presign-name" title=entry.path.clone()>
+                                            {display_name(&entry.path)}
+                                        </span>
+                                        <span class="presign-expiry">{expiry_label(&entry.expires_at)}</span>
+                                        <button disabled=expired on:click=move |_| copy(url.clone())>
+                                            "Copy"
+                                        </button>
+                                    </div>
+                                }
+                            }).collect::<Vec<_>>().into_any()
+                        }
+                    }}
+                </div>
+
+                <div class="dialog-buttons">
+                    <button class="danger" on:click=on_clear>"Clear History"</button>
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() on:click=on_generate>
+                        "Generate & Copy"
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
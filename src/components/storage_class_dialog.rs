@@ -0,0 +1,173 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::object_service::{change_storage_class, restore_objects};
+
+const STORAGE_CLASSES: [(&str, &str); 7] = [
+    ("STANDARD", "Standard"),
+    ("STANDARD_IA", "Standard-IA"),
+    ("ONEZONE_IA", "One Zone-IA"),
+    ("INTELLIGENT_TIERING", "Intelligent-Tiering"),
+    ("GLACIER_IR", "Glacier Instant Retrieval"),
+    ("GLACIER", "Glacier Flexible Retrieval"),
+    ("DEEP_ARCHIVE", "Glacier Deep Archive"),
+];
+
+const RESTORE_TIERS: [(&str, &str); 3] = [
+    (
+        "Expedited",
+        "Expedited (minutes, Glacier Flexible Retrieval only)",
+    ),
+    ("Standard", "Standard (hours)"),
+    ("Bulk", "Bulk (up to two days, cheapest)"),
+];
+
+/// The name S3 shows for a storage class; unknown classes are kept as is.
+pub fn storage_class_label(class: &str) -> &str {
+    STORAGE_CLASSES
+        .iter()
+        .find(|(value, _)| *value == class)
+        .map(|(_, label)| *label)
+        .unwrap_or(class)
+}
+
+fn describe(paths: &[String]) -> String {
+    match paths {
+        [path] => format!("{} and any objects below it", path),
+        _ => format!("{} selected items and any objects below them", paths.len()),
+    }
+}
+
+/// Moves objects, and everything below the selected folders, to another
+/// storage class by copying each object onto itself.
+#[component]
+pub fn StorageClassDialog(
+    paths: Vec<String>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let summary = describe(&paths);
+    let paths = StoredValue::new(paths);
+    let (class, set_class) = signal("STANDARD_IA".to_string());
+    let (status, set_status) = signal(Option::<Result<String, String>>::None);
+    let (busy, set_busy) = signal(false);
+
+    let on_apply = move |_| {
+        set_busy.set(true);
+        set_status.set(None);
+        spawn_local(async move {
+            let result = change_storage_class(paths.get_value(), class.get_untracked())
+                .await
+                .map(|count| match count {
+                    1 => "1 object changed".to_string(),
+                    count => format!("{} objects changed", count),
+                });
+            set_status.set(Some(result));
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Change Storage Class"</h3>
+                <p class="dialog-message">{summary}</p>
+                <label class="account-field">
+                    <span>"Storage class"</span>
+                    <select on:change=move |e| set_class.set(event_target_value(&e))>
+                        {STORAGE_CLASSES.iter().map(|&(value, label)| view! {
+                            <option value=value selected=move || class.get() == value>{label}</option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+                <p class="vault-note">
+                    "Archived objects have to be restored before their class can be changed. Moving out of an infrequent access or archive class early is charged for the minimum storage duration."
+                </p>
+                {move || status.get().map(|status| match status {
+                    Ok(message) => view! { <div class="account-status">{message}</div> },
+                    Err(message) => view! { <div class="account-status error">{message}</div> },
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() on:click=on_apply>
+                        {move || if busy.get() { "Changing…" } else { "Apply" }}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Requests temporary copies of archived objects. Objects that are not in
+/// Glacier Flexible Retrieval or Deep Archive are skipped.
+#[component]
+pub fn RestoreDialog(paths: Vec<String>, #[prop(into)] on_close: Callback<()>) -> impl IntoView {
+    let summary = describe(&paths);
+    let paths = StoredValue::new(paths);
+    let (days, set_days) = signal(7);
+    let (tier, set_tier) = signal("Standard".to_string());
+    let (status, set_status) = signal(Option::<Result<String, String>>::None);
+    let (busy, set_busy) = signal(false);
+
+    let on_restore = move |_| {
+        set_busy.set(true);
+        set_status.set(None);
+        spawn_local(async move {
+            let result = restore_objects(
+                paths.get_value(),
+                days.get_untracked(),
+                tier.get_untracked(),
+            )
+            .await
+            .map(|count| match count {
+                0 => "Nothing to restore; no archived objects were found".to_string(),
+                1 => "Restore requested for 1 object".to_string(),
+                count => format!("Restore requested for {} objects", count),
+            });
+            set_status.set(Some(result));
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Restore from Archive"</h3>
+                <p class="dialog-message">{summary}</p>
+                <label class="account-field">
+                    <span>"Keep the restored copy for (days)"</span>
+                    <input
+                        type="number"
+                        min="1"
+                        prop:value=move || days.get().to_string()
+                        on:input=move |e| {
+                            if let Ok(value) = event_target_value(&e).parse() {
+                                set_days.set(value);
+                            }
+                        }
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"Retrieval tier"</span>
+                    <select on:change=move |e| set_tier.set(event_target_value(&e))>
+                        {RESTORE_TIERS.iter().map(|&(value, label)| view! {
+                            <option value=value selected=move || tier.get() == value>{label}</option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+                <p class="vault-note">
+                    "Restored objects can be previewed and downloaded once the copy is ready. The columns update when it is."
+                </p>
+                {move || status.get().map(|status| match status {
+                    Ok(message) => view! { <div class="account-status">{message}</div> },
+                    Err(message) => view! { <div class="account-status error">{message}</div> },
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() on:click=on_restore>
+                        {move || if busy.get() { "Requesting…" } else { "Restore" }}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,331 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashMap;
+
+use crate::services::sync_service::{plan_sync, run_sync};
+use crate::types::{SyncAction, SyncOptions, SyncPlan};
+use crate::utils::format::format_file_size;
+
+// Policy, direction and how the pair is described
+const MODES: [(&str, &str, &str); 5] = [
+    ("mirror", "upload", "Mirror the local folder to S3"),
+    ("mirror", "download", "Mirror S3 to the local folder"),
+    (
+        "two-way",
+        "upload",
+        "Two-way, copying changes and deletes both ways",
+    ),
+    (
+        "never-delete",
+        "upload",
+        "Copy new and changed files to S3, never delete",
+    ),
+    (
+        "never-delete",
+        "download",
+        "Copy new and changed files from S3, never delete",
+    ),
+];
+
+const COMPARE_MODES: [(&str, &str); 3] = [
+    ("size-mtime", "Size and modification time"),
+    ("etag", "ETag (MD5 of the content)"),
+    ("checksum", "SHA-256 checksums (reads files in full)"),
+];
+
+const RESOLUTIONS: [(&str, &str); 4] = [
+    ("ask", "Ask for each conflict"),
+    ("keep-both", "Keep both, renaming the local copy"),
+    ("newer-wins", "The newer copy wins"),
+    ("local-wins", "The local folder wins"),
+];
+
+fn operation_label(operation: &str) -> &'static str {
+    match operation {
+        "upload" => "Upload",
+        "download" => "Download",
+        "delete-local" => "Delete local",
+        "delete-remote" => "Delete in S3",
+        "keep-both" => "Keep both",
+        "skip" => "Skip",
+        _ => "",
+    }
+}
+
+fn change_label(change: &str) -> &'static str {
+    match change {
+        "new" => "New",
+        "modified" => "Modified",
+        "deleted" => "Deleted",
+        "conflict" => "Conflict",
+        _ => "",
+    }
+}
+
+// Operation of an action, after any choice made for its conflict
+fn operation_of<'a>(action: &'a SyncAction, chosen: &'a HashMap<String, String>) -> &'a str {
+    chosen.get(&action.path).unwrap_or(&action.operation)
+}
+
+fn summary(plan: &SyncPlan, chosen: &HashMap<String, String>) -> String {
+    let count = |operations: &[&str]| -> (usize, u64) {
+        plan.actions
+            .iter()
+            .filter(|action| operations.contains(&operation_of(action, chosen)))
+            .fold((0, 0), |(count, size), action| {
+                (count + 1, size + action.size)
+            })
+    };
+
+    let mut parts = Vec::new();
+    for (operations, verb) in [
+        (&["upload"][..], "to upload"),
+        (&["download"][..], "to download"),
+        (&["keep-both"][..], "to keep both of"),
+        (&["delete-local", "delete-remote"][..], "to delete"),
+        (&["skip"][..], "skipped"),
+    ] {
+        let (files, size) = count(operations);
+        if files > 0 {
+            parts.push(format!("{} {} ({})", files, verb, format_file_size(size)));
+        }
+    }
+    parts.push(format!("{} unchanged", plan.unchanged));
+
+    parts.join(", ")
+}
+
+/// Compares a local folder with an S3 prefix, shows the uploads, downloads
+/// and deletes a sync would make, and queues them once reviewed. Conflicts of
+/// a two-way sync can be resolved one by one in the plan.
+#[component]
+pub fn SyncDialog(
+    local: String,
+    remote: String,
+    #[prop(into)] on_started: Callback<()>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (local, set_local) = signal(local);
+    let (remote, set_remote) = signal(remote);
+    let (mode, set_mode) = signal(0usize);
+    let (compare, set_compare) = signal("size-mtime".to_string());
+    let (resolution, set_resolution) = signal("ask".to_string());
+    let (plan, set_plan) = signal(Option::<SyncPlan>::None);
+    // Operations picked for conflicts, by path
+    let (chosen, set_chosen) = signal(HashMap::<String, String>::new());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    // Any change makes the shown plan stale
+    let edited = move || {
+        set_plan.set(None);
+        set_chosen.set(HashMap::new());
+        set_error.set(None);
+    };
+    let two_way = move || MODES[mode.get()].0 == "two-way";
+
+    let on_preview = move |_| {
+        let (policy, direction, _) = MODES[mode.get_untracked()];
+        let options = SyncOptions {
+            local: local.get_untracked().trim().to_string(),
+            remote: remote.get_untracked().trim().to_string(),
+            policy: policy.to_string(),
+            direction: direction.to_string(),
+            compare: compare.get_untracked(),
+            resolution: resolution.get_untracked(),
+        };
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match plan_sync(options).await {
+                Ok(new_plan) => set_plan.set(Some(new_plan)),
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_sync = move |_| {
+        let Some(mut plan) = plan.get_untracked() else {
+            return;
+        };
+        chosen.with_untracked(|chosen| {
+            for action in &mut plan.actions {
+                if let Some(operation) = chosen.get(&action.path) {
+                    action.operation = operation.clone();
+                }
+            }
+        });
+        set_busy.set(true);
+        spawn_local(async move {
+            match run_sync(plan).await {
+                Ok(run) if run.errors.is_empty() => on_started.run(()),
+                // The rest of the plan is under way, so it cannot be run again
+                Ok(run) => {
+                    set_plan.set(None);
+                    set_chosen.set(HashMap::new());
+                    set_error.set(Some(format!(
+                        "Some steps could not be started: {}",
+                        run.errors.join("; ")
+                    )));
+                    set_busy.set(false);
+                }
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    let plan_summary = move || {
+        plan.with(|plan| {
+            plan.as_ref()
+                .map(|plan| chosen.with(|chosen| summary(plan, chosen)))
+                .unwrap_or_default()
+        })
+    };
+
+    let action_row = move |action: SyncAction| {
+        let operation = if action.choices.is_empty() {
+            view! {
+                <span class=format!("sync-operation {}", action.operation)>
+                    {operation_label(&action.operation)}
+                </span>
+            }
+            .into_any()
+        } else {
+            let path = action.path.clone();
+            let planned = action.operation.clone();
+            let current =
+                move || chosen.with(|chosen| chosen.get(&path).cloned().unwrap_or(planned.clone()));
+            let path = action.path.clone();
+            view! {
+                <select on:change=move |e| {
+                    let operation = event_target_value(&e);
+                    set_chosen.update(|chosen| {
+                        chosen.insert(path.clone(), operation);
+                    });
+                }>
+                    {action.choices.iter().map(|choice| {
+                        let choice = choice.clone();
+                        let current = current.clone();
+                        view! {
+                            <option value=choice.clone() selected=move || current() == choice>
+                                {operation_label(&choice)}
+                            </option>
+                        }
+                    }).collect::<Vec<_>>()}
+                </select>
+            }
+            .into_any()
+        };
+
+        view! {
+            <div class="sync-action">
+                {operation}
+                <span class=format!("sync-change {}", action.change)>{change_label(&action.change)}</span>
+                <span class="sync-path" title=action.path.clone()>{action.path.clone()}</span>
+                <span class="sync-size">{format_file_size(action.size)}</span>
+                <span class="sync-reason">{action.reason}</span>
+            </div>
+        }
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog sync-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Sync"</h3>
+                <label class="account-field">
+                    <span>"Local folder"</span>
+                    <input
+                        type="text"
+                        placeholder="/home/me/Projects/site"
+                        prop:value=move || local.get()
+                        on:input=move |e| {
+                            set_local.set(event_target_value(&e));
+                            edited();
+                        }
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"S3 folder"</span>
+                    <input
+                        type="text"
+                        placeholder="s3://bucket/prefix/"
+                        prop:value=move || remote.get()
+                        on:input=move |e| {
+                            set_remote.set(event_target_value(&e));
+                            edited();
+                        }
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"Policy"</span>
+                    <select on:change=move |e| {
+                        if let Ok(index) = event_target_value(&e).parse() {
+                            set_mode.set(index);
+                        }
+                        edited();
+                    }>
+                        {MODES.iter().enumerate().map(|(index, &(_, _, label))| view! {
+                            <option value=index.to_string() selected=move || mode.get() == index>{label}</option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+                <label class="account-field">
+                    <span>"Compare files by"</span>
+                    <select on:change=move |e| {
+                        set_compare.set(event_target_value(&e));
+                        edited();
+                    }>
+                        {COMPARE_MODES.iter().map(|&(value, label)| view! {
+                            <option value=value selected=move || compare.get() == value>{label}</option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </label>
+                <Show when=two_way>
+                    <label class="account-field">
+                        <span>"When both sides changed"</span>
+                        <select on:change=move |e| {
+                            set_resolution.set(event_target_value(&e));
+                            edited();
+                        }>
+                            {RESOLUTIONS.iter().map(|&(value, label)| view! {
+                                <option value=value selected=move || resolution.get() == value>{label}</option>
+                            }).collect::<Vec<_>>()}
+                        </select>
+                    </label>
+                </Show>
+
+                {move || plan.get().map(|plan| view! {
+                    <div class="sync-plan">
+                        <div class="sync-summary">{plan_summary}</div>
+                        {if plan.actions.is_empty() {
+                            view! { <div class="sync-empty">"Both sides are in sync"</div> }.into_any()
+                        } else {
+                            view! {
+                                <div class="sync-actions">
+                                    {plan.actions.into_iter().map(action_row).collect::<Vec<_>>()}
+                                </div>
+                            }.into_any()
+                        }}
+                    </div>
+                })}
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    <button disabled=move || busy.get() on:click=on_preview>
+                        {move || if busy.get() && plan.get().is_none() { "Comparing…" } else { "Preview" }}
+                    </button>
+                    <button disabled=move || busy.get() || plan.with(Option::is_none) on:click=on_sync>
+                        "Sync"
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,513 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::transfer_service::{
+    abort_download, abort_upload, cancel_delete, cancel_transfer, clear_finished_deletes,
+    clear_finished_transfers, get_transfer_settings, list_incomplete_downloads,
+    list_incomplete_uploads, pause_transfer, resume_download, resume_transfer, resume_upload,
+    update_transfer_settings,
+};
+use crate::types::{
+    DeleteJob, DownloadState, Encryption, TransferJob, TransferSettings, UploadState,
+};
+use crate::utils::format::{format_duration, format_file_size};
+
+fn display_name(path: &str) -> String {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn percent(done: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64 * 100.0).min(100.0)
+    }
+}
+
+// Replace a job in the list, or add it when it is new
+pub fn upsert_job(jobs: &mut Vec<TransferJob>, job: TransferJob) {
+    match jobs.iter_mut().find(|existing| existing.id == job.id) {
+        Some(existing) => *existing = job,
+        None => jobs.push(job),
+    }
+}
+
+pub fn upsert_delete(deletes: &mut Vec<DeleteJob>, job: DeleteJob) {
+    match deletes.iter_mut().find(|existing| existing.id == job.id) {
+        Some(existing) => *existing = job,
+        None => deletes.push(job),
+    }
+}
+
+// Failed keys shown per delete; the rest are only counted
+const MAX_FAILURES_SHOWN: usize = 50;
+
+fn delete_view(job: DeleteJob, set_deletes: WriteSignal<Vec<DeleteJob>>) -> impl IntoView {
+    let id = job.id;
+    let status = job.status.clone();
+    let name = match job.paths.as_slice() {
+        [path] => display_name(path),
+        paths => format!("{} items", paths.len()),
+    };
+    let failed = job.failures.len() as u64;
+
+    let detail = match status.as_str() {
+        "running" => format!("Deleted {} of {} found", job.deleted, job.found),
+        "completed" => format!("Deleted {} objects", job.deleted),
+        "cancelled" => format!("Cancelled after deleting {} objects", job.deleted),
+        _ => job.error.clone().unwrap_or_else(|| "Failed".to_string()),
+    };
+
+    let hidden = job.failures.len().saturating_sub(MAX_FAILURES_SHOWN);
+    let failures = (!job.failures.is_empty()).then(|| view! {
+        <ul class="delete-failures">
+            {job.failures.into_iter().take(MAX_FAILURES_SHOWN).map(|failure| view! {
+                <li title=failure.message.clone()>
+                    <span class="delete-failure-code">{failure.code.unwrap_or_else(|| "Error".to_string())}</span>
+                    {display_name(&failure.path)}
+                </li>
+            }).collect::<Vec<_>>()}
+            {(hidden > 0).then(|| view! { <li>{format!("…and {} more", hidden)}</li> })}
+        </ul>
+    });
+
+    view! {
+        <div class=format!("transfer-item {}", status)>
+            <div class="transfer-info">
+                <div class="transfer-name" title=job.paths.join("\n")>
+                    {if job.bucket {
+                        format!("Emptying and deleting {}", name)
+                    } else {
+                        format!("Deleting {}", name)
+                    }}
+                </div>
+                <div class="transfer-progress">
+                    <div
+                        class="transfer-progress-fill"
+                        style=format!("width: {:.1}%", percent(job.deleted + failed, job.found))
+                    ></div>
+                </div>
+                <div class="transfer-detail">{detail}</div>
+                {failures}
+            </div>
+            <div class="transfer-actions">
+                {(status == "running").then(|| view! {
+                    <button on:click=move |_| spawn_local(async move {
+                        if let Ok(job) = cancel_delete(id).await {
+                            set_deletes.update(|deletes| upsert_delete(deletes, job));
+                        }
+                    })>
+                        "Cancel"
+                    </button>
+                })}
+            </div>
+        </div>
+    }
+}
+
+const MIB: u64 = 1024 * 1024;
+
+// An upload or download an earlier session left unfinished
+#[derive(Clone)]
+struct Interrupted {
+    id: String,
+    upload: bool,
+    source: String,
+    dest: String,
+    done: u64,
+    total: u64,
+}
+
+fn object_uri(account: Option<&str>, bucket: &str, key: &str) -> String {
+    match account {
+        Some(account) => format!("s3://{}@{}/{}", account, bucket, key),
+        None => format!("s3://{}/{}", bucket, key),
+    }
+}
+
+impl From<UploadState> for Interrupted {
+    fn from(state: UploadState) -> Self {
+        Self {
+            id: state.id,
+            upload: true,
+            source: state.source,
+            dest: object_uri(state.account.as_deref(), &state.bucket, &state.key),
+            done: (state.completed_parts.len() as u64 * state.part_size).min(state.file_size),
+            total: state.file_size,
+        }
+    }
+}
+
+impl From<DownloadState> for Interrupted {
+    fn from(state: DownloadState) -> Self {
+        Self {
+            id: state.id,
+            upload: false,
+            source: object_uri(state.account.as_deref(), &state.bucket, &state.key),
+            dest: state.dest,
+            done: (state.completed_chunks.len() as u64 * state.chunk_size).min(state.size),
+            total: state.size,
+        }
+    }
+}
+
+// Whether `path` is `root` or lies below it
+fn within(path: &str, root: &str) -> bool {
+    let root = root.trim_end_matches('/');
+    path == root
+        || path
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Recorded transfers, less those a job of this session is already handling
+async fn load_interrupted(jobs: &[TransferJob]) -> Result<Vec<Interrupted>, String> {
+    let uploads = list_incomplete_uploads().await?;
+    let downloads = list_incomplete_downloads().await?;
+
+    Ok(uploads
+        .into_iter()
+        .map(Interrupted::from)
+        .chain(downloads.into_iter().map(Interrupted::from))
+        .filter(|transfer| {
+            !jobs.iter().any(|job| {
+                if transfer.upload {
+                    within(&transfer.source, &job.source)
+                } else {
+                    job.dest
+                        .as_deref()
+                        .is_some_and(|dest| within(&transfer.dest, dest))
+                }
+            })
+        })
+        .collect())
+}
+
+fn interrupted_view(
+    transfer: Interrupted,
+    set_interrupted: WriteSignal<Vec<Interrupted>>,
+    on_resumed: impl Fn(Result<TransferJob, String>) + Copy + 'static,
+    set_panel_error: WriteSignal<Option<String>>,
+) -> impl IntoView {
+    let forget =
+        move |id: &str| set_interrupted.update(|all| all.retain(|transfer| transfer.id != id));
+    let (resume_id, abort_id) = (transfer.id.clone(), transfer.id.clone());
+    let upload = transfer.upload;
+
+    view! {
+        <div class="transfer-item paused">
+            <div class="transfer-info">
+                <div class="transfer-name" title=transfer.source.clone()>
+                    {format!(
+                        "{} {}",
+                        if upload { "Uploading" } else { "Downloading" },
+                        display_name(&transfer.source)
+                    )}
+                </div>
+                <div class="transfer-dest" title=transfer.dest.clone()>
+                    {format!("to {}", transfer.dest)}
+                </div>
+                <div class="transfer-progress">
+                    <div
+                        class="transfer-progress-fill"
+                        style=format!("width: {:.1}%", percent(transfer.done, transfer.total))
+                    ></div>
+                </div>
+                <div class="transfer-detail">
+                    {format!(
+                        "Interrupted at {} of {}",
+                        format_file_size(transfer.done),
+                        format_file_size(transfer.total)
+                    )}
+                </div>
+            </div>
+            <div class="transfer-actions">
+                <button on:click=move |_| {
+                    let id = resume_id.clone();
+                    spawn_local(async move {
+                        let result = if upload {
+                            resume_upload(id.clone()).await
+                        } else {
+                            resume_download(id.clone()).await
+                        };
+                        if result.is_ok() {
+                            forget(&id);
+                        }
+                        on_resumed(result);
+                    });
+                }>
+                    "Resume"
+                </button>
+                <button on:click=move |_| {
+                    let id = abort_id.clone();
+                    spawn_local(async move {
+                        let result = if upload {
+                            abort_upload(id.clone()).await
+                        } else {
+                            abort_download(id.clone()).await
+                        };
+                        match result {
+                            Ok(()) => forget(&id),
+                            Err(e) => set_panel_error.set(Some(e)),
+                        }
+                    });
+                }>
+                    "Discard"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn SettingsForm(
+    settings: TransferSettings,
+    set_panel_error: WriteSignal<Option<String>>,
+    #[prop(into)] on_saved: Callback<()>,
+) -> impl IntoView {
+    let (part_mib, set_part_mib) = signal(settings.part_size / MIB);
+    let (concurrency, set_concurrency) = signal(settings.concurrency);
+
+    let save = move |_| {
+        let settings = TransferSettings {
+            part_size: part_mib.get_untracked() * MIB,
+            concurrency: concurrency.get_untracked(),
+        };
+        spawn_local(async move {
+            match update_transfer_settings(settings).await {
+                Ok(_) => {
+                    set_panel_error.set(None);
+                    on_saved.run(());
+                }
+                Err(e) => set_panel_error.set(Some(e)),
+            }
+        });
+    };
+
+    view! {
+        <div class="transfer-settings">
+            <label class="account-field">
+                <span>"Part size (MB)"</span>
+                <input
+                    type="number"
+                    min="5"
+                    prop:value=move || part_mib.get().to_string()
+                    on:input=move |e| {
+                        if let Ok(value) = event_target_value(&e).parse() {
+                            set_part_mib.set(value);
+                        }
+                    }
+                />
+            </label>
+            <label class="account-field">
+                <span>"Parts in parallel"</span>
+                <input
+                    type="number"
+                    min="1"
+                    max="32"
+                    prop:value=move || concurrency.get().to_string()
+                    on:input=move |e| {
+                        if let Ok(value) = event_target_value(&e).parse() {
+                            set_concurrency.set(value);
+                        }
+                    }
+                />
+            </label>
+            <button on:click=save>"Save"</button>
+        </div>
+    }
+}
+
+#[component]
+pub fn TransfersPanel(
+    jobs: ReadSignal<Vec<TransferJob>>,
+    set_jobs: WriteSignal<Vec<TransferJob>>,
+    deletes: ReadSignal<Vec<DeleteJob>>,
+    set_deletes: WriteSignal<Vec<DeleteJob>>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (panel_error, set_panel_error) = signal(Option::<String>::None);
+    let (interrupted, set_interrupted) = signal(Vec::<Interrupted>::new());
+    // The settings being edited, loaded when the form is opened
+    let (settings, set_settings) = signal(Option::<TransferSettings>::None);
+
+    let refresh_interrupted = move || {
+        let jobs = jobs.get_untracked();
+        spawn_local(async move {
+            match load_interrupted(&jobs).await {
+                Ok(transfers) => set_interrupted.set(transfers),
+                Err(e) => set_panel_error.set(Some(e)),
+            }
+        })
+    };
+    refresh_interrupted();
+
+    let apply = move |result: Result<TransferJob, String>| match result {
+        Ok(job) => {
+            set_panel_error.set(None);
+            set_jobs.update(|jobs| upsert_job(jobs, job));
+        }
+        Err(e) => set_panel_error.set(Some(e)),
+    };
+
+    view! {
+        <div class="transfers-panel">
+            <div class="transfers-header">
+                <span class="transfers-title">"Transfers"</span>
+                <div class="transfers-header-actions">
+                    <button on:click=move |_| {
+                        if settings.with_untracked(Option::is_some) {
+                            set_settings.set(None);
+                            return;
+                        }
+                        spawn_local(async move {
+                            match get_transfer_settings().await {
+                                Ok(loaded) => set_settings.set(Some(loaded)),
+                                Err(e) => set_panel_error.set(Some(e)),
+                            }
+                        });
+                    }>
+                        "Settings"
+                    </button>
+                    <button on:click=move |_| {
+                        spawn_local(async move {
+                            match clear_finished_transfers().await {
+                                Ok(remaining) => set_jobs.set(remaining),
+                                Err(e) => set_panel_error.set(Some(e)),
+                            }
+                            match clear_finished_deletes().await {
+                                Ok(remaining) => set_deletes.set(remaining),
+                                Err(e) => set_panel_error.set(Some(e)),
+                            }
+                            // Transfers of failed jobs can now be resumed from here
+                            refresh_interrupted();
+                        });
+                    }>
+                        "Clear Finished"
+                    </button>
+                    <button class="toolbar-btn" on:click=move |_| on_close.run(()) title="Close">
+                        <svg width="14" height="14" viewBox="0 0 24 24" fill="currentColor">
+                            <path d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z"/>
+                        </svg>
+                    </button>
+                </div>
+            </div>
+
+            {move || panel_error.get().map(|e| view! { <div class="transfers-error">{e}</div> })}
+
+            {move || settings.get().map(|current| view! {
+                <SettingsForm
+                    settings=current
+                    set_panel_error=set_panel_error
+                    on_saved=move || set_settings.set(None)
+                />
+            })}
+
+            <div class="transfers-list">
+                {move || interrupted.get().into_iter()
+                    .map(|transfer| interrupted_view(transfer, set_interrupted, apply, set_panel_error))
+                    .collect::<Vec<_>>()}
+                {move || deletes.get().into_iter().rev()
+                    .map(|job| delete_view(job, set_deletes))
+                    .collect::<Vec<_>>()}
+                {move || {
+                    let jobs = jobs.get();
+                    if jobs.is_empty() && deletes.with(Vec::is_empty) && interrupted.with(Vec::is_empty) {
+                        return view! { <div class="transfers-empty">"No transfers"</div> }.into_any();
+                    }
+
+                    jobs.into_iter().rev().map(|job| {
+                        let id = job.id;
+                        let status = job.status.clone();
+                        let verb = if job.kind == "move" { "Moving" } else { "Copying" };
+                        let total = job.total_bytes.unwrap_or(0);
+                        let overall = percent(job.transferred_bytes, total);
+
+                        let detail = match status.as_str() {
+                            "running" => {
+                                let mut parts = vec![match job.total_bytes {
+                                    Some(total) => format!(
+                                        "{} of {}",
+                                        format_file_size(job.transferred_bytes),
+                                        format_file_size(total)
+                                    ),
+                                    None => format_file_size(job.transferred_bytes),
+                                }];
+                                if job.bytes_per_second > 0 {
+                                    parts.push(format!("{}/s", format_file_size(job.bytes_per_second)));
+                                }
+                                if let Some(eta) = job.eta_seconds {
+                                    parts.push(format!("{} left", format_duration(eta)));
+                                }
+                                parts.join(" — ")
+                            }
+                            "queued" => "Waiting…".to_string(),
+                            "paused" => format!("Paused at {}", format_file_size(job.transferred_bytes)),
+                            "completed" => "Done".to_string(),
+                            "cancelled" => "Cancelled".to_string(),
+                            _ => job.error.clone().unwrap_or_else(|| "Failed".to_string()),
+                        };
+
+                        let current_file = job.current_file.clone().filter(|_| status == "running").map(|file| {
+                            let file_percent = percent(job.file_bytes, job.file_size);
+                            view! {
+                                <div class="transfer-file">
+                                    <span class="transfer-file-name">{display_name(&file)}</span>
+                                    <div class="transfer-progress small">
+                                        <div class="transfer-progress-fill" style=format!("width: {:.1}%", file_percent)></div>
+                                    </div>
+                                </div>
+                            }
+                        });
+
+                        let can_pause = status == "running" || status == "queued";
+                        let can_resume = status == "paused" || status == "failed";
+                        let can_cancel = can_pause || status == "paused";
+
+                        view! {
+                            <div class=format!("transfer-item {}", status)>
+                                <div class="transfer-info">
+                                    <div class="transfer-name" title=job.source.clone()>
+                                        {format!("{} {}", verb, display_name(&job.source))}
+                                    </div>
+                                    <div class="transfer-dest" title=job.dest_dir.clone()>
+                                        {match job.encryption {
+                                            Encryption::BucketDefault => format!("to {}", job.dest_dir),
+                                            ref encryption => format!("to {} ({})", job.dest_dir, encryption.label()),
+                                        }}
+                                    </div>
+                                    <div class="transfer-progress">
+                                        <div class="transfer-progress-fill" style=format!("width: {:.1}%", overall)></div>
+                                    </div>
+                                    {current_file}
+                                    <div class="transfer-detail">{detail}</div>
+                                </div>
+                                <div class="transfer-actions">
+                                    {can_pause.then(|| view! {
+                                        <button on:click=move |_| spawn_local(async move { apply(pause_transfer(id).await) })>
+                                            "Pause"
+                                        </button>
+                                    })}
+                                    {can_resume.then(|| view! {
+                                        <button on:click=move |_| spawn_local(async move { apply(resume_transfer(id).await) })>
+                                            {if status == "failed" { "Retry" } else { "Resume" }}
+                                        </button>
+                                    })}
+                                    {can_cancel.then(|| view! {
+                                        <button on:click=move |_| spawn_local(async move { apply(cancel_transfer(id).await) })>
+                                            "Cancel"
+                                        </button>
+                                    })}
+                                </div>
+                            </div>
+                        }
+                    }).collect::<Vec<_>>().into_any()
+                }}
+            </div>
+        </div>
+    }
+}
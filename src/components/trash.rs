@@ -0,0 +1,213 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashSet;
+
+use crate::services::trash_service::{
+    delete_from_trash, empty_trash, list_trash, restore_from_trash,
+};
+use crate::types::TrashItem;
+use crate::utils::format::format_file_size;
+
+// "2026-03-14T09:26:53" as "2026-03-14 09:26"
+fn deleted_text(item: &TrashItem) -> String {
+    item.deleted
+        .as_deref()
+        .map(|deleted| deleted.replacen('T', " ", 1).chars().take(16).collect())
+        .unwrap_or_default()
+}
+
+fn folder_of(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(folder, _)| if folder.is_empty() { "/" } else { folder })
+        .unwrap_or(path)
+}
+
+// What deleting for good is about to remove
+#[derive(Clone, PartialEq)]
+enum Purge {
+    Selected(Vec<String>),
+    Everything,
+}
+
+/// Lists what is in the Trash and puts selected items back where they were.
+/// Deleting for good asks first.
+#[component]
+pub fn TrashDialog(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
+    let (items, set_items) = signal(Vec::<TrashItem>::new());
+    let (selected, set_selected) = signal(HashSet::<String>::new());
+    let (purge, set_purge) = signal(Option::<Purge>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let reload = move || {
+        spawn_local(async move {
+            match list_trash().await {
+                Ok(list) => {
+                    set_selected.update(|selected| {
+                        selected.retain(|id| list.iter().any(|item| &item.id == id))
+                    });
+                    set_items.set(list);
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+    reload();
+
+    let selected_ids = move || {
+        items.with_untracked(|items| {
+            selected.with_untracked(|selected| {
+                items
+                    .iter()
+                    .filter(|item| selected.contains(&item.id))
+                    .map(|item| item.id.clone())
+                    .collect::<Vec<_>>()
+            })
+        })
+    };
+
+    let on_restore = move |_| {
+        let ids = selected_ids();
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match restore_from_trash(ids).await {
+                Ok(restored) if restored.errors.is_empty() => {}
+                Ok(restored) => set_error.set(Some(format!(
+                    "Some items could not be put back: {}",
+                    restored.errors.join("; ")
+                ))),
+                Err(e) => set_error.set(Some(e)),
+            }
+            reload();
+        });
+    };
+
+    let on_purge = move |_| {
+        let Some(target) = purge.get_untracked() else {
+            return;
+        };
+        set_purge.set(None);
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            let result = match target {
+                Purge::Selected(ids) => delete_from_trash(ids).await,
+                Purge::Everything => empty_trash().await,
+            };
+            match result {
+                Ok(errors) if errors.is_empty() => {}
+                Ok(errors) => set_error.set(Some(format!(
+                    "Some items could not be deleted: {}",
+                    errors.join("; ")
+                ))),
+                Err(e) => set_error.set(Some(e)),
+            }
+            reload();
+        });
+    };
+
+    let row = move |item: TrashItem| {
+        let id = item.id.clone();
+        let is_selected = {
+            let id = id.clone();
+            move || selected.with(|selected| selected.contains(&id))
+        };
+        let toggle = move |_| {
+            set_purge.set(None);
+            set_selected.update(|selected| {
+                if !selected.remove(&id) {
+                    selected.insert(id.clone());
+                }
+            });
+        };
+        let size = match (item.is_dir, item.size) {
+            (true, _) => "Folder".to_string(),
+            (false, Some(size)) => format_file_size(size),
+            (false, None) => String::new(),
+        };
+
+        view! {
+            <div
+                class=move || format!("trash-item {}", if is_selected() { "selected" } else { "" })
+                on:click=toggle
+            >
+                <span class="trash-name" title=item.name.clone()>{item.name.clone()}</span>
+                <span class="trash-origin" title=item.original_path.clone()>
+                    {folder_of(&item.original_path).to_string()}
+                </span>
+                <span class="trash-size">{size}</span>
+                <span class="trash-date">{deleted_text(&item)}</span>
+            </div>
+        }
+    };
+
+    let purge_message = move || {
+        purge.get().map(|target| match target {
+            Purge::Selected(ids) if ids.len() == 1 => {
+                "The selected item will be deleted for good. This cannot be undone.".to_string()
+            }
+            Purge::Selected(ids) => format!(
+                "{} items will be deleted for good. This cannot be undone.",
+                ids.len()
+            ),
+            Purge::Everything => format!(
+                "All {} items in the Trash will be deleted for good. This cannot be undone.",
+                items.with(Vec::len)
+            ),
+        })
+    };
+    let nothing_selected = move || selected.with(HashSet::is_empty);
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog trash-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Trash"</h3>
+                {move || if items.with(Vec::is_empty) {
+                    view! { <div class="sync-empty">"The Trash is empty"</div> }.into_any()
+                } else {
+                    view! {
+                        <div class="trash-list">
+                            {items.get().into_iter().map(row).collect::<Vec<_>>()}
+                        </div>
+                    }.into_any()
+                }}
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+
+                {move || match purge_message() {
+                    Some(message) => view! {
+                        <p class="dialog-message">{message}</p>
+                        <div class="dialog-buttons">
+                            <button class="danger" on:click=on_purge>"Delete Permanently"</button>
+                            <button on:click=move |_| set_purge.set(None)>"Cancel"</button>
+                        </div>
+                    }.into_any(),
+                    None => view! {
+                        <div class="dialog-buttons">
+                            <button
+                                class="danger"
+                                disabled=move || busy.get() || items.with(Vec::is_empty)
+                                on:click=move |_| set_purge.set(Some(Purge::Everything))
+                            >
+                                "Empty Trash…"
+                            </button>
+                            <button
+                                disabled=move || busy.get() || nothing_selected()
+                                on:click=move |_| set_purge.set(Some(Purge::Selected(selected_ids())))
+                            >
+                                "Delete Permanently…"
+                            </button>
+                            <button on:click=move |_| on_close.run(())>"Close"</button>
+                            <button disabled=move || busy.get() || nothing_selected() on:click=on_restore>
+                                "Put Back"
+                            </button>
+                        </div>
+                    }.into_any(),
+                }}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,251 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::vault_service::{
+    create_vault, lock_vault, reset_vault, set_vault_timeout, unlock_vault,
+};
+use crate::types::VaultStatus;
+
+// Idle timeouts offered in the settings, in seconds
+const TIMEOUTS: [(u64, &str); 6] = [
+    (300, "5 minutes"),
+    (900, "15 minutes"),
+    (1800, "30 minutes"),
+    (3600, "1 hour"),
+    (14400, "4 hours"),
+    (28800, "8 hours"),
+];
+
+#[component]
+pub fn VaultDialog(
+    status: ReadSignal<VaultStatus>,
+    set_status: WriteSignal<VaultStatus>,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (passphrase, set_passphrase) = signal(String::new());
+    let (confirm, set_confirm) = signal(String::new());
+    let (use_keychain, set_use_keychain) = signal(false);
+    let (confirm_reset, set_confirm_reset) = signal(false);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    // Apply the new status, closing the dialog once the vault is usable
+    let finish = move |result: Result<VaultStatus, String>, close: bool| {
+        set_busy.set(false);
+        set_passphrase.set(String::new());
+        set_confirm.set(String::new());
+        match result {
+            Ok(new_status) => {
+                set_error.set(None);
+                set_status.set(new_status);
+                if close {
+                    on_close.run(());
+                }
+            }
+            Err(e) => set_error.set(Some(e)),
+        }
+    };
+
+    let on_create = move || {
+        let passphrase = if use_keychain.get_untracked() {
+            None
+        } else {
+            if passphrase.get_untracked() != confirm.get_untracked() {
+                set_error.set(Some("The passphrases do not match".to_string()));
+                return;
+            }
+            Some(passphrase.get_untracked())
+        };
+        set_busy.set(true);
+        spawn_local(async move { finish(create_vault(passphrase).await, true) });
+    };
+
+    let on_unlock = move || {
+        let passphrase = status
+            .with_untracked(|s| s.backend.as_deref() != Some("keychain"))
+            .then(|| passphrase.get_untracked());
+        set_busy.set(true);
+        spawn_local(async move { finish(unlock_vault(passphrase).await, true) });
+    };
+
+    let on_lock = move |_| {
+        set_busy.set(true);
+        spawn_local(async move { finish(lock_vault().await, true) });
+    };
+
+    let on_timeout = move |seconds: u64| {
+        set_busy.set(true);
+        spawn_local(async move { finish(set_vault_timeout(seconds).await, false) });
+    };
+
+    let on_reset = move |_| {
+        if !confirm_reset.get_untracked() {
+            set_confirm_reset.set(true);
+            return;
+        }
+        set_confirm_reset.set(false);
+        set_busy.set(true);
+        spawn_local(async move { finish(reset_vault().await, false) });
+    };
+
+    let passphrase_field =
+        move |label: &'static str, value: ReadSignal<String>, set_value: WriteSignal<String>| {
+            view! {
+                <label class="account-field">
+                    <span>{label}</span>
+                    <input
+                        type="password"
+                        prop:value=move || value.get()
+                        on:input=move |e| set_value.set(event_target_value(&e))
+                    />
+                </label>
+            }
+        };
+
+    let setup_view = move || {
+        view! {
+            <p class="vault-note">
+                "Secret keys of your S3 accounts are encrypted with a master passphrase. "
+                "The passphrase itself is never stored."
+            </p>
+            {move || status.with(|s| s.keychain_available).then(|| view! {
+                <label class="account-checkbox">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || use_keychain.get()
+                        on:change=move |e| set_use_keychain.set(event_target_checked(&e))
+                    />
+                    <span>"Keep the key in the system keychain instead"</span>
+                </label>
+            })}
+            <Show when=move || !use_keychain.get()>
+                {passphrase_field("Master Passphrase", passphrase, set_passphrase)}
+                {passphrase_field("Confirm Passphrase", confirm, set_confirm)}
+            </Show>
+        }
+    };
+
+    let unlock_view = move || {
+        view! {
+            <Show
+                when=move || status.with(|s| s.backend.as_deref() != Some("keychain"))
+                fallback=|| view! {
+                    <p class="vault-note">"The vault key is read from the system keychain."</p>
+                }
+            >
+                <label class="account-field">
+                    <span>"Master Passphrase"</span>
+                    <input
+                        type="password"
+                        autofocus
+                        prop:value=move || passphrase.get()
+                        on:input=move |e| set_passphrase.set(event_target_value(&e))
+                        on:keydown=move |e| {
+                            if e.key() == "Enter" && !busy.get_untracked() {
+                                on_unlock();
+                            }
+                        }
+                    />
+                </label>
+            </Show>
+        }
+    };
+
+    let settings_view = move || {
+        view! {
+            <div class="account-profile-row">
+                <span>"Key"</span>
+                {move || match status.with(|s| s.backend.clone()).as_deref() {
+                    Some("keychain") => "System keychain",
+                    _ => "Master passphrase",
+                }}
+            </div>
+            <label class="account-field">
+                <span>"Lock after being idle for"</span>
+                <select
+                    class="vault-timeout"
+                    disabled=move || busy.get()
+                    on:change=move |e| {
+                        if let Ok(seconds) = event_target_value(&e).parse() {
+                            on_timeout(seconds);
+                        }
+                    }
+                >
+                    {TIMEOUTS.iter().map(|&(seconds, label)| view! {
+                        <option
+                            value=seconds.to_string()
+                            selected=move || status.with(|s| s.idle_timeout_secs == seconds)
+                        >
+                            {label}
+                        </option>
+                    }).collect::<Vec<_>>()}
+                </select>
+            </label>
+        }
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog vault-dialog" on:click=move |e| e.stop_propagation()>
+                <h3>
+                    {move || status.with(|s| {
+                        if !s.initialized {
+                            "Set Up Credential Vault"
+                        } else if s.locked {
+                            "Unlock Credential Vault"
+                        } else {
+                            "Credential Vault"
+                        }
+                    })}
+                </h3>
+
+                {move || status.with(|s| {
+                    if !s.initialized {
+                        setup_view().into_any()
+                    } else if s.locked {
+                        unlock_view().into_any()
+                    } else {
+                        settings_view().into_any()
+                    }
+                })}
+
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+                {move || confirm_reset.get().then(|| view! {
+                    <div class="account-status error">
+                        "Resetting deletes every stored secret key. Click Reset again to confirm."
+                    </div>
+                })}
+
+                <div class="dialog-buttons">
+                    {move || status.with(|s| s.initialized).then(|| view! {
+                        <button class="danger" disabled=move || busy.get() on:click=on_reset>
+                            "Reset"
+                        </button>
+                    })}
+                    <button on:click=move |_| on_close.run(())>"Close"</button>
+                    {move || status.with(|s| {
+                        if !s.initialized {
+                            view! {
+                                <button disabled=move || busy.get() on:click=move |_| on_create()>
+                                    "Create"
+                                </button>
+                            }.into_any()
+                        } else if s.locked {
+                            view! {
+                                <button disabled=move || busy.get() on:click=move |_| on_unlock()>
+                                    "Unlock"
+                                </button>
+                            }.into_any()
+                        } else {
+                            view! {
+                                <button disabled=move || busy.get() on:click=on_lock>"Lock Now"</button>
+                            }.into_any()
+                        }
+                    })}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,210 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::services::watch_service::{
+    add_watch_folder, list_watch_folders, listen_watch_updates, remove_watch_folder,
+    set_watch_paused,
+};
+use crate::types::WatchStatus;
+
+// Editor swap files and the like are rarely worth uploading
+const DEFAULT_IGNORE: &str = "*.tmp\n*.swp\n*~\n.DS_Store\n";
+
+fn folder_name(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+fn status_text(status: &WatchStatus) -> String {
+    match status.state.as_str() {
+        "scanning" => "Looking for changes…".to_string(),
+        "paused" => "Paused".to_string(),
+        "failed" => status
+            .error
+            .clone()
+            .unwrap_or_else(|| "Stopped".to_string()),
+        _ if status.pending > 0 => format!("{} being written", status.pending),
+        _ if status.uploads > 0 => format!("Watching, {} uploaded", status.uploads),
+        _ => "Watching".to_string(),
+    }
+}
+
+/// Sidebar section listing the watch folders, with their status pushed from
+/// the backend as it changes.
+#[component]
+pub fn WatchFolders(
+    #[prop(into)] on_add: Callback<()>,
+    #[prop(into)] on_open: Callback<String>,
+) -> impl IntoView {
+    let (watches, set_watches) = signal(Vec::<WatchStatus>::new());
+
+    spawn_local(async move {
+        if let Ok(list) = list_watch_folders().await {
+            set_watches.set(list);
+        }
+        let _ = listen_watch_updates(move |status| {
+            set_watches.update(|watches| {
+                match watches
+                    .iter_mut()
+                    .find(|watch| watch.folder.id == status.folder.id)
+                {
+                    Some(watch) => *watch = status,
+                    None => watches.push(status),
+                }
+            });
+        })
+        .await;
+    });
+
+    let toggle = move |id: String, paused: bool| {
+        spawn_local(async move {
+            let _ = set_watch_paused(id, paused).await;
+        });
+    };
+
+    let remove = move |id: String| {
+        spawn_local(async move {
+            if remove_watch_folder(id.clone()).await.is_ok() {
+                set_watches.update(|watches| watches.retain(|watch| watch.folder.id != id));
+            }
+        });
+    };
+
+    let row = move |status: WatchStatus| {
+        let local = status.folder.local.clone();
+        let (toggle_id, remove_id) = (status.folder.id.clone(), status.folder.id.clone());
+        let paused = status.folder.paused;
+        // A failed watch is retried by resuming it
+        let resumable = paused || status.state == "failed";
+
+        view! {
+            <div
+                class="sidebar-item watch-folder"
+                title=format!("{} → {}", status.folder.local, status.folder.remote)
+                on:click=move |_| on_open.run(local.clone())
+            >
+                <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                    <path d="M12 4.5C7 4.5 2.73 7.61 1 12c1.73 4.39 6 7.5 11 7.5s9.27-3.11 11-7.5c-1.73-4.39-6-7.5-11-7.5zM12 17c-2.76 0-5-2.24-5-5s2.24-5 5-5 5 2.24 5 5-2.24 5-5 5zm0-8c-1.66 0-3 1.34-3 3s1.34 3 3 3 3-1.34 3-3-1.34-3-3-3z"/>
+                </svg>
+                <div class="watch-folder-text">
+                    <span>{folder_name(&status.folder.local).to_string()}</span>
+                    <span class=format!("watch-status {}", status.state)>{status_text(&status)}</span>
+                </div>
+                <button
+                    class="watch-folder-action"
+                    title=if resumable { "Resume" } else { "Pause" }
+                    on:click=move |e| {
+                        e.stop_propagation();
+                        toggle(toggle_id.clone(), !resumable);
+                    }
+                >
+                    {if resumable { "▶" } else { "⏸" }}
+                </button>
+                <button
+                    class="watch-folder-action"
+                    title="Stop watching"
+                    on:click=move |e| {
+                        e.stop_propagation();
+                        remove(remove_id.clone());
+                    }
+                >
+                    "×"
+                </button>
+            </div>
+        }
+    };
+
+    view! {
+        <div class="sidebar-section">
+            <div class="sidebar-title">"Watch Folders"</div>
+            {move || watches.get().into_iter().map(row).collect::<Vec<_>>()}
+            <div class="sidebar-item" on:click=move |_| on_add.run(())>
+                <svg width="16" height="16" viewBox="0 0 24 24" fill="currentColor">
+                    <path d="M19 13h-6v6h-2v-6H5v-2h6V5h2v6h6v2z"/>
+                </svg>
+                <span>"Add Watch Folder…"</span>
+            </div>
+        </div>
+    }
+}
+
+/// Sets up a local folder whose new and changed files are uploaded to an S3
+/// prefix as they are written.
+#[component]
+pub fn WatchFolderDialog(
+    local: String,
+    remote: String,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (local, set_local) = signal(local);
+    let (remote, set_remote) = signal(remote);
+    let (ignore, set_ignore) = signal(DEFAULT_IGNORE.to_string());
+    let (error, set_error) = signal(Option::<String>::None);
+    let (busy, set_busy) = signal(false);
+
+    let on_add = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match add_watch_folder(
+                local.get_untracked().trim().to_string(),
+                remote.get_untracked().trim().to_string(),
+                ignore.get_untracked(),
+            )
+            .await
+            {
+                Ok(_) => on_close.run(()),
+                Err(e) => {
+                    set_error.set(Some(e));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="dialog-overlay" on:click=move |_| on_close.run(())>
+            <div class="dialog" on:click=move |e| e.stop_propagation()>
+                <h3>"Watch Folder"</h3>
+                <label class="account-field">
+                    <span>"Local folder"</span>
+                    <input
+                        type="text"
+                        placeholder="/home/me/Projects/site/dist"
+                        prop:value=move || local.get()
+                        on:input=move |e| set_local.set(event_target_value(&e))
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"Upload to"</span>
+                    <input
+                        type="text"
+                        placeholder="s3://bucket/prefix/"
+                        prop:value=move || remote.get()
+                        on:input=move |e| set_remote.set(event_target_value(&e))
+                    />
+                </label>
+                <label class="account-field">
+                    <span>"Ignore (.gitignore patterns)"</span>
+                    <textarea
+                        class="watch-ignore"
+                        spellcheck="false"
+                        prop:value=move || ignore.get()
+                        on:input=move |e| set_ignore.set(event_target_value(&e))
+                    ></textarea>
+                </label>
+                <p class="vault-note">
+                    "New and changed files are uploaded once they have stopped changing for a couple of seconds. Nothing is deleted from S3. Changes made while the app was closed are picked up when it starts again."
+                </p>
+
+                {move || error.get().map(|message| view! {
+                    <div class="account-status error">{message}</div>
+                })}
+                <div class="dialog-buttons">
+                    <button on:click=move |_| on_close.run(())>"Cancel"</button>
+                    <button disabled=move || busy.get() on:click=on_add>"Watch"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,111 @@
+use crate::types::{
+    Account, AccountArgs, AccountIdArgs, AccountInput, RoleSessionArgs, TestAccountArgs,
+};
+use crate::utils::tauri::{invoke, is_tauri_available};
+use wasm_bindgen::prelude::*;
+
+pub async fn list_accounts() -> Result<Vec<Account>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_accounts", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<Account>>(result) {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => Err(format!("Failed to parse accounts: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list accounts: {e:?}")),
+    }
+}
+
+pub async fn save_account(account: AccountInput) -> Result<Account, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&AccountArgs { account }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("save_account", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Account>(result) {
+            Ok(account) => Ok(account),
+            Err(e) => Err(format!("Failed to parse response: {e:?}")),
+        },
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn delete_account(id: String) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&AccountIdArgs { id }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("delete_account", args).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Connect with the settings as entered, returning a short success message.
+/// Roles with an MFA device need `mfa_code`.
+pub async fn test_account(
+    account: AccountInput,
+    mfa_code: Option<String>,
+) -> Result<String, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&TestAccountArgs { account, mfa_code }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("test_account", args).await {
+        Ok(result) => Ok(result.as_string().unwrap_or_default()),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Assume the role of an account with an MFA token code. Returns when the
+/// session expires, as an RFC 3339 timestamp.
+pub async fn start_role_session(id: String, mfa_code: String) -> Result<String, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&RoleSessionArgs { id, mfa_code }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("start_role_session", args).await {
+        Ok(result) => Ok(result.as_string().unwrap_or_default()),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Whether a request failed because a role session needs a new MFA code.
+pub fn is_mfa_error(message: &str) -> bool {
+    message.contains("MFA code required")
+}
+
+/// The account ID of an `s3://<account>@bucket/key` path.
+pub fn account_of(path: &str) -> Option<String> {
+    let authority = path.strip_prefix("s3://")?.split('/').next()?;
+    authority
+        .split_once('@')
+        .map(|(account, _)| account.to_string())
+}
+
+// Commands reject with the plain message string, show it as is
+pub fn error_message(error: JsValue) -> String {
+    error.as_string().unwrap_or_else(|| format!("{error:?}"))
+}
@@ -0,0 +1,136 @@
+use crate::services::account_service::error_message;
+use crate::types::{
+    BucketArgs, BucketProperties, BucketPropertiesArgs, FormatLifecycleArgs,
+    LifecycleConfiguration, ParseLifecycleArgs, PropertyDiff, PutLifecycleArgs,
+};
+use crate::utils::tauri::{invoke, is_tauri_available};
+
+/// Policy, CORS rules and public access block of the bucket `path` points into.
+pub async fn get_bucket_properties(path: String) -> Result<BucketProperties, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&BucketArgs { path })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("get_bucket_properties", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<BucketProperties>(result)
+            .map_err(|e| format!("Failed to parse bucket properties: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Validate edited properties and diff each changed setting against the bucket.
+pub async fn preview_bucket_properties(
+    path: String,
+    properties: BucketProperties,
+) -> Result<Vec<PropertyDiff>, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&BucketPropertiesArgs { path, properties })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("preview_bucket_properties", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<PropertyDiff>>(result)
+            .map_err(|e| format!("Failed to parse the changes: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn put_bucket_properties(
+    path: String,
+    properties: BucketProperties,
+) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&BucketPropertiesArgs { path, properties })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("put_bucket_properties", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Lifecycle rules of the bucket `path` points into.
+pub async fn get_bucket_lifecycle(path: String) -> Result<LifecycleConfiguration, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&BucketArgs { path })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("get_bucket_lifecycle", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<LifecycleConfiguration>(result)
+            .map_err(|e| format!("Failed to parse lifecycle rules: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Validate and save lifecycle rules; saving no rules removes them all.
+pub async fn put_bucket_lifecycle(
+    path: String,
+    configuration: LifecycleConfiguration,
+) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&PutLifecycleArgs {
+        path,
+        configuration,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("put_bucket_lifecycle", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Render lifecycle rules as `json` or `xml`.
+pub async fn format_lifecycle(
+    configuration: LifecycleConfiguration,
+    format: String,
+) -> Result<String, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&FormatLifecycleArgs {
+        configuration,
+        format,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("format_lifecycle", args).await {
+        Ok(result) => result
+            .as_string()
+            .ok_or_else(|| "Failed to format lifecycle rules".to_string()),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn parse_lifecycle(
+    text: String,
+    format: String,
+) -> Result<LifecycleConfiguration, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&ParseLifecycleArgs { text, format })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("parse_lifecycle", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<LifecycleConfiguration>(result)
+            .map_err(|e| format!("Failed to parse lifecycle rules: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::services::account_service::error_message;
+use crate::types::{
+    CustomerKeyArgs, DefaultEncryptionArgs, Encryption, EncryptionSettings, EncryptionSettingsArgs,
+};
+use crate::utils::tauri::{invoke, is_tauri_available};
+use wasm_bindgen::prelude::*;
+
+async fn call(cmd: &str, args: JsValue) -> Result<EncryptionSettings, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    match invoke(cmd, args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<EncryptionSettings>(result)
+            .map_err(|e| format!("Failed to parse encryption settings: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Default encryption of the account `path` is in, and whether it has a
+/// customer key.
+pub async fn get_encryption_settings(path: String) -> Result<EncryptionSettings, String> {
+    let args = serde_wasm_bindgen::to_value(&EncryptionSettingsArgs { path })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    call("get_encryption_settings", args).await
+}
+
+pub async fn set_default_encryption(
+    path: String,
+    encryption: Encryption,
+) -> Result<EncryptionSettings, String> {
+    let args = serde_wasm_bindgen::to_value(&DefaultEncryptionArgs { path, encryption })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    call("set_default_encryption", args).await
+}
+
+/// Store the customer key of the account, or remove it with `None`.
+pub async fn set_customer_key(
+    path: String,
+    key: Option<String>,
+) -> Result<EncryptionSettings, String> {
+    let args = serde_wasm_bindgen::to_value(&CustomerKeyArgs { path, key })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    call("set_customer_key", args).await
+}
+
+/// A new random 256-bit key, as base64.
+pub async fn generate_customer_key() -> Result<String, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    match invoke("generate_customer_key", JsValue::NULL).await {
+        Ok(result) => result
+            .as_string()
+            .ok_or_else(|| "Failed to parse the generated key".to_string()),
+        Err(e) => Err(error_message(e)),
+    }
+}
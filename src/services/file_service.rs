@@ -1,94 +1,236 @@
+use crate::services::account_service::error_message;
 use crate::types::{
-    CopyItemArgs, CreateFolderArgs, DeleteItemArgs, FileItem, FilePreview, MoveItemArgs,
-    PreviewFileArgs, RenameItemArgs, SearchFilesArgs,
+    CreateBucketArgs, CreateFolderArgs, DeleteBucketArgs, DeleteJob, DirectoryChange,
+    DirectoryContents, FileItem, FilePreview, NewBucket, PreviewFileArgs, ReadDirArgs,
+    RenameItemArgs, SearchFilesArgs, VersionArgs, WatchDirectoriesArgs,
 };
-use crate::utils::tauri::{invoke, is_tauri_available};
+use crate::utils::tauri::{invoke, is_tauri_available, listen};
 use leptos::prelude::*;
+use wasm_bindgen::prelude::*;
 
-pub async fn create_new_folder(parent_path: String, folder_name: String) {
+const DIRECTORY_EVENT: &str = "directory-changed";
+
+pub async fn read_directory_page(
+    path: String,
+    continuation_token: Option<String>,
+) -> Result<DirectoryContents, String> {
     if !is_tauri_available() {
-        return;
+        return Err("Tauri not available".to_string());
     }
 
-    let args = match serde_wasm_bindgen::to_value(&CreateFolderArgs {
-        path: parent_path,
-        name: folder_name,
+    let args = match serde_wasm_bindgen::to_value(&ReadDirArgs {
+        path,
+        continuation_token,
     }) {
         Ok(args) => args,
-        Err(_) => return, // Silent fail for now, could add error handling
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
     };
 
-    let _ = invoke("create_folder", args).await;
+    match invoke("read_directory", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<DirectoryContents>(result) {
+            Ok(contents) => Ok(contents),
+            Err(e) => Err(format!("Failed to parse directory contents: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to load directory: {e:?}")),
+    }
 }
 
-pub async fn delete_selected_item(item_path: String) {
+/// List one page of every object version and delete marker below an S3 prefix.
+pub async fn read_versions_page(
+    path: String,
+    continuation_token: Option<String>,
+) -> Result<DirectoryContents, String> {
     if !is_tauri_available() {
-        return;
+        return Err("Tauri not available".to_string());
     }
 
-    let args = match serde_wasm_bindgen::to_value(&DeleteItemArgs { path: item_path }) {
+    let args = match serde_wasm_bindgen::to_value(&ReadDirArgs {
+        path,
+        continuation_token,
+    }) {
         Ok(args) => args,
-        Err(_) => return, // Silent fail for now, could add error handling
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
     };
 
-    let _ = invoke("delete_item", args).await;
+    match invoke("read_versions", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<DirectoryContents>(result) {
+            Ok(contents) => Ok(contents),
+            Err(e) => Err(format!("Failed to parse versions: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list versions: {e:?}")),
+    }
 }
 
-pub async fn rename_selected_item(old_path: String, new_name: String) {
+async fn version_command(command: &str, path: String, version_id: String) -> Result<(), String> {
     if !is_tauri_available() {
-        return;
+        return Err("Tauri not available".to_string());
     }
 
-    let args = match serde_wasm_bindgen::to_value(&RenameItemArgs { old_path, new_name }) {
+    let args = match serde_wasm_bindgen::to_value(&VersionArgs { path, version_id }) {
         Ok(args) => args,
-        Err(_) => return, // Silent fail for now, could add error handling
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
     };
 
-    let _ = invoke("rename_item", args).await;
+    match invoke(command, args).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.as_string().unwrap_or_else(|| format!("{e:?}"))),
+    }
+}
+
+/// Make an older version of an object the current one again.
+pub async fn restore_version(path: String, version_id: String) -> Result<(), String> {
+    version_command("restore_version", path, version_id).await
+}
+
+/// Permanently delete a version; deleting a delete marker undeletes the object.
+pub async fn delete_version(path: String, version_id: String) -> Result<(), String> {
+    version_command("delete_version", path, version_id).await
+}
+
+/// Whether a path lies inside an S3 bucket, where objects can have versions.
+pub fn is_s3_bucket_path(path: &str) -> bool {
+    path.strip_prefix("s3://")
+        .and_then(|rest| rest.split('/').next())
+        .map(|authority| authority.rsplit('@').next().unwrap_or(authority))
+        .is_some_and(|bucket| !bucket.is_empty())
+}
+
+// A bucket itself, as listed under an account
+pub fn is_s3_bucket_root(path: &str) -> bool {
+    is_s3_bucket_path(path)
+        && path
+            .strip_prefix("s3://")
+            .and_then(|rest| rest.split_once('/'))
+            .is_some_and(|(_, key)| key.is_empty())
 }
 
-pub async fn copy_selected_item(source_path: String, dest_dir: String) -> Result<String, String> {
+/// Have the backend watch exactly these directories for changes. S3 paths are
+/// skipped, since S3 does not report changes.
+pub async fn watch_directories(paths: Vec<String>) -> Result<(), String> {
     if !is_tauri_available() {
-        return Err("Tauri not available".to_string());
+        return Ok(());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&WatchDirectoriesArgs { paths })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("watch_directories", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Call `on_change` with every change in a watched directory, for as long as
+/// the app runs.
+pub async fn listen_directory_changes(
+    on_change: impl Fn(DirectoryChange) + 'static,
+) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(change) = serde_wasm_bindgen::from_value::<DirectoryChange>(payload) {
+            on_change(change);
+        }
+    });
+
+    if let Err(e) = listen(DIRECTORY_EVENT, &handler).await {
+        return Err(format!("Failed to listen for directory changes: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
+
+pub async fn create_new_folder(parent_path: String, folder_name: String) {
+    if !is_tauri_available() {
+        return;
     }
 
-    let args = match serde_wasm_bindgen::to_value(&CopyItemArgs {
-        source: source_path,
-        destination: dest_dir,
+    let args = match serde_wasm_bindgen::to_value(&CreateFolderArgs {
+        path: parent_path,
+        name: folder_name,
     }) {
         Ok(args) => args,
-        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+        Err(_) => return, // Silent fail for now, could add error handling
     };
 
-    match invoke("copy_item", args).await {
-        Ok(result) => match serde_wasm_bindgen::from_value::<String>(result) {
-            Ok(new_path) => Ok(new_path),
-            Err(e) => Err(format!("Failed to parse response: {e:?}")),
-        },
-        Err(e) => Err(format!("Failed to copy item: {e:?}")),
+    let _ = invoke("create_folder", args).await;
+}
+
+/// Create a bucket in the bucket list at `root_path` and return its path.
+pub async fn create_bucket(
+    root_path: String,
+    name: String,
+    options: NewBucket,
+) -> Result<String, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&CreateBucketArgs {
+        path: root_path,
+        name,
+        options,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("create_bucket", args).await {
+        Ok(result) => result
+            .as_string()
+            .ok_or_else(|| "Failed to read the bucket path".to_string()),
+        Err(e) => Err(error_message(e)),
     }
 }
 
-pub async fn move_selected_item(source_path: String, dest_dir: String) -> Result<String, String> {
+/// Delete an empty bucket.
+pub async fn delete_bucket(bucket_path: String) -> Result<(), String> {
     if !is_tauri_available() {
         return Err("Tauri not available".to_string());
     }
 
-    let args = match serde_wasm_bindgen::to_value(&MoveItemArgs {
-        source: source_path,
-        destination: dest_dir,
-    }) {
+    let args = serde_wasm_bindgen::to_value(&DeleteBucketArgs { path: bucket_path })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("delete_bucket", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Delete everything in a bucket and then the bucket, in the background.
+pub async fn empty_and_delete_bucket(bucket_path: String) -> Result<DeleteJob, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&DeleteBucketArgs { path: bucket_path })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("empty_and_delete_bucket", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<DeleteJob>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn rename_selected_item(old_path: String, new_name: String) {
+    if !is_tauri_available() {
+        return;
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&RenameItemArgs { old_path, new_name }) {
         Ok(args) => args,
-        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+        Err(_) => return, // Silent fail for now, could add error handling
     };
 
-    match invoke("move_item", args).await {
-        Ok(result) => match serde_wasm_bindgen::from_value::<String>(result) {
-            Ok(new_path) => Ok(new_path),
-            Err(e) => Err(format!("Failed to parse response: {e:?}")),
-        },
-        Err(e) => Err(format!("Failed to move item: {e:?}")),
-    }
+    let _ = invoke("rename_item", args).await;
 }
 
 pub async fn search_files(
@@ -112,6 +254,12 @@ pub async fn search_files(
                 size: Some(1024),
                 modified: Some("2024-01-15 10:30:00".to_string()),
                 icon: "document-text".to_string(),
+                version_id: None,
+                is_latest: false,
+                is_delete_marker: false,
+                storage_class: None,
+                archived: false,
+                restoring: false,
             },
             FileItem {
                 name: format!("{query}_folder"),
@@ -120,6 +268,12 @@ pub async fn search_files(
                 size: None,
                 modified: Some("2024-01-14 15:45:00".to_string()),
                 icon: "folder".to_string(),
+                version_id: None,
+                is_latest: false,
+                is_delete_marker: false,
+                storage_class: None,
+                archived: false,
+                restoring: false,
             },
         ];
 
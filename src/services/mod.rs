@@ -1 +1,10 @@
+pub mod account_service;
+pub mod bucket_service;
+pub mod encryption_service;
 pub mod file_service;
+pub mod object_service;
+pub mod sync_service;
+pub mod transfer_service;
+pub mod trash_service;
+pub mod vault_service;
+pub mod watch_service;
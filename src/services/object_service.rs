@@ -0,0 +1,158 @@
+use crate::services::account_service::error_message;
+use crate::types::{
+    FileItem, ObjectMetadata, ObjectMetadataArgs, ObjectPatch, PresignArgs, PresignedUrl,
+    RestoreObjectsArgs, StorageClassArgs, UpdateObjectsArgs,
+};
+use crate::utils::tauri::{invoke, is_tauri_available, listen};
+use wasm_bindgen::prelude::*;
+
+const RESTORE_EVENT: &str = "restore-updated";
+
+/// Headers, user metadata, encryption, checksum and tags of an S3 object.
+pub async fn get_object_metadata(
+    path: String,
+    version_id: Option<String>,
+) -> Result<ObjectMetadata, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&ObjectMetadataArgs { path, version_id })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("get_object_metadata", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<ObjectMetadata>(result)
+            .map_err(|e| format!("Failed to parse object metadata: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Apply the same metadata and tag changes to every given object.
+pub async fn update_objects(paths: Vec<String>, patch: ObjectPatch) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&UpdateObjectsArgs { paths, patch })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("update_objects", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Copy objects, and everything below the given folders, into another storage
+/// class. Returns how many objects were changed.
+pub async fn change_storage_class(
+    paths: Vec<String>,
+    storage_class: String,
+) -> Result<usize, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&StorageClassArgs {
+        paths,
+        storage_class,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("change_storage_class", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<usize>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Request restores of the archived objects among `paths` and below them.
+/// Returns how many were requested.
+pub async fn restore_objects(paths: Vec<String>, days: i32, tier: String) -> Result<usize, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&RestoreObjectsArgs { paths, days, tier })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("restore_objects", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<usize>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Call `on_restored` with each object whose restored copy became readable.
+pub async fn listen_restore_updates(
+    on_restored: impl Fn(FileItem) + 'static,
+) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(item) = serde_wasm_bindgen::from_value::<FileItem>(payload) {
+            on_restored(item);
+        }
+    });
+
+    if let Err(e) = listen(RESTORE_EVENT, &handler).await {
+        return Err(format!("Failed to listen for restores: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
+
+/// Sign a GET or PUT link to an object; it is added to the history.
+pub async fn presign_url(
+    path: String,
+    method: String,
+    expires_in_secs: u64,
+) -> Result<PresignedUrl, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&PresignArgs {
+        path,
+        method,
+        expires_in_secs,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("presign_url", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<PresignedUrl>(result)
+            .map_err(|e| format!("Failed to parse presigned URL: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Links generated in this session, newest first.
+pub async fn list_presigned_urls() -> Result<Vec<PresignedUrl>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_presigned_urls", JsValue::NULL).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<PresignedUrl>>(result)
+            .map_err(|e| format!("Failed to parse presigned URLs: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn clear_presigned_urls() -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    invoke("clear_presigned_urls", JsValue::NULL)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
@@ -0,0 +1,35 @@
+use crate::services::account_service::error_message;
+use crate::types::{PlanSyncArgs, RunSyncArgs, SyncOptions, SyncPlan, SyncRun};
+use crate::utils::tauri::{invoke, is_tauri_available};
+
+/// Compare both sides of a sync and list what it would do, changing nothing.
+pub async fn plan_sync(options: SyncOptions) -> Result<SyncPlan, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&PlanSyncArgs { options })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("plan_sync", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<SyncPlan>(result)
+            .map_err(|e| format!("Failed to parse the sync plan: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Queue the transfers and deletes of a reviewed plan.
+pub async fn run_sync(plan: SyncPlan) -> Result<SyncRun, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&RunSyncArgs { plan })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("run_sync", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<SyncRun>(result)
+            .map_err(|e| format!("Failed to parse the sync result: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
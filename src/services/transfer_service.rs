@@ -0,0 +1,328 @@
+use crate::services::account_service::error_message;
+use crate::types::{
+    DeleteItemsArgs, DeleteJob, DownloadState, QueueTransferArgs, TransferIdArgs, TransferJob,
+    TransferSettings, TransferSettingsArgs, TransferStateArgs, UploadState,
+};
+use crate::utils::tauri::{invoke, is_tauri_available, listen};
+use wasm_bindgen::prelude::*;
+
+const TRANSFER_EVENT: &str = "transfer-updated";
+const DELETE_EVENT: &str = "delete-updated";
+
+/// Queue a copy or move; with `version_id` that version of an S3 object is
+/// copied instead of the current one.
+pub async fn queue_transfer(
+    source_path: String,
+    version_id: Option<String>,
+    dest_dir: String,
+    kind: &str,
+) -> Result<TransferJob, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&QueueTransferArgs {
+        source_path,
+        dest_dir,
+        kind: kind.to_string(),
+        version_id,
+    }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("queue_transfer", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<TransferJob>(result) {
+            Ok(job) => Ok(job),
+            Err(e) => Err(format!("Failed to parse response: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to queue transfer: {e:?}")),
+    }
+}
+
+pub async fn list_transfers() -> Result<Vec<TransferJob>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_transfers", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<TransferJob>>(result) {
+            Ok(jobs) => Ok(jobs),
+            Err(e) => Err(format!("Failed to parse transfers: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list transfers: {e:?}")),
+    }
+}
+
+// Shared by pause, resume and cancel, which all take a job ID
+async fn control_transfer(command: &str, id: u64) -> Result<TransferJob, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&TransferIdArgs { id }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke(command, args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<TransferJob>(result) {
+            Ok(job) => Ok(job),
+            Err(e) => Err(format!("Failed to parse response: {e:?}")),
+        },
+        Err(e) => Err(format!("Transfer update failed: {e:?}")),
+    }
+}
+
+pub async fn pause_transfer(id: u64) -> Result<TransferJob, String> {
+    control_transfer("pause_transfer", id).await
+}
+
+pub async fn resume_transfer(id: u64) -> Result<TransferJob, String> {
+    control_transfer("resume_transfer", id).await
+}
+
+pub async fn cancel_transfer(id: u64) -> Result<TransferJob, String> {
+    control_transfer("cancel_transfer", id).await
+}
+
+pub async fn clear_finished_transfers() -> Result<Vec<TransferJob>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("clear_finished_transfers", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<TransferJob>>(result) {
+            Ok(jobs) => Ok(jobs),
+            Err(e) => Err(format!("Failed to parse transfers: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to clear transfers: {e:?}")),
+    }
+}
+
+pub async fn get_transfer_settings() -> Result<TransferSettings, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    match invoke("get_transfer_settings", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<TransferSettings>(result) {
+            Ok(settings) => Ok(settings),
+            Err(e) => Err(format!("Failed to parse settings: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to load transfer settings: {e:?}")),
+    }
+}
+
+/// Save the part size and concurrency; the backend returns them clamped to
+/// what S3 accepts.
+pub async fn update_transfer_settings(
+    settings: TransferSettings,
+) -> Result<TransferSettings, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&TransferSettingsArgs {
+        new_settings: settings,
+    }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("update_transfer_settings", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<TransferSettings>(result) {
+            Ok(settings) => Ok(settings),
+            Err(e) => Err(format!("Failed to parse settings: {e:?}")),
+        },
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn list_incomplete_uploads() -> Result<Vec<UploadState>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_incomplete_uploads", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<UploadState>>(result) {
+            Ok(uploads) => Ok(uploads),
+            Err(e) => Err(format!("Failed to parse uploads: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list unfinished uploads: {e:?}")),
+    }
+}
+
+pub async fn list_incomplete_downloads() -> Result<Vec<DownloadState>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_incomplete_downloads", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<DownloadState>>(result) {
+            Ok(downloads) => Ok(downloads),
+            Err(e) => Err(format!("Failed to parse downloads: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list unfinished downloads: {e:?}")),
+    }
+}
+
+// Shared by the commands acting on a recorded upload or download
+async fn invoke_with_state_id(command: &str, id: String) -> Result<JsValue, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&TransferStateArgs { id }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    invoke(command, args).await.map_err(error_message)
+}
+
+/// Queue the rest of an unfinished upload as a transfer job.
+pub async fn resume_upload(id: String) -> Result<TransferJob, String> {
+    let result = invoke_with_state_id("resume_upload", id).await?;
+
+    serde_wasm_bindgen::from_value::<TransferJob>(result)
+        .map_err(|e| format!("Failed to parse response: {e:?}"))
+}
+
+/// Queue the rest of an unfinished download as a transfer job.
+pub async fn resume_download(id: String) -> Result<TransferJob, String> {
+    let result = invoke_with_state_id("resume_download", id).await?;
+
+    serde_wasm_bindgen::from_value::<TransferJob>(result)
+        .map_err(|e| format!("Failed to parse response: {e:?}"))
+}
+
+/// Abort an unfinished upload, discarding the parts S3 holds.
+pub async fn abort_upload(id: String) -> Result<(), String> {
+    invoke_with_state_id("abort_upload", id).await.map(|_| ())
+}
+
+/// Abort an unfinished download, removing its partial file.
+pub async fn abort_download(id: String) -> Result<(), String> {
+    invoke_with_state_id("abort_download", id).await.map(|_| ())
+}
+
+/// Call `on_update` with every job the backend reports as changed, for as long
+/// as the app runs.
+pub async fn listen_transfer_updates(
+    on_update: impl Fn(TransferJob) + 'static,
+) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(job) = serde_wasm_bindgen::from_value::<TransferJob>(payload) {
+            on_update(job);
+        }
+    });
+
+    if let Err(e) = listen(TRANSFER_EVENT, &handler).await {
+        return Err(format!("Failed to listen for transfers: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
+
+/// Delete items in the background; S3 folders go with everything below them.
+pub async fn delete_items(paths: Vec<String>) -> Result<DeleteJob, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&DeleteItemsArgs { paths }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("delete_items", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<DeleteJob>(result) {
+            Ok(job) => Ok(job),
+            Err(e) => Err(format!("Failed to parse response: {e:?}")),
+        },
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn list_deletes() -> Result<Vec<DeleteJob>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_deletes", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<DeleteJob>>(result) {
+            Ok(jobs) => Ok(jobs),
+            Err(e) => Err(format!("Failed to parse deletes: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to list deletes: {e:?}")),
+    }
+}
+
+pub async fn cancel_delete(id: u64) -> Result<DeleteJob, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = match serde_wasm_bindgen::to_value(&TransferIdArgs { id }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    match invoke("cancel_delete", args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<DeleteJob>(result) {
+            Ok(job) => Ok(job),
+            Err(e) => Err(format!("Failed to parse response: {e:?}")),
+        },
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn clear_finished_deletes() -> Result<Vec<DeleteJob>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("clear_finished_deletes", JsValue::NULL).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<Vec<DeleteJob>>(result) {
+            Ok(jobs) => Ok(jobs),
+            Err(e) => Err(format!("Failed to parse deletes: {e:?}")),
+        },
+        Err(e) => Err(format!("Failed to clear deletes: {e:?}")),
+    }
+}
+
+/// Call `on_update` with every delete the backend reports as changed.
+pub async fn listen_delete_updates(on_update: impl Fn(DeleteJob) + 'static) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(job) = serde_wasm_bindgen::from_value::<DeleteJob>(payload) {
+            on_update(job);
+        }
+    });
+
+    if let Err(e) = listen(DELETE_EVENT, &handler).await {
+        return Err(format!("Failed to listen for deletes: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
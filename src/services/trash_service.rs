@@ -0,0 +1,79 @@
+use crate::services::account_service::error_message;
+use crate::types::{DeleteItemsArgs, Restored, TrashIdsArgs, TrashItem, Trashed};
+use crate::utils::tauri::{invoke, is_tauri_available};
+use wasm_bindgen::prelude::*;
+
+/// Move local items to the Trash and return those moved, for undoing the
+/// move, along with those that could not be.
+pub async fn move_to_trash(paths: Vec<String>) -> Result<Trashed, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&DeleteItemsArgs { paths })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("move_to_trash", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Trashed>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn list_trash() -> Result<Vec<TrashItem>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_trash", JsValue::NULL).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<TrashItem>>(result)
+            .map_err(|e| format!("Failed to parse the Trash: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Put items back where they came from and return their paths there, along
+/// with the items that could not be put back.
+pub async fn restore_from_trash(ids: Vec<String>) -> Result<Restored, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&TrashIdsArgs { ids })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("restore_from_trash", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Restored>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Delete items in the Trash for good and return those that could not be.
+pub async fn delete_from_trash(ids: Vec<String>) -> Result<Vec<String>, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&TrashIdsArgs { ids })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("delete_from_trash", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<String>>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Delete everything in the Trash for good and return what could not be.
+pub async fn empty_trash() -> Result<Vec<String>, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    match invoke("empty_trash", JsValue::NULL).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<String>>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
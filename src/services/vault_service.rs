@@ -0,0 +1,86 @@
+use crate::services::account_service::error_message;
+use crate::types::{VaultPassphraseArgs, VaultStatus, VaultTimeoutArgs};
+use crate::utils::tauri::{invoke, is_tauri_available, listen};
+use wasm_bindgen::prelude::*;
+
+const VAULT_EVENT: &str = "vault-updated";
+
+/// Whether an error came from a credential vault that is locked or not set up
+/// yet, so the caller can ask for the passphrase.
+pub fn is_vault_error(message: &str) -> bool {
+    message.contains("credential vault")
+}
+
+async fn call(cmd: &str, args: JsValue) -> Result<VaultStatus, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    match invoke(cmd, args).await {
+        Ok(result) => match serde_wasm_bindgen::from_value::<VaultStatus>(result) {
+            Ok(status) => Ok(status),
+            Err(e) => Err(format!("Failed to parse vault status: {e:?}")),
+        },
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+fn passphrase_args(passphrase: Option<String>) -> Result<JsValue, String> {
+    serde_wasm_bindgen::to_value(&VaultPassphraseArgs { passphrase })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))
+}
+
+pub async fn get_vault_status() -> Result<VaultStatus, String> {
+    call("get_vault_status", JsValue::NULL).await
+}
+
+/// Set up the vault; without a passphrase the key goes to the system keychain.
+pub async fn create_vault(passphrase: Option<String>) -> Result<VaultStatus, String> {
+    call("create_vault", passphrase_args(passphrase)?).await
+}
+
+pub async fn unlock_vault(passphrase: Option<String>) -> Result<VaultStatus, String> {
+    call("unlock_vault", passphrase_args(passphrase)?).await
+}
+
+pub async fn lock_vault() -> Result<VaultStatus, String> {
+    call("lock_vault", JsValue::NULL).await
+}
+
+pub async fn set_vault_timeout(seconds: u64) -> Result<VaultStatus, String> {
+    let args = match serde_wasm_bindgen::to_value(&VaultTimeoutArgs { seconds }) {
+        Ok(args) => args,
+        Err(e) => return Err(format!("Failed to serialize arguments: {e:?}")),
+    };
+
+    call("set_vault_timeout", args).await
+}
+
+pub async fn reset_vault() -> Result<VaultStatus, String> {
+    call("reset_vault", JsValue::NULL).await
+}
+
+/// Call `on_update` whenever the backend locks the vault on its own.
+pub async fn listen_vault_updates(on_update: impl Fn(VaultStatus) + 'static) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(status) = serde_wasm_bindgen::from_value::<VaultStatus>(payload) {
+            on_update(status);
+        }
+    });
+
+    if let Err(e) = listen(VAULT_EVENT, &handler).await {
+        return Err(format!("Failed to listen for vault changes: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use crate::services::account_service::error_message;
+use crate::types::{AddWatchFolderArgs, SetWatchPausedArgs, WatchFolderIdArgs, WatchStatus};
+use crate::utils::tauri::{invoke, is_tauri_available, listen};
+use wasm_bindgen::prelude::*;
+
+const WATCH_EVENT: &str = "watch-updated";
+
+pub async fn list_watch_folders() -> Result<Vec<WatchStatus>, String> {
+    if !is_tauri_available() {
+        return Ok(Vec::new());
+    }
+
+    match invoke("list_watch_folders", JsValue::NULL).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<Vec<WatchStatus>>(result)
+            .map_err(|e| format!("Failed to parse watch folders: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+/// Start uploading new and changed files of `local` to the S3 prefix `remote`.
+pub async fn add_watch_folder(
+    local: String,
+    remote: String,
+    ignore: String,
+) -> Result<WatchStatus, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&AddWatchFolderArgs {
+        local,
+        remote,
+        ignore,
+    })
+    .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("add_watch_folder", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<WatchStatus>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn set_watch_paused(id: String, paused: bool) -> Result<WatchStatus, String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&SetWatchPausedArgs { id, paused })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    match invoke("set_watch_paused", args).await {
+        Ok(result) => serde_wasm_bindgen::from_value::<WatchStatus>(result)
+            .map_err(|e| format!("Failed to parse response: {e:?}")),
+        Err(e) => Err(error_message(e)),
+    }
+}
+
+pub async fn remove_watch_folder(id: String) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Err("Tauri not available".to_string());
+    }
+
+    let args = serde_wasm_bindgen::to_value(&WatchFolderIdArgs { id })
+        .map_err(|e| format!("Failed to serialize arguments: {e:?}"))?;
+
+    invoke("remove_watch_folder", args)
+        .await
+        .map(|_| ())
+        .map_err(error_message)
+}
+
+/// Call `on_update` with every watch folder whose status changes, for as long
+/// as the app runs.
+pub async fn listen_watch_updates(on_update: impl Fn(WatchStatus) + 'static) -> Result<(), String> {
+    if !is_tauri_available() {
+        return Ok(());
+    }
+
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        if let Ok(status) = serde_wasm_bindgen::from_value::<WatchStatus>(payload) {
+            on_update(status);
+        }
+    });
+
+    if let Err(e) = listen(WATCH_EVENT, &handler).await {
+        return Err(format!("Failed to listen for watch folders: {e:?}"));
+    }
+
+    // The listener stays registered for the lifetime of the window
+    handler.forget();
+
+    Ok(())
+}
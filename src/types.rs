@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileItem {
@@ -8,6 +9,18 @@ pub struct FileItem {
     pub size: Option<u64>,
     pub modified: Option<String>,
     pub icon: String,
+    #[serde(default)]
+    pub version_id: Option<String>, // set in the versions listing of S3 objects
+    #[serde(default)]
+    pub is_latest: bool,
+    #[serde(default)]
+    pub is_delete_marker: bool,
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    #[serde(default)]
+    pub archived: bool, // in Glacier or Deep Archive with no restored copy
+    #[serde(default)]
+    pub restoring: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,40 +28,94 @@ pub struct DirectoryContents {
     pub current_path: String,
     pub parent_path: Option<String>,
     pub items: Vec<FileItem>,
+    #[serde(default)]
+    pub next_token: Option<String>,
+}
+
+// Listings are ordered folders first, then by name regardless of case
+fn listing_order(item: &FileItem) -> (bool, String) {
+    (!item.is_dir, item.name.to_lowercase())
+}
+
+impl DirectoryContents {
+    /// Add the next page of the listing. S3 sorts each page on its own, so
+    /// the page is merged in rather than appended.
+    pub fn append_page(&mut self, page: DirectoryContents) {
+        self.items.extend(page.items);
+        // Stable, so versions of an object keep their order
+        self.items.sort_by_cached_key(listing_order);
+        self.next_token = page.next_token;
+    }
+
+    /// Apply a change pushed for this directory, keeping the listing's order:
+    /// folders first, then by name regardless of case. An entry that sorts
+    /// past the pages loaded so far is left for the next page to bring.
+    pub fn apply_change(&mut self, change: &DirectoryChange) {
+        self.items.retain(|item| item.path != change.path);
+        let Some(item) = change.item.clone() else {
+            return;
+        };
+
+        let index = self
+            .items
+            .partition_point(|existing| listing_order(existing) < listing_order(&item));
+        if index < self.items.len() || self.next_token.is_none() {
+            self.items.insert(index, item);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ReadDirArgs {
     pub path: String,
+    pub continuation_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CreateFolderArgs {
+pub struct WatchDirectoriesArgs {
+    pub paths: Vec<String>,
+}
+
+// An entry added, removed or modified in a directory shown as a column
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryChange {
+    pub dir: String,
+    pub kind: String, // "added", "removed" or "modified"
     pub path: String,
-    pub name: String,
+    pub item: Option<FileItem>, // None once removed
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeleteItemArgs {
+pub struct CreateFolderArgs {
     pub path: String,
+    pub name: String,
+}
+
+// Settings a bucket is created with; no region means the account region
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewBucket {
+    pub region: Option<String>,
+    pub object_lock: bool,
+    pub versioning: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RenameItemArgs {
-    pub old_path: String,
-    pub new_name: String,
+pub struct CreateBucketArgs {
+    pub path: String,
+    pub name: String,
+    pub options: NewBucket,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CopyItemArgs {
-    pub source: String,
-    pub destination: String,
+pub struct DeleteBucketArgs {
+    pub path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MoveItemArgs {
-    pub source: String,
-    pub destination: String,
+pub struct RenameItemArgs {
+    pub old_path: String,
+    pub new_name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,3 +136,653 @@ pub struct FilePreview {
     pub size: u64,
     pub encoding: String, // "text" or "base64"
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferJob {
+    pub id: u64,
+    pub kind: String, // "copy" or "move"
+    pub source: String,
+    pub dest_dir: String,
+    pub dest: Option<String>,
+    pub status: String, // "queued", "running", "paused", "completed", "failed" or "cancelled"
+    pub total_bytes: Option<u64>,
+    pub transferred_bytes: u64,
+    pub current_file: Option<String>,
+    pub file_bytes: u64,
+    pub file_size: u64,
+    pub bytes_per_second: u64,
+    pub eta_seconds: Option<u64>,
+    pub error: Option<String>,
+    pub created: String,
+    #[serde(default)]
+    pub encryption: Encryption,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferSettings {
+    pub part_size: u64, // Bytes per part of a multipart transfer
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSettingsArgs {
+    pub new_settings: TransferSettings,
+}
+
+// A multipart upload an earlier session left unfinished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadState {
+    pub id: String,
+    #[serde(default)]
+    pub account: Option<String>,
+    pub source: String,
+    pub bucket: String,
+    pub key: String,
+    pub part_size: u64,
+    pub file_size: u64,
+    pub completed_parts: BTreeMap<String, String>, // ETags by part number
+}
+
+// A ranged download an earlier session left unfinished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadState {
+    pub id: String,
+    #[serde(default)]
+    pub account: Option<String>,
+    pub bucket: String,
+    pub key: String,
+    pub dest: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    pub completed_chunks: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStateArgs {
+    pub id: String,
+}
+
+// An object a batch delete could not remove, with the S3 error code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFailure {
+    pub path: String,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteJob {
+    pub id: u64,
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub bucket: bool, // Emptying the bucket in paths, then deleting it
+    pub status: String, // "running", "completed", "failed" or "cancelled"
+    pub found: u64,
+    pub deleted: u64,
+    pub failures: Vec<DeleteFailure>,
+    pub error: Option<String>,
+    pub created: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteItemsArgs {
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueTransferArgs {
+    pub source_path: String,
+    pub dest_dir: String,
+    pub kind: String,
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionArgs {
+    pub path: String,
+    pub version_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferIdArgs {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    pub endpoint_url: Option<String>,
+    pub region: Option<String>,
+    pub path_style: bool,
+    pub access_key_id: String,
+    pub default_bucket: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>, // set for profiles imported from ~/.aws
+    #[serde(default)]
+    pub credential_source: Option<String>,
+    #[serde(default)]
+    pub role: Option<RoleConfig>,
+}
+
+// IAM role assumed through STS, optionally with an MFA device
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoleConfig {
+    pub role_arn: String,
+    pub session_name: Option<String>,
+    pub external_id: Option<String>,
+    pub mfa_serial: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub sts_endpoint_url: Option<String>,
+}
+
+impl Account {
+    /// Path of the bucket list of this account.
+    pub fn root_path(&self) -> String {
+        format!("s3://{}@", self.id)
+    }
+}
+
+// Account settings as edited in the dialog; an empty secret keeps the stored one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountInput {
+    pub id: Option<String>,
+    pub name: String,
+    pub endpoint_url: Option<String>,
+    pub region: Option<String>,
+    pub path_style: bool,
+    pub access_key_id: String,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub default_bucket: Option<String>,
+    pub role: Option<RoleConfig>,
+}
+
+impl From<&Account> for AccountInput {
+    fn from(account: &Account) -> Self {
+        Self {
+            id: Some(account.id.clone()),
+            name: account.name.clone(),
+            endpoint_url: account.endpoint_url.clone(),
+            region: account.region.clone(),
+            path_style: account.path_style,
+            access_key_id: account.access_key_id.clone(),
+            secret_access_key: None,
+            session_token: None,
+            default_bucket: account.default_bucket.clone(),
+            role: account.role.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountArgs {
+    pub account: AccountInput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountIdArgs {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestAccountArgs {
+    pub account: AccountInput,
+    pub mfa_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleSessionArgs {
+    pub id: String,
+    pub mfa_code: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub initialized: bool,
+    pub locked: bool,
+    pub backend: Option<String>, // "passphrase" or "keychain"
+    pub keychain_available: bool,
+    pub idle_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultPassphraseArgs {
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultTimeoutArgs {
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObjectMetadata {
+    pub path: String,
+    pub version_id: Option<String>,
+    pub size: u64,
+    pub modified: Option<String>,
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+    pub content_language: Option<String>,
+    pub metadata: Vec<(String, String)>, // x-amz-meta-* without the prefix
+    pub e_tag: Option<String>,
+    pub storage_class: Option<String>,
+    pub archived: bool,
+    pub restore: Option<RestoreState>, // set once a restore has been requested
+    pub encryption: Option<String>,    // "SSE-S3", "SSE-KMS", "DSSE-KMS" or "SSE-C"
+    pub kms_key_id: Option<String>,
+    pub checksum: Option<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreState {
+    pub ongoing: bool,
+    pub expiry: Option<String>, // when the restored copy goes away
+}
+
+// Headers left as None are kept, empty ones removed; metadata and tag entries
+// without a value are removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObjectPatch {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub storage_class: Option<String>,
+    pub metadata: Vec<(String, Option<String>)>,
+    pub tags: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageClassArgs {
+    pub paths: Vec<String>,
+    pub storage_class: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreObjectsArgs {
+    pub paths: Vec<String>,
+    pub days: i32,
+    pub tier: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectMetadataArgs {
+    pub path: String,
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub path: String,
+    pub method: String,     // "GET" or "PUT"
+    pub created_at: String, // RFC 3339
+    pub expires_at: String, // RFC 3339
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignArgs {
+    pub path: String,
+    pub method: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateObjectsArgs {
+    pub paths: Vec<String>,
+    pub patch: ObjectPatch,
+}
+
+// Lifecycle rules in the shape the AWS CLI uses; see the backend for what
+// each field accepts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleConfiguration {
+    #[serde(default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub status: String, // "Enabled" or "Disabled"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<LifecycleFilter>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<Expiration>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub noncurrent_version_transitions: Vec<NoncurrentVersionTransition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_version_expiration: Option<NoncurrentVersionExpiration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<LifecycleTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_less_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub and: Option<LifecycleAnd>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleAnd {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<LifecycleTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size_less_than: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleTag {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Transition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>, // YYYY-MM-DD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    pub storage_class: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Expiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expired_object_delete_marker: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NoncurrentVersionTransition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_days: Option<i32>,
+    pub storage_class: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_noncurrent_versions: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NoncurrentVersionExpiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noncurrent_days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_noncurrent_versions: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AbortIncompleteMultipartUpload {
+    pub days_after_initiation: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PutLifecycleArgs {
+    pub path: String,
+    pub configuration: LifecycleConfiguration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatLifecycleArgs {
+    pub configuration: LifecycleConfiguration,
+    pub format: String, // "json" or "xml"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseLifecycleArgs {
+    pub text: String,
+    pub format: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketProperties {
+    pub policy: Option<String>,
+    pub cors_rules: Vec<CorsRule>,
+    pub public_access_block: PublicAccessBlock,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CorsRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublicAccessBlock {
+    pub block_public_acls: bool,
+    pub ignore_public_acls: bool,
+    pub block_public_policy: bool,
+    pub restrict_public_buckets: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: String, // "same", "added" or "removed"
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyDiff {
+    pub setting: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketPropertiesArgs {
+    pub path: String,
+    pub properties: BucketProperties,
+}
+
+// How uploads and copies to an account are encrypted
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum Encryption {
+    #[default]
+    BucketDefault,
+    SseS3,
+    SseKms {
+        key_id: Option<String>,
+    },
+    SseC,
+}
+
+impl Encryption {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encryption::BucketDefault => "Bucket default",
+            Encryption::SseS3 => "SSE-S3",
+            Encryption::SseKms { .. } => "SSE-KMS",
+            Encryption::SseC => "SSE-C",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionSettings {
+    pub default_encryption: Encryption,
+    pub customer_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionSettingsArgs {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefaultEncryptionArgs {
+    pub path: String,
+    pub encryption: Encryption,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerKeyArgs {
+    pub path: String,
+    pub key: Option<String>,
+}
+
+// A sync between a local folder and an S3 prefix, as planned before it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOptions {
+    pub local: String,
+    pub remote: String,
+    pub policy: String,     // "mirror", "two-way" or "never-delete"
+    pub direction: String,  // "upload" or "download"
+    pub compare: String,    // "size-mtime", "etag" or "checksum"
+    pub resolution: String, // "keep-both", "newer-wins", "local-wins" or "ask"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncAction {
+    pub path: String,
+    pub operation: String, // "upload", "download", "delete-local", "delete-remote", "keep-both" or "skip"
+    pub change: String,    // "new", "modified", "deleted" or "conflict"
+    pub size: u64,
+    pub reason: String,
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub options: SyncOptions,
+    pub actions: Vec<SyncAction>,
+    pub unchanged: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub transfers: usize,
+    pub deletes: usize,
+    #[serde(default)]
+    pub errors: Vec<String>, // steps that could not be started, as "path: error"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanSyncArgs {
+    pub options: SyncOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSyncArgs {
+    pub plan: SyncPlan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub id: String,
+    pub local: String,
+    pub remote: String,
+    pub ignore: String, // .gitignore style patterns, one per line
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub folder: WatchFolder,
+    pub state: String, // "scanning", "watching", "paused" or "failed"
+    pub pending: usize,
+    pub uploads: u64,
+    pub last_upload: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddWatchFolderArgs {
+    pub local: String,
+    pub remote: String,
+    pub ignore: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetWatchPausedArgs {
+    pub id: String,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolderIdArgs {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    pub deleted: Option<String>,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trashed {
+    pub items: Vec<TrashItem>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restored {
+    pub paths: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashIdsArgs {
+    pub ids: Vec<String>,
+}
@@ -0,0 +1,11 @@
+use wasm_bindgen_futures::JsFuture;
+
+/// Put text on the system clipboard.
+pub async fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let window = web_sys::window().ok_or("No window available")?;
+
+    JsFuture::from(window.navigator().clipboard().write_text(text))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to copy to the clipboard: {e:?}"))
+}
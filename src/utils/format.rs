@@ -14,3 +14,13 @@ pub fn format_file_size(size: u64) -> String {
         format!("{:.1} {}", size, UNITS[unit_index])
     }
 }
+
+pub fn format_duration(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
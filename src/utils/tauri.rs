@@ -4,6 +4,13 @@ use wasm_bindgen::prelude::*;
 extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], catch)]
     pub async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    // Resolves to a function that removes the listener again
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"], catch)]
+    pub async fn listen(
+        event: &str,
+        handler: &Closure<dyn FnMut(JsValue)>,
+    ) -> Result<JsValue, JsValue>;
 }
 
 // Check if we're running in Tauri environment